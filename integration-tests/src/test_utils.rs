@@ -1,9 +1,12 @@
+use skaja_server::{config::Config, Server, ServerHandle};
 use std::{
     io::{BufRead, BufReader},
-    net::TcpListener,
     panic,
-    path::PathBuf,
+    path::{Path, PathBuf},
     process::Stdio,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
 };
 
 fn skaja_server_exe() -> PathBuf {
@@ -25,53 +28,71 @@ fn skaja_server_exe() -> PathBuf {
     path
 }
 
-fn get_available_port() -> Option<u16> {
-    (8000..9000).find(|port| port_is_available(*port))
+/// A path in the system's temp directory that is unique to the current test process.
+pub fn temp_path(name: &str) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let mut path = std::env::temp_dir();
+    path.push(format!(
+        "skaja-{}-{}-{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::SeqCst),
+        name
+    ));
+
+    path
 }
 
-fn port_is_available(port: u16) -> bool {
-    TcpListener::bind(("127.0.0.1", port)).is_ok()
+/// Start a server on an available port in the current process.
+pub fn spawn_server() -> ServerHandle {
+    spawn_server_with(Server::new())
 }
 
-pub fn launch_server_process() -> (std::process::Child, String) {
-    let target_port = get_available_port().unwrap();
-    let target_address = format!("127.0.0.1:{}", target_port);
+/// Start a server that reads its config from the given TOML file, except for the address
+/// since it's always bound to an available port.
+pub fn spawn_server_with_config(config_path: &Path) -> ServerHandle {
+    let mut server = Server::new();
+    server
+        .apply_config(Config::load(config_path).unwrap())
+        .unwrap();
+
+    spawn_server_with(server)
+}
+
+fn spawn_server_with(mut server: Server) -> ServerHandle {
+    server.set_address("127.0.0.1:0".parse().unwrap());
+    server.spawn().expect("Failed to start server")
+}
+
+/// Launch a server process that reads its config from the given TOML file, for the tests
+/// that need a process of their own, e.g. to send it signals.
+pub fn launch_server_process_with_config(config_path: &Path) -> (std::process::Child, String) {
     let mut process_handle = std::process::Command::new(skaja_server_exe())
+        .arg("--config")
+        .arg(config_path)
         .arg("--address")
-        .arg(&target_address)
+        .arg("127.0.0.1:0")
         .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
+        .stderr(Stdio::null())
         .spawn()
         .expect("Failed to launch server process");
 
-    let server_stdout = process_handle.stdout.as_mut().unwrap();
-    let stdout_buf = BufReader::new(server_stdout);
-    let lines = stdout_buf.lines();
-    let mut server_listening = false;
-
-    // Wait for the server to start listening
-    for line in lines.flatten() {
-        if line.contains("Server listening on") {
-            server_listening = true;
-            break;
-        } else if line.contains("Address already in use") {
-            return launch_server_process();
-        }
-    }
+    let server_stdout = process_handle.stdout.take().unwrap();
+    let mut lines = BufReader::new(server_stdout).lines();
 
-    if !server_listening {
-        let server_stderr = process_handle.stderr.as_mut().unwrap();
-        let stderr_buf = BufReader::new(server_stderr);
-        let lines = stderr_buf.lines();
-
-        // Wait for the server to start listening
-        for line in lines.flatten() {
-            if line.contains("Address already in use") {
-                println!("Process panicked, relaunching server in another port");
-                return launch_server_process();
-            }
-        }
-    }
+    // Wait for the server to start listening, the log tells which port it's bound to.
+    let target_address = lines
+        .by_ref()
+        .map_while(Result::ok)
+        .find_map(|line| {
+            let (_, address) = line.split_once("Server listening on: ")?;
+            address.split_whitespace().next().map(str::to_owned)
+        })
+        .expect("Server process exited before listening");
+
+    // Keep draining the server's output, otherwise the server blocks
+    // once the pipe's buffer is full.
+    thread::spawn(move || lines.map_while(Result::ok).for_each(drop));
 
     println!(
         "Launched server process with pid {} on: {}",
@@ -82,22 +103,63 @@ pub fn launch_server_process() -> (std::process::Child, String) {
     (process_handle, target_address)
 }
 
+/// Wait for the server process to exit on its own, panics if it takes too long.
+pub fn wait_for_exit(process_handle: &mut std::process::Child) -> std::process::ExitStatus {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline {
+        if let Some(status) = process_handle.try_wait().unwrap() {
+            return status;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    process_handle.kill().unwrap();
+    process_handle.wait().unwrap();
+    panic!("Server process didn't exit in time.");
+}
+
 pub fn new_client(server_address: &str) -> skaja_client::Client {
     skaja_client::Client::connect(server_address.parse().unwrap())
 }
 
-/// Run the provided test function with a server process. It launches a server
-/// process on a random port, runs the provided test function,
-/// and then kills the server.
+/// Run the provided test function with a server. It starts a server on an
+/// available port, runs the provided test function, and then shuts the server down.
 pub fn with_server<T>(test: T)
 where
     T: FnOnce(String) + panic::UnwindSafe,
 {
-    let (mut server_handle, server_address) = launch_server_process();
+    run_with_server(spawn_server(), test);
+}
+
+/// Same as [`with_server`], but the server serves the connections with the given number of threads.
+pub fn with_server_threads<T>(threads: usize, test: T)
+where
+    T: FnOnce(String) + panic::UnwindSafe,
+{
+    let mut server = Server::new();
+    server.set_threads(threads);
+    run_with_server(spawn_server_with(server), test);
+}
+
+/// Same as [`with_server`], but the server reads its config from the given TOML file.
+pub fn with_server_config<T>(config_path: &Path, test: T)
+where
+    T: FnOnce(String) + panic::UnwindSafe,
+{
+    run_with_server(spawn_server_with_config(config_path), test);
+}
+
+fn run_with_server<T>(server_handle: ServerHandle, test: T)
+where
+    T: FnOnce(String) + panic::UnwindSafe,
+{
+    let server_address = server_handle.local_addr().to_string();
 
     let test_result = panic::catch_unwind(|| test(server_address));
 
-    server_handle.kill().unwrap();
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
 
     if let Err(e) = test_result {
         panic::resume_unwind(e);
@@ -0,0 +1,183 @@
+use integration_tests::test_utils::{
+    new_client, temp_path, with_server, with_server_config, with_server_threads,
+};
+use skaja_lib::StatusCodes;
+use std::{fs, thread};
+
+const CONTENDERS: usize = 8;
+
+#[test]
+pub fn set_nx_and_xx_should_only_set_the_key_in_the_given_state() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+
+        assert!(!client.set_xx("key", "v1").unwrap());
+        assert!(client.set_nx("key", "v1").unwrap());
+        assert!(!client.set_nx("key", "v2").unwrap());
+        assert!(client.set_xx("key", "v3").unwrap());
+
+        let response = client.get("key").unwrap();
+        assert_eq!(response.message(), Some(b"v3".as_slice()));
+    });
+}
+
+#[test]
+pub fn getset_and_getdel_should_reply_with_the_previous_value() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+
+        assert_eq!(client.getset("key", "v1").unwrap(), None);
+        assert_eq!(client.getset("key", "v2").unwrap(), Some(b"v1".to_vec()));
+        assert_eq!(client.getdel("key").unwrap(), Some(b"v2".to_vec()));
+        assert_eq!(client.getdel("key").unwrap(), None);
+        assert_eq!(
+            client.get("key").unwrap().status_code(),
+            StatusCodes::ErrNotFound
+        );
+    });
+}
+
+#[test]
+pub fn compare_and_swap_should_only_go_through_on_the_expected_value_or_version() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+
+        let response = client.cas_value("key", "v1", "v2").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::ErrNotFound);
+
+        client.set("key", "v1").unwrap();
+        let response = client.cas_value("key", "stale", "v2").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::ErrConflict);
+        let response = client.cas_value("key", "v1", "v2").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+
+        let version = client.gets("key").unwrap().unwrap().version;
+        client.set("key", "v3").unwrap();
+        let response = client.cas("key", "v4", version).unwrap();
+        assert_eq!(response.status_code(), StatusCodes::ErrConflict);
+
+        let version = client.gets("key").unwrap().unwrap().version;
+        let response = client.cas("key", "v4", version).unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+        let response = client.get("key").unwrap();
+        assert_eq!(response.message(), Some(b"v4".as_slice()));
+    });
+}
+
+#[test]
+pub fn compare_and_swap_should_go_through_after_writes_that_change_nothing() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+
+        client.set("key", "v1").unwrap();
+        let version = client.gets("key").unwrap().unwrap().version;
+
+        assert!(!client.set_nx("key", "v2").unwrap());
+        let response = client.lset("key", 0, "item").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::ErrWrongType);
+        let response = client.ltrim("key", 0, -1).unwrap();
+        assert_eq!(response.status_code(), StatusCodes::ErrWrongType);
+
+        let response = client.cas("key", "v2", version).unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+        let response = client.get("key").unwrap();
+        assert_eq!(response.message(), Some(b"v2".as_slice()));
+    });
+}
+
+#[test]
+pub fn concurrent_set_nx_should_elect_exactly_one_leader() {
+    with_server_threads(4, |server_address| {
+        let contenders: Vec<_> = (0..CONTENDERS)
+            .map(|contender| {
+                let server_address = server_address.clone();
+                thread::spawn(move || {
+                    let mut client = new_client(&server_address);
+                    client.set_nx("leader", contender.to_string()).unwrap()
+                })
+            })
+            .collect();
+
+        let winners: Vec<usize> = contenders
+            .into_iter()
+            .map(|contender| contender.join().unwrap())
+            .enumerate()
+            .filter(|(_, won)| *won)
+            .map(|(contender, _)| contender)
+            .collect();
+        assert_eq!(winners.len(), 1);
+
+        let mut client = new_client(&server_address);
+        let response = client.get("leader").unwrap();
+        assert_eq!(response.message(), Some(winners[0].to_string().as_bytes()));
+    });
+}
+
+#[test]
+pub fn concurrent_compare_and_swap_should_let_each_job_be_claimed_once() {
+    with_server_threads(4, |server_address| {
+        let jobs: Vec<String> = (0..32).map(|i| format!("job-{}", i)).collect();
+        let mut client = new_client(&server_address);
+        for job in &jobs {
+            client.set(job.as_str(), "pending").unwrap();
+        }
+
+        let workers: Vec<_> = (0..CONTENDERS)
+            .map(|worker| {
+                let server_address = server_address.clone();
+                let jobs = jobs.clone();
+                thread::spawn(move || {
+                    let mut client = new_client(&server_address);
+                    jobs.into_iter()
+                        .filter(|job| {
+                            let claimed = format!("claimed by {}", worker);
+                            let response = client.cas_value(job.as_str(), "pending", claimed);
+                            response.unwrap().status_code() == StatusCodes::Ok
+                        })
+                        .count()
+                })
+            })
+            .collect();
+
+        let claimed: usize = workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap())
+            .sum();
+        assert_eq!(claimed, jobs.len());
+    });
+}
+
+#[test]
+pub fn conditional_writes_should_survive_a_restart_with_append_only_file() {
+    let aof_path = temp_path("appendonly.aof");
+    let config_path = temp_path("config.toml");
+    let config = format!("[append_only]\npath = {:?}\nfsync = \"always\"\n", aof_path);
+    fs::write(&config_path, config).unwrap();
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        assert_eq!(client.getset("fresh", "v1").unwrap(), None);
+        assert!(client.set_nx("leader", "a").unwrap());
+        assert!(!client.set_nx("leader", "b").unwrap());
+        client.set("job", "pending").unwrap();
+        client.cas_value("job", "pending", "claimed").unwrap();
+        client.set("gone", "v").unwrap();
+        client.getdel("gone").unwrap();
+    });
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        assert_eq!(
+            client.mget(["fresh", "leader", "job", "gone"]).unwrap(),
+            vec![
+                Some(b"v1".to_vec()),
+                Some(b"a".to_vec()),
+                Some(b"claimed".to_vec()),
+                None
+            ]
+        );
+    });
+
+    fs::remove_file(aof_path).unwrap();
+    fs::remove_file(config_path).unwrap();
+}
@@ -0,0 +1,111 @@
+use integration_tests::test_utils::{new_client, with_server, with_server_threads};
+use skaja_lib::{Command, Expiry, StatusCodes};
+use std::thread;
+
+const CLIENTS: usize = 8;
+const INCREMENTS: usize = 500;
+
+#[test]
+pub fn counters_should_start_from_0_and_keep_the_expiry() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+
+        assert_eq!(client.incr("counter").unwrap(), 1);
+        assert_eq!(client.incr_by("counter", 41).unwrap(), 42);
+        assert_eq!(client.decr("counter").unwrap(), 41);
+        assert_eq!(client.incr_by("counter", -50).unwrap(), -9);
+
+        client
+            .set_with_expiry("session", "10", Expiry::Seconds(100))
+            .unwrap();
+        assert_eq!(client.incr("session").unwrap(), 11);
+        let response = client.ttl("session").unwrap();
+        assert_eq!(response.message(), Some(b"100".as_slice()));
+
+        assert_eq!(client.incr_by_float("float", 10.5).unwrap(), 10.5);
+        assert_eq!(client.incr_by_float("float", -0.25).unwrap(), 10.25);
+        assert_eq!(client.incr_by_float("counter", 0.5).unwrap(), -8.5);
+        let response = client.get("float").unwrap();
+        assert_eq!(response.message(), Some(b"10.25".as_slice()));
+    });
+}
+
+#[test]
+pub fn incrementing_a_value_that_is_not_a_number_should_fail() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+        client.set("text", "abc").unwrap();
+        client.set("float", "1.5").unwrap();
+        client.set("max", i64::MAX.to_string()).unwrap();
+        client.set("huge", "1e308").unwrap();
+
+        for command in [
+            Command::IncrBy(b"text".to_vec(), 1),
+            Command::IncrBy(b"float".to_vec(), 1),
+            Command::IncrBy(b"max".to_vec(), 1),
+            Command::IncrByFloat(b"text".to_vec(), 1.0),
+            Command::IncrByFloat(b"huge".to_vec(), 1e308),
+        ] {
+            let response = client.send(command).unwrap();
+            assert_eq!(response.status_code(), StatusCodes::ErrNotNumeric);
+        }
+
+        // The values are left untouched.
+        let response = client.get("max").unwrap();
+        assert_eq!(response.message(), Some(i64::MAX.to_string().as_bytes()));
+        assert!(client.incr("text").is_err());
+    });
+}
+
+#[test]
+pub fn concurrent_increments_on_the_same_counter_should_not_be_lost() {
+    with_server_threads(4, |server_address| {
+        let clients: Vec<_> = (0..CLIENTS)
+            .map(|_| {
+                let server_address = server_address.clone();
+                thread::spawn(move || {
+                    let mut client = new_client(&server_address);
+                    for _ in 0..INCREMENTS {
+                        client.incr("counter").unwrap();
+                        client.incr_by("counter", 2).unwrap();
+                        client.decr("counter").unwrap();
+                    }
+                })
+            })
+            .collect();
+
+        for client in clients {
+            client.join().unwrap();
+        }
+
+        let mut client = new_client(&server_address);
+        let expected = (CLIENTS * INCREMENTS * 2) as i64;
+        assert_eq!(client.incr_by("counter", 0).unwrap(), expected);
+    });
+}
+
+#[test]
+pub fn concurrent_float_increments_on_the_same_counter_should_not_be_lost() {
+    with_server_threads(4, |server_address| {
+        let clients: Vec<_> = (0..CLIENTS)
+            .map(|_| {
+                let server_address = server_address.clone();
+                thread::spawn(move || {
+                    let mut client = new_client(&server_address);
+                    for _ in 0..INCREMENTS {
+                        // Exactly representable, so the total doesn't depend on the order.
+                        client.incr_by_float("counter", 0.5).unwrap();
+                    }
+                })
+            })
+            .collect();
+
+        for client in clients {
+            client.join().unwrap();
+        }
+
+        let mut client = new_client(&server_address);
+        let expected = (CLIENTS * INCREMENTS) as f64 * 0.5;
+        assert_eq!(client.incr_by_float("counter", 0.0).unwrap(), expected);
+    });
+}
@@ -0,0 +1,43 @@
+use integration_tests::test_utils::{new_client, spawn_server};
+use skaja_server::Server;
+use std::io;
+
+#[test]
+pub fn server_bound_to_port_0_should_report_the_actual_address() {
+    let server_handle = spawn_server();
+    let address = server_handle.local_addr();
+    assert_ne!(address.port(), 0);
+
+    let mut client = new_client(&address.to_string());
+    client.set("hello", "world").unwrap();
+    let response = client.get("hello").unwrap();
+    assert_eq!(response.message(), Some(b"world".as_slice()));
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn binding_to_an_address_in_use_should_result_in_err() {
+    let server_handle = spawn_server();
+
+    let mut server = Server::new();
+    server.set_address(server_handle.local_addr());
+    let err = server.spawn().err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+}
+
+#[test]
+pub fn binding_without_an_address_should_result_in_err() {
+    let err = Server::new().bind().err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+pub fn dropping_the_handle_should_stop_the_server() {
+    let server_handle = spawn_server();
+    let address = server_handle.local_addr();
+    drop(server_handle);
+
+    assert!(std::net::TcpStream::connect(address).is_err());
+}
@@ -0,0 +1,86 @@
+use integration_tests::test_utils::{new_client, with_server};
+use skaja_lib::{Expiry, StatusCodes};
+use std::{thread, time::Duration};
+
+#[test]
+pub fn key_set_with_expiry_should_be_gone_after_it_expires() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+        let response = client
+            .set_with_expiry("session", "data", Expiry::Milliseconds(200))
+            .unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+
+        let response = client.get("session").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+        assert_eq!(response.message(), Some(b"data".as_slice()));
+
+        thread::sleep(Duration::from_millis(300));
+
+        let response = client.get("session").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::ErrNotFound);
+    })
+}
+
+#[test]
+pub fn ttl_should_report_the_remaining_time_to_live() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+        client.set("hello", "world").unwrap();
+
+        let response = client.ttl("hello").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+        assert_eq!(response.message(), Some(b"-1".as_slice()));
+
+        let response = client.expire("hello", 100).unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+
+        let response = client.ttl("hello").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+        assert_eq!(response.message(), Some(b"100".as_slice()));
+
+        let response = client.ttl("missing").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::ErrNotFound);
+    })
+}
+
+#[test]
+pub fn persist_should_remove_the_timeout() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+        client.set("hello", "world").unwrap();
+        client.pexpire("hello", 200).unwrap();
+
+        let response = client.persist("hello").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+
+        thread::sleep(Duration::from_millis(300));
+
+        let response = client.get("hello").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+        assert_eq!(response.message(), Some(b"world".as_slice()));
+    })
+}
+
+#[test]
+pub fn setting_a_key_again_should_discard_its_timeout() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+        client
+            .set_with_expiry("hello", "world", Expiry::Seconds(10))
+            .unwrap();
+        client.set("hello", "there").unwrap();
+
+        let response = client.ttl("hello").unwrap();
+        assert_eq!(response.message(), Some(b"-1".as_slice()));
+    })
+}
+
+#[test]
+pub fn expiring_a_missing_key_should_result_in_client_error() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+        let response = client.expire("missing", 10).unwrap();
+        assert_eq!(response.status_code(), StatusCodes::ErrNotFound);
+    })
+}
@@ -0,0 +1,238 @@
+use integration_tests::test_utils::{
+    new_client, temp_path, with_server, with_server_config, with_server_threads,
+};
+use skaja_lib::{Command, StatusCodes};
+use std::{collections::HashMap, fs, thread};
+
+const CLIENTS: usize = 8;
+const INCREMENTS: usize = 250;
+
+fn fields(fields: &[(&str, &str)]) -> HashMap<Vec<u8>, Vec<u8>> {
+    fields
+        .iter()
+        .map(|(field, value)| (field.as_bytes().to_vec(), value.as_bytes().to_vec()))
+        .collect()
+}
+
+fn sorted(mut items: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+    items.sort();
+    items
+}
+
+#[test]
+pub fn fields_should_be_set_read_and_removed() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+
+        assert_eq!(
+            client
+                .hset("user:1", [("name", "ada"), ("lang", "en")])
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            client
+                .hset("user:1", [("lang", "fr"), ("city", "paris")])
+                .unwrap(),
+            1
+        );
+
+        assert_eq!(client.hget("user:1", "lang").unwrap(), Some(b"fr".to_vec()));
+        assert_eq!(client.hget("user:1", "email").unwrap(), None);
+        assert_eq!(client.hget("user:2", "name").unwrap(), None);
+        assert_eq!(
+            client.hmget("user:1", ["name", "email", "city"]).unwrap(),
+            vec![Some(b"ada".to_vec()), None, Some(b"paris".to_vec())]
+        );
+        assert_eq!(client.hmget("user:2", ["name"]).unwrap(), vec![None]);
+
+        assert_eq!(
+            client.hgetall("user:1").unwrap(),
+            fields(&[("name", "ada"), ("lang", "fr"), ("city", "paris")])
+        );
+        assert_eq!(
+            sorted(client.hkeys("user:1").unwrap()),
+            vec![b"city".to_vec(), b"lang".to_vec(), b"name".to_vec()]
+        );
+        assert_eq!(
+            sorted(client.hvals("user:1").unwrap()),
+            vec![b"ada".to_vec(), b"fr".to_vec(), b"paris".to_vec()]
+        );
+        assert_eq!(client.hlen("user:1").unwrap(), 3);
+        assert!(client.hexists("user:1", "city").unwrap());
+        assert!(!client.hexists("user:1", "email").unwrap());
+
+        assert_eq!(client.hdel("user:1", ["city", "email"]).unwrap(), 1);
+        assert_eq!(client.hdel("user:1", ["name", "lang"]).unwrap(), 2);
+
+        // The hash is gone once it's empty.
+        assert_eq!(client.exists(["user:1"]).unwrap(), 0);
+        assert_eq!(client.hlen("user:1").unwrap(), 0);
+        assert_eq!(client.hgetall("user:1").unwrap(), fields(&[]));
+        assert_eq!(client.hdel("user:1", ["name"]).unwrap(), 0);
+    });
+}
+
+#[test]
+pub fn hincrby_should_start_from_0_and_reject_values_that_are_not_integers() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+
+        assert_eq!(client.hincr_by("user:1", "visits", 1).unwrap(), 1);
+        assert_eq!(client.hincr_by("user:1", "visits", 41).unwrap(), 42);
+        assert_eq!(client.hincr_by("user:1", "visits", -50).unwrap(), -8);
+
+        client
+            .hset(
+                "user:1",
+                [("name", "ada"), ("max", i64::MAX.to_string().as_str())],
+            )
+            .unwrap();
+        for command in [
+            Command::HIncrBy(b"user:1".to_vec(), b"name".to_vec(), 1),
+            Command::HIncrBy(b"user:1".to_vec(), b"max".to_vec(), 1),
+        ] {
+            let response = client.send(command).unwrap();
+            assert_eq!(response.status_code(), StatusCodes::ErrNotNumeric);
+        }
+
+        assert_eq!(
+            client.hgetall("user:1").unwrap(),
+            fields(&[
+                ("visits", "-8"),
+                ("name", "ada"),
+                ("max", i64::MAX.to_string().as_str())
+            ])
+        );
+    });
+}
+
+#[test]
+pub fn commands_on_the_wrong_type_should_fail_without_changing_the_value() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+        client.hset("hash", [("field", "value")]).unwrap();
+        client.rpush("list", ["a"]).unwrap();
+        client.set("string", "value").unwrap();
+
+        for command in [
+            Command::Get(b"hash".to_vec()),
+            Command::IncrBy(b"hash".to_vec(), 1),
+            Command::LPush(b"hash".to_vec(), vec![b"a".to_vec()]),
+            Command::HSet(b"list".to_vec(), vec![(b"a".to_vec(), b"b".to_vec())]),
+            Command::HGet(b"string".to_vec(), b"field".to_vec()),
+            Command::HGetAll(b"string".to_vec()),
+            Command::HDel(b"string".to_vec(), vec![b"field".to_vec()]),
+            Command::HIncrBy(b"string".to_vec(), b"field".to_vec(), 1),
+        ] {
+            let response = client.send(command).unwrap();
+            assert_eq!(response.status_code(), StatusCodes::ErrWrongType);
+        }
+
+        assert_eq!(
+            client.hgetall("hash").unwrap(),
+            fields(&[("field", "value")])
+        );
+        assert_eq!(client.lrange("list", 0, -1).unwrap(), vec![b"a".to_vec()]);
+        assert_eq!(
+            client.get("string").unwrap().message(),
+            Some(b"value".as_slice())
+        );
+    });
+}
+
+#[test]
+pub fn concurrent_updates_to_the_same_hash_should_not_be_lost() {
+    with_server_threads(4, |server_address| {
+        let clients: Vec<_> = (0..CLIENTS)
+            .map(|client_id| {
+                let server_address = server_address.clone();
+                thread::spawn(move || {
+                    let mut client = new_client(&server_address);
+                    for i in 0..INCREMENTS {
+                        client.hincr_by("stats", "requests", 1).unwrap();
+                        let field = format!("client:{}", client_id);
+                        client
+                            .hset("stats", [(field.as_str(), i.to_string().as_str())])
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+
+        for client in clients {
+            client.join().unwrap();
+        }
+
+        let mut client = new_client(&server_address);
+        let stats = client.hgetall("stats").unwrap();
+        assert_eq!(stats.len(), CLIENTS + 1);
+        assert_eq!(
+            stats[b"requests".as_slice()],
+            (CLIENTS * INCREMENTS).to_string().into_bytes()
+        );
+        for client_id in 0..CLIENTS {
+            let field = format!("client:{}", client_id).into_bytes();
+            assert_eq!(stats[&field], (INCREMENTS - 1).to_string().into_bytes());
+        }
+    });
+}
+
+#[test]
+pub fn hashes_should_survive_a_restart_with_snapshot() {
+    let snapshot_path = temp_path("dump.skaja");
+    let config_path = temp_path("config.toml");
+    let config = format!("[snapshot]\npath = {:?}\nsave = []\n", snapshot_path);
+    fs::write(&config_path, config).unwrap();
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        client
+            .hset("user:1", [("name", "ada"), ("empty", "")])
+            .unwrap();
+        client.rpush("list", ["a"]).unwrap();
+
+        let response = client.save().unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+    });
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        assert_eq!(
+            client.hgetall("user:1").unwrap(),
+            fields(&[("name", "ada"), ("empty", "")])
+        );
+        assert_eq!(client.lrange("list", 0, -1).unwrap(), vec![b"a".to_vec()]);
+    });
+
+    fs::remove_file(config_path).unwrap();
+    fs::remove_file(snapshot_path).unwrap();
+}
+
+#[test]
+pub fn hashes_should_survive_a_restart_with_append_only_file() {
+    let aof_path = temp_path("appendonly.aof");
+    let config_path = temp_path("config.toml");
+    let config = format!("[append_only]\npath = {:?}\nfsync = \"always\"\n", aof_path);
+    fs::write(&config_path, config).unwrap();
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        client
+            .hset("user:1", [("name", "ada"), ("lang", "en")])
+            .unwrap();
+        client.hincr_by("user:1", "visits", 3).unwrap();
+        client.hdel("user:1", ["lang"]).unwrap();
+    });
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        assert_eq!(
+            client.hgetall("user:1").unwrap(),
+            fields(&[("name", "ada"), ("visits", "3")])
+        );
+    });
+
+    fs::remove_file(aof_path).unwrap();
+    fs::remove_file(config_path).unwrap();
+}
@@ -0,0 +1,299 @@
+use integration_tests::test_utils::new_client;
+use skaja_server::{Server, ServerHandle};
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+fn spawn_http_server() -> ServerHandle {
+    let mut server = Server::new();
+    server.set_address("127.0.0.1:0".parse().unwrap());
+    server.set_http_address("127.0.0.1:0".parse().unwrap());
+    server.spawn().expect("Failed to start server")
+}
+
+fn connect(server_handle: &ServerHandle) -> TcpStream {
+    let connection = TcpStream::connect(server_handle.http_addr().unwrap()).unwrap();
+    connection
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    connection
+}
+
+/// Read a response, returning its status, its headers and its body.
+fn read_response(connection: &mut TcpStream) -> (u16, String, String) {
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        connection.read_exact(&mut byte).unwrap();
+        head.push(byte[0]);
+    }
+
+    let head = String::from_utf8(head).unwrap();
+    let status = head[9..12].parse().unwrap();
+    let content_length: usize = head
+        .lines()
+        .find_map(|line| line.strip_prefix("Content-Length: "))
+        .map(|length| length.parse().unwrap())
+        .unwrap_or(0);
+
+    let mut body = vec![0u8; content_length];
+    connection.read_exact(&mut body).unwrap();
+    (status, head, String::from_utf8(body).unwrap())
+}
+
+/// Send the request and assert that the expected status and body come back.
+fn assert_response(
+    connection: &mut TcpStream,
+    method: &str,
+    target: &str,
+    body: &str,
+    expected: (u16, &str),
+) {
+    let request = format!(
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+        method,
+        target,
+        body.len(),
+        body
+    );
+    connection.write_all(request.as_bytes()).unwrap();
+
+    let (status, _, body) = read_response(connection);
+    assert_eq!((status, body.as_str()), expected);
+}
+
+#[test]
+pub fn keys_should_be_stored_read_and_deleted_over_http() {
+    let server_handle = spawn_http_server();
+    let mut connection = connect(&server_handle);
+
+    assert_response(
+        &mut connection,
+        "GET",
+        "/keys/greeting",
+        "",
+        (404, "{\"error\":\"Key not found\"}"),
+    );
+    assert_response(
+        &mut connection,
+        "PUT",
+        "/keys/greeting",
+        "hello \"world\"",
+        (200, "{\"key\":\"greeting\",\"stored\":true}"),
+    );
+    assert_response(
+        &mut connection,
+        "GET",
+        "/keys/greeting",
+        "",
+        (
+            200,
+            "{\"key\":\"greeting\",\"value\":\"hello \\\"world\\\"\"}",
+        ),
+    );
+    assert_response(
+        &mut connection,
+        "DELETE",
+        "/keys/greeting",
+        "",
+        (200, "{\"key\":\"greeting\",\"deleted\":true}"),
+    );
+    assert_response(
+        &mut connection,
+        "DELETE",
+        "/keys/greeting",
+        "",
+        (404, "{\"error\":\"Key not found\"}"),
+    );
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn keys_should_be_percent_decoded_and_listed_by_prefix() {
+    let server_handle = spawn_http_server();
+    let mut connection = connect(&server_handle);
+
+    for key in ["user%3A1", "user%3A2", "other"] {
+        assert_response(
+            &mut connection,
+            "PUT",
+            &format!("/keys/{}", key),
+            "v",
+            (
+                200,
+                &format!(
+                    "{{\"key\":\"{}\",\"stored\":true}}",
+                    key.replace("%3A", ":")
+                ),
+            ),
+        );
+    }
+
+    assert_response(
+        &mut connection,
+        "GET",
+        "/keys?prefix=user%3A",
+        "",
+        (200, "{\"keys\":[\"user:1\",\"user:2\"]}"),
+    );
+    assert_response(
+        &mut connection,
+        "GET",
+        "/keys",
+        "",
+        (200, "{\"keys\":[\"other\",\"user:1\",\"user:2\"]}"),
+    );
+
+    let mut client = new_client(&server_handle.local_addr().to_string());
+    assert_eq!(
+        client.keys("user:").unwrap(),
+        vec![b"user:1".to_vec(), b"user:2".to_vec()]
+    );
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn ttl_query_should_set_the_expiry_of_the_key() {
+    let server_handle = spawn_http_server();
+    let mut connection = connect(&server_handle);
+
+    assert_response(
+        &mut connection,
+        "PUT",
+        "/keys/session?ttl=100",
+        "v",
+        (200, "{\"key\":\"session\",\"stored\":true}"),
+    );
+    assert_response(
+        &mut connection,
+        "PUT",
+        "/keys/session?ttl=soon",
+        "v",
+        (400, "{\"error\":\"Invalid ttl\"}"),
+    );
+
+    let mut client = new_client(&server_handle.local_addr().to_string());
+    let response = client.ttl("session").unwrap();
+    assert_eq!(response.message(), Some(b"100".as_slice()));
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn binary_values_should_be_sent_in_base64() {
+    let server_handle = spawn_http_server();
+    let mut client = new_client(&server_handle.local_addr().to_string());
+    client.set("binary", vec![0, 159, 146, 150, 255]).unwrap();
+
+    let mut connection = connect(&server_handle);
+    assert_response(
+        &mut connection,
+        "GET",
+        "/keys/binary",
+        "",
+        (200, "{\"key\":\"binary\",\"value_base64\":\"AJ+Slv8=\"}"),
+    );
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn unknown_routes_and_methods_should_be_rejected() {
+    let server_handle = spawn_http_server();
+    let mut connection = connect(&server_handle);
+
+    assert_response(
+        &mut connection,
+        "GET",
+        "/nope",
+        "",
+        (404, "{\"error\":\"Not found\"}"),
+    );
+    assert_response(
+        &mut connection,
+        "POST",
+        "/keys/a",
+        "",
+        (405, "{\"error\":\"Method not allowed\"}"),
+    );
+    assert_response(
+        &mut connection,
+        "GET",
+        "/keys/bad%zz",
+        "",
+        (400, "{\"error\":\"Invalid key\"}"),
+    );
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn connection_should_be_closed_when_the_client_asks_to() {
+    let server_handle = spawn_http_server();
+    let mut connection = connect(&server_handle);
+
+    connection
+        .write_all(b"PUT /keys/a HTTP/1.1\r\nContent-Length: 1\r\nConnection: close\r\n\r\nv")
+        .unwrap();
+    let mut response = String::new();
+    connection.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.contains("Connection: close\r\n"));
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn body_should_be_waited_for_after_100_continue() {
+    let server_handle = spawn_http_server();
+    let mut connection = connect(&server_handle);
+
+    connection
+        .write_all(b"PUT /keys/a HTTP/1.1\r\nContent-Length: 5\r\nExpect: 100-continue\r\n\r\n")
+        .unwrap();
+    let mut reply = vec![0u8; 25];
+    connection.read_exact(&mut reply).unwrap();
+    assert_eq!(reply, b"HTTP/1.1 100 Continue\r\n\r\n");
+
+    connection.write_all(b"value").unwrap();
+    let (status, head, body) = read_response(&mut connection);
+    assert_eq!(status, 200);
+    assert!(head.contains("Content-Type: application/json\r\n"));
+    assert_eq!(body, "{\"key\":\"a\",\"stored\":true}");
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn oversized_body_should_be_answered_with_413_and_disconnected() {
+    let mut server = Server::new();
+    server.set_address("127.0.0.1:0".parse().unwrap());
+    server.set_http_address("127.0.0.1:0".parse().unwrap());
+    server.set_request_limits(skaja_lib::FrameLimits {
+        max_value_size: 4,
+        ..Default::default()
+    });
+    let server_handle = server.spawn().unwrap();
+    let mut connection = connect(&server_handle);
+
+    connection
+        .write_all(b"PUT /keys/a HTTP/1.1\r\nContent-Length: 5\r\n\r\n")
+        .unwrap();
+    let mut response = String::new();
+    connection.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
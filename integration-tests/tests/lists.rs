@@ -0,0 +1,243 @@
+use integration_tests::test_utils::{
+    new_client, temp_path, with_server, with_server_config, with_server_threads,
+};
+use skaja_lib::{Command, StatusCodes};
+use std::{collections::HashSet, fs, thread};
+
+const PRODUCERS: usize = 4;
+const CONSUMERS: usize = 4;
+const JOBS: usize = 250;
+
+fn items(items: &[&str]) -> Vec<Vec<u8>> {
+    items.iter().map(|item| item.as_bytes().to_vec()).collect()
+}
+
+#[test]
+pub fn items_should_be_pushed_and_popped_at_both_ends() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+
+        assert_eq!(client.rpush("list", ["b", "c"]).unwrap(), 2);
+        assert_eq!(client.lpush("list", ["a", "z"]).unwrap(), 4);
+        assert_eq!(
+            client.lrange("list", 0, -1).unwrap(),
+            items(&["z", "a", "b", "c"])
+        );
+        assert_eq!(client.lrange("list", -2, 100).unwrap(), items(&["b", "c"]));
+        assert_eq!(client.lrange("list", 3, 1).unwrap(), items(&[]));
+        assert_eq!(client.llen("list").unwrap(), 4);
+        assert_eq!(client.lindex("list", -1).unwrap(), Some(b"c".to_vec()));
+        assert_eq!(client.lindex("list", 4).unwrap(), None);
+
+        assert_eq!(client.lpop("list").unwrap(), Some(b"z".to_vec()));
+        assert_eq!(client.rpop("list").unwrap(), Some(b"c".to_vec()));
+
+        let response = client
+            .send(Command::LPop(b"list".to_vec(), Some(5)))
+            .unwrap();
+        assert_eq!(response.array().unwrap(), items(&["a", "b"]));
+
+        // The list is gone once it's empty.
+        assert_eq!(client.exists(["list"]).unwrap(), 0);
+        assert_eq!(client.lpop("list").unwrap(), None);
+        assert_eq!(client.llen("list").unwrap(), 0);
+        assert_eq!(client.lrange("list", 0, -1).unwrap(), items(&[]));
+    });
+}
+
+#[test]
+pub fn items_should_be_replaced_trimmed_and_removed() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+        client
+            .rpush("list", ["a", "b", "a", "c", "a", "b"])
+            .unwrap();
+
+        assert_eq!(client.lrem("list", -1, "a").unwrap(), 1);
+        assert_eq!(
+            client.lrange("list", 0, -1).unwrap(),
+            items(&["a", "b", "a", "c", "b"])
+        );
+        assert_eq!(client.lrem("list", 1, "b").unwrap(), 1);
+        assert_eq!(client.lrem("list", 0, "a").unwrap(), 2);
+        assert_eq!(client.lrange("list", 0, -1).unwrap(), items(&["c", "b"]));
+
+        let response = client.lset("list", -1, "d").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+        let response = client.lset("list", 2, "d").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::ErrInvalidRequest);
+        let response = client.lset("missing", 0, "d").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::ErrNotFound);
+
+        client.rpush("list", ["e", "f", "g"]).unwrap();
+        client.ltrim("list", 1, -2).unwrap();
+        assert_eq!(
+            client.lrange("list", 0, -1).unwrap(),
+            items(&["d", "e", "f"])
+        );
+
+        client.ltrim("list", 5, 10).unwrap();
+        assert_eq!(client.exists(["list"]).unwrap(), 0);
+    });
+}
+
+#[test]
+pub fn commands_on_the_wrong_type_should_fail_without_changing_the_value() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+        client.rpush("list", ["a"]).unwrap();
+        client.set("string", "value").unwrap();
+
+        for command in [
+            Command::Get(b"list".to_vec()),
+            Command::Gets(b"list".to_vec()),
+            Command::GetSet(b"list".to_vec(), b"value".to_vec()),
+            Command::GetDel(b"list".to_vec()),
+            Command::IncrBy(b"list".to_vec(), 1),
+            Command::LPush(b"string".to_vec(), vec![b"a".to_vec()]),
+            Command::LPop(b"string".to_vec(), None),
+            Command::LRange(b"string".to_vec(), 0, -1),
+            Command::LLen(b"string".to_vec()),
+        ] {
+            let response = client.send(command).unwrap();
+            assert_eq!(response.status_code(), StatusCodes::ErrWrongType);
+        }
+
+        assert_eq!(client.lrange("list", 0, -1).unwrap(), items(&["a"]));
+        assert_eq!(
+            client.get("string").unwrap().message(),
+            Some(b"value".as_slice())
+        );
+
+        // Commands that work on any key still do, and a set overwrites the list.
+        assert_eq!(
+            client.mget(["list", "string"]).unwrap(),
+            vec![None, Some(b"value".to_vec())]
+        );
+        client.set("list", "value").unwrap();
+        assert_eq!(
+            client.get("list").unwrap().message(),
+            Some(b"value".as_slice())
+        );
+    });
+}
+
+#[test]
+pub fn concurrent_producers_and_consumers_should_hand_over_each_job_once() {
+    with_server_threads(4, |server_address| {
+        let producers: Vec<_> = (0..PRODUCERS)
+            .map(|producer| {
+                let server_address = server_address.clone();
+                thread::spawn(move || {
+                    let mut client = new_client(&server_address);
+                    for job in 0..JOBS {
+                        client
+                            .rpush("queue", [format!("{}-{}", producer, job)])
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+
+        let consumers: Vec<_> = (0..CONSUMERS)
+            .map(|_| {
+                let server_address = server_address.clone();
+                thread::spawn(move || {
+                    let mut client = new_client(&server_address);
+                    let mut jobs = Vec::new();
+                    let mut misses = 0;
+                    // Keep going until the queue stays empty for a while after the
+                    // producers are likely done.
+                    while misses < 100 {
+                        match client.lpop("queue").unwrap() {
+                            Some(job) => {
+                                jobs.push(job);
+                                misses = 0;
+                            }
+                            None => {
+                                misses += 1;
+                                thread::yield_now();
+                            }
+                        }
+                    }
+                    jobs
+                })
+            })
+            .collect();
+
+        for producer in producers {
+            producer.join().unwrap();
+        }
+
+        let mut consumed: Vec<Vec<u8>> = consumers
+            .into_iter()
+            .flat_map(|consumer| consumer.join().unwrap())
+            .collect();
+
+        // Whatever the consumers gave up on is still in the queue.
+        let mut client = new_client(&server_address);
+        while let Some(job) = client.lpop("queue").unwrap() {
+            consumed.push(job);
+        }
+
+        let unique: HashSet<&Vec<u8>> = consumed.iter().collect();
+        assert_eq!(consumed.len(), PRODUCERS * JOBS);
+        assert_eq!(unique.len(), PRODUCERS * JOBS);
+    });
+}
+
+#[test]
+pub fn lists_should_survive_a_restart_with_snapshot() {
+    let snapshot_path = temp_path("dump.skaja");
+    let config_path = temp_path("config.toml");
+    let config = format!("[snapshot]\npath = {:?}\nsave = []\n", snapshot_path);
+    fs::write(&config_path, config).unwrap();
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        client.rpush("list", ["a", "", "c"]).unwrap();
+        client.set("string", "value").unwrap();
+
+        let response = client.save().unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+    });
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        assert_eq!(
+            client.lrange("list", 0, -1).unwrap(),
+            items(&["a", "", "c"])
+        );
+        assert_eq!(
+            client.get("string").unwrap().message(),
+            Some(b"value".as_slice())
+        );
+    });
+
+    fs::remove_file(config_path).unwrap();
+    fs::remove_file(snapshot_path).unwrap();
+}
+
+#[test]
+pub fn lists_should_survive_a_restart_with_append_only_file() {
+    let aof_path = temp_path("appendonly.aof");
+    let config_path = temp_path("config.toml");
+    let config = format!("[append_only]\npath = {:?}\nfsync = \"always\"\n", aof_path);
+    fs::write(&config_path, config).unwrap();
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        client.rpush("list", ["a", "b", "c", "d"]).unwrap();
+        client.lpop("list").unwrap();
+        client.lset("list", 0, "x").unwrap();
+        client.lrem("list", 0, "c").unwrap();
+    });
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        assert_eq!(client.lrange("list", 0, -1).unwrap(), items(&["x", "d"]));
+    });
+
+    fs::remove_file(aof_path).unwrap();
+    fs::remove_file(config_path).unwrap();
+}
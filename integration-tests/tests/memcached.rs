@@ -0,0 +1,194 @@
+use integration_tests::test_utils::new_client;
+use skaja_server::{Server, ServerHandle};
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+fn spawn_memcached_server() -> ServerHandle {
+    let mut server = Server::new();
+    server.set_address("127.0.0.1:0".parse().unwrap());
+    server.set_memcached_address("127.0.0.1:0".parse().unwrap());
+    server.spawn().expect("Failed to start server")
+}
+
+fn connect(server_handle: &ServerHandle) -> TcpStream {
+    let connection = TcpStream::connect(server_handle.memcached_addr().unwrap()).unwrap();
+    connection
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    connection
+}
+
+/// Send the request and assert that exactly the expected reply comes back.
+fn assert_reply(connection: &mut TcpStream, request: &str, expected: &str) {
+    connection.write_all(request.as_bytes()).unwrap();
+
+    let mut reply = vec![0u8; expected.len()];
+    connection.read_exact(&mut reply).unwrap();
+    assert_eq!(String::from_utf8_lossy(&reply), expected);
+}
+
+/// Read a reply line by line until the `END` of a retrieval.
+fn read_until_end(connection: &mut TcpStream) -> String {
+    let mut reply = Vec::new();
+    let mut byte = [0u8; 1];
+    while !reply.ends_with(b"END\r\n") {
+        connection.read_exact(&mut byte).unwrap();
+        reply.push(byte[0]);
+    }
+    String::from_utf8(reply).unwrap()
+}
+
+#[test]
+pub fn storage_and_retrieval_commands_should_be_answered_like_memcached() {
+    let server_handle = spawn_memcached_server();
+    let mut connection = connect(&server_handle);
+
+    assert_reply(&mut connection, "set a 42 0 5\r\nhello\r\n", "STORED\r\n");
+    assert_reply(&mut connection, "set b 0 0 2\r\nhi\r\n", "STORED\r\n");
+    assert_reply(
+        &mut connection,
+        "get a missing b\r\n",
+        "VALUE a 42 5\r\nhello\r\nVALUE b 0 2\r\nhi\r\nEND\r\n",
+    );
+    assert_reply(&mut connection, "get missing\r\n", "END\r\n");
+    assert_reply(&mut connection, "delete a\r\n", "DELETED\r\n");
+    assert_reply(&mut connection, "delete a\r\n", "NOT_FOUND\r\n");
+    assert_reply(&mut connection, "touch b 100\r\n", "TOUCHED\r\n");
+    assert_reply(&mut connection, "touch a 100\r\n", "NOT_FOUND\r\n");
+    assert_reply(&mut connection, "nope\r\n", "ERROR\r\n");
+    assert_reply(
+        &mut connection,
+        "set a 0 0\r\n",
+        "CLIENT_ERROR bad command line format\r\n",
+    );
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn cas_should_only_store_if_the_value_is_unchanged() {
+    let server_handle = spawn_memcached_server();
+    let mut connection = connect(&server_handle);
+
+    assert_reply(&mut connection, "set key 7 0 2\r\nv1\r\n", "STORED\r\n");
+    connection.write_all(b"gets key\r\n").unwrap();
+    let reply = read_until_end(&mut connection);
+    let header = reply.lines().next().unwrap();
+    let (prefix, unique) = header.rsplit_once(' ').unwrap();
+    assert_eq!(prefix, "VALUE key 7 2");
+
+    let stale: u64 = unique.parse().unwrap();
+    assert_reply(
+        &mut connection,
+        &format!("cas key 7 0 2 {}\r\nv2\r\n", stale),
+        "STORED\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &format!("cas key 7 0 2 {}\r\nv3\r\n", stale),
+        "EXISTS\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        "cas missing 0 0 1 1\r\nv\r\n",
+        "NOT_FOUND\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        "get key\r\n",
+        "VALUE key 7 2\r\nv2\r\nEND\r\n",
+    );
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn counters_should_only_be_changed_if_they_exist() {
+    let server_handle = spawn_memcached_server();
+    let mut connection = connect(&server_handle);
+
+    assert_reply(&mut connection, "incr counter 1\r\n", "NOT_FOUND\r\n");
+    assert_reply(&mut connection, "set counter 0 0 2\r\n10\r\n", "STORED\r\n");
+    assert_reply(&mut connection, "incr counter 5\r\n", "15\r\n");
+    assert_reply(&mut connection, "decr counter 3\r\n", "12\r\n");
+    assert_reply(&mut connection, "decr counter 100\r\n", "0\r\n");
+    assert_reply(&mut connection, "set text 0 0 3\r\nabc\r\n", "STORED\r\n");
+    assert_reply(
+        &mut connection,
+        "incr text 1\r\n",
+        "CLIENT_ERROR cannot increment or decrement non-numeric value\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        "incr counter -1\r\n",
+        "CLIENT_ERROR invalid numeric delta argument\r\n",
+    );
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn noreply_should_silence_the_reply() {
+    let server_handle = spawn_memcached_server();
+    let mut connection = connect(&server_handle);
+
+    connection
+        .write_all(b"set key 0 0 1 noreply\r\nv\r\nincr missing 1 noreply\r\n")
+        .unwrap();
+    assert_reply(
+        &mut connection,
+        "get key\r\n",
+        "VALUE key 0 1\r\nv\r\nEND\r\n",
+    );
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn memcached_and_native_clients_should_share_the_keyspace() {
+    let server_handle = spawn_memcached_server();
+    let mut connection = connect(&server_handle);
+
+    assert_reply(&mut connection, "set shared 0 0 3\r\nyes\r\n", "STORED\r\n");
+    let mut client = new_client(&server_handle.local_addr().to_string());
+    let response = client.get("shared").unwrap();
+    assert_eq!(response.message(), Some(b"yes".as_slice()));
+
+    client.set("native", "value").unwrap();
+    assert_reply(
+        &mut connection,
+        "get native\r\n",
+        "VALUE native 0 5\r\nvalue\r\nEND\r\n",
+    );
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn oversized_data_block_should_disconnect_the_client() {
+    let mut server = Server::new();
+    server.set_address("127.0.0.1:0".parse().unwrap());
+    server.set_memcached_address("127.0.0.1:0".parse().unwrap());
+    server.set_request_limits(skaja_lib::FrameLimits {
+        max_value_size: 4,
+        ..Default::default()
+    });
+    let server_handle = server.spawn().unwrap();
+    let mut connection = connect(&server_handle);
+
+    connection.write_all(b"set key 0 0 5\r\n").unwrap();
+    let mut reply = String::new();
+    connection.read_to_string(&mut reply).unwrap();
+    assert!(reply.starts_with("CLIENT_ERROR "));
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
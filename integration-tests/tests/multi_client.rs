@@ -8,18 +8,19 @@ pub fn client1_set_command_result_should_be_visible_to_client2() {
 
         client1
             .send(skaja_lib::Command::Set(
-                "hello".to_string(),
-                "world".to_string(),
+                b"hello".to_vec(),
+                b"world".to_vec(),
+                skaja_lib::SetOptions::default(),
             ))
             .unwrap();
 
         let response = client2
-            .send(skaja_lib::Command::Get("hello".to_string()))
+            .send(skaja_lib::Command::Get(b"hello".to_vec()))
             .unwrap();
 
         let status_code = response.status_code();
         assert_eq!(status_code, skaja_lib::StatusCodes::Ok);
-        assert_eq!(response.message(), Some("world"));
+        assert_eq!(response.message(), Some(b"world".as_slice()));
     })
 }
 
@@ -31,28 +32,65 @@ pub fn client1_del_command_result_should_be_visible_to_client2() {
 
         client1
             .send(skaja_lib::Command::Set(
-                "hello".to_string(),
-                "world".to_string(),
+                b"hello".to_vec(),
+                b"world".to_vec(),
+                skaja_lib::SetOptions::default(),
             ))
             .unwrap();
 
         let response = client2
-            .send(skaja_lib::Command::Get("hello".to_string()))
+            .send(skaja_lib::Command::Get(b"hello".to_vec()))
             .unwrap();
 
         let status_code = response.status_code();
         assert_eq!(status_code, skaja_lib::StatusCodes::Ok);
-        assert_eq!(response.message(), Some("world"));
+        assert_eq!(response.message(), Some(b"world".as_slice()));
 
         client1
-            .send(skaja_lib::Command::Delete("hello".to_string()))
+            .send(skaja_lib::Command::Delete(b"hello".to_vec()))
             .unwrap();
 
         let response = client2
-            .send(skaja_lib::Command::Get("hello".to_string()))
+            .send(skaja_lib::Command::Get(b"hello".to_vec()))
             .unwrap();
 
         let status_code = response.status_code();
         assert_eq!(status_code, skaja_lib::StatusCodes::ErrNotFound);
     })
 }
+
+#[test]
+pub fn reconnecting_clients_should_only_receive_their_own_responses() {
+    with_server(|server_address| {
+        let workers: Vec<_> = (0..8)
+            .map(|worker| {
+                let server_address = server_address.clone();
+                std::thread::spawn(move || {
+                    // Keep one client alive for the whole run, so its token would be
+                    // handed out to the reconnecting clients if tokens were reused.
+                    let mut long_lived = new_client(&server_address);
+                    let long_lived_key = format!("long-lived:{}", worker);
+                    long_lived.set(long_lived_key.clone(), "alive").unwrap();
+
+                    for round in 0..20 {
+                        let mut client = new_client(&server_address);
+                        let key = format!("worker:{}:round:{}", worker, round);
+                        let response = client.set(key.clone(), key.clone()).unwrap();
+                        assert_eq!(response.status_code(), skaja_lib::StatusCodes::Ok);
+
+                        let response = client.get(key.clone()).unwrap();
+                        assert_eq!(response.message(), Some(key.as_bytes()));
+                        drop(client);
+
+                        let response = long_lived.get(long_lived_key.clone()).unwrap();
+                        assert_eq!(response.message(), Some(b"alive".as_slice()));
+                    }
+                })
+            })
+            .collect();
+
+        for worker in workers {
+            worker.join().unwrap();
+        }
+    })
+}
@@ -0,0 +1,134 @@
+use integration_tests::test_utils::{
+    new_client, temp_path, with_server, with_server_config, with_server_threads,
+};
+use skaja_lib::StatusCodes;
+use std::{fs, thread};
+
+#[test]
+pub fn multi_key_commands_should_work_on_all_of_the_keys() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+
+        let response = client.mset([("a", "1"), ("b", "2"), ("c", "3")]).unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+
+        let values = client.mget(["a", "missing", "c"]).unwrap();
+        assert_eq!(values, vec![Some(b"1".to_vec()), None, Some(b"3".to_vec())]);
+
+        assert_eq!(client.exists(["a", "a", "missing"]).unwrap(), 2);
+        assert_eq!(client.delete_many(["a", "b", "missing"]).unwrap(), 2);
+        assert_eq!(client.exists(["a", "b", "c"]).unwrap(), 1);
+    });
+}
+
+#[test]
+pub fn msetnx_should_set_none_of_the_keys_if_any_exists() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+        client.set("taken", "old").unwrap();
+
+        assert!(!client.msetnx([("free", "new"), ("taken", "new")]).unwrap());
+        assert_eq!(
+            client.mget(["free", "taken"]).unwrap(),
+            vec![None, Some(b"old".to_vec())]
+        );
+
+        assert!(client.msetnx([("free", "new"), ("other", "new")]).unwrap());
+        assert_eq!(client.exists(["free", "other"]).unwrap(), 2);
+    });
+}
+
+#[test]
+pub fn concurrent_msetnx_on_the_same_keys_should_let_exactly_one_client_win() {
+    with_server_threads(4, |server_address| {
+        let keys: Vec<String> = (0..32).map(|i| format!("lock-{}", i)).collect();
+
+        let contenders: Vec<_> = (0..8)
+            .map(|contender| {
+                let server_address = server_address.clone();
+                // Each contender goes through the keys in a different order.
+                let mut keys = keys.clone();
+                keys.rotate_left(contender * 4);
+
+                thread::spawn(move || {
+                    let mut client = new_client(&server_address);
+                    let pairs = keys.into_iter().map(|key| (key, contender.to_string()));
+                    client.msetnx(pairs).unwrap()
+                })
+            })
+            .collect();
+
+        let winners: Vec<usize> = contenders
+            .into_iter()
+            .map(|contender| contender.join().unwrap())
+            .enumerate()
+            .filter(|(_, won)| *won)
+            .map(|(contender, _)| contender)
+            .collect();
+        assert_eq!(winners.len(), 1);
+
+        // All of the keys hold the value of the winner, none of them was set by the others.
+        let mut client = new_client(&server_address);
+        let winner = Some(winners[0].to_string().into_bytes());
+        assert!(client
+            .mget(keys)
+            .unwrap()
+            .into_iter()
+            .all(|value| value == winner));
+    });
+}
+
+#[test]
+pub fn multi_key_writes_should_survive_a_restart_with_append_only_file() {
+    let aof_path = temp_path("appendonly.aof");
+    let config_path = temp_path("config.toml");
+    let config = format!("[append_only]\npath = {:?}\nfsync = \"always\"\n", aof_path);
+    fs::write(&config_path, config).unwrap();
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        client.mset([("a", "1"), ("b", "2"), ("c", "3")]).unwrap();
+        client.delete_many(["b", "c"]).unwrap();
+        assert!(client.msetnx([("c", "4"), ("d", "5")]).unwrap());
+    });
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        assert_eq!(
+            client.mget(["a", "b", "c", "d"]).unwrap(),
+            vec![
+                Some(b"1".to_vec()),
+                None,
+                Some(b"4".to_vec()),
+                Some(b"5".to_vec())
+            ]
+        );
+    });
+
+    fs::remove_file(aof_path).unwrap();
+    fs::remove_file(config_path).unwrap();
+}
+
+#[test]
+pub fn multi_key_writes_that_change_nothing_should_not_be_logged() {
+    let aof_path = temp_path("appendonly.aof");
+    let config_path = temp_path("config.toml");
+    let config = format!("[append_only]\npath = {:?}\nfsync = \"always\"\n", aof_path);
+    fs::write(&config_path, config).unwrap();
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        client.mset([("a", "1"), ("b", "2")]).unwrap();
+        let logged = fs::metadata(&aof_path).unwrap().len();
+
+        assert!(!client.msetnx([("b", "3"), ("c", "4")]).unwrap());
+        assert_eq!(client.delete_many(["c", "d"]).unwrap(), 0);
+        assert_eq!(fs::metadata(&aof_path).unwrap().len(), logged);
+
+        assert_eq!(client.delete_many(["b", "c"]).unwrap(), 1);
+        assert!(fs::metadata(&aof_path).unwrap().len() > logged);
+    });
+
+    fs::remove_file(aof_path).unwrap();
+    fs::remove_file(config_path).unwrap();
+}
@@ -0,0 +1,68 @@
+use integration_tests::test_utils::{new_client, with_server_threads};
+use skaja_lib::{Command, SetOptions, StatusCodes};
+use std::thread;
+
+#[test]
+pub fn writes_from_clients_on_different_threads_should_be_visible_to_each_other() {
+    with_server_threads(4, |server_address| {
+        // Consecutive connections are handed to different threads.
+        let mut clients: Vec<_> = (0..4).map(|_| new_client(&server_address)).collect();
+
+        for (i, client) in clients.iter_mut().enumerate() {
+            let response = client.set(format!("key-{}", i), format!("value-{}", i));
+            assert_eq!(response.unwrap().status_code(), StatusCodes::Ok);
+        }
+
+        for client in clients.iter_mut() {
+            for i in 0..4 {
+                let response = client.get(format!("key-{}", i)).unwrap();
+                let value = format!("value-{}", i).into_bytes();
+                assert_eq!(response.status_code(), StatusCodes::Ok);
+                assert_eq!(response.message(), Some(value.as_slice()));
+            }
+        }
+
+        let response = clients[3].delete("key-0").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+        let response = clients[0].get("key-0").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::ErrNotFound);
+    });
+}
+
+#[test]
+pub fn concurrent_clients_should_each_get_their_own_responses() {
+    with_server_threads(4, |server_address| {
+        let workers: Vec<_> = (0..16)
+            .map(|worker| {
+                let server_address = server_address.clone();
+                thread::spawn(move || {
+                    let mut client = new_client(&server_address);
+
+                    let commands = (0..100).flat_map(|i| {
+                        let key = format!("worker:{}:key:{}", worker, i).into_bytes();
+                        [
+                            Command::Set(key.clone(), key.clone(), SetOptions::default()),
+                            Command::Get(key),
+                        ]
+                    });
+                    let responses = client.pipeline(commands).unwrap();
+
+                    for (i, responses) in responses.chunks(2).enumerate() {
+                        let key = format!("worker:{}:key:{}", worker, i).into_bytes();
+                        assert_eq!(responses[0].status_code(), StatusCodes::Ok);
+                        assert_eq!(responses[1].message(), Some(key.as_slice()));
+                    }
+                })
+            })
+            .collect();
+
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        // Everything written by the other clients is visible to a new one.
+        let mut client = new_client(&server_address);
+        let response = client.get("worker:15:key:99").unwrap();
+        assert_eq!(response.message(), Some(b"worker:15:key:99".as_slice()));
+    });
+}
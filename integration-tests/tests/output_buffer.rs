@@ -0,0 +1,114 @@
+use integration_tests::test_utils::{new_client, temp_path, with_server, with_server_config};
+use skaja_lib::{Command, Extract, Request, StatusCodes};
+use std::{
+    fs,
+    io::{Read, Write},
+    net::TcpStream,
+    path::PathBuf,
+    thread,
+    time::Duration,
+};
+
+fn output_buffer_config(limits: &str) -> PathBuf {
+    let config_path = temp_path("config.toml");
+    fs::write(&config_path, format!("[output_buffer]\n{}\n", limits)).unwrap();
+    config_path
+}
+
+/// Send `count` GET requests for the key in a single write, without reading the responses.
+fn send_gets(server_address: &str, key: &[u8], count: usize) -> TcpStream {
+    let request: Request = Command::Get(key.to_vec()).extract().unwrap();
+    let payload = request.payload().repeat(count);
+
+    let mut connection = TcpStream::connect(server_address).unwrap();
+    connection.write_all(&payload).unwrap();
+    connection
+}
+
+/// Read until the server closes the connection, returns the number of bytes read.
+fn read_until_closed(connection: &mut TcpStream) -> usize {
+    connection
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+
+    let mut chunk = vec![0u8; 64 * 1024];
+    let mut total = 0;
+    loop {
+        match connection.read(&mut chunk) {
+            Ok(0) => return total,
+            Ok(n) => total += n,
+            Err(e) if e.kind() == std::io::ErrorKind::ConnectionReset => return total,
+            Err(e) => panic!("Connection wasn't closed by the server: {}", e),
+        }
+    }
+}
+
+#[test]
+pub fn response_bigger_than_socket_buffer_should_reach_slow_reader() {
+    with_server(|server_address| {
+        let value = b"v".repeat(8 * 1024 * 1024);
+        let mut client = new_client(&server_address);
+        client.set("big", value.clone()).unwrap();
+
+        let mut connection = send_gets(&server_address, b"big", 1);
+
+        // The server can't write the whole response at once while nobody's reading.
+        thread::sleep(Duration::from_millis(300));
+
+        let mut headers = [0u8; 8];
+        connection.read_exact(&mut headers).unwrap();
+        let status = u32::from_le_bytes(headers[..4].try_into().unwrap());
+        let len = u32::from_le_bytes(headers[4..].try_into().unwrap()) as usize;
+        assert_eq!(status, u32::from(StatusCodes::Ok));
+        assert_eq!(len, value.len());
+
+        let mut message = vec![0u8; len];
+        connection.read_exact(&mut message).unwrap();
+        assert!(message == value);
+    });
+}
+
+#[test]
+pub fn exceeding_hard_limit_should_disconnect_the_client() {
+    let config_path = output_buffer_config("hard_limit = 1048576\nsoft_limit = 0");
+
+    with_server_config(&config_path, |server_address| {
+        let value = b"v".repeat(256 * 1024);
+        let mut client = new_client(&server_address);
+        client.set("big", value.clone()).unwrap();
+
+        let mut connection = send_gets(&server_address, b"big", 64);
+        let read = read_until_closed(&mut connection);
+        assert!(read < value.len() * 64);
+
+        // Other clients aren't affected.
+        let response = client.get("big").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+        assert_eq!(response.message(), Some(value.as_slice()));
+    });
+
+    fs::remove_file(config_path).unwrap();
+}
+
+#[test]
+pub fn exceeding_soft_limit_for_too_long_should_disconnect_the_client() {
+    let config_path =
+        output_buffer_config("hard_limit = 0\nsoft_limit = 1048576\nsoft_seconds = 1");
+
+    with_server_config(&config_path, |server_address| {
+        let value = b"v".repeat(1024 * 1024);
+        let mut client = new_client(&server_address);
+        client.set("big", value.clone()).unwrap();
+
+        let mut connection = send_gets(&server_address, b"big", 64);
+        thread::sleep(Duration::from_millis(1500));
+
+        let read = read_until_closed(&mut connection);
+        assert!(read < value.len() * 64);
+
+        let response = client.get("big").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+    });
+
+    fs::remove_file(config_path).unwrap();
+}
@@ -0,0 +1,280 @@
+use integration_tests::test_utils::{new_client, temp_path, with_server_config};
+use skaja_lib::{Command, Expiry, Extract, Request, SetOptions, StatusCodes};
+use skaja_server::{config::Config, Server};
+use std::{fs, io, path::PathBuf};
+
+fn append_only_config(fsync: &str) -> (PathBuf, PathBuf) {
+    let aof_path = temp_path("appendonly.aof");
+    let config_path = temp_path("config.toml");
+    let config = format!(
+        "[append_only]\npath = {:?}\nfsync = \"{}\"\n",
+        aof_path, fsync
+    );
+    fs::write(&config_path, config).unwrap();
+
+    (config_path, aof_path)
+}
+
+#[test]
+pub fn writes_should_survive_a_restart_with_append_only_file() {
+    let (config_path, aof_path) = append_only_config("always");
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        client.set("hello", "world").unwrap();
+        client.set("gone", "soon").unwrap();
+        client.delete("gone").unwrap();
+        client
+            .set_with_expiry("session", "data", Expiry::Seconds(100))
+            .unwrap();
+    });
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        let response = client.get("hello").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+        assert_eq!(response.message(), Some(b"world".as_slice()));
+
+        let response = client.get("gone").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::ErrNotFound);
+
+        let response = client.ttl("session").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+        let ttl: u64 = String::from_utf8_lossy(response.message().unwrap())
+            .parse()
+            .unwrap();
+        assert!(ttl > 90 && ttl <= 100);
+    });
+
+    fs::remove_file(config_path).unwrap();
+    fs::remove_file(aof_path).unwrap();
+}
+
+#[test]
+pub fn incomplete_command_at_the_end_of_append_only_file_should_be_discarded() {
+    let (config_path, aof_path) = append_only_config("everysec");
+
+    let mut command = Command::Set(b"hello".to_vec(), b"world".to_vec(), SetOptions::default());
+    let request: Request = command.extract().unwrap();
+    let mut aof = request.payload().to_vec();
+    aof.extend_from_slice(&request.payload()[..7]);
+    fs::write(&aof_path, aof).unwrap();
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        let response = client.get("hello").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+        assert_eq!(response.message(), Some(b"world".as_slice()));
+
+        client.set("after", "restart").unwrap();
+    });
+
+    // The write might not have been fsynced yet, but the append-only file
+    // is synced when the server shuts down so it's not lost.
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        let response = client.get("after").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+        assert_eq!(response.message(), Some(b"restart".as_slice()));
+    });
+
+    fs::remove_file(config_path).unwrap();
+    fs::remove_file(aof_path).unwrap();
+}
+
+// Every write to `/dev/full` fails as if the disk was full.
+#[cfg(target_os = "linux")]
+#[test]
+pub fn failing_to_write_the_append_only_file_should_only_fail_the_command() {
+    let config_path = temp_path("config.toml");
+    let config = "[append_only]\npath = \"/dev/full\"\nfsync = \"no\"\n";
+    fs::write(&config_path, config).unwrap();
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        let response = client.set("hello", "world").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::ErrInternal);
+        let msg = String::from_utf8_lossy(response.message().unwrap());
+        assert!(msg.starts_with("Failed writing to append-only file"));
+
+        // The server keeps serving this client and the others.
+        let response = client.get("missing").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::ErrNotFound);
+
+        let mut other_client = new_client(&server_address);
+        let response = other_client.get("missing").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::ErrNotFound);
+    });
+
+    fs::remove_file(config_path).unwrap();
+}
+
+fn snapshot_config(save_rules: &str) -> (PathBuf, PathBuf) {
+    let snapshot_path = temp_path("dump.skaja");
+    let config_path = temp_path("config.toml");
+    let config = format!(
+        "[snapshot]\npath = {:?}\nsave = [{}]\n",
+        snapshot_path, save_rules
+    );
+    fs::write(&config_path, config).unwrap();
+
+    (config_path, snapshot_path)
+}
+
+#[test]
+pub fn saved_snapshot_should_be_loaded_on_restart() {
+    let (config_path, snapshot_path) = snapshot_config("");
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        client.set("hello", "world").unwrap();
+        client.set(vec![0, 255], vec![1, 2, 3]).unwrap();
+        client
+            .set_with_expiry("session", "data", Expiry::Seconds(100))
+            .unwrap();
+        client
+            .set_with_expiry("expired", "data", Expiry::Milliseconds(1))
+            .unwrap();
+
+        let response = client.save().unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+    });
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        let response = client.get("hello").unwrap();
+        assert_eq!(response.message(), Some(b"world".as_slice()));
+
+        let response = client.get(vec![0, 255]).unwrap();
+        assert_eq!(response.message(), Some([1, 2, 3].as_slice()));
+
+        let response = client.ttl("session").unwrap();
+        let ttl: u64 = String::from_utf8_lossy(response.message().unwrap())
+            .parse()
+            .unwrap();
+        assert!(ttl > 90 && ttl <= 100);
+
+        let response = client.get("expired").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::ErrNotFound);
+    });
+
+    fs::remove_file(config_path).unwrap();
+    fs::remove_file(snapshot_path).unwrap();
+}
+
+#[test]
+pub fn background_save_should_write_snapshot_without_blocking() {
+    let (config_path, snapshot_path) = snapshot_config("");
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        for i in 0..1000 {
+            client.set(format!("key:{}", i), "value").unwrap();
+        }
+
+        let response = client.bgsave().unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+
+        // The server keeps serving requests while saving.
+        let response = client.get("key:999").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+
+        wait_for_file(&snapshot_path);
+    });
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        let response = client.get("key:500").unwrap();
+        assert_eq!(response.message(), Some(b"value".as_slice()));
+    });
+
+    fs::remove_file(config_path).unwrap();
+    fs::remove_file(snapshot_path).unwrap();
+}
+
+#[test]
+pub fn save_should_be_refused_while_a_background_save_is_in_progress() {
+    let (config_path, snapshot_path) = snapshot_config("");
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        client.set("hello", "world").unwrap();
+
+        // They are all handled before the server gets to notice the background save finished.
+        let responses = client
+            .pipeline(vec![Command::BgSave, Command::Save, Command::BgSave])
+            .unwrap();
+        assert_eq!(responses[0].status_code(), StatusCodes::Ok);
+        assert_eq!(responses[1].status_code(), StatusCodes::ErrConflict);
+        assert_eq!(responses[2].status_code(), StatusCodes::ErrConflict);
+
+        wait_for_file(&snapshot_path);
+    });
+
+    fs::remove_file(config_path).unwrap();
+    fs::remove_file(snapshot_path).unwrap();
+}
+
+#[test]
+pub fn snapshot_of_an_unknown_version_should_be_refused() {
+    let (config_path, snapshot_path) = snapshot_config("");
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        client.set("hello", "world").unwrap();
+        client.save().unwrap();
+    });
+
+    // The version comes right after the magic bytes.
+    let mut snapshot = fs::read(&snapshot_path).unwrap();
+    snapshot[5..9].copy_from_slice(&u32::MAX.to_le_bytes());
+    fs::write(&snapshot_path, snapshot).unwrap();
+
+    let mut server = Server::new();
+    server
+        .apply_config(Config::load(&config_path).unwrap())
+        .unwrap();
+    server.set_address("127.0.0.1:0".parse().unwrap());
+    let Err(e) = server.spawn() else {
+        panic!("Server started with a snapshot of an unknown version.");
+    };
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    assert!(e.to_string().contains(&format!("version {}", u32::MAX)));
+
+    fs::remove_file(config_path).unwrap();
+    fs::remove_file(snapshot_path).unwrap();
+}
+
+#[test]
+pub fn snapshot_should_be_saved_when_a_save_rule_is_satisfied() {
+    let (config_path, snapshot_path) = snapshot_config("{ seconds = 0, writes = 2 }");
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        client.set("hello", "world").unwrap();
+        client.set("foo", "bar").unwrap();
+
+        wait_for_file(&snapshot_path);
+    });
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        let response = client.get("foo").unwrap();
+        assert_eq!(response.message(), Some(b"bar".as_slice()));
+    });
+
+    fs::remove_file(config_path).unwrap();
+    fs::remove_file(snapshot_path).unwrap();
+}
+
+fn wait_for_file(path: &std::path::Path) {
+    for _ in 0..50 {
+        if path.exists() {
+            return;
+        }
+
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+
+    panic!("{} was never written", path.display());
+}
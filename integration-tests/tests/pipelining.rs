@@ -0,0 +1,109 @@
+use integration_tests::test_utils::{new_client, with_server};
+use skaja_lib::{Command, SetOptions, StatusCodes};
+
+#[test]
+pub fn pipelined_commands_should_be_answered_in_order() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+
+        let mut commands = Vec::new();
+        for i in 0..200 {
+            let key = format!("key-{}", i).into_bytes();
+            let value = format!("value-{}", i).into_bytes();
+            commands.push(Command::Set(key.clone(), value, SetOptions::default()));
+            commands.push(Command::Get(key.clone()));
+            commands.push(Command::Delete(key.clone()));
+            commands.push(Command::Get(key));
+        }
+
+        let responses = client.pipeline(commands).unwrap();
+        assert_eq!(responses.len(), 800);
+
+        for (i, responses) in responses.chunks(4).enumerate() {
+            let value = format!("value-{}", i).into_bytes();
+            assert_eq!(responses[0].status_code(), StatusCodes::Ok);
+            assert_eq!(responses[1].status_code(), StatusCodes::Ok);
+            assert_eq!(responses[1].message(), Some(value.as_slice()));
+            assert_eq!(responses[2].status_code(), StatusCodes::Ok);
+            assert_eq!(responses[3].status_code(), StatusCodes::ErrNotFound);
+        }
+
+        // The connection is still usable for regular requests afterwards.
+        let response = client.set("hello", "world").unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+        let response = client.get("hello").unwrap();
+        assert_eq!(response.message(), Some(b"world".as_slice()));
+    });
+}
+
+#[test]
+pub fn requests_sent_in_a_single_write_should_all_be_answered() {
+    use skaja_lib::{Extract, Request, Response, ResponseDecoder};
+    use std::io::Write;
+
+    with_server(|server_address| {
+        let set_request: Request =
+            Command::Set(b"hello".to_vec(), b"world".to_vec(), SetOptions::default())
+                .extract()
+                .unwrap();
+        let get_request: Request = Command::Get(b"hello".to_vec()).extract().unwrap();
+
+        let mut payload = set_request.payload().to_vec();
+        payload.extend_from_slice(get_request.payload());
+
+        // A blocking stream, so reading waits until the responses arrive.
+        let mut connection = std::net::TcpStream::connect(&server_address).unwrap();
+        connection.write_all(&payload).unwrap();
+
+        let mut decoder = ResponseDecoder::new();
+        let mut responses: Vec<Response> = Vec::new();
+        let mut chunk = [0u8; 1024];
+        while responses.len() < 2 {
+            let n = std::io::Read::read(&mut connection, &mut chunk).unwrap();
+            assert_ne!(n, 0, "Connection closed before all responses arrived.");
+            decoder.feed(&chunk[..n]);
+            while let Some(response) = decoder.decode() {
+                responses.push(response.into());
+            }
+        }
+
+        assert_eq!(responses[0].status_code(), StatusCodes::Ok);
+        assert_eq!(responses[1].status_code(), StatusCodes::Ok);
+        assert_eq!(responses[1].message(), Some(b"world".as_slice()));
+    });
+}
+
+#[test]
+pub fn requests_sent_before_closing_the_write_side_should_be_answered() {
+    use skaja_lib::{Extract, Request, Response, ResponseDecoder};
+    use std::{io::Write, net::Shutdown};
+
+    with_server(|server_address| {
+        let set_request: Request =
+            Command::Set(b"hello".to_vec(), b"world".to_vec(), SetOptions::default())
+                .extract()
+                .unwrap();
+        let get_request: Request = Command::Get(b"hello".to_vec()).extract().unwrap();
+
+        let mut payload = set_request.payload().to_vec();
+        payload.extend_from_slice(get_request.payload());
+
+        let mut connection = std::net::TcpStream::connect(&server_address).unwrap();
+        connection.write_all(&payload).unwrap();
+        connection.shutdown(Shutdown::Write).unwrap();
+
+        // The server answers everything it got, then closes the connection.
+        let mut bytes = Vec::new();
+        std::io::Read::read_to_end(&mut connection, &mut bytes).unwrap();
+
+        let mut decoder = ResponseDecoder::new();
+        decoder.feed(&bytes);
+        let responses: Vec<Response> = std::iter::from_fn(|| decoder.decode())
+            .map(Response::from)
+            .collect();
+
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0].status_code(), StatusCodes::Ok);
+        assert_eq!(responses[1].message(), Some(b"world".as_slice()));
+    });
+}
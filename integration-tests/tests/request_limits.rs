@@ -0,0 +1,100 @@
+use integration_tests::test_utils::{new_client, temp_path, with_server_config};
+use skaja_lib::{Command, Extract, Request, Response, ResponseDecoder, StatusCodes};
+use std::{
+    fs,
+    io::{Read, Write},
+    net::TcpStream,
+    path::PathBuf,
+    time::Duration,
+};
+
+fn request_limits_config(limits: &str) -> PathBuf {
+    let config_path = temp_path("config.toml");
+    fs::write(&config_path, format!("[request_limits]\n{}\n", limits)).unwrap();
+    config_path
+}
+
+/// Read the responses until the server closes the connection, or until `max` are read.
+fn read_responses(connection: &mut TcpStream, max: usize) -> Vec<Response> {
+    connection
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+
+    let mut decoder = ResponseDecoder::new();
+    let mut responses = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while responses.len() < max {
+        match connection.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => decoder.feed(&chunk[..n]),
+            Err(e) if e.kind() == std::io::ErrorKind::ConnectionReset => break,
+            Err(e) => panic!("Connection wasn't closed by the server: {}", e),
+        }
+
+        responses.extend(std::iter::from_fn(|| decoder.decode().map(Response::from)));
+    }
+
+    responses
+}
+
+#[test]
+pub fn hostile_length_header_should_be_answered_and_disconnected() {
+    let config_path = request_limits_config("max_value_size = 1024");
+
+    with_server_config(&config_path, |server_address| {
+        let mut connection = TcpStream::connect(&server_address).unwrap();
+
+        // A complete request first, it's answered before the hostile one.
+        let request: Request = Command::Get(b"key".to_vec()).extract().unwrap();
+        connection.write_all(request.payload()).unwrap();
+
+        // "set key <4 GiB value>", without ever sending the value.
+        let mut payload = 3_u32.to_le_bytes().to_vec();
+        for part in [b"set".as_slice(), b"key".as_slice()] {
+            payload.extend_from_slice(&(part.len() as u32).to_le_bytes());
+            payload.extend_from_slice(part);
+        }
+        payload.extend_from_slice(&u32::MAX.to_le_bytes());
+        connection.write_all(&payload).unwrap();
+
+        let responses = read_responses(&mut connection, usize::MAX);
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0].status_code(), StatusCodes::ErrNotFound);
+        assert_eq!(responses[1].status_code(), StatusCodes::ErrPayloadTooLarge);
+
+        // Other clients aren't affected.
+        let mut client = new_client(&server_address);
+        assert_eq!(
+            client.set("key", "value").unwrap().status_code(),
+            StatusCodes::Ok
+        );
+    });
+
+    fs::remove_file(config_path).unwrap();
+}
+
+#[test]
+pub fn requests_larger_than_the_read_buffer_should_still_be_served() {
+    let config_path = request_limits_config("max_request_size = 64");
+
+    with_server_config(&config_path, |server_address| {
+        // Way more than a request's worth of bytes in a single write.
+        let request: Request = Command::Set(b"k".to_vec(), b"v".to_vec(), Default::default())
+            .extract()
+            .unwrap();
+        let payload = request.payload().repeat(1000);
+
+        let mut connection = TcpStream::connect(&server_address).unwrap();
+        connection.write_all(&payload).unwrap();
+
+        let responses = read_responses(&mut connection, 1000);
+        assert_eq!(responses.len(), 1000);
+        assert!(responses.iter().all(|r| r.status_code() == StatusCodes::Ok));
+
+        let mut client = new_client(&server_address);
+        let response = client.set("key", "v".repeat(100)).unwrap();
+        assert_eq!(response.status_code(), StatusCodes::ErrPayloadTooLarge);
+    });
+
+    fs::remove_file(config_path).unwrap();
+}
@@ -0,0 +1,493 @@
+use integration_tests::test_utils::new_client;
+use skaja_server::{Server, ServerHandle};
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+fn spawn_resp_server() -> ServerHandle {
+    let mut server = Server::new();
+    server.set_address("127.0.0.1:0".parse().unwrap());
+    server.set_resp_address("127.0.0.1:0".parse().unwrap());
+    server.spawn().expect("Failed to start server")
+}
+
+fn connect(server_handle: &ServerHandle) -> TcpStream {
+    let connection = TcpStream::connect(server_handle.resp_addr().unwrap()).unwrap();
+    connection
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    connection
+}
+
+/// Encode the arguments as a RESP array of bulk strings, the way Redis clients send commands.
+fn command(args: &[&str]) -> Vec<u8> {
+    let mut out = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        out.extend_from_slice(format!("${}\r\n{}\r\n", arg.len(), arg).as_bytes());
+    }
+    out
+}
+
+/// Send the request and assert that exactly the expected reply comes back.
+fn assert_reply(connection: &mut TcpStream, request: &[u8], expected: &str) {
+    connection.write_all(request).unwrap();
+
+    let mut reply = vec![0u8; expected.len()];
+    connection.read_exact(&mut reply).unwrap();
+    assert_eq!(String::from_utf8_lossy(&reply), expected);
+}
+
+#[test]
+pub fn resp_commands_should_be_answered_like_redis() {
+    let server_handle = spawn_resp_server();
+    let mut connection = connect(&server_handle);
+
+    assert_reply(&mut connection, &command(&["PING"]), "+PONG\r\n");
+    assert_reply(&mut connection, &command(&["ECHO", "hi"]), "$2\r\nhi\r\n");
+    assert_reply(&mut connection, &command(&["SET", "k", "v"]), "+OK\r\n");
+    assert_reply(&mut connection, &command(&["GET", "k"]), "$1\r\nv\r\n");
+    assert_reply(&mut connection, &command(&["GET", "missing"]), "$-1\r\n");
+    assert_reply(&mut connection, &command(&["TTL", "k"]), ":-1\r\n");
+    assert_reply(&mut connection, &command(&["TTL", "missing"]), ":-2\r\n");
+    assert_reply(&mut connection, &command(&["EXPIRE", "k", "100"]), ":1\r\n");
+    assert_reply(&mut connection, &command(&["TTL", "k"]), ":100\r\n");
+    assert_reply(&mut connection, &command(&["DEL", "k"]), ":1\r\n");
+    assert_reply(&mut connection, &command(&["DEL", "k"]), ":0\r\n");
+    assert_reply(
+        &mut connection,
+        &command(&["NOPE"]),
+        "-ERR Unknown command \"nope\"\r\n",
+    );
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn resp_and_native_clients_should_share_the_keyspace() {
+    let server_handle = spawn_resp_server();
+    let mut connection = connect(&server_handle);
+
+    assert_reply(
+        &mut connection,
+        &command(&["SET", "shared", "yes"]),
+        "+OK\r\n",
+    );
+    let mut client = new_client(&server_handle.local_addr().to_string());
+    let response = client.get("shared").unwrap();
+    assert_eq!(response.message(), Some(b"yes".as_slice()));
+
+    client.set("shared:2", "too").unwrap();
+    assert_reply(
+        &mut connection,
+        &command(&["KEYS", "shared*"]),
+        "*2\r\n$6\r\nshared\r\n$8\r\nshared:2\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["KEYS", "s?ared"]),
+        "-ERR only prefix patterns are supported\r\n",
+    );
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn multi_key_commands_should_be_answered_like_redis() {
+    let server_handle = spawn_resp_server();
+    let mut connection = connect(&server_handle);
+
+    assert_reply(
+        &mut connection,
+        &command(&["MSET", "a", "1", "b", "2"]),
+        "+OK\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["MGET", "a", "missing", "b"]),
+        "*3\r\n$1\r\n1\r\n$-1\r\n$1\r\n2\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["MSETNX", "a", "3", "c", "3"]),
+        ":0\r\n",
+    );
+    assert_reply(&mut connection, &command(&["EXISTS", "a", "c"]), ":1\r\n");
+    assert_reply(&mut connection, &command(&["DEL", "a", "b", "c"]), ":2\r\n");
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn conditional_writes_should_be_answered_like_redis() {
+    let server_handle = spawn_resp_server();
+    let mut connection = connect(&server_handle);
+
+    assert_reply(
+        &mut connection,
+        &command(&["SET", "k", "v1", "XX"]),
+        "$-1\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["SET", "k", "v1", "NX", "EX", "100"]),
+        "+OK\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["SET", "k", "v2", "NX"]),
+        "$-1\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["GETSET", "k", "v2"]),
+        "$2\r\nv1\r\n",
+    );
+    assert_reply(&mut connection, &command(&["GETDEL", "k"]), "$2\r\nv2\r\n");
+    assert_reply(&mut connection, &command(&["GETDEL", "k"]), "$-1\r\n");
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn hash_commands_should_be_answered_like_redis() {
+    let server_handle = spawn_resp_server();
+    let mut connection = connect(&server_handle);
+
+    assert_reply(
+        &mut connection,
+        &command(&["HSET", "user", "name", "ada"]),
+        ":1\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["HGET", "user", "name"]),
+        "$3\r\nada\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["HGET", "user", "age"]),
+        "$-1\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["HMGET", "user", "name", "age"]),
+        "*2\r\n$3\r\nada\r\n$-1\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["HGETALL", "user"]),
+        "*2\r\n$4\r\nname\r\n$3\r\nada\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["HEXISTS", "user", "name"]),
+        ":1\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["HEXISTS", "user", "age"]),
+        ":0\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["HINCRBY", "user", "age", "36"]),
+        ":36\r\n",
+    );
+    assert_reply(&mut connection, &command(&["HLEN", "user"]), ":2\r\n");
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn set_commands_should_be_answered_like_redis() {
+    let server_handle = spawn_resp_server();
+    let mut connection = connect(&server_handle);
+
+    assert_reply(
+        &mut connection,
+        &command(&["SADD", "a", "x", "y"]),
+        ":2\r\n",
+    );
+    assert_reply(&mut connection, &command(&["SADD", "b", "y"]), ":1\r\n");
+    assert_reply(
+        &mut connection,
+        &command(&["SISMEMBER", "a", "x"]),
+        ":1\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["SINTER", "a", "b"]),
+        "*1\r\n$1\r\ny\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["SDIFFSTORE", "c", "a", "b"]),
+        ":1\r\n",
+    );
+    assert_reply(&mut connection, &command(&["SPOP", "c"]), "$1\r\nx\r\n");
+    assert_reply(&mut connection, &command(&["SPOP", "c"]), "$-1\r\n");
+    assert_reply(&mut connection, &command(&["SCARD", "a"]), ":2\r\n");
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn sorted_set_commands_should_be_answered_like_redis() {
+    let server_handle = spawn_resp_server();
+    let mut connection = connect(&server_handle);
+
+    assert_reply(
+        &mut connection,
+        &command(&["ZADD", "board", "10", "ada", "5", "bob"]),
+        ":2\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["ZINCRBY", "board", "2.5", "bob"]),
+        "$3\r\n7.5\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["ZREVRANGE", "board", "0", "-1", "WITHSCORES"]),
+        "*4\r\n$3\r\nada\r\n$2\r\n10\r\n$3\r\nbob\r\n$3\r\n7.5\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["ZRANGEBYSCORE", "board", "(7.5", "+inf"]),
+        "*1\r\n$3\r\nada\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["ZRANK", "board", "ada"]),
+        ":1\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["ZRANK", "board", "cy"]),
+        "$-1\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["ZSCORE", "board", "cy"]),
+        "$-1\r\n",
+    );
+    assert_reply(&mut connection, &command(&["ZCARD", "board"]), ":2\r\n");
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn stream_commands_should_be_answered_like_redis() {
+    let server_handle = spawn_resp_server();
+    let mut connection = connect(&server_handle);
+
+    assert_reply(
+        &mut connection,
+        &command(&["XADD", "events", "1-1", "type", "click"]),
+        "$3\r\n1-1\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["XADD", "events", "1-1", "type", "view"]),
+        "-ERR The ID must be higher than the one of the last entry\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["XRANGE", "events", "-", "+"]),
+        "*1\r\n*2\r\n$3\r\n1-1\r\n*2\r\n$4\r\ntype\r\n$5\r\nclick\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["XGROUP", "CREATE", "events", "workers", "0"]),
+        "+OK\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&[
+            "XREADGROUP",
+            "GROUP",
+            "workers",
+            "ada",
+            "STREAMS",
+            "events",
+            ">",
+        ]),
+        "*1\r\n*2\r\n$6\r\nevents\r\n\
+         *1\r\n*2\r\n$3\r\n1-1\r\n*2\r\n$4\r\ntype\r\n$5\r\nclick\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&[
+            "XREADGROUP",
+            "GROUP",
+            "workers",
+            "ada",
+            "STREAMS",
+            "events",
+            ">",
+        ]),
+        "$-1\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["XPENDING", "events", "workers"]),
+        "*4\r\n:1\r\n$3\r\n1-1\r\n$3\r\n1-1\r\n*1\r\n*2\r\n$3\r\nada\r\n$1\r\n1\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["XACK", "events", "workers", "1-1", "2-0"]),
+        ":1\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["XPENDING", "events", "workers"]),
+        "*4\r\n:0\r\n$-1\r\n$-1\r\n$-1\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["XACK", "events", "nope", "1-1"]),
+        "-NOGROUP No such key or consumer group\r\n",
+    );
+    assert_reply(&mut connection, &command(&["XLEN", "events"]), ":1\r\n");
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn list_commands_should_be_answered_like_redis() {
+    let server_handle = spawn_resp_server();
+    let mut connection = connect(&server_handle);
+
+    assert_reply(
+        &mut connection,
+        &command(&["RPUSH", "list", "a", "b", "c"]),
+        ":3\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["LRANGE", "list", "0", "-1"]),
+        "*3\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\nc\r\n",
+    );
+    assert_reply(&mut connection, &command(&["LPOP", "list"]), "$1\r\na\r\n");
+    assert_reply(
+        &mut connection,
+        &command(&["RPOP", "list", "5"]),
+        "*2\r\n$1\r\nc\r\n$1\r\nb\r\n",
+    );
+    assert_reply(&mut connection, &command(&["LPOP", "list"]), "$-1\r\n");
+    assert_reply(&mut connection, &command(&["SET", "k", "v"]), "+OK\r\n");
+    assert_reply(
+        &mut connection,
+        &command(&["LPUSH", "k", "a"]),
+        "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n",
+    );
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn counter_commands_should_be_answered_like_redis() {
+    let server_handle = spawn_resp_server();
+    let mut connection = connect(&server_handle);
+
+    assert_reply(&mut connection, &command(&["INCR", "n"]), ":1\r\n");
+    assert_reply(&mut connection, &command(&["INCRBY", "n", "9"]), ":10\r\n");
+    assert_reply(&mut connection, &command(&["DECRBY", "n", "3"]), ":7\r\n");
+    assert_reply(&mut connection, &command(&["DECR", "n"]), ":6\r\n");
+    assert_reply(
+        &mut connection,
+        &command(&["INCRBYFLOAT", "n", "0.5"]),
+        "$3\r\n6.5\r\n",
+    );
+    assert_reply(
+        &mut connection,
+        &command(&["INCR", "n"]),
+        "-ERR Value is not an integer or out of range\r\n",
+    );
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn hello_3_should_switch_to_resp3() {
+    let server_handle = spawn_resp_server();
+    let mut connection = connect(&server_handle);
+
+    connection.write_all(&command(&["HELLO", "3"])).unwrap();
+    let mut header = [0u8; 4];
+    connection.read_exact(&mut header).unwrap();
+    assert_eq!(&header, b"%6\r\n");
+
+    // The rest of the map, up to the last field which is an empty array.
+    let mut map = Vec::new();
+    let mut byte = [0u8; 1];
+    while !map.ends_with(b"*0\r\n") {
+        connection.read_exact(&mut byte).unwrap();
+        map.push(byte[0]);
+    }
+    assert!(String::from_utf8_lossy(&map).contains(":3\r\n"));
+
+    assert_reply(&mut connection, &command(&["GET", "missing"]), "_\r\n");
+    assert_reply(
+        &mut connection,
+        &command(&["HELLO", "4"]),
+        "-NOPROTO unsupported protocol version\r\n",
+    );
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn inline_and_pipelined_commands_should_be_answered_in_order() {
+    let server_handle = spawn_resp_server();
+    let mut connection = connect(&server_handle);
+
+    let mut pipeline = b"SET a 1\r\n".to_vec();
+    pipeline.extend(command(&["GET", "a"]));
+    pipeline.extend_from_slice(b"GET b\r\n");
+    assert_reply(&mut connection, &pipeline, "+OK\r\n$1\r\n1\r\n$-1\r\n");
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn quit_should_close_the_connection() {
+    let server_handle = spawn_resp_server();
+    let mut connection = connect(&server_handle);
+
+    assert_reply(&mut connection, &command(&["QUIT"]), "+OK\r\n");
+    let mut rest = Vec::new();
+    connection.read_to_end(&mut rest).unwrap();
+    assert!(rest.is_empty());
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn malformed_resp_should_be_answered_with_a_protocol_error() {
+    let server_handle = spawn_resp_server();
+    let mut connection = connect(&server_handle);
+
+    connection.write_all(b"*1\r\n$x\r\n").unwrap();
+    let mut reply = Vec::new();
+    connection.read_to_end(&mut reply).unwrap();
+    assert!(reply.starts_with(b"-ERR Protocol error"));
+
+    server_handle.shutdown().unwrap();
+    server_handle.join().unwrap();
+}
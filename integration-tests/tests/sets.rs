@@ -0,0 +1,260 @@
+use integration_tests::test_utils::{
+    new_client, temp_path, with_server, with_server_config, with_server_threads,
+};
+use skaja_lib::{Command, StatusCodes};
+use std::{collections::HashSet, fs, sync::Mutex, thread};
+
+const CLIENTS: usize = 8;
+const MEMBERS: usize = 100;
+
+fn members(members: &[&str]) -> HashSet<Vec<u8>> {
+    members
+        .iter()
+        .map(|member| member.as_bytes().to_vec())
+        .collect()
+}
+
+#[test]
+pub fn members_should_be_added_checked_and_removed() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+
+        assert_eq!(client.sadd("flag:beta", ["ada", "bob", "ada"]).unwrap(), 2);
+        assert_eq!(client.sadd("flag:beta", ["bob", "cy"]).unwrap(), 1);
+        assert_eq!(
+            client.smembers("flag:beta").unwrap(),
+            members(&["ada", "bob", "cy"])
+        );
+        assert_eq!(client.scard("flag:beta").unwrap(), 3);
+        assert!(client.sismember("flag:beta", "bob").unwrap());
+        assert!(!client.sismember("flag:beta", "dan").unwrap());
+        assert!(!client.sismember("flag:gamma", "bob").unwrap());
+
+        assert_eq!(client.srem("flag:beta", ["bob", "dan"]).unwrap(), 1);
+        assert_eq!(client.srem("flag:beta", ["ada", "cy"]).unwrap(), 2);
+
+        // The set is gone once it's empty.
+        assert_eq!(client.exists(["flag:beta"]).unwrap(), 0);
+        assert_eq!(client.scard("flag:beta").unwrap(), 0);
+        assert_eq!(client.smembers("flag:beta").unwrap(), members(&[]));
+        assert_eq!(client.srem("flag:beta", ["ada"]).unwrap(), 0);
+    });
+}
+
+#[test]
+pub fn random_members_should_be_picked_from_the_set() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+        let all = members(&["a", "b", "c", "d", "e"]);
+        client.sadd("set", all.iter().cloned()).unwrap();
+
+        let member = client.srandmember("set").unwrap().unwrap();
+        assert!(all.contains(&member));
+
+        let response = client
+            .send(Command::SRandMember(b"set".to_vec(), Some(3)))
+            .unwrap();
+        let picked = response.array().unwrap();
+        assert_eq!(picked.len(), 3);
+        assert_eq!(picked.iter().collect::<HashSet<_>>().len(), 3);
+        assert!(picked.iter().all(|member| all.contains(member)));
+
+        let response = client
+            .send(Command::SRandMember(b"set".to_vec(), Some(10)))
+            .unwrap();
+        assert_eq!(response.array().unwrap().len(), 5);
+
+        let response = client
+            .send(Command::SRandMember(b"set".to_vec(), Some(-20)))
+            .unwrap();
+        let picked = response.array().unwrap();
+        assert_eq!(picked.len(), 20);
+        assert!(picked.iter().all(|member| all.contains(member)));
+        assert_eq!(client.scard("set").unwrap(), 5);
+
+        // Picking with repeats isn't bounded by the size of the set, only by a limit.
+        let response = client
+            .send(Command::SRandMember(b"set".to_vec(), Some(-4_000_000_000)))
+            .unwrap();
+        assert_eq!(response.status_code(), StatusCodes::ErrInvalidRequest);
+        let response = client
+            .send(Command::SRandMember(b"set".to_vec(), Some(i64::MAX)))
+            .unwrap();
+        assert_eq!(response.array().unwrap().len(), 5);
+
+        let response = client
+            .send(Command::SPop(b"set".to_vec(), Some(2)))
+            .unwrap();
+        let mut popped: HashSet<Vec<u8>> = response.array().unwrap().into_iter().collect();
+        assert_eq!(popped.len(), 2);
+        while let Some(member) = client.spop("set").unwrap() {
+            assert!(popped.insert(member));
+        }
+        assert_eq!(popped, all);
+
+        assert_eq!(client.exists(["set"]).unwrap(), 0);
+        assert_eq!(client.srandmember("set").unwrap(), None);
+        let response = client
+            .send(Command::SPop(b"set".to_vec(), Some(2)))
+            .unwrap();
+        assert_eq!(response.array().unwrap(), Vec::<Vec<u8>>::new());
+    });
+}
+
+#[test]
+pub fn set_algebra_should_combine_the_sets() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+        client.sadd("a", ["1", "2", "3", "4"]).unwrap();
+        client.sadd("b", ["2", "3", "5"]).unwrap();
+        client.sadd("c", ["3", "6"]).unwrap();
+
+        assert_eq!(client.sinter(["a", "b", "c"]).unwrap(), members(&["3"]));
+        assert_eq!(client.sinter(["a", "missing"]).unwrap(), members(&[]));
+        assert_eq!(
+            client.sunion(["a", "b", "missing"]).unwrap(),
+            members(&["1", "2", "3", "4", "5"])
+        );
+        assert_eq!(client.sdiff(["a", "b"]).unwrap(), members(&["1", "4"]));
+        assert_eq!(client.sdiff(["a", "b", "c"]).unwrap(), members(&["1", "4"]));
+        assert_eq!(client.sdiff(["missing", "a"]).unwrap(), members(&[]));
+
+        // The destination is overwritten whatever it held.
+        client.set("dest", "value").unwrap();
+        assert_eq!(client.sinterstore("dest", ["a", "b"]).unwrap(), 2);
+        assert_eq!(client.smembers("dest").unwrap(), members(&["2", "3"]));
+        assert_eq!(client.sunionstore("dest", ["b", "c"]).unwrap(), 4);
+        assert_eq!(
+            client.smembers("dest").unwrap(),
+            members(&["2", "3", "5", "6"])
+        );
+        assert_eq!(client.sdiffstore("dest", ["dest", "a"]).unwrap(), 2);
+        assert_eq!(client.smembers("dest").unwrap(), members(&["5", "6"]));
+
+        assert_eq!(
+            client.sinterstore("dest", ["a", "c", "missing"]).unwrap(),
+            0
+        );
+        assert_eq!(client.exists(["dest"]).unwrap(), 0);
+    });
+}
+
+#[test]
+pub fn commands_on_the_wrong_type_should_fail_without_changing_the_value() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+        client.sadd("set", ["a"]).unwrap();
+        client.set("string", "value").unwrap();
+
+        for command in [
+            Command::Get(b"set".to_vec()),
+            Command::HGetAll(b"set".to_vec()),
+            Command::SAdd(b"string".to_vec(), vec![b"a".to_vec()]),
+            Command::SPop(b"string".to_vec(), None),
+            Command::SMembers(b"string".to_vec()),
+            Command::SInter(vec![b"set".to_vec(), b"string".to_vec()]),
+            Command::SUnionStore(b"dest".to_vec(), vec![b"set".to_vec(), b"string".to_vec()]),
+        ] {
+            let response = client.send(command).unwrap();
+            assert_eq!(response.status_code(), StatusCodes::ErrWrongType);
+        }
+
+        assert_eq!(client.smembers("set").unwrap(), members(&["a"]));
+        assert_eq!(
+            client.get("string").unwrap().message(),
+            Some(b"value".as_slice())
+        );
+        assert_eq!(client.exists(["dest"]).unwrap(), 0);
+    });
+}
+
+#[test]
+pub fn concurrent_adds_to_the_same_set_should_count_each_member_once() {
+    with_server_threads(4, |server_address| {
+        let clients: Vec<_> = (0..CLIENTS)
+            .map(|_| {
+                let server_address = server_address.clone();
+                thread::spawn(move || {
+                    let mut client = new_client(&server_address);
+                    (0..MEMBERS)
+                        .map(|member| client.sadd("flag", [member.to_string()]).unwrap())
+                        .sum::<i64>()
+                })
+            })
+            .collect();
+
+        let added: i64 = clients
+            .into_iter()
+            .map(|client| client.join().unwrap())
+            .sum();
+        assert_eq!(added, MEMBERS as i64);
+
+        let mut client = new_client(&server_address);
+        assert_eq!(client.scard("flag").unwrap(), MEMBERS as i64);
+    });
+}
+
+#[test]
+pub fn sets_should_survive_a_restart_with_snapshot() {
+    let snapshot_path = temp_path("dump.skaja");
+    let config_path = temp_path("config.toml");
+    let config = format!("[snapshot]\npath = {:?}\nsave = []\n", snapshot_path);
+    fs::write(&config_path, config).unwrap();
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        client.sadd("set", ["a", "", "c"]).unwrap();
+        client.hset("hash", [("field", "value")]).unwrap();
+
+        let response = client.save().unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+    });
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        assert_eq!(client.smembers("set").unwrap(), members(&["a", "", "c"]));
+        assert_eq!(
+            client.hget("hash", "field").unwrap(),
+            Some(b"value".to_vec())
+        );
+    });
+
+    fs::remove_file(config_path).unwrap();
+    fs::remove_file(snapshot_path).unwrap();
+}
+
+#[test]
+pub fn sets_should_survive_a_restart_with_append_only_file() {
+    let aof_path = temp_path("appendonly.aof");
+    let config_path = temp_path("config.toml");
+    let config = format!("[append_only]\npath = {:?}\nfsync = \"always\"\n", aof_path);
+    fs::write(&config_path, config).unwrap();
+
+    let remaining = Mutex::new(HashSet::new());
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        client.sadd("a", ["1", "2", "3", "4", "5", "6"]).unwrap();
+        client.sadd("b", ["1", "2"]).unwrap();
+        client.srem("a", ["6"]).unwrap();
+        client.sdiffstore("c", ["a", "b"]).unwrap();
+
+        // The popped members have to be the same ones once the log is replayed.
+        client.spop("c").unwrap();
+        client.send(Command::SPop(b"c".to_vec(), Some(1))).unwrap();
+        *remaining.lock().unwrap() = client.smembers("c").unwrap();
+    });
+    let remaining = remaining.into_inner().unwrap();
+    assert_eq!(remaining.len(), 1);
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        assert_eq!(
+            client.smembers("a").unwrap(),
+            members(&["1", "2", "3", "4", "5"])
+        );
+        assert_eq!(client.smembers("c").unwrap(), remaining);
+    });
+
+    fs::remove_file(aof_path).unwrap();
+    fs::remove_file(config_path).unwrap();
+}
@@ -0,0 +1,122 @@
+use integration_tests::test_utils::{
+    launch_server_process_with_config, new_client, spawn_server, spawn_server_with_config,
+    temp_path, wait_for_exit,
+};
+use skaja_lib::{Command, SetOptions, ShutdownMode, StatusCodes};
+use std::{fs, path::PathBuf};
+
+fn snapshot_config() -> (PathBuf, PathBuf) {
+    let snapshot_path = temp_path("dump.skaja");
+    let config_path = temp_path("config.toml");
+    let config = format!(
+        "[snapshot]\npath = {:?}\nsave = [{{ seconds = 3600, writes = 1 }}]\n",
+        snapshot_path
+    );
+    fs::write(&config_path, config).unwrap();
+
+    (config_path, snapshot_path)
+}
+
+#[test]
+pub fn shutdown_command_should_stop_the_server() {
+    let server_handle = spawn_server();
+
+    let mut client = new_client(&server_handle.local_addr().to_string());
+    let response = client
+        .send(Command::Shutdown(ShutdownMode::Default))
+        .unwrap();
+    assert_eq!(response.status_code(), StatusCodes::Ok);
+
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn pending_responses_should_be_written_before_shutting_down() {
+    let server_handle = spawn_server();
+
+    let value = b"v".repeat(8 * 1024 * 1024);
+    let mut client = new_client(&server_handle.local_addr().to_string());
+    let responses = client
+        .pipeline([
+            Command::Set(b"big".to_vec(), value.clone(), SetOptions::default()),
+            Command::Get(b"big".to_vec()),
+            Command::Get(b"big".to_vec()),
+            Command::Shutdown(ShutdownMode::Default),
+        ])
+        .unwrap();
+
+    assert_eq!(responses.len(), 4);
+    assert_eq!(responses[1].message(), Some(value.as_slice()));
+    assert_eq!(responses[2].message(), Some(value.as_slice()));
+    assert_eq!(responses[3].status_code(), StatusCodes::Ok);
+
+    server_handle.join().unwrap();
+}
+
+#[test]
+pub fn shutdown_nosave_should_skip_the_final_snapshot() {
+    let (config_path, snapshot_path) = snapshot_config();
+    let server_handle = spawn_server_with_config(&config_path);
+
+    let mut client = new_client(&server_handle.local_addr().to_string());
+    client.set("hello", "world").unwrap();
+    client
+        .send(Command::Shutdown(ShutdownMode::NoSave))
+        .unwrap();
+
+    server_handle.join().unwrap();
+    assert!(!snapshot_path.exists());
+
+    fs::remove_file(config_path).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+pub fn sigterm_should_save_the_final_snapshot_before_exiting() {
+    let (config_path, snapshot_path) = snapshot_config();
+    let (mut server_handle, server_address) = launch_server_process_with_config(&config_path);
+
+    let mut client = new_client(&server_address);
+    client.set("hello", "world").unwrap();
+
+    let status = std::process::Command::new("kill")
+        .arg("-TERM")
+        .arg(server_handle.id().to_string())
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(wait_for_exit(&mut server_handle).success());
+
+    let server_handle = spawn_server_with_config(&config_path);
+    let mut client = new_client(&server_handle.local_addr().to_string());
+    let response = client.get("hello").unwrap();
+    assert_eq!(response.status_code(), StatusCodes::Ok);
+    assert_eq!(response.message(), Some(b"world".as_slice()));
+
+    drop(server_handle);
+    fs::remove_file(config_path).unwrap();
+    fs::remove_file(snapshot_path).unwrap();
+}
+
+#[test]
+pub fn shutdown_should_stop_every_worker_thread() {
+    let config_path = temp_path("config.toml");
+    fs::write(&config_path, "threads = 4\n").unwrap();
+    let server_handle = spawn_server_with_config(&config_path);
+    let server_address = server_handle.local_addr().to_string();
+
+    // Consecutive connections are handed to different threads.
+    let mut clients: Vec<_> = (0..4).map(|_| new_client(&server_address)).collect();
+    for client in clients.iter_mut() {
+        assert_eq!(
+            client.get("missing").unwrap().status_code(),
+            StatusCodes::ErrNotFound
+        );
+    }
+
+    let response = clients[2].shutdown_server(ShutdownMode::Default).unwrap();
+    assert_eq!(response.status_code(), StatusCodes::Ok);
+    server_handle.join().unwrap();
+
+    fs::remove_file(config_path).unwrap();
+}
@@ -6,8 +6,9 @@ pub fn setting_a_key_should_result_in_ok() {
         let mut client = new_client(&server_address);
         let response = client
             .send(skaja_lib::Command::Set(
-                "hello".to_string(),
-                "world".to_string(),
+                b"hello".to_vec(),
+                b"world".to_vec(),
+                skaja_lib::SetOptions::default(),
             ))
             .unwrap();
 
@@ -23,17 +24,18 @@ pub fn getting_existing_key_should_result_in_ok() {
         let mut client = new_client(&server_address);
         client
             .send(skaja_lib::Command::Set(
-                "hello".to_string(),
-                "world".to_string(),
+                b"hello".to_vec(),
+                b"world".to_vec(),
+                skaja_lib::SetOptions::default(),
             ))
             .unwrap();
 
         let response = client
-            .send(skaja_lib::Command::Get("hello".to_string()))
+            .send(skaja_lib::Command::Get(b"hello".to_vec()))
             .unwrap();
         let status_code = response.status_code();
         assert_eq!(status_code, skaja_lib::StatusCodes::Ok);
-        assert_eq!(response.message(), Some("world"));
+        assert_eq!(response.message(), Some(b"world".as_slice()));
     })
 }
 
@@ -42,7 +44,7 @@ pub fn getting_non_existing_key_should_result_in_client_error() {
     with_server(|server_address| {
         let mut client = new_client(&server_address);
         let response = client
-            .send(skaja_lib::Command::Get("hello".to_string()))
+            .send(skaja_lib::Command::Get(b"hello".to_vec()))
             .unwrap();
         let status_code = response.status_code();
         assert_eq!(status_code, skaja_lib::StatusCodes::ErrNotFound);
@@ -56,12 +58,13 @@ pub fn deleting_existing_key_should_result_in_ok() {
         let mut client = new_client(&server_address);
         client
             .send(skaja_lib::Command::Set(
-                "hello".to_string(),
-                "world".to_string(),
+                b"hello".to_vec(),
+                b"world".to_vec(),
+                skaja_lib::SetOptions::default(),
             ))
             .unwrap();
         let response = client
-            .send(skaja_lib::Command::Delete("hello".to_string()))
+            .send(skaja_lib::Command::Delete(b"hello".to_vec()))
             .unwrap();
         let status_code = response.status_code();
         assert_eq!(status_code, skaja_lib::StatusCodes::Ok);
@@ -74,10 +77,160 @@ pub fn deleting_non_existent_key_should_result_in_client_error() {
     with_server(|server_address| {
         let mut client = new_client(&server_address);
         let response = client
-            .send(skaja_lib::Command::Delete("hello".to_string()))
+            .send(skaja_lib::Command::Delete(b"hello".to_vec()))
             .unwrap();
         let status_code = response.status_code();
         assert_eq!(status_code, skaja_lib::StatusCodes::ErrNotFound);
         assert_eq!(response.message(), None);
     })
 }
+
+#[test]
+pub fn request_split_across_multiple_writes_should_result_in_ok() {
+    use skaja_lib::{Extract, OutOf, RawResponse, Request, Response};
+    use std::{io::Write, thread, time::Duration};
+
+    with_server(|server_address| {
+        let value = b"a".repeat(64 * 1024);
+        let set_request: Request = skaja_lib::Command::Set(
+            b"hello".to_vec(),
+            value.clone(),
+            skaja_lib::SetOptions::default(),
+        )
+        .extract()
+        .unwrap();
+        let get_request: Request = skaja_lib::Command::Get(b"hello".to_vec())
+            .extract()
+            .unwrap();
+
+        // A blocking stream, so reading the response waits until all of it arrives.
+        let connection = std::net::TcpStream::connect(&server_address).unwrap();
+        let mut connection = mio::net::TcpStream::from_std(connection);
+
+        for chunk in set_request.payload().chunks(1000) {
+            connection.write_all(chunk).unwrap();
+            connection.flush().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        let response: Response = RawResponse::outof(&mut connection).unwrap().into();
+        assert_eq!(response.status_code(), skaja_lib::StatusCodes::Ok);
+
+        connection.write_all(get_request.payload()).unwrap();
+        let response: Response = RawResponse::outof(&mut connection).unwrap().into();
+        assert_eq!(response.status_code(), skaja_lib::StatusCodes::Ok);
+        assert_eq!(response.message(), Some(value.as_slice()));
+    });
+}
+
+#[test]
+pub fn non_utf8_key_and_value_should_be_stored_as_is() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+        let key = vec![0xff, 0x00, 0xfe];
+        let value = vec![0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0xc3];
+
+        let response = client.set(key.clone(), value.clone()).unwrap();
+        assert_eq!(response.status_code(), skaja_lib::StatusCodes::Ok);
+
+        let response = client.get(key.clone()).unwrap();
+        assert_eq!(response.status_code(), skaja_lib::StatusCodes::Ok);
+        assert_eq!(response.message(), Some(value.as_slice()));
+    })
+}
+
+/// Encode the parts of a request as is, so that requests the client refuses to build can be sent.
+fn raw_request(parts: &[&[u8]]) -> Vec<u8> {
+    let mut payload = (parts.len() as u32).to_le_bytes().to_vec();
+    for part in parts {
+        payload.extend_from_slice(&(part.len() as u32).to_le_bytes());
+        payload.extend_from_slice(part);
+    }
+
+    payload
+}
+
+fn send_raw(stream: &mut std::net::TcpStream, parts: &[&[u8]]) -> skaja_lib::Response {
+    use std::io::{Read, Write};
+
+    stream.write_all(&raw_request(parts)).unwrap();
+
+    let mut decoder = skaja_lib::ResponseDecoder::new();
+    let mut buf = [0; 1024];
+    loop {
+        if let Some(response) = decoder.decode() {
+            return response.into();
+        }
+
+        let read = stream.read(&mut buf).unwrap();
+        assert!(read > 0, "Server closed the connection");
+        decoder.feed(&buf[..read]);
+    }
+}
+
+#[test]
+pub fn invalid_requests_should_be_answered_with_the_reason() {
+    with_server(|server_address| {
+        let mut stream = std::net::TcpStream::connect(server_address).unwrap();
+
+        let response = send_raw(&mut stream, &[b"nope", b"key"]);
+        assert_eq!(
+            response.status_code(),
+            skaja_lib::StatusCodes::ErrUnknownCommand
+        );
+        assert_eq!(
+            response.message(),
+            Some(b"Unknown command \"nope\"".as_slice())
+        );
+
+        let response = send_raw(&mut stream, &[b"get"]);
+        assert_eq!(
+            response.status_code(),
+            skaja_lib::StatusCodes::ErrWrongArity
+        );
+        assert_eq!(
+            response.message(),
+            Some(b"\"get\" command needs 1 argument".as_slice())
+        );
+
+        let response = send_raw(&mut stream, &[b"expire", b"key", b"soon"]);
+        assert_eq!(
+            response.status_code(),
+            skaja_lib::StatusCodes::ErrInvalidRequest
+        );
+
+        // The connection is still usable afterwards.
+        let response = send_raw(&mut stream, &[b"set", b"key", b"value"]);
+        assert_eq!(response.status_code(), skaja_lib::StatusCodes::Ok);
+    })
+}
+
+#[test]
+pub fn hello_should_negotiate_the_protocol_version() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+        let hello = client.hello().unwrap();
+        assert_eq!(hello.version, skaja_lib::PROTOCOL_VERSION);
+        assert!(hello.server.starts_with("skaja"));
+        assert!(hello.has_capability("pipelining"));
+        assert!(hello.has_capability("conditional-writes"));
+        assert!(hello.has_capability("lists"));
+        assert!(hello.has_capability("hashes"));
+        assert!(hello.has_capability("sets"));
+        assert!(hello.has_capability("sorted-sets"));
+        assert!(hello.has_capability("streams"));
+
+        // A client newer than the server gets the newest version the server speaks.
+        let response = client
+            .send(skaja_lib::Command::Hello(skaja_lib::PROTOCOL_VERSION + 1))
+            .unwrap();
+        let hello = skaja_lib::Hello::decode(response.message().unwrap()).unwrap();
+        assert_eq!(hello.version, skaja_lib::PROTOCOL_VERSION);
+
+        let response = client.send(skaja_lib::Command::Hello(0)).unwrap();
+        assert_eq!(
+            response.status_code(),
+            skaja_lib::StatusCodes::ErrInvalidRequest
+        );
+    })
+}
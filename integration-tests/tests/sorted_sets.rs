@@ -0,0 +1,314 @@
+use integration_tests::test_utils::{
+    new_client, temp_path, with_server, with_server_config, with_server_threads,
+};
+use skaja_lib::{Command, ScoreBound, StatusCodes};
+use std::{collections::HashSet, fs, thread};
+
+const CLIENTS: usize = 8;
+const INCREMENTS: usize = 100;
+const JOBS: usize = 200;
+
+fn members(members: &[&str]) -> Vec<Vec<u8>> {
+    members
+        .iter()
+        .map(|member| member.as_bytes().to_vec())
+        .collect()
+}
+
+fn scored(members: &[(&str, f64)]) -> Vec<(Vec<u8>, f64)> {
+    members
+        .iter()
+        .map(|(member, score)| (member.as_bytes().to_vec(), *score))
+        .collect()
+}
+
+#[test]
+pub fn members_should_be_added_scored_and_removed() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+
+        assert_eq!(
+            client
+                .zadd("board", [(10.0, "ada"), (5.0, "bob"), (7.5, "cy")])
+                .unwrap(),
+            3
+        );
+        assert_eq!(
+            client.zadd("board", [(1.0, "cy"), (2.0, "dan")]).unwrap(),
+            1
+        );
+        assert_eq!(client.zcard("board").unwrap(), 4);
+        assert_eq!(client.zscore("board", "cy").unwrap(), Some(1.0));
+        assert_eq!(client.zscore("board", "eve").unwrap(), None);
+        assert_eq!(client.zscore("missing", "ada").unwrap(), None);
+        assert_eq!(client.zrank("board", "bob").unwrap(), Some(2));
+        assert_eq!(client.zrank("board", "eve").unwrap(), None);
+
+        assert_eq!(client.zincr_by("board", 6.5, "dan").unwrap(), 8.5);
+        assert_eq!(client.zincr_by("board", -3.0, "eve").unwrap(), -3.0);
+
+        assert_eq!(client.zrem("board", ["bob", "fay"]).unwrap(), 1);
+        assert_eq!(
+            client.zrem("board", ["ada", "cy", "dan", "eve"]).unwrap(),
+            4
+        );
+
+        // The sorted set is gone once it's empty.
+        assert_eq!(client.exists(["board"]).unwrap(), 0);
+        assert_eq!(client.zcard("board").unwrap(), 0);
+        assert_eq!(client.zrange("board", 0, -1).unwrap(), members(&[]));
+        assert_eq!(client.zrem("board", ["ada"]).unwrap(), 0);
+    });
+}
+
+#[test]
+pub fn leaderboard_should_be_ranged_by_position_in_both_directions() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+        client
+            .zadd(
+                "board",
+                [(30.0, "ada"), (10.0, "bob"), (20.0, "cy"), (20.0, "al")],
+            )
+            .unwrap();
+
+        // Members with the same score are ordered by their bytes.
+        assert_eq!(
+            client.zrange("board", 0, -1).unwrap(),
+            members(&["bob", "al", "cy", "ada"])
+        );
+        assert_eq!(
+            client.zrevrange_with_scores("board", 0, 2).unwrap(),
+            scored(&[("ada", 30.0), ("cy", 20.0), ("al", 20.0)])
+        );
+        assert_eq!(
+            client.zrange_with_scores("board", -2, 100).unwrap(),
+            scored(&[("cy", 20.0), ("ada", 30.0)])
+        );
+        assert_eq!(client.zrevrange("board", 3, 1).unwrap(), members(&[]));
+        assert_eq!(client.zrevrange("missing", 0, -1).unwrap(), members(&[]));
+    });
+}
+
+#[test]
+pub fn delay_queue_should_be_ranged_by_score_with_limits() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+        for due in 1..=10 {
+            client
+                .zadd("jobs", [(due as f64 * 100.0, format!("job:{}", due))])
+                .unwrap();
+        }
+
+        let due = client
+            .zrangebyscore("jobs", f64::NEG_INFINITY, 500.0, Some((0, 3)))
+            .unwrap();
+        assert_eq!(due, members(&["job:1", "job:2", "job:3"]));
+        let due = client
+            .zrangebyscore("jobs", f64::NEG_INFINITY, 500.0, Some((3, 3)))
+            .unwrap();
+        assert_eq!(due, members(&["job:4", "job:5"]));
+
+        assert_eq!(
+            client
+                .zrangebyscore_with_scores(
+                    "jobs",
+                    ScoreBound::exclusive(800.0),
+                    f64::INFINITY,
+                    None
+                )
+                .unwrap(),
+            scored(&[("job:9", 900.0), ("job:10", 1000.0)])
+        );
+        assert_eq!(
+            client
+                .zrangebyscore("jobs", 300.0, ScoreBound::exclusive(300.0), None)
+                .unwrap(),
+            members(&[])
+        );
+        assert_eq!(
+            client.zrangebyscore("jobs", 700.0, 200.0, None).unwrap(),
+            members(&[])
+        );
+    });
+}
+
+#[test]
+pub fn scores_should_not_become_nan() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+        client.zadd("board", [(f64::INFINITY, "ada")]).unwrap();
+
+        let response = client
+            .send(Command::ZIncrBy(
+                b"board".to_vec(),
+                f64::NEG_INFINITY,
+                b"ada".to_vec(),
+            ))
+            .unwrap();
+        assert_eq!(response.status_code(), StatusCodes::ErrNotNumeric);
+        assert_eq!(client.zscore("board", "ada").unwrap(), Some(f64::INFINITY));
+    });
+}
+
+#[test]
+pub fn commands_on_the_wrong_type_should_fail_without_changing_the_value() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+        client.zadd("board", [(1.0, "ada")]).unwrap();
+        client.sadd("set", ["ada"]).unwrap();
+
+        for command in [
+            Command::Get(b"board".to_vec()),
+            Command::SMembers(b"board".to_vec()),
+            Command::ZAdd(b"set".to_vec(), vec![(1.0, b"bob".to_vec())]),
+            Command::ZIncrBy(b"set".to_vec(), 1.0, b"ada".to_vec()),
+            Command::ZScore(b"set".to_vec(), b"ada".to_vec()),
+            Command::ZRange(b"set".to_vec(), 0, -1, false),
+            Command::ZCard(b"set".to_vec()),
+        ] {
+            let response = client.send(command).unwrap();
+            assert_eq!(response.status_code(), StatusCodes::ErrWrongType);
+        }
+
+        assert_eq!(
+            client.zrange_with_scores("board", 0, -1).unwrap(),
+            scored(&[("ada", 1.0)])
+        );
+        assert_eq!(client.smembers("set").unwrap().len(), 1);
+    });
+}
+
+#[test]
+pub fn concurrent_increments_of_the_same_member_should_not_be_lost() {
+    with_server_threads(4, |server_address| {
+        let clients: Vec<_> = (0..CLIENTS)
+            .map(|_| {
+                let server_address = server_address.clone();
+                thread::spawn(move || {
+                    let mut client = new_client(&server_address);
+                    for _ in 0..INCREMENTS {
+                        client.zincr_by("board", 1.0, "ada").unwrap();
+                    }
+                })
+            })
+            .collect();
+
+        for client in clients {
+            client.join().unwrap();
+        }
+
+        let mut client = new_client(&server_address);
+        assert_eq!(
+            client.zscore("board", "ada").unwrap(),
+            Some((CLIENTS * INCREMENTS) as f64)
+        );
+    });
+}
+
+#[test]
+pub fn concurrent_workers_should_claim_each_due_job_once() {
+    with_server_threads(4, |server_address| {
+        let mut client = new_client(&server_address);
+        let jobs: Vec<(f64, String)> = (0..JOBS)
+            .map(|job| (job as f64, format!("job:{}", job)))
+            .collect();
+        client.zadd("jobs", jobs).unwrap();
+
+        let workers: Vec<_> = (0..CLIENTS)
+            .map(|_| {
+                let server_address = server_address.clone();
+                thread::spawn(move || {
+                    let mut client = new_client(&server_address);
+                    let mut claimed = Vec::new();
+                    loop {
+                        let due = client
+                            .zrangebyscore("jobs", f64::NEG_INFINITY, f64::INFINITY, Some((0, 5)))
+                            .unwrap();
+                        if due.is_empty() {
+                            break claimed;
+                        }
+
+                        // Only the worker whose removal went through gets the job.
+                        for job in due {
+                            if client.zrem("jobs", [job.clone()]).unwrap() == 1 {
+                                claimed.push(job);
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        let claimed: Vec<Vec<u8>> = workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect();
+        let unique: HashSet<&Vec<u8>> = claimed.iter().collect();
+        assert_eq!(claimed.len(), JOBS);
+        assert_eq!(unique.len(), JOBS);
+    });
+}
+
+#[test]
+pub fn sorted_sets_should_survive_a_restart_with_snapshot() {
+    let snapshot_path = temp_path("dump.skaja");
+    let config_path = temp_path("config.toml");
+    let config = format!("[snapshot]\npath = {:?}\nsave = []\n", snapshot_path);
+    fs::write(&config_path, config).unwrap();
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        client
+            .zadd(
+                "board",
+                [(0.1, "ada"), (f64::NEG_INFINITY, ""), (-2.0, "bob")],
+            )
+            .unwrap();
+        client.sadd("set", ["a"]).unwrap();
+
+        let response = client.save().unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+    });
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        assert_eq!(
+            client.zrange_with_scores("board", 0, -1).unwrap(),
+            scored(&[("", f64::NEG_INFINITY), ("bob", -2.0), ("ada", 0.1)])
+        );
+        assert_eq!(client.scard("set").unwrap(), 1);
+    });
+
+    fs::remove_file(config_path).unwrap();
+    fs::remove_file(snapshot_path).unwrap();
+}
+
+#[test]
+pub fn sorted_sets_should_survive_a_restart_with_append_only_file() {
+    let aof_path = temp_path("appendonly.aof");
+    let config_path = temp_path("config.toml");
+    let config = format!("[append_only]\npath = {:?}\nfsync = \"always\"\n", aof_path);
+    fs::write(&config_path, config).unwrap();
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        client
+            .zadd("board", [(1.0, "ada"), (2.0, "bob"), (3.0, "cy")])
+            .unwrap();
+        client.zincr_by("board", 0.1, "ada").unwrap();
+        client.zrem("board", ["bob"]).unwrap();
+        client.zadd("board", [(f64::INFINITY, "dan")]).unwrap();
+    });
+
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        assert_eq!(
+            client.zrange_with_scores("board", 0, -1).unwrap(),
+            scored(&[("ada", 1.1), ("cy", 3.0), ("dan", f64::INFINITY)])
+        );
+    });
+
+    fs::remove_file(aof_path).unwrap();
+    fs::remove_file(config_path).unwrap();
+}
@@ -0,0 +1,401 @@
+use integration_tests::test_utils::{
+    new_client, temp_path, with_server, with_server_config, with_server_threads,
+};
+use skaja_lib::{Command, PendingRange, PendingSummary, StatusCodes, StreamEntry, StreamId};
+use std::{collections::HashSet, fs, sync::Mutex, thread};
+
+const CONSUMERS: usize = 8;
+const JOBS: usize = 200;
+
+fn ids(entries: &[StreamEntry]) -> Vec<StreamId> {
+    entries.iter().map(|entry| entry.id).collect()
+}
+
+fn all_pending() -> PendingRange {
+    PendingRange {
+        min_idle: None,
+        start: StreamId::MIN,
+        end: StreamId::MAX,
+        count: usize::MAX,
+        consumer: None,
+    }
+}
+
+#[test]
+pub fn entries_should_be_added_ranged_and_trimmed() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+
+        let added: Vec<StreamId> = (0..5)
+            .map(|n| client.xadd("events", [("n", n.to_string())]).unwrap())
+            .collect();
+        assert!(added.windows(2).all(|ids| ids[0] < ids[1]));
+        assert_eq!(client.xlen("events").unwrap(), 5);
+
+        let entries = client
+            .xrange("events", StreamId::MIN, StreamId::MAX, None)
+            .unwrap();
+        assert_eq!(ids(&entries), added);
+        assert_eq!(entries[2].fields, vec![(b"n".to_vec(), b"2".to_vec())]);
+        let entries = client
+            .xrange("events", added[1], StreamId::MAX, Some(2))
+            .unwrap();
+        assert_eq!(ids(&entries), added[1..3]);
+        assert!(client
+            .xrange("events", added[3], added[1], None)
+            .unwrap()
+            .is_empty());
+
+        // The IDs only ever grow, even when they're given.
+        let response = client
+            .send(Command::XAdd(
+                b"events".to_vec(),
+                Some(added[4]),
+                vec![(b"n".to_vec(), b"5".to_vec())],
+            ))
+            .unwrap();
+        assert_eq!(response.status_code(), StatusCodes::ErrInvalidRequest);
+
+        assert_eq!(client.xtrim("events", 2).unwrap(), 3);
+        assert_eq!(client.xtrim("events", 2).unwrap(), 0);
+        let entries = client
+            .xrange("events", StreamId::MIN, StreamId::MAX, None)
+            .unwrap();
+        assert_eq!(ids(&entries), added[3..]);
+
+        // The stream is kept once it's empty, and its IDs keep growing.
+        assert_eq!(client.xtrim("events", 0).unwrap(), 2);
+        assert_eq!(client.exists(["events"]).unwrap(), 1);
+        assert_eq!(client.xlen("events").unwrap(), 0);
+        assert!(client.xadd("events", [("n", "6")]).unwrap() > added[4]);
+
+        assert_eq!(client.xlen("missing").unwrap(), 0);
+        assert_eq!(client.xtrim("missing", 0).unwrap(), 0);
+    });
+}
+
+#[test]
+pub fn consumer_group_should_deliver_acknowledge_and_claim_entries() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+
+        assert!(client
+            .xgroup_create("jobs", "workers", None, false)
+            .is_err());
+        assert!(client.xgroup_create("jobs", "workers", None, true).unwrap());
+        assert!(!client.xgroup_create("jobs", "workers", None, true).unwrap());
+
+        let added: Vec<StreamId> = (0..3)
+            .map(|n| client.xadd("jobs", [("job", n.to_string())]).unwrap())
+            .collect();
+
+        let read = client
+            .xreadgroup("workers", "ada", Some(2), [("jobs", None)])
+            .unwrap();
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].0, b"jobs");
+        assert_eq!(ids(&read[0].1), added[..2]);
+
+        let read = client
+            .xreadgroup("workers", "bob", None, [("jobs", None)])
+            .unwrap();
+        assert_eq!(ids(&read[0].1), added[2..]);
+        assert!(client
+            .xreadgroup("workers", "bob", None, [("jobs", None)])
+            .unwrap()
+            .is_empty());
+
+        assert_eq!(
+            client.xpending("jobs", "workers").unwrap(),
+            PendingSummary {
+                count: 3,
+                range: Some((added[0], added[2])),
+                consumers: vec![(b"ada".to_vec(), 2), (b"bob".to_vec(), 1)],
+            }
+        );
+
+        // Reading from an ID goes through the entries still pending for the consumer.
+        let read = client
+            .xreadgroup("workers", "ada", None, [("jobs", Some(StreamId::MIN))])
+            .unwrap();
+        assert_eq!(ids(&read[0].1), added[..2]);
+
+        assert_eq!(
+            client
+                .xack("jobs", "workers", [added[0], added[2]])
+                .unwrap(),
+            2
+        );
+        assert_eq!(client.xack("jobs", "workers", [added[0]]).unwrap(), 0);
+
+        // Ada's last entry hasn't been idle for a minute yet, bob takes it over anyway.
+        let claimed = client
+            .xclaim("jobs", "workers", "bob", 60_000, [added[1]])
+            .unwrap();
+        assert!(claimed.is_empty());
+        let claimed = client
+            .xclaim("jobs", "workers", "bob", 0, [added[1]])
+            .unwrap();
+        assert_eq!(ids(&claimed), [added[1]]);
+
+        let pending = client
+            .xpending_range("jobs", "workers", all_pending())
+            .unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, added[1]);
+        assert_eq!(pending[0].consumer, b"bob");
+        assert_eq!(pending[0].deliveries, 2);
+
+        let range = PendingRange {
+            consumer: Some(b"ada".to_vec()),
+            ..all_pending()
+        };
+        assert!(client
+            .xpending_range("jobs", "workers", range)
+            .unwrap()
+            .is_empty());
+
+        for command in [
+            Command::XAck(b"jobs".to_vec(), b"nope".to_vec(), vec![added[1]]),
+            Command::XPending(b"missing".to_vec(), b"workers".to_vec(), None),
+            Command::XReadGroup(
+                b"workers".to_vec(),
+                b"ada".to_vec(),
+                None,
+                vec![(b"jobs".to_vec(), None), (b"missing".to_vec(), None)],
+            ),
+        ] {
+            let response = client.send(command).unwrap();
+            assert_eq!(response.status_code(), StatusCodes::ErrNoGroup);
+        }
+    });
+}
+
+#[test]
+pub fn reading_a_group_while_its_stream_expires_should_not_break_the_server() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+        for key in ["lasting", "fleeting"] {
+            client.xgroup_create(key, "workers", None, true).unwrap();
+        }
+        client.pexpire("fleeting", 20).unwrap();
+
+        // Reading until the stream is gone, at some point its timeout runs out in the
+        // middle of a read.
+        let read = || {
+            Command::XReadGroup(
+                b"workers".to_vec(),
+                b"ada".to_vec(),
+                None,
+                vec![(b"lasting".to_vec(), None), (b"fleeting".to_vec(), None)],
+            )
+        };
+        loop {
+            client.xadd("lasting", [("n", "1")]).unwrap();
+            client.xadd("fleeting", [("n", "1")]).unwrap();
+
+            let response = client.send(read()).unwrap();
+            match response.status_code() {
+                StatusCodes::Ok => continue,
+                StatusCodes::ErrNoGroup => break,
+                status_code => panic!("Unexpected status code: {:?}", status_code),
+            }
+        }
+
+        // The entries read from the other stream are still pending for the consumer.
+        let summary = client.xpending("lasting", "workers").unwrap();
+        assert_eq!(summary.count, client.xlen("lasting").unwrap() as u64 - 1);
+    });
+}
+
+#[test]
+pub fn commands_on_the_wrong_type_should_fail_without_changing_the_value() {
+    with_server(|server_address| {
+        let mut client = new_client(&server_address);
+        let id = client.xadd("events", [("a", "1")]).unwrap();
+        client.sadd("set", ["a"]).unwrap();
+
+        for command in [
+            Command::Get(b"events".to_vec()),
+            Command::SMembers(b"events".to_vec()),
+            Command::XAdd(b"set".to_vec(), None, vec![(b"a".to_vec(), b"1".to_vec())]),
+            Command::XLen(b"set".to_vec()),
+            Command::XGroupCreate(b"set".to_vec(), b"workers".to_vec(), None, true),
+            Command::XReadGroup(
+                b"workers".to_vec(),
+                b"ada".to_vec(),
+                None,
+                vec![(b"set".to_vec(), None)],
+            ),
+        ] {
+            let response = client.send(command).unwrap();
+            assert_eq!(response.status_code(), StatusCodes::ErrWrongType);
+        }
+
+        let entries = client
+            .xrange("events", StreamId::MIN, StreamId::MAX, None)
+            .unwrap();
+        assert_eq!(ids(&entries), [id]);
+        assert_eq!(client.smembers("set").unwrap().len(), 1);
+    });
+}
+
+#[test]
+pub fn concurrent_consumers_should_each_get_distinct_entries() {
+    with_server_threads(4, |server_address| {
+        let mut client = new_client(&server_address);
+        client
+            .xgroup_create("jobs", "workers", Some(StreamId::MIN), true)
+            .unwrap();
+        for job in 0..JOBS {
+            client.xadd("jobs", [("job", job.to_string())]).unwrap();
+        }
+
+        let consumers: Vec<_> = (0..CONSUMERS)
+            .map(|consumer| {
+                let server_address = server_address.clone();
+                thread::spawn(move || {
+                    let mut client = new_client(&server_address);
+                    let name = format!("consumer:{}", consumer);
+                    let mut processed = Vec::new();
+                    loop {
+                        let read = client
+                            .xreadgroup("workers", name.as_str(), Some(5), [("jobs", None)])
+                            .unwrap();
+                        let Some((_, entries)) = read.into_iter().next() else {
+                            break processed;
+                        };
+
+                        let ids = ids(&entries);
+                        let acknowledged = client.xack("jobs", "workers", ids.clone()).unwrap();
+                        assert_eq!(acknowledged, ids.len() as i64);
+                        processed.extend(ids);
+                    }
+                })
+            })
+            .collect();
+
+        let processed: Vec<StreamId> = consumers
+            .into_iter()
+            .flat_map(|consumer| consumer.join().unwrap())
+            .collect();
+        let unique: HashSet<&StreamId> = processed.iter().collect();
+        assert_eq!(processed.len(), JOBS);
+        assert_eq!(unique.len(), JOBS);
+        assert_eq!(client.xpending("jobs", "workers").unwrap().count, 0);
+    });
+}
+
+#[test]
+pub fn streams_should_survive_a_restart_with_snapshot() {
+    let snapshot_path = temp_path("dump.skaja");
+    let config_path = temp_path("config.toml");
+    let config = format!("[snapshot]\npath = {:?}\nsave = []\n", snapshot_path);
+    fs::write(&config_path, config).unwrap();
+
+    let added = Mutex::new(Vec::new());
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        client
+            .xgroup_create("jobs", "workers", Some(StreamId::MIN), true)
+            .unwrap();
+        let mut added = added.lock().unwrap();
+        for job in 0..4 {
+            added.push(client.xadd("jobs", [("job", job.to_string())]).unwrap());
+        }
+        client
+            .xreadgroup("workers", "ada", Some(2), [("jobs", None)])
+            .unwrap();
+        client.xtrim("jobs", 3).unwrap();
+        client.xgroup_create("empty", "idle", None, true).unwrap();
+
+        let response = client.save().unwrap();
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+    });
+
+    let added = added.into_inner().unwrap();
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        let entries = client
+            .xrange("jobs", StreamId::MIN, StreamId::MAX, None)
+            .unwrap();
+        assert_eq!(ids(&entries), added[1..]);
+        assert_eq!(entries[0].fields, vec![(b"job".to_vec(), b"1".to_vec())]);
+        assert_eq!(
+            client.xpending("jobs", "workers").unwrap(),
+            PendingSummary {
+                count: 1,
+                range: Some((added[1], added[1])),
+                consumers: vec![(b"ada".to_vec(), 1)],
+            }
+        );
+
+        // The group carries on after the entries it delivered.
+        let read = client
+            .xreadgroup("workers", "bob", None, [("jobs", None)])
+            .unwrap();
+        assert_eq!(ids(&read[0].1), added[2..]);
+        assert!(client.xadd("jobs", [("job", "4")]).unwrap() > added[3]);
+
+        assert_eq!(client.xlen("empty").unwrap(), 0);
+        assert!(!client.xgroup_create("empty", "idle", None, false).unwrap());
+    });
+
+    fs::remove_file(config_path).unwrap();
+    fs::remove_file(snapshot_path).unwrap();
+}
+
+#[test]
+pub fn streams_should_survive_a_restart_with_append_only_file() {
+    let aof_path = temp_path("appendonly.aof");
+    let config_path = temp_path("config.toml");
+    let config = format!("[append_only]\npath = {:?}\nfsync = \"always\"\n", aof_path);
+    fs::write(&config_path, config).unwrap();
+
+    let added = Mutex::new(Vec::new());
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        client.xgroup_create("jobs", "workers", None, true).unwrap();
+        let mut added = added.lock().unwrap();
+        for job in 0..3 {
+            added.push(client.xadd("jobs", [("job", job.to_string())]).unwrap());
+        }
+        client
+            .xreadgroup("workers", "ada", None, [("jobs", None)])
+            .unwrap();
+        client.xack("jobs", "workers", [added[0]]).unwrap();
+        client
+            .xclaim("jobs", "workers", "bob", 0, [added[2]])
+            .unwrap();
+        // Claims of entries that aren't idle long enough do nothing, replayed or not.
+        client
+            .xclaim("jobs", "workers", "bob", 60_000, [added[1]])
+            .unwrap();
+        // Trimming the entry still pending for ada leaves nothing of it to claim.
+        client.xtrim("jobs", 1).unwrap();
+        let claimed = client
+            .xclaim("jobs", "workers", "bob", 0, [added[1]])
+            .unwrap();
+        assert!(claimed.is_empty());
+    });
+
+    let added = added.into_inner().unwrap();
+    with_server_config(&config_path, |server_address| {
+        let mut client = new_client(&server_address);
+        let entries = client
+            .xrange("jobs", StreamId::MIN, StreamId::MAX, None)
+            .unwrap();
+        assert_eq!(ids(&entries), added[2..]);
+        assert_eq!(
+            client.xpending("jobs", "workers").unwrap(),
+            PendingSummary {
+                count: 1,
+                range: Some((added[2], added[2])),
+                consumers: vec![(b"bob".to_vec(), 1)],
+            }
+        );
+    });
+
+    fs::remove_file(aof_path).unwrap();
+    fs::remove_file(config_path).unwrap();
+}
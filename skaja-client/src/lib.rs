@@ -1,15 +1,24 @@
 use core::panic;
 use mio::{net::TcpStream, Events, Interest, Poll};
-use skaja_lib::{Command, OutOf, RawResponse, Request, Response, CLIENT_TOKEN};
+use skaja_lib::{
+    decode_streams, Command, Expiry, Hello, OutOf, PendingEntry, PendingRange, PendingSummary,
+    ReadStreams, Request, Response, ResponseDecoder, ScoreBound, ScoreRangeOptions, SetCondition,
+    SetOptions, ShutdownMode, StatusCodes, StreamEntry, StreamId, Versioned, CLIENT_TOKEN,
+    PROTOCOL_VERSION,
+};
 use std::{
-    io::{self, Write},
+    collections::{HashMap, HashSet},
+    io::{self, ErrorKind, Write},
     net::SocketAddr,
+    str::FromStr,
     time::Duration,
 };
 
 pub struct Client {
     connection: TcpStream,
     poller: Poll,
+    // Buffers the bytes received from the server until they form complete responses.
+    decoder: ResponseDecoder,
 }
 
 impl Client {
@@ -30,45 +39,932 @@ impl Client {
             .register(&mut connection, CLIENT_TOKEN, Interest::WRITABLE)
             .unwrap();
 
-        Self { connection, poller }
+        Self {
+            connection,
+            poller,
+            decoder: ResponseDecoder::new(),
+        }
     }
 
     pub fn shutdown(&mut self) -> Result<(), io::Error> {
         self.connection.shutdown(std::net::Shutdown::Both)
     }
 
-    pub fn send(&mut self, mut command: Command) -> Result<Response, io::Error> {
-        let request = Request::outof(&mut command)?;
+    /// Get the value of the given key.
+    pub fn get(&mut self, key: impl Into<Vec<u8>>) -> Result<Response, io::Error> {
+        self.send(Command::Get(key.into()))
+    }
+
+    /// Set the value of the given key, keys and values can be arbitrary bytes.
+    pub fn set(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        value: impl Into<Vec<u8>>,
+    ) -> Result<Response, io::Error> {
+        self.send(Command::Set(
+            key.into(),
+            value.into(),
+            SetOptions::default(),
+        ))
+    }
+
+    /// Set the value of the given key, the key is removed once the expiry has passed.
+    pub fn set_with_expiry(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        value: impl Into<Vec<u8>>,
+        expiry: Expiry,
+    ) -> Result<Response, io::Error> {
+        let options = SetOptions {
+            expiry: Some(expiry),
+            ..Default::default()
+        };
+        self.send(Command::Set(key.into(), value.into(), options))
+    }
+
+    /// Get the value of the given key along with its version, None if the key doesn't exist.
+    pub fn gets(&mut self, key: impl Into<Vec<u8>>) -> Result<Option<Versioned>, io::Error> {
+        let response = self.send(Command::Gets(key.into()))?;
+        let msg = response.message().unwrap_or_default();
+        match response.status_code() {
+            StatusCodes::Ok => Versioned::decode(msg)
+                .map(Some)
+                .map_err(|e| io::Error::new(ErrorKind::InvalidData, e)),
+            StatusCodes::ErrNotFound => Ok(None),
+            _ => Err(response_error(&response)),
+        }
+    }
+
+    /// Set the value of the given key only if its version is still the one read with
+    /// [`Client::gets`], the status is [`StatusCodes::ErrConflict`] otherwise.
+    pub fn cas(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        value: impl Into<Vec<u8>>,
+        version: u64,
+    ) -> Result<Response, io::Error> {
+        self.send(Command::Cas(
+            key.into(),
+            value.into(),
+            version,
+            SetOptions::default(),
+        ))
+    }
+
+    /// Set the value of the given key only if its current value is the expected one, the
+    /// status is [`StatusCodes::ErrConflict`] otherwise.
+    pub fn cas_value(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        expected: impl Into<Vec<u8>>,
+        value: impl Into<Vec<u8>>,
+    ) -> Result<Response, io::Error> {
+        self.send(Command::CasValue(
+            key.into(),
+            expected.into(),
+            value.into(),
+            SetOptions::default(),
+        ))
+    }
+
+    /// Set the value of the given key only if it doesn't exist, returns whether it was set.
+    pub fn set_nx(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        value: impl Into<Vec<u8>>,
+    ) -> Result<bool, io::Error> {
+        self.set_if(key, value, SetCondition::IfAbsent)
+    }
+
+    /// Set the value of the given key only if it exists, returns whether it was set.
+    pub fn set_xx(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        value: impl Into<Vec<u8>>,
+    ) -> Result<bool, io::Error> {
+        self.set_if(key, value, SetCondition::IfPresent)
+    }
+
+    fn set_if(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        value: impl Into<Vec<u8>>,
+        condition: SetCondition,
+    ) -> Result<bool, io::Error> {
+        let options = SetOptions {
+            condition: Some(condition),
+            ..Default::default()
+        };
+        let response = self.send(Command::Set(key.into(), value.into(), options))?;
+        match response.status_code() {
+            StatusCodes::Ok => Ok(true),
+            StatusCodes::ErrConflict | StatusCodes::ErrNotFound => Ok(false),
+            _ => Err(response_error(&response)),
+        }
+    }
+
+    /// Set the value of the given key, returns the value it had before if any.
+    pub fn getset(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        value: impl Into<Vec<u8>>,
+    ) -> Result<Option<Vec<u8>>, io::Error> {
+        value_or_none(self.send(Command::GetSet(key.into(), value.into()))?)
+    }
+
+    /// Delete the given key, returns the value it had if any.
+    pub fn getdel(&mut self, key: impl Into<Vec<u8>>) -> Result<Option<Vec<u8>>, io::Error> {
+        value_or_none(self.send(Command::GetDel(key.into()))?)
+    }
+
+    /// Set a timeout in seconds on the given key.
+    pub fn expire(&mut self, key: impl Into<Vec<u8>>, secs: u64) -> Result<Response, io::Error> {
+        self.send(Command::Expire(key.into(), secs))
+    }
+
+    /// Set a timeout in milliseconds on the given key.
+    pub fn pexpire(&mut self, key: impl Into<Vec<u8>>, millis: u64) -> Result<Response, io::Error> {
+        self.send(Command::PExpire(key.into(), millis))
+    }
+
+    /// Get the remaining time to live of the given key in seconds, -1 if it has no timeout.
+    pub fn ttl(&mut self, key: impl Into<Vec<u8>>) -> Result<Response, io::Error> {
+        self.send(Command::Ttl(key.into()))
+    }
+
+    /// Remove the timeout of the given key.
+    pub fn persist(&mut self, key: impl Into<Vec<u8>>) -> Result<Response, io::Error> {
+        self.send(Command::Persist(key.into()))
+    }
+
+    /// Delete the given key.
+    pub fn delete(&mut self, key: impl Into<Vec<u8>>) -> Result<Response, io::Error> {
+        self.send(Command::Delete(key.into()))
+    }
+
+    /// List the keys starting with the prefix, all of them if it's empty, sorted.
+    pub fn keys(&mut self, prefix: impl Into<Vec<u8>>) -> Result<Vec<Vec<u8>>, io::Error> {
+        array(self.send(Command::Keys(prefix.into()))?)
+    }
+
+    /// Get the values of the given keys at once, None for the keys that don't exist.
+    pub fn mget<K: Into<Vec<u8>>>(
+        &mut self,
+        keys: impl IntoIterator<Item = K>,
+    ) -> Result<Vec<Option<Vec<u8>>>, io::Error> {
+        let keys = keys.into_iter().map(Into::into).collect();
+        nullable_array(self.send(Command::MGet(keys))?)
+    }
+
+    /// Set the values of the given keys, all at once.
+    pub fn mset<K: Into<Vec<u8>>, V: Into<Vec<u8>>>(
+        &mut self,
+        pairs: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<Response, io::Error> {
+        let pairs = pairs
+            .into_iter()
+            .map(|(key, value)| (key.into(), value.into()))
+            .collect();
+        self.send(Command::MSet(pairs))
+    }
+
+    /// Set the values of the given keys only if none of them exists, all at once.
+    /// Returns whether they were set.
+    pub fn msetnx<K: Into<Vec<u8>>, V: Into<Vec<u8>>>(
+        &mut self,
+        pairs: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<bool, io::Error> {
+        let pairs = pairs
+            .into_iter()
+            .map(|(key, value)| (key.into(), value.into()))
+            .collect();
+        Ok(number::<i64>(self.send(Command::MSetNx(pairs))?)? == 1)
+    }
+
+    /// Count how many of the given keys exist.
+    pub fn exists<K: Into<Vec<u8>>>(
+        &mut self,
+        keys: impl IntoIterator<Item = K>,
+    ) -> Result<i64, io::Error> {
+        let keys = keys.into_iter().map(Into::into).collect();
+        number(self.send(Command::Exists(keys))?)
+    }
+
+    /// Delete the given keys, returns how many of them existed.
+    pub fn delete_many<K: Into<Vec<u8>>>(
+        &mut self,
+        keys: impl IntoIterator<Item = K>,
+    ) -> Result<i64, io::Error> {
+        let keys = keys.into_iter().map(Into::into).collect();
+        number(self.send(Command::DeleteMany(keys))?)
+    }
+
+    /// Add 1 to the integer stored at the given key, returns the new value.
+    pub fn incr(&mut self, key: impl Into<Vec<u8>>) -> Result<i64, io::Error> {
+        self.incr_by(key, 1)
+    }
+
+    /// Subtract 1 from the integer stored at the given key, returns the new value.
+    pub fn decr(&mut self, key: impl Into<Vec<u8>>) -> Result<i64, io::Error> {
+        self.incr_by(key, -1)
+    }
+
+    /// Add the delta to the integer stored at the given key, returns the new value.
+    /// A missing key counts as 0.
+    pub fn incr_by(&mut self, key: impl Into<Vec<u8>>, delta: i64) -> Result<i64, io::Error> {
+        number(self.send(Command::IncrBy(key.into(), delta))?)
+    }
+
+    /// Add the delta to the floating point number stored at the given key, returns the
+    /// new value. A missing key counts as 0.
+    pub fn incr_by_float(&mut self, key: impl Into<Vec<u8>>, delta: f64) -> Result<f64, io::Error> {
+        number(self.send(Command::IncrByFloat(key.into(), delta))?)
+    }
+
+    /// Insert the values at the head of the list stored at the given key, the last one
+    /// ending up first. Returns the length of the list.
+    pub fn lpush<V: Into<Vec<u8>>>(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        values: impl IntoIterator<Item = V>,
+    ) -> Result<i64, io::Error> {
+        let values = values.into_iter().map(Into::into).collect();
+        number(self.send(Command::LPush(key.into(), values))?)
+    }
+
+    /// Insert the values at the tail of the list stored at the given key.
+    /// Returns the length of the list.
+    pub fn rpush<V: Into<Vec<u8>>>(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        values: impl IntoIterator<Item = V>,
+    ) -> Result<i64, io::Error> {
+        let values = values.into_iter().map(Into::into).collect();
+        number(self.send(Command::RPush(key.into(), values))?)
+    }
+
+    /// Remove and return the first item of the list stored at the given key.
+    pub fn lpop(&mut self, key: impl Into<Vec<u8>>) -> Result<Option<Vec<u8>>, io::Error> {
+        value_or_none(self.send(Command::LPop(key.into(), None))?)
+    }
+
+    /// Remove and return the last item of the list stored at the given key.
+    pub fn rpop(&mut self, key: impl Into<Vec<u8>>) -> Result<Option<Vec<u8>>, io::Error> {
+        value_or_none(self.send(Command::RPop(key.into(), None))?)
+    }
+
+    /// Get the items of the list stored at the given key between the indexes, both
+    /// included. Negative indexes count from the tail.
+    pub fn lrange(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        start: i64,
+        stop: i64,
+    ) -> Result<Vec<Vec<u8>>, io::Error> {
+        array(self.send(Command::LRange(key.into(), start, stop))?)
+    }
+
+    /// Get the length of the list stored at the given key.
+    pub fn llen(&mut self, key: impl Into<Vec<u8>>) -> Result<i64, io::Error> {
+        number(self.send(Command::LLen(key.into()))?)
+    }
+
+    /// Get the item at the index of the list stored at the given key.
+    pub fn lindex(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        index: i64,
+    ) -> Result<Option<Vec<u8>>, io::Error> {
+        value_or_none(self.send(Command::LIndex(key.into(), index))?)
+    }
+
+    /// Replace the item at the index of the list stored at the given key.
+    pub fn lset(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        index: i64,
+        value: impl Into<Vec<u8>>,
+    ) -> Result<Response, io::Error> {
+        self.send(Command::LSet(key.into(), index, value.into()))
+    }
+
+    /// Only keep the items of the list stored at the given key between the indexes.
+    pub fn ltrim(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        start: i64,
+        stop: i64,
+    ) -> Result<Response, io::Error> {
+        self.send(Command::LTrim(key.into(), start, stop))
+    }
+
+    /// Remove the items equal to the value from the list stored at the given key, see
+    /// [`Command::LRem`] for what the count means. Returns how many were removed.
+    pub fn lrem(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        count: i64,
+        value: impl Into<Vec<u8>>,
+    ) -> Result<i64, io::Error> {
+        number(self.send(Command::LRem(key.into(), count, value.into()))?)
+    }
+
+    /// Set the fields of the hash stored at the given key to the values.
+    /// Returns how many of the fields are new.
+    pub fn hset<F: Into<Vec<u8>>, V: Into<Vec<u8>>>(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        pairs: impl IntoIterator<Item = (F, V)>,
+    ) -> Result<i64, io::Error> {
+        let pairs = pairs
+            .into_iter()
+            .map(|(field, value)| (field.into(), value.into()))
+            .collect();
+        number(self.send(Command::HSet(key.into(), pairs))?)
+    }
+
+    /// Get the value of the field of the hash stored at the given key.
+    pub fn hget(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        field: impl Into<Vec<u8>>,
+    ) -> Result<Option<Vec<u8>>, io::Error> {
+        value_or_none(self.send(Command::HGet(key.into(), field.into()))?)
+    }
+
+    /// Get the values of the fields of the hash stored at the given key, None for the
+    /// fields that don't exist.
+    pub fn hmget<F: Into<Vec<u8>>>(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        fields: impl IntoIterator<Item = F>,
+    ) -> Result<Vec<Option<Vec<u8>>>, io::Error> {
+        let fields = fields.into_iter().map(Into::into).collect();
+        nullable_array(self.send(Command::HMGet(key.into(), fields))?)
+    }
+
+    /// Remove the fields from the hash stored at the given key.
+    /// Returns how many of them existed.
+    pub fn hdel<F: Into<Vec<u8>>>(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        fields: impl IntoIterator<Item = F>,
+    ) -> Result<i64, io::Error> {
+        let fields = fields.into_iter().map(Into::into).collect();
+        number(self.send(Command::HDel(key.into(), fields))?)
+    }
+
+    /// Get all the fields of the hash stored at the given key along with their values.
+    pub fn hgetall(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+    ) -> Result<HashMap<Vec<u8>, Vec<u8>>, io::Error> {
+        let items = array(self.send(Command::HGetAll(key.into()))?)?;
+        if !items.len().is_multiple_of(2) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "Response is not made of fields and values",
+            ));
+        }
+
+        let mut items = items.into_iter();
+        let mut fields = HashMap::new();
+        while let (Some(field), Some(value)) = (items.next(), items.next()) {
+            fields.insert(field, value);
+        }
+
+        Ok(fields)
+    }
+
+    /// Get the fields of the hash stored at the given key, in no particular order.
+    pub fn hkeys(&mut self, key: impl Into<Vec<u8>>) -> Result<Vec<Vec<u8>>, io::Error> {
+        array(self.send(Command::HKeys(key.into()))?)
+    }
+
+    /// Get the values of the hash stored at the given key, in no particular order.
+    pub fn hvals(&mut self, key: impl Into<Vec<u8>>) -> Result<Vec<Vec<u8>>, io::Error> {
+        array(self.send(Command::HVals(key.into()))?)
+    }
+
+    /// Get the number of fields in the hash stored at the given key.
+    pub fn hlen(&mut self, key: impl Into<Vec<u8>>) -> Result<i64, io::Error> {
+        number(self.send(Command::HLen(key.into()))?)
+    }
+
+    /// Check whether the field exists in the hash stored at the given key.
+    pub fn hexists(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        field: impl Into<Vec<u8>>,
+    ) -> Result<bool, io::Error> {
+        let response = self.send(Command::HExists(key.into(), field.into()))?;
+        match response.status_code() {
+            StatusCodes::Ok => Ok(true),
+            StatusCodes::ErrNotFound => Ok(false),
+            _ => Err(response_error(&response)),
+        }
+    }
+
+    /// Add the delta to the integer stored in the field of the hash stored at the given
+    /// key, returns the new value.
+    pub fn hincr_by(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        field: impl Into<Vec<u8>>,
+        delta: i64,
+    ) -> Result<i64, io::Error> {
+        number(self.send(Command::HIncrBy(key.into(), field.into(), delta))?)
+    }
+
+    /// Add the members to the set stored at the given key.
+    /// Returns how many of them are new.
+    pub fn sadd<M: Into<Vec<u8>>>(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        members: impl IntoIterator<Item = M>,
+    ) -> Result<i64, io::Error> {
+        let members = members.into_iter().map(Into::into).collect();
+        number(self.send(Command::SAdd(key.into(), members))?)
+    }
+
+    /// Remove the members from the set stored at the given key.
+    /// Returns how many of them existed.
+    pub fn srem<M: Into<Vec<u8>>>(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        members: impl IntoIterator<Item = M>,
+    ) -> Result<i64, io::Error> {
+        let members = members.into_iter().map(Into::into).collect();
+        number(self.send(Command::SRem(key.into(), members))?)
+    }
+
+    /// Check whether the member is in the set stored at the given key.
+    pub fn sismember(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        member: impl Into<Vec<u8>>,
+    ) -> Result<bool, io::Error> {
+        let response = self.send(Command::SIsMember(key.into(), member.into()))?;
+        match response.status_code() {
+            StatusCodes::Ok => Ok(true),
+            StatusCodes::ErrNotFound => Ok(false),
+            _ => Err(response_error(&response)),
+        }
+    }
+
+    /// Get the members of the set stored at the given key.
+    pub fn smembers(&mut self, key: impl Into<Vec<u8>>) -> Result<HashSet<Vec<u8>>, io::Error> {
+        set(self.send(Command::SMembers(key.into()))?)
+    }
+
+    /// Get the number of members in the set stored at the given key.
+    pub fn scard(&mut self, key: impl Into<Vec<u8>>) -> Result<i64, io::Error> {
+        number(self.send(Command::SCard(key.into()))?)
+    }
+
+    /// Remove and return a random member of the set stored at the given key.
+    pub fn spop(&mut self, key: impl Into<Vec<u8>>) -> Result<Option<Vec<u8>>, io::Error> {
+        value_or_none(self.send(Command::SPop(key.into(), None))?)
+    }
+
+    /// Return a random member of the set stored at the given key.
+    pub fn srandmember(&mut self, key: impl Into<Vec<u8>>) -> Result<Option<Vec<u8>>, io::Error> {
+        value_or_none(self.send(Command::SRandMember(key.into(), None))?)
+    }
+
+    /// Get the members that are in all of the sets stored at the given keys.
+    pub fn sinter<K: Into<Vec<u8>>>(
+        &mut self,
+        keys: impl IntoIterator<Item = K>,
+    ) -> Result<HashSet<Vec<u8>>, io::Error> {
+        let keys = keys.into_iter().map(Into::into).collect();
+        set(self.send(Command::SInter(keys))?)
+    }
+
+    /// Get the members that are in any of the sets stored at the given keys.
+    pub fn sunion<K: Into<Vec<u8>>>(
+        &mut self,
+        keys: impl IntoIterator<Item = K>,
+    ) -> Result<HashSet<Vec<u8>>, io::Error> {
+        let keys = keys.into_iter().map(Into::into).collect();
+        set(self.send(Command::SUnion(keys))?)
+    }
+
+    /// Get the members of the first set that aren't in any of the other ones.
+    pub fn sdiff<K: Into<Vec<u8>>>(
+        &mut self,
+        keys: impl IntoIterator<Item = K>,
+    ) -> Result<HashSet<Vec<u8>>, io::Error> {
+        let keys = keys.into_iter().map(Into::into).collect();
+        set(self.send(Command::SDiff(keys))?)
+    }
+
+    /// Same as [`Client::sinter`], but the result is stored at the destination.
+    /// Returns its size.
+    pub fn sinterstore<K: Into<Vec<u8>>>(
+        &mut self,
+        destination: impl Into<Vec<u8>>,
+        keys: impl IntoIterator<Item = K>,
+    ) -> Result<i64, io::Error> {
+        let keys = keys.into_iter().map(Into::into).collect();
+        number(self.send(Command::SInterStore(destination.into(), keys))?)
+    }
+
+    /// Same as [`Client::sunion`], but the result is stored at the destination.
+    /// Returns its size.
+    pub fn sunionstore<K: Into<Vec<u8>>>(
+        &mut self,
+        destination: impl Into<Vec<u8>>,
+        keys: impl IntoIterator<Item = K>,
+    ) -> Result<i64, io::Error> {
+        let keys = keys.into_iter().map(Into::into).collect();
+        number(self.send(Command::SUnionStore(destination.into(), keys))?)
+    }
+
+    /// Same as [`Client::sdiff`], but the result is stored at the destination.
+    /// Returns its size.
+    pub fn sdiffstore<K: Into<Vec<u8>>>(
+        &mut self,
+        destination: impl Into<Vec<u8>>,
+        keys: impl IntoIterator<Item = K>,
+    ) -> Result<i64, io::Error> {
+        let keys = keys.into_iter().map(Into::into).collect();
+        number(self.send(Command::SDiffStore(destination.into(), keys))?)
+    }
+
+    /// Add the members to the sorted set stored at the given key with the scores given
+    /// before them, or update their scores. Returns how many of them are new.
+    pub fn zadd<M: Into<Vec<u8>>>(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        members: impl IntoIterator<Item = (f64, M)>,
+    ) -> Result<i64, io::Error> {
+        let members = members
+            .into_iter()
+            .map(|(score, member)| (score, member.into()))
+            .collect();
+        number(self.send(Command::ZAdd(key.into(), members))?)
+    }
+
+    /// Remove the members from the sorted set stored at the given key.
+    /// Returns how many of them existed.
+    pub fn zrem<M: Into<Vec<u8>>>(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        members: impl IntoIterator<Item = M>,
+    ) -> Result<i64, io::Error> {
+        let members = members.into_iter().map(Into::into).collect();
+        number(self.send(Command::ZRem(key.into(), members))?)
+    }
+
+    /// Get the score of the member of the sorted set stored at the given key.
+    pub fn zscore(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        member: impl Into<Vec<u8>>,
+    ) -> Result<Option<f64>, io::Error> {
+        number_or_none(self.send(Command::ZScore(key.into(), member.into()))?)
+    }
+
+    /// Add the delta to the score of the member of the sorted set stored at the given key,
+    /// returns the new score. A missing member counts as 0.
+    pub fn zincr_by(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        delta: f64,
+        member: impl Into<Vec<u8>>,
+    ) -> Result<f64, io::Error> {
+        number(self.send(Command::ZIncrBy(key.into(), delta, member.into()))?)
+    }
+
+    /// Get the position of the member in the sorted set stored at the given key, from the
+    /// lowest score.
+    pub fn zrank(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        member: impl Into<Vec<u8>>,
+    ) -> Result<Option<i64>, io::Error> {
+        number_or_none(self.send(Command::ZRank(key.into(), member.into()))?)
+    }
+
+    /// Get the members of the sorted set stored at the given key between the positions,
+    /// both included, from the lowest score. Negative positions count from the highest.
+    pub fn zrange(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        start: i64,
+        stop: i64,
+    ) -> Result<Vec<Vec<u8>>, io::Error> {
+        array(self.send(Command::ZRange(key.into(), start, stop, false))?)
+    }
+
+    /// Same as [`Client::zrange`], along with the scores.
+    pub fn zrange_with_scores(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        start: i64,
+        stop: i64,
+    ) -> Result<Vec<(Vec<u8>, f64)>, io::Error> {
+        scored(self.send(Command::ZRange(key.into(), start, stop, true))?)
+    }
+
+    /// Same as [`Client::zrange`], from the highest score.
+    pub fn zrevrange(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        start: i64,
+        stop: i64,
+    ) -> Result<Vec<Vec<u8>>, io::Error> {
+        array(self.send(Command::ZRevRange(key.into(), start, stop, false))?)
+    }
+
+    /// Same as [`Client::zrevrange`], along with the scores.
+    pub fn zrevrange_with_scores(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        start: i64,
+        stop: i64,
+    ) -> Result<Vec<(Vec<u8>, f64)>, io::Error> {
+        scored(self.send(Command::ZRevRange(key.into(), start, stop, true))?)
+    }
+
+    /// Get the members of the sorted set stored at the given key with a score between the
+    /// bounds, from the lowest score. The limit skips the given number of members and
+    /// keeps at most the given number of the ones after them.
+    pub fn zrangebyscore(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        min: impl Into<ScoreBound>,
+        max: impl Into<ScoreBound>,
+        limit: Option<(usize, usize)>,
+    ) -> Result<Vec<Vec<u8>>, io::Error> {
+        let options = ScoreRangeOptions {
+            with_scores: false,
+            limit,
+        };
+        let command = Command::ZRangeByScore(key.into(), min.into(), max.into(), options);
+        array(self.send(command)?)
+    }
+
+    /// Same as [`Client::zrangebyscore`], along with the scores.
+    pub fn zrangebyscore_with_scores(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        min: impl Into<ScoreBound>,
+        max: impl Into<ScoreBound>,
+        limit: Option<(usize, usize)>,
+    ) -> Result<Vec<(Vec<u8>, f64)>, io::Error> {
+        let options = ScoreRangeOptions {
+            with_scores: true,
+            limit,
+        };
+        let command = Command::ZRangeByScore(key.into(), min.into(), max.into(), options);
+        scored(self.send(command)?)
+    }
+
+    /// Get the number of members in the sorted set stored at the given key.
+    pub fn zcard(&mut self, key: impl Into<Vec<u8>>) -> Result<i64, io::Error> {
+        number(self.send(Command::ZCard(key.into()))?)
+    }
+
+    /// Append an entry with the fields and their values to the stream stored at the given
+    /// key, returns the ID made for it from the current time.
+    pub fn xadd<F: Into<Vec<u8>>, V: Into<Vec<u8>>>(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        fields: impl IntoIterator<Item = (F, V)>,
+    ) -> Result<StreamId, io::Error> {
+        let fields = fields
+            .into_iter()
+            .map(|(field, value)| (field.into(), value.into()))
+            .collect();
+        number(self.send(Command::XAdd(key.into(), None, fields))?)
+    }
+
+    /// Get the entries of the stream stored at the given key between the IDs, both
+    /// included, at most the count if one is given.
+    pub fn xrange(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        start: StreamId,
+        end: StreamId,
+        count: Option<usize>,
+    ) -> Result<Vec<StreamEntry>, io::Error> {
+        let response = self.send(Command::XRange(key.into(), start, end, count))?;
+        decoded(response, StreamEntry::decode_many)
+    }
+
+    /// Get the number of entries in the stream stored at the given key.
+    pub fn xlen(&mut self, key: impl Into<Vec<u8>>) -> Result<i64, io::Error> {
+        number(self.send(Command::XLen(key.into()))?)
+    }
+
+    /// Remove the oldest entries of the stream stored at the given key so that at most
+    /// `max_len` are left, returns how many were removed.
+    pub fn xtrim(&mut self, key: impl Into<Vec<u8>>, max_len: usize) -> Result<i64, io::Error> {
+        number(self.send(Command::XTrim(key.into(), max_len))?)
+    }
+
+    /// Create the consumer group on the stream stored at the given key, reading after the
+    /// given ID or the entries added from now on. Returns false if the group already exists.
+    pub fn xgroup_create(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        group: impl Into<Vec<u8>>,
+        start: Option<StreamId>,
+        make_stream: bool,
+    ) -> Result<bool, io::Error> {
+        let command = Command::XGroupCreate(key.into(), group.into(), start, make_stream);
+        let response = self.send(command)?;
+        match response.status_code() {
+            StatusCodes::Ok => Ok(true),
+            StatusCodes::ErrConflict => Ok(false),
+            _ => Err(response_error(&response)),
+        }
+    }
+
+    /// Read entries of the streams stored at the given keys for the consumer of the group,
+    /// see [`Command::XReadGroup`]. Only the streams with entries to read are returned.
+    pub fn xreadgroup<K: Into<Vec<u8>>>(
+        &mut self,
+        group: impl Into<Vec<u8>>,
+        consumer: impl Into<Vec<u8>>,
+        count: Option<usize>,
+        streams: impl IntoIterator<Item = (K, Option<StreamId>)>,
+    ) -> Result<ReadStreams, io::Error> {
+        let streams = streams
+            .into_iter()
+            .map(|(key, after)| (key.into(), after))
+            .collect();
+        let command = Command::XReadGroup(group.into(), consumer.into(), count, streams);
+        let response = self.send(command)?;
+        match response.status_code() {
+            StatusCodes::ErrNotFound => Ok(Vec::new()),
+            _ => decoded(response, decode_streams),
+        }
+    }
+
+    /// Acknowledge the entries pending in the consumer group of the stream stored at the
+    /// given key, returns how many of them were pending.
+    pub fn xack(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        group: impl Into<Vec<u8>>,
+        ids: impl IntoIterator<Item = StreamId>,
+    ) -> Result<i64, io::Error> {
+        let ids = ids.into_iter().collect();
+        number(self.send(Command::XAck(key.into(), group.into(), ids))?)
+    }
+
+    /// Get a summary of the entries pending in the consumer group of the stream stored at
+    /// the given key.
+    pub fn xpending(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        group: impl Into<Vec<u8>>,
+    ) -> Result<PendingSummary, io::Error> {
+        let response = self.send(Command::XPending(key.into(), group.into(), None))?;
+        decoded(response, PendingSummary::decode)
+    }
+
+    /// Get the entries pending in the consumer group of the stream stored at the given key
+    /// that are in the range.
+    pub fn xpending_range(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        group: impl Into<Vec<u8>>,
+        range: PendingRange,
+    ) -> Result<Vec<PendingEntry>, io::Error> {
+        let response = self.send(Command::XPending(key.into(), group.into(), Some(range)))?;
+        decoded(response, PendingEntry::decode_many)
+    }
+
+    /// Hand the entries pending in the consumer group of the stream stored at the given
+    /// key over to the consumer, those idle for at least `min_idle` milliseconds. Returns
+    /// the entries it got.
+    pub fn xclaim(
+        &mut self,
+        key: impl Into<Vec<u8>>,
+        group: impl Into<Vec<u8>>,
+        consumer: impl Into<Vec<u8>>,
+        min_idle: u64,
+        ids: impl IntoIterator<Item = StreamId>,
+    ) -> Result<Vec<StreamEntry>, io::Error> {
+        let ids = ids.into_iter().collect();
+        let command = Command::XClaim(key.into(), group.into(), consumer.into(), min_idle, ids);
+        decoded(self.send(command)?, StreamEntry::decode_many)
+    }
+
+    /// Save a snapshot of the keyspace, returns once it's done.
+    pub fn save(&mut self) -> Result<Response, io::Error> {
+        self.send(Command::Save)
+    }
+
+    /// Start saving a snapshot of the keyspace in the background.
+    pub fn bgsave(&mut self) -> Result<Response, io::Error> {
+        self.send(Command::BgSave)
+    }
+
+    /// Stop the server, the connection is closed by the server once it's done.
+    pub fn shutdown_server(&mut self, mode: ShutdownMode) -> Result<Response, io::Error> {
+        self.send(Command::Shutdown(mode))
+    }
+
+    /// Negotiate the version of the protocol with the server and ask it what it supports.
+    pub fn hello(&mut self) -> Result<Hello, io::Error> {
+        let response = self.send(Command::Hello(PROTOCOL_VERSION))?;
+        let msg = response.message().unwrap_or_default();
+        if response.status_code() != StatusCodes::Ok {
+            let msg = String::from_utf8_lossy(msg);
+            return Err(io::Error::new(ErrorKind::Unsupported, msg));
+        }
+
+        Hello::decode(msg).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+    }
+
+    pub fn send(&mut self, command: Command) -> Result<Response, io::Error> {
+        let mut responses = self.pipeline([command])?;
+        Ok(responses.remove(0))
+    }
+
+    /// Send all of the commands without waiting for the response of each one in between,
+    /// saving a round trip per command. The responses are returned in the same order as
+    /// the commands.
+    pub fn pipeline(
+        &mut self,
+        commands: impl IntoIterator<Item = Command>,
+    ) -> Result<Vec<Response>, io::Error> {
+        let mut payload = Vec::new();
+        let mut expected = 0;
+        for mut command in commands {
+            payload.extend_from_slice(Request::outof(&mut command)?.payload());
+            expected += 1;
+        }
+
+        let mut responses = Vec::with_capacity(expected);
+        if expected == 0 {
+            return Ok(responses);
+        }
+
+        // Keep reading while writing, otherwise the server might stop reading our requests
+        // because we aren't reading its responses, for big enough pipelines.
+        self.poller.registry().reregister(
+            &mut self.connection,
+            CLIENT_TOKEN,
+            Interest::READABLE | Interest::WRITABLE,
+        )?;
 
         let mut events = Events::with_capacity(1);
+        let mut written = 0;
 
-        loop {
+        while responses.len() < expected {
             self.poller.poll(&mut events, Some(Duration::new(30, 0)))?;
 
             for event in events.iter() {
-                if event.is_writable() {
-                    self.connection.write_all(request.payload())?;
-                    self.connection.flush()?;
-
-                    self.poller.registry().reregister(
-                        &mut self.connection,
-                        CLIENT_TOKEN,
-                        Interest::READABLE,
-                    )?;
+                // Write as much as the socket accepts, the rest is written once it's
+                // writable again.
+                while event.is_writable() && written < payload.len() {
+                    match self.connection.write(&payload[written..]) {
+                        Ok(n) => written += n,
+                        Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                        Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                        Err(e) => return Err(e),
+                    }
+
+                    if written == payload.len() {
+                        self.connection.flush()?;
+                        self.poller.registry().reregister(
+                            &mut self.connection,
+                            CLIENT_TOKEN,
+                            Interest::READABLE,
+                        )?;
+                    }
                 }
 
                 if event.is_readable() {
-                    let response: Response = RawResponse::outof(&mut self.connection)?.into();
-                    self.poller.registry().reregister(
-                        &mut self.connection,
-                        CLIENT_TOKEN,
-                        Interest::WRITABLE,
-                    )?;
-
-                    return Ok(response);
+                    // The server might close the connection right after the last response,
+                    // e.g. when it's shutting down, so decode what's been read first.
+                    let read = self.decoder.read_from(&mut self.connection);
+                    while let Some(response) = self.decoder.decode() {
+                        responses.push(response.into());
+                    }
+
+                    if let Err(e) = read {
+                        if responses.len() < expected {
+                            return Err(e);
+                        }
+                    }
                 }
             }
         }
+
+        self.poller.registry().reregister(
+            &mut self.connection,
+            CLIENT_TOKEN,
+            Interest::WRITABLE,
+        )?;
+
+        Ok(responses)
     }
 }
 
@@ -80,3 +976,102 @@ impl Drop for Client {
         }
     }
 }
+
+/// The error a failed response stands for, with the message the server sent.
+fn response_error(response: &Response) -> io::Error {
+    let msg = match response.message() {
+        Some(msg) => String::from_utf8_lossy(msg).into_owned(),
+        None => response.status_code().to_string(),
+    };
+    io::Error::other(msg)
+}
+
+/// The value sent as the message of a successful response, None if the key doesn't exist.
+fn value_or_none(response: Response) -> Result<Option<Vec<u8>>, io::Error> {
+    match response.status_code() {
+        StatusCodes::Ok => Ok(Some(response.message().unwrap_or_default().to_vec())),
+        StatusCodes::ErrNotFound => Ok(None),
+        _ => Err(response_error(&response)),
+    }
+}
+
+/// The items sent as the message of a successful response.
+fn array(response: Response) -> Result<Vec<Vec<u8>>, io::Error> {
+    match response.status_code() {
+        StatusCodes::Ok => response
+            .array()
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e)),
+        _ => Err(response_error(&response)),
+    }
+}
+
+/// The distinct items sent as the message of a successful response.
+fn set(response: Response) -> Result<HashSet<Vec<u8>>, io::Error> {
+    array(response).map(|items| items.into_iter().collect())
+}
+
+/// The items sent as the message of a successful response, None for the missing ones.
+fn nullable_array(response: Response) -> Result<Vec<Option<Vec<u8>>>, io::Error> {
+    match response.status_code() {
+        StatusCodes::Ok => response
+            .nullable_array()
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e)),
+        _ => Err(response_error(&response)),
+    }
+}
+
+/// The members sent as the message of a successful response, each followed by its score.
+fn scored(response: Response) -> Result<Vec<(Vec<u8>, f64)>, io::Error> {
+    let items = array(response)?;
+    if !items.len().is_multiple_of(2) {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "Response is not made of members and scores",
+        ));
+    }
+
+    let invalid_score = || io::Error::new(ErrorKind::InvalidData, "Score is not a number");
+    let mut items = items.into_iter();
+    let mut members = Vec::new();
+    while let (Some(member), Some(score)) = (items.next(), items.next()) {
+        let score = std::str::from_utf8(&score)
+            .ok()
+            .and_then(|score| score.parse().ok())
+            .ok_or_else(invalid_score)?;
+        members.push((member, score));
+    }
+
+    Ok(members)
+}
+
+/// The message of a successful response, decoded with the given function.
+fn decoded<T>(
+    response: Response,
+    decode: impl FnOnce(&[u8]) -> Result<T, String>,
+) -> Result<T, io::Error> {
+    match response.status_code() {
+        StatusCodes::Ok => decode(response.message().unwrap_or_default())
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e)),
+        _ => Err(response_error(&response)),
+    }
+}
+
+/// The number sent as the message of a successful response, None if it doesn't exist.
+fn number_or_none<T: FromStr>(response: Response) -> Result<Option<T>, io::Error> {
+    match response.status_code() {
+        StatusCodes::ErrNotFound => Ok(None),
+        _ => number(response).map(Some),
+    }
+}
+
+/// The number sent as the message of a successful response.
+fn number<T: FromStr>(response: Response) -> Result<T, io::Error> {
+    if response.status_code() != StatusCodes::Ok {
+        return Err(response_error(&response));
+    }
+
+    std::str::from_utf8(response.message().unwrap_or_default())
+        .ok()
+        .and_then(|number| number.parse().ok())
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "Response is not a number"))
+}
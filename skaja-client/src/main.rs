@@ -28,8 +28,8 @@ fn main() -> Result<(), String> {
 
         let command = match Command::try_from(input.clone()) {
             Ok(command) => command,
-            Err(_) => {
-                println!("Unrecognized command: {}.", input);
+            Err(err) => {
+                println!("Error: {}.", err);
                 continue;
             }
         };
@@ -2,3 +2,9 @@ use mio::Token;
 
 pub const SERVER_TOKEN: Token = Token(0);
 pub const CLIENT_TOKEN: Token = Token(1);
+
+/// The newest version of the protocol this build speaks, see [`crate::Command::Hello`].
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// The oldest version of the protocol this build still speaks.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
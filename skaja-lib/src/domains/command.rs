@@ -1,100 +1,1894 @@
-use super::Request;
+use super::{Request, StatusCodes, StreamId};
 use crate::Extract;
-use std::{env::Args, io};
+use std::{borrow::Cow, env::Args, fmt, io, str::FromStr};
 
 /// The commands that can be sent to the server.
 #[derive(Debug, PartialEq)]
 pub enum Command {
-    Get(String),
-    Set(String, String),
-    Delete(String),
+    Get(Vec<u8>),
+    Set(Vec<u8>, Vec<u8>, SetOptions),
+    Delete(Vec<u8>),
+    /// Set a timeout on the key in seconds.
+    Expire(Vec<u8>, u64),
+    /// Set a timeout on the key in milliseconds.
+    PExpire(Vec<u8>, u64),
+    /// Make the key expire at the given unix timestamp in milliseconds.
+    PExpireAt(Vec<u8>, u64),
+    /// Get the remaining time to live of the key in seconds.
+    Ttl(Vec<u8>),
+    /// Remove the timeout of the key.
+    Persist(Vec<u8>),
+    /// Save a snapshot of the keyspace, blocking the server until it's done.
+    Save,
+    /// Save a snapshot of the keyspace in the background.
+    BgSave,
+    /// Stop the server once the responses that are already on their way have been written.
+    Shutdown(ShutdownMode),
+    /// Negotiate the version of the protocol, given the newest one the client speaks,
+    /// and ask the server what it supports. The server replies with a [`crate::Hello`].
+    Hello(u32),
+    /// Get the value of the key along with its flags and version, the server replies
+    /// with a [`crate::Versioned`].
+    Gets(Vec<u8>),
+    /// Set the value of the key only if its version is still the given one, i.e. nobody
+    /// has written the key since it was read with [`Command::Gets`].
+    Cas(Vec<u8>, Vec<u8>, u64, SetOptions),
+    /// Set the value of the key only if its current value is the expected one, given
+    /// before the new one. Replies like [`Command::Cas`].
+    CasValue(Vec<u8>, Vec<u8>, Vec<u8>, SetOptions),
+    /// Set the value of the key and reply with the value it had before, the same as a
+    /// [`Command::Get`] would.
+    GetSet(Vec<u8>, Vec<u8>),
+    /// Delete the key and reply with the value it had, the same as a [`Command::Get`] would.
+    GetDel(Vec<u8>),
+    /// Add the delta to the unsigned number stored at the key, only if the key exists.
+    /// Going below 0 stops at 0 and going above `u64::MAX` wraps around, the same as
+    /// memcached's `incr` and `decr`.
+    IncrExisting(Vec<u8>, i64),
+    /// Add the delta to the signed 64-bit integer stored at the key, a missing key counts
+    /// as 0. The server replies with the new value. `incr`, `decr` and `decrby` are parsed
+    /// into it.
+    IncrBy(Vec<u8>, i64),
+    /// Add the delta to the floating point number stored at the key, a missing key counts
+    /// as 0. The server replies with the new value.
+    IncrByFloat(Vec<u8>, f64),
+    /// List the keys starting with the prefix, all of them if it's empty. The server replies
+    /// with the keys sorted, encoded with [`crate::encode_array`].
+    Keys(Vec<u8>),
+    /// Get the values of the keys, the server replies with them encoded with
+    /// [`crate::encode_nullable_array`], nil for the keys that don't exist.
+    MGet(Vec<Vec<u8>>),
+    /// Set the values of the keys, all at once.
+    MSet(Vec<(Vec<u8>, Vec<u8>)>),
+    /// Set the values of the keys only if none of them exists, all at once. The server
+    /// replies with 1 if they were set and 0 otherwise.
+    MSetNx(Vec<(Vec<u8>, Vec<u8>)>),
+    /// Count how many of the keys exist, a key that's given twice is counted twice.
+    Exists(Vec<Vec<u8>>),
+    /// Delete the keys, the server replies with how many of them existed. A `del` with
+    /// several keys is parsed into it, see [`Command::Delete`] for a single one.
+    DeleteMany(Vec<Vec<u8>>),
+    /// Insert the values at the head of the list one after the other, so the last one ends
+    /// up first. The list is created if the key doesn't exist, the server replies with
+    /// its length.
+    LPush(Vec<u8>, Vec<Vec<u8>>),
+    /// Same as [`Command::LPush`], at the tail of the list.
+    RPush(Vec<u8>, Vec<Vec<u8>>),
+    /// Remove the first item of the list and reply with it, or that many items encoded
+    /// with [`crate::encode_array`] if a count is given.
+    LPop(Vec<u8>, Option<usize>),
+    /// Same as [`Command::LPop`], from the tail of the list.
+    RPop(Vec<u8>, Option<usize>),
+    /// Get the items between the indexes, both included, encoded with
+    /// [`crate::encode_array`]. Negative indexes count from the tail, -1 being the last item.
+    LRange(Vec<u8>, i64, i64),
+    /// Get the length of the list, 0 if the key doesn't exist.
+    LLen(Vec<u8>),
+    /// Get the item at the index, which counts from the tail if it's negative.
+    LIndex(Vec<u8>, i64),
+    /// Replace the item at the index, which counts from the tail if it's negative.
+    LSet(Vec<u8>, i64, Vec<u8>),
+    /// Only keep the items between the indexes, both included, the same as
+    /// [`Command::LRange`] would get them.
+    LTrim(Vec<u8>, i64, i64),
+    /// Remove the items equal to the value, at most `count` of them from the head if it's
+    /// positive, from the tail if it's negative, all of them if it's 0. The server replies
+    /// with how many were removed.
+    LRem(Vec<u8>, i64, Vec<u8>),
+    /// Set the fields of the hash to the values, the hash is created if the key doesn't
+    /// exist. The server replies with how many of the fields are new.
+    HSet(Vec<u8>, Vec<(Vec<u8>, Vec<u8>)>),
+    /// Get the value of the field of the hash.
+    HGet(Vec<u8>, Vec<u8>),
+    /// Get the values of the fields of the hash, the server replies with them encoded with
+    /// [`crate::encode_nullable_array`], nil for the fields that don't exist.
+    HMGet(Vec<u8>, Vec<Vec<u8>>),
+    /// Remove the fields from the hash, the server replies with how many of them existed.
+    HDel(Vec<u8>, Vec<Vec<u8>>),
+    /// Get the fields of the hash along with their values, encoded with
+    /// [`crate::encode_array`] as each field followed by its value, in no particular order.
+    HGetAll(Vec<u8>),
+    /// Get the fields of the hash, in no particular order.
+    HKeys(Vec<u8>),
+    /// Get the values of the hash, in no particular order.
+    HVals(Vec<u8>),
+    /// Get the number of fields in the hash, 0 if the key doesn't exist.
+    HLen(Vec<u8>),
+    /// Check whether the field exists in the hash.
+    HExists(Vec<u8>, Vec<u8>),
+    /// Add the delta to the signed 64-bit integer stored in the field of the hash, a
+    /// missing field counts as 0. The server replies with the new value.
+    HIncrBy(Vec<u8>, Vec<u8>, i64),
+    /// Add the members to the set, the set is created if the key doesn't exist. The server
+    /// replies with how many of the members are new.
+    SAdd(Vec<u8>, Vec<Vec<u8>>),
+    /// Remove the members from the set, the server replies with how many of them existed.
+    SRem(Vec<u8>, Vec<Vec<u8>>),
+    /// Check whether the member is in the set.
+    SIsMember(Vec<u8>, Vec<u8>),
+    /// Get the members of the set, in no particular order.
+    SMembers(Vec<u8>),
+    /// Get the number of members in the set, 0 if the key doesn't exist.
+    SCard(Vec<u8>),
+    /// Remove a random member of the set and reply with it, or that many distinct members
+    /// encoded with [`crate::encode_array`] if a count is given.
+    SPop(Vec<u8>, Option<usize>),
+    /// Same as [`Command::SPop`] without removing the members. A negative count allows
+    /// the same member to be picked several times, and picks exactly that many of them.
+    SRandMember(Vec<u8>, Option<i64>),
+    /// Get the members that are in all of the sets, a missing key counts as an empty set.
+    SInter(Vec<Vec<u8>>),
+    /// Get the members that are in any of the sets.
+    SUnion(Vec<Vec<u8>>),
+    /// Get the members of the first set that aren't in any of the others.
+    SDiff(Vec<Vec<u8>>),
+    /// Same as [`Command::SInter`], but the result is stored at the destination given
+    /// first, overwriting whatever was there. The server replies with its size.
+    SInterStore(Vec<u8>, Vec<Vec<u8>>),
+    /// Same as [`Command::SInterStore`] for [`Command::SUnion`].
+    SUnionStore(Vec<u8>, Vec<Vec<u8>>),
+    /// Same as [`Command::SInterStore`] for [`Command::SDiff`].
+    SDiffStore(Vec<u8>, Vec<Vec<u8>>),
+    /// Add the members to the sorted set with the scores given before them, or update the
+    /// scores of the ones already in it. The sorted set is created if the key doesn't exist,
+    /// the server replies with how many of the members are new.
+    ZAdd(Vec<u8>, Vec<(f64, Vec<u8>)>),
+    /// Remove the members from the sorted set, the server replies with how many of them
+    /// existed.
+    ZRem(Vec<u8>, Vec<Vec<u8>>),
+    /// Get the score of the member of the sorted set.
+    ZScore(Vec<u8>, Vec<u8>),
+    /// Add the delta to the score of the member of the sorted set, a missing member counts
+    /// as 0. The server replies with the new score.
+    ZIncrBy(Vec<u8>, f64, Vec<u8>),
+    /// Get the position of the member in the sorted set, from the lowest score.
+    ZRank(Vec<u8>, Vec<u8>),
+    /// Get the members between the positions, both included, from the lowest score.
+    /// Negative positions count from the highest score, -1 being the last member. The
+    /// server replies with them encoded with [`crate::encode_array`], each followed by its
+    /// score if `withscores` is given, i.e. the flag is set.
+    ZRange(Vec<u8>, i64, i64, bool),
+    /// Same as [`Command::ZRange`], from the highest score.
+    ZRevRange(Vec<u8>, i64, i64, bool),
+    /// Get the members with a score between the bounds, from the lowest score. Replies
+    /// like [`Command::ZRange`].
+    ZRangeByScore(Vec<u8>, ScoreBound, ScoreBound, ScoreRangeOptions),
+    /// Get the number of members in the sorted set, 0 if the key doesn't exist.
+    ZCard(Vec<u8>),
+    /// Append an entry with the fields and their values to the stream, which is created if
+    /// the key doesn't exist. The ID is made from the current time if none is given, `*` on
+    /// the wire, otherwise it has to be higher than the one of the last entry. The server
+    /// replies with the ID of the entry.
+    XAdd(Vec<u8>, Option<StreamId>, Vec<(Vec<u8>, Vec<u8>)>),
+    /// Get the entries of the stream between the IDs, both included, at most the count if
+    /// one is given. The server replies with them encoded with
+    /// [`crate::StreamEntry::encode_many`].
+    XRange(Vec<u8>, StreamId, StreamId, Option<usize>),
+    /// Get the number of entries in the stream, 0 if the key doesn't exist.
+    XLen(Vec<u8>),
+    /// Remove the oldest entries of the stream so that at most the given number are left,
+    /// `maxlen` on the wire. The server replies with how many were removed.
+    XTrim(Vec<u8>, usize),
+    /// Create the consumer group on the stream, the group starts reading after the given
+    /// ID, or from the entries added after now if none is given, `$` on the wire. The
+    /// stream is created if the key doesn't exist and the flag is set, `mkstream` on the
+    /// wire.
+    XGroupCreate(Vec<u8>, Vec<u8>, Option<StreamId>, bool),
+    /// Read entries of the streams for the consumer of the group, at most the count from
+    /// each of them if one is given. Without an ID, `>` on the wire, the entries that were
+    /// never delivered to the group are delivered to the consumer and become pending until
+    /// acknowledged. With one, the entries already pending for the consumer after it are
+    /// read again. The server replies with the entries encoded with
+    /// [`crate::encode_streams`], or not found if there are none.
+    XReadGroup(
+        Vec<u8>,
+        Vec<u8>,
+        Option<usize>,
+        Vec<(Vec<u8>, Option<StreamId>)>,
+    ),
+    /// Acknowledge the entries pending in the consumer group of the stream, the server
+    /// replies with how many of them were pending.
+    XAck(Vec<u8>, Vec<u8>, Vec<StreamId>),
+    /// Get the entries pending in the consumer group of the stream, as a
+    /// [`crate::PendingSummary`] if no range is given, otherwise the ones in the range
+    /// encoded with [`crate::PendingEntry::encode_many`].
+    XPending(Vec<u8>, Vec<u8>, Option<PendingRange>),
+    /// Hand the entries pending in the consumer group of the stream over to the consumer,
+    /// those that have been idle for at least the given milliseconds. The server replies
+    /// with the entries it claimed, encoded like [`Command::XRange`].
+    XClaim(Vec<u8>, Vec<u8>, Vec<u8>, u64, Vec<StreamId>),
 }
 
-impl TryFrom<String> for Command {
-    type Error = String;
+/// Whether the server saves a snapshot of the keyspace before shutting down.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ShutdownMode {
+    /// Only save if there are save rules configured.
+    #[default]
+    Default,
+    Save,
+    NoSave,
+}
 
-    fn try_from(string_command: String) -> Result<Self, Self::Error> {
-        let string_command = string_command.to_lowercase();
-        let splitted_string = string_command.split(' ').collect::<Vec<&str>>();
+/// Why a command couldn't be parsed, each kind is answered with its own status code.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandError {
+    /// The request doesn't make up a valid command, e.g. an argument is malformed.
+    Invalid(String),
+    /// The name of the command isn't recognized.
+    Unknown(String),
+    /// The command got the wrong number of arguments.
+    WrongArity(String),
+}
+
+impl CommandError {
+    /// The status code the server answers the invalid command with.
+    pub fn status_code(&self) -> StatusCodes {
+        match self {
+            CommandError::Invalid(_) => StatusCodes::ErrInvalidRequest,
+            CommandError::Unknown(_) => StatusCodes::ErrUnknownCommand,
+            CommandError::WrongArity(_) => StatusCodes::ErrWrongArity,
+        }
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::Invalid(msg)
+            | CommandError::Unknown(msg)
+            | CommandError::WrongArity(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+/// One end of the range of a [`Command::ZRangeByScore`], `-inf` and `+inf` on the wire
+/// for the infinities.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreBound {
+    pub score: f64,
+
+    /// Whether the members with exactly this score are left out, `(` before the score on
+    /// the wire.
+    pub exclusive: bool,
+}
+
+impl ScoreBound {
+    pub fn inclusive(score: f64) -> Self {
+        ScoreBound {
+            score,
+            exclusive: false,
+        }
+    }
+
+    pub fn exclusive(score: f64) -> Self {
+        ScoreBound {
+            score,
+            exclusive: true,
+        }
+    }
+
+    /// Whether the score is on the right side of the bound, given it's the lower one.
+    pub fn is_below(&self, score: f64) -> bool {
+        match self.exclusive {
+            true => self.score < score,
+            false => self.score <= score,
+        }
+    }
+
+    /// Whether the score is on the right side of the bound, given it's the upper one.
+    pub fn is_above(&self, score: f64) -> bool {
+        match self.exclusive {
+            true => score < self.score,
+            false => score <= self.score,
+        }
+    }
+
+    fn parse(arg: &[u8]) -> Result<Self, CommandError> {
+        let (score, exclusive) = match arg.strip_prefix(b"(") {
+            Some(score) => (score, true),
+            None => (arg, false),
+        };
+
+        Ok(ScoreBound {
+            score: parse_score(score)?,
+            exclusive,
+        })
+    }
+}
+
+impl From<f64> for ScoreBound {
+    fn from(score: f64) -> Self {
+        ScoreBound::inclusive(score)
+    }
+}
+
+impl fmt::Display for ScoreBound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.exclusive {
+            write!(f, "(")?;
+        }
+
+        match self.score {
+            f64::INFINITY => write!(f, "+inf"),
+            f64::NEG_INFINITY => write!(f, "-inf"),
+            score => write!(f, "{}", score),
+        }
+    }
+}
+
+/// The optional arguments of [`Command::ZRangeByScore`].
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ScoreRangeOptions {
+    /// Reply with each member followed by its score, `withscores` on the wire.
+    pub with_scores: bool,
+
+    /// Skip the given number of members and reply with at most the given number of the
+    /// ones that come after them, `limit offset count` on the wire.
+    pub limit: Option<(usize, usize)>,
+}
+
+/// The range of pending entries of a [`Command::XPending`], `[idle min-idle] start end
+/// count [consumer]` on the wire.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingRange {
+    /// Only the entries that have been idle for at least the milliseconds.
+    pub min_idle: Option<u64>,
+
+    pub start: StreamId,
+    pub end: StreamId,
+
+    /// The maximum number of entries.
+    pub count: usize,
+
+    /// Only the entries pending for the consumer.
+    pub consumer: Option<Vec<u8>>,
+}
+
+/// The optional arguments of [`Command::Set`].
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SetOptions {
+    /// When the key should expire, it lives forever if None.
+    pub expiry: Option<Expiry>,
+
+    /// Stored along with the value for the client, the server doesn't interpret them.
+    pub flags: u32,
+
+    /// Only set the value if the key is in the given state, it's always set if None.
+    pub condition: Option<SetCondition>,
+}
+
+/// When a [`Command::Set`] goes through, otherwise the server replies with
+/// [`StatusCodes::ErrConflict`] if the key exists and [`StatusCodes::ErrNotFound`] if it doesn't.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SetCondition {
+    /// Only if the key doesn't exist, `nx` on the wire.
+    IfAbsent,
+    /// Only if the key exists, `xx` on the wire.
+    IfPresent,
+}
+
+/// How long a key should live for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Expiry {
+    Seconds(u64),
+    Milliseconds(u64),
+    /// Lives until the given unix timestamp in milliseconds.
+    UnixMillis(u64),
+}
+
+impl Command {
+    /// The name of the command as it's sent over the wire.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::Get(_) => "get",
+            Command::Set(_, _, _) => "set",
+            Command::Delete(_) => "del",
+            Command::Expire(_, _) => "expire",
+            Command::PExpire(_, _) => "pexpire",
+            Command::PExpireAt(_, _) => "pexpireat",
+            Command::Ttl(_) => "ttl",
+            Command::Persist(_) => "persist",
+            Command::Save => "save",
+            Command::BgSave => "bgsave",
+            Command::Shutdown(_) => "shutdown",
+            Command::Hello(_) => "hello",
+            Command::Gets(_) => "gets",
+            Command::Cas(_, _, _, _) => "cas",
+            Command::CasValue(_, _, _, _) => "casvalue",
+            Command::GetSet(_, _) => "getset",
+            Command::GetDel(_) => "getdel",
+            Command::IncrExisting(_, _) => "incrx",
+            Command::IncrBy(_, _) => "incrby",
+            Command::IncrByFloat(_, _) => "incrbyfloat",
+            Command::Keys(_) => "keys",
+            Command::MGet(_) => "mget",
+            Command::MSet(_) => "mset",
+            Command::MSetNx(_) => "msetnx",
+            Command::Exists(_) => "exists",
+            Command::DeleteMany(_) => "mdel",
+            Command::LPush(_, _) => "lpush",
+            Command::RPush(_, _) => "rpush",
+            Command::LPop(_, _) => "lpop",
+            Command::RPop(_, _) => "rpop",
+            Command::LRange(_, _, _) => "lrange",
+            Command::LLen(_) => "llen",
+            Command::LIndex(_, _) => "lindex",
+            Command::LSet(_, _, _) => "lset",
+            Command::LTrim(_, _, _) => "ltrim",
+            Command::LRem(_, _, _) => "lrem",
+            Command::HSet(_, _) => "hset",
+            Command::HGet(_, _) => "hget",
+            Command::HMGet(_, _) => "hmget",
+            Command::HDel(_, _) => "hdel",
+            Command::HGetAll(_) => "hgetall",
+            Command::HKeys(_) => "hkeys",
+            Command::HVals(_) => "hvals",
+            Command::HLen(_) => "hlen",
+            Command::HExists(_, _) => "hexists",
+            Command::HIncrBy(_, _, _) => "hincrby",
+            Command::SAdd(_, _) => "sadd",
+            Command::SRem(_, _) => "srem",
+            Command::SIsMember(_, _) => "sismember",
+            Command::SMembers(_) => "smembers",
+            Command::SCard(_) => "scard",
+            Command::SPop(_, _) => "spop",
+            Command::SRandMember(_, _) => "srandmember",
+            Command::SInter(_) => "sinter",
+            Command::SUnion(_) => "sunion",
+            Command::SDiff(_) => "sdiff",
+            Command::SInterStore(_, _) => "sinterstore",
+            Command::SUnionStore(_, _) => "sunionstore",
+            Command::SDiffStore(_, _) => "sdiffstore",
+            Command::ZAdd(_, _) => "zadd",
+            Command::ZRem(_, _) => "zrem",
+            Command::ZScore(_, _) => "zscore",
+            Command::ZIncrBy(_, _, _) => "zincrby",
+            Command::ZRank(_, _) => "zrank",
+            Command::ZRange(_, _, _, _) => "zrange",
+            Command::ZRevRange(_, _, _, _) => "zrevrange",
+            Command::ZRangeByScore(_, _, _, _) => "zrangebyscore",
+            Command::ZCard(_) => "zcard",
+            Command::XAdd(_, _, _) => "xadd",
+            Command::XRange(_, _, _, _) => "xrange",
+            Command::XLen(_) => "xlen",
+            Command::XTrim(_, _) => "xtrim",
+            Command::XGroupCreate(_, _, _, _) => "xgroup",
+            Command::XReadGroup(_, _, _, _) => "xreadgroup",
+            Command::XAck(_, _, _) => "xack",
+            Command::XPending(_, _, _) => "xpending",
+            Command::XClaim(_, _, _, _, _) => "xclaim",
+        }
+    }
+
+    /// Whether the command modifies the keyspace.
+    pub fn is_write(&self) -> bool {
+        match self {
+            Command::Get(_)
+            | Command::Ttl(_)
+            | Command::Save
+            | Command::BgSave
+            | Command::Shutdown(_)
+            | Command::Hello(_)
+            | Command::Gets(_)
+            | Command::Keys(_)
+            | Command::MGet(_)
+            | Command::Exists(_)
+            | Command::LRange(_, _, _)
+            | Command::LLen(_)
+            | Command::LIndex(_, _)
+            | Command::HGet(_, _)
+            | Command::HMGet(_, _)
+            | Command::HGetAll(_)
+            | Command::HKeys(_)
+            | Command::HVals(_)
+            | Command::HLen(_)
+            | Command::HExists(_, _)
+            | Command::SIsMember(_, _)
+            | Command::SMembers(_)
+            | Command::SCard(_)
+            | Command::SRandMember(_, _)
+            | Command::SInter(_)
+            | Command::SUnion(_)
+            | Command::SDiff(_)
+            | Command::ZScore(_, _)
+            | Command::ZRank(_, _)
+            | Command::ZRange(_, _, _, _)
+            | Command::ZRevRange(_, _, _, _)
+            | Command::ZRangeByScore(_, _, _, _)
+            | Command::ZCard(_)
+            | Command::XRange(_, _, _, _)
+            | Command::XLen(_)
+            | Command::XPending(_, _, _) => false,
+            Command::Set(_, _, _)
+            | Command::Cas(_, _, _, _)
+            | Command::CasValue(_, _, _, _)
+            | Command::GetSet(_, _)
+            | Command::GetDel(_)
+            | Command::IncrExisting(_, _)
+            | Command::IncrBy(_, _)
+            | Command::IncrByFloat(_, _)
+            | Command::Delete(_)
+            | Command::Expire(_, _)
+            | Command::PExpire(_, _)
+            | Command::PExpireAt(_, _)
+            | Command::Persist(_)
+            | Command::MSet(_)
+            | Command::MSetNx(_)
+            | Command::DeleteMany(_)
+            | Command::LPush(_, _)
+            | Command::RPush(_, _)
+            | Command::LPop(_, _)
+            | Command::RPop(_, _)
+            | Command::LSet(_, _, _)
+            | Command::LTrim(_, _, _)
+            | Command::LRem(_, _, _)
+            | Command::HSet(_, _)
+            | Command::HDel(_, _)
+            | Command::HIncrBy(_, _, _)
+            | Command::SAdd(_, _)
+            | Command::SRem(_, _)
+            | Command::SPop(_, _)
+            | Command::SInterStore(_, _)
+            | Command::SUnionStore(_, _)
+            | Command::SDiffStore(_, _)
+            | Command::ZAdd(_, _)
+            | Command::ZRem(_, _)
+            | Command::ZIncrBy(_, _, _)
+            | Command::XAdd(_, _, _)
+            | Command::XTrim(_, _)
+            | Command::XGroupCreate(_, _, _, _)
+            | Command::XReadGroup(_, _, _, _)
+            | Command::XAck(_, _, _)
+            | Command::XClaim(_, _, _, _, _) => true,
+        }
+    }
+
+    /// The key the command operates on, None for the commands that work on the whole keyspace
+    /// or on several keys.
+    pub fn key(&self) -> Option<&[u8]> {
+        match self {
+            Command::Get(key)
+            | Command::Set(key, _, _)
+            | Command::Delete(key)
+            | Command::Expire(key, _)
+            | Command::PExpire(key, _)
+            | Command::PExpireAt(key, _)
+            | Command::Ttl(key)
+            | Command::Persist(key)
+            | Command::Gets(key)
+            | Command::Cas(key, _, _, _)
+            | Command::CasValue(key, _, _, _)
+            | Command::GetSet(key, _)
+            | Command::GetDel(key)
+            | Command::IncrExisting(key, _)
+            | Command::IncrBy(key, _)
+            | Command::IncrByFloat(key, _)
+            | Command::LPush(key, _)
+            | Command::RPush(key, _)
+            | Command::LPop(key, _)
+            | Command::RPop(key, _)
+            | Command::LRange(key, _, _)
+            | Command::LLen(key)
+            | Command::LIndex(key, _)
+            | Command::LSet(key, _, _)
+            | Command::LTrim(key, _, _)
+            | Command::LRem(key, _, _)
+            | Command::HSet(key, _)
+            | Command::HGet(key, _)
+            | Command::HMGet(key, _)
+            | Command::HDel(key, _)
+            | Command::HGetAll(key)
+            | Command::HKeys(key)
+            | Command::HVals(key)
+            | Command::HLen(key)
+            | Command::HExists(key, _)
+            | Command::HIncrBy(key, _, _)
+            | Command::SAdd(key, _)
+            | Command::SRem(key, _)
+            | Command::SIsMember(key, _)
+            | Command::SMembers(key)
+            | Command::SCard(key)
+            | Command::SPop(key, _)
+            | Command::SRandMember(key, _)
+            | Command::ZAdd(key, _)
+            | Command::ZRem(key, _)
+            | Command::ZScore(key, _)
+            | Command::ZIncrBy(key, _, _)
+            | Command::ZRank(key, _)
+            | Command::ZRange(key, _, _, _)
+            | Command::ZRevRange(key, _, _, _)
+            | Command::ZRangeByScore(key, _, _, _)
+            | Command::ZCard(key)
+            | Command::XAdd(key, _, _)
+            | Command::XRange(key, _, _, _)
+            | Command::XLen(key)
+            | Command::XTrim(key, _)
+            | Command::XGroupCreate(key, _, _, _)
+            | Command::XAck(key, _, _)
+            | Command::XPending(key, _, _)
+            | Command::XClaim(key, _, _, _, _) => Some(key),
+            Command::Save
+            | Command::BgSave
+            | Command::Shutdown(_)
+            | Command::Hello(_)
+            | Command::Keys(_)
+            | Command::MGet(_)
+            | Command::MSet(_)
+            | Command::MSetNx(_)
+            | Command::Exists(_)
+            | Command::DeleteMany(_)
+            | Command::SInter(_)
+            | Command::SUnion(_)
+            | Command::SDiff(_)
+            | Command::SInterStore(_, _)
+            | Command::SUnionStore(_, _)
+            | Command::SDiffStore(_, _)
+            | Command::XReadGroup(_, _, _, _) => None,
+        }
+    }
+
+    /// All of the keys the command operates on, in the order they were given.
+    pub fn keys(&self) -> Vec<&[u8]> {
+        match self {
+            Command::MGet(keys)
+            | Command::Exists(keys)
+            | Command::DeleteMany(keys)
+            | Command::SInter(keys)
+            | Command::SUnion(keys)
+            | Command::SDiff(keys) => keys.iter().map(Vec::as_slice).collect(),
+            Command::SInterStore(destination, keys)
+            | Command::SUnionStore(destination, keys)
+            | Command::SDiffStore(destination, keys) => {
+                let mut all = vec![destination.as_slice()];
+                all.extend(keys.iter().map(Vec::as_slice));
+                all
+            }
+            Command::MSet(pairs) | Command::MSetNx(pairs) => {
+                pairs.iter().map(|(key, _)| key.as_slice()).collect()
+            }
+            Command::XReadGroup(_, _, _, streams) => {
+                streams.iter().map(|(key, _)| key.as_slice()).collect()
+            }
+            command => command.key().into_iter().collect(),
+        }
+    }
 
-        if splitted_string.is_empty() {
-            return Err("No command provided".to_string());
+    /// The arguments of the command as they're sent over the wire, excluding the name.
+    fn args(&self) -> Vec<Cow<'_, [u8]>> {
+        match self {
+            Command::Get(key)
+            | Command::Delete(key)
+            | Command::Ttl(key)
+            | Command::Persist(key)
+            | Command::Gets(key)
+            | Command::GetDel(key)
+            | Command::LLen(key)
+            | Command::LPop(key, None)
+            | Command::RPop(key, None)
+            | Command::HGetAll(key)
+            | Command::HKeys(key)
+            | Command::HVals(key)
+            | Command::HLen(key)
+            | Command::SMembers(key)
+            | Command::SCard(key)
+            | Command::SPop(key, None)
+            | Command::SRandMember(key, None)
+            | Command::ZCard(key)
+            | Command::XLen(key) => {
+                vec![Cow::from(key.as_slice())]
+            }
+            Command::SRandMember(key, Some(count)) => vec![
+                Cow::from(key.as_slice()),
+                Cow::from(count.to_string().into_bytes()),
+            ],
+            Command::LPop(key, Some(count))
+            | Command::RPop(key, Some(count))
+            | Command::SPop(key, Some(count)) => vec![
+                Cow::from(key.as_slice()),
+                Cow::from(count.to_string().into_bytes()),
+            ],
+            Command::LPush(key, values)
+            | Command::RPush(key, values)
+            | Command::HMGet(key, values)
+            | Command::HDel(key, values)
+            | Command::SAdd(key, values)
+            | Command::SRem(key, values)
+            | Command::SInterStore(key, values)
+            | Command::SUnionStore(key, values)
+            | Command::SDiffStore(key, values)
+            | Command::ZRem(key, values) => {
+                let mut args = vec![Cow::from(key.as_slice())];
+                args.extend(values.iter().map(|value| Cow::from(value.as_slice())));
+                args
+            }
+            Command::LRange(key, start, stop) | Command::LTrim(key, start, stop) => vec![
+                Cow::from(key.as_slice()),
+                Cow::from(start.to_string().into_bytes()),
+                Cow::from(stop.to_string().into_bytes()),
+            ],
+            Command::LIndex(key, index) => vec![
+                Cow::from(key.as_slice()),
+                Cow::from(index.to_string().into_bytes()),
+            ],
+            Command::LSet(key, number, value) | Command::LRem(key, number, value) => vec![
+                Cow::from(key.as_slice()),
+                Cow::from(number.to_string().into_bytes()),
+                Cow::from(value.as_slice()),
+            ],
+            Command::HSet(key, pairs) => {
+                let mut args = vec![Cow::from(key.as_slice())];
+                args.extend(pairs.iter().flat_map(|(field, value)| {
+                    [Cow::from(field.as_slice()), Cow::from(value.as_slice())]
+                }));
+                args
+            }
+            Command::ZAdd(key, members) => {
+                let mut args = vec![Cow::from(key.as_slice())];
+                args.extend(members.iter().flat_map(|(score, member)| {
+                    [
+                        Cow::from(score.to_string().into_bytes()),
+                        Cow::from(member.as_slice()),
+                    ]
+                }));
+                args
+            }
+            Command::ZIncrBy(key, delta, member) => vec![
+                Cow::from(key.as_slice()),
+                Cow::from(delta.to_string().into_bytes()),
+                Cow::from(member.as_slice()),
+            ],
+            Command::ZRange(key, start, stop, with_scores)
+            | Command::ZRevRange(key, start, stop, with_scores) => {
+                let mut args = vec![
+                    Cow::from(key.as_slice()),
+                    Cow::from(start.to_string().into_bytes()),
+                    Cow::from(stop.to_string().into_bytes()),
+                ];
+                if *with_scores {
+                    args.push(Cow::from(b"withscores".as_slice()));
+                }
+                args
+            }
+            Command::ZRangeByScore(key, min, max, options) => {
+                let mut args = vec![
+                    Cow::from(key.as_slice()),
+                    Cow::from(min.to_string().into_bytes()),
+                    Cow::from(max.to_string().into_bytes()),
+                ];
+                args.extend(options.args());
+                args
+            }
+            Command::XAdd(key, id, fields) => {
+                let id = match id {
+                    Some(id) => Cow::from(id.to_string().into_bytes()),
+                    None => Cow::from(b"*".as_slice()),
+                };
+                let mut args = vec![Cow::from(key.as_slice()), id];
+                args.extend(fields.iter().flat_map(|(field, value)| {
+                    [Cow::from(field.as_slice()), Cow::from(value.as_slice())]
+                }));
+                args
+            }
+            Command::XRange(key, start, end, count) => {
+                let mut args = vec![
+                    Cow::from(key.as_slice()),
+                    Cow::from(start.to_string().into_bytes()),
+                    Cow::from(end.to_string().into_bytes()),
+                ];
+                if let Some(count) = count {
+                    args.push(Cow::from(b"count".as_slice()));
+                    args.push(Cow::from(count.to_string().into_bytes()));
+                }
+                args
+            }
+            Command::XTrim(key, max_len) => vec![
+                Cow::from(key.as_slice()),
+                Cow::from(b"maxlen".as_slice()),
+                Cow::from(max_len.to_string().into_bytes()),
+            ],
+            Command::XGroupCreate(key, group, start, mkstream) => {
+                let start = match start {
+                    Some(start) => Cow::from(start.to_string().into_bytes()),
+                    None => Cow::from(b"$".as_slice()),
+                };
+                let mut args = vec![
+                    Cow::from(b"create".as_slice()),
+                    Cow::from(key.as_slice()),
+                    Cow::from(group.as_slice()),
+                    start,
+                ];
+                if *mkstream {
+                    args.push(Cow::from(b"mkstream".as_slice()));
+                }
+                args
+            }
+            Command::XReadGroup(group, consumer, count, streams) => {
+                let mut args = vec![
+                    Cow::from(b"group".as_slice()),
+                    Cow::from(group.as_slice()),
+                    Cow::from(consumer.as_slice()),
+                ];
+                if let Some(count) = count {
+                    args.push(Cow::from(b"count".as_slice()));
+                    args.push(Cow::from(count.to_string().into_bytes()));
+                }
+                args.push(Cow::from(b"streams".as_slice()));
+                args.extend(streams.iter().map(|(key, _)| Cow::from(key.as_slice())));
+                args.extend(streams.iter().map(|(_, id)| match id {
+                    Some(id) => Cow::from(id.to_string().into_bytes()),
+                    None => Cow::from(b">".as_slice()),
+                }));
+                args
+            }
+            Command::XAck(key, group, ids) => {
+                let mut args = vec![Cow::from(key.as_slice()), Cow::from(group.as_slice())];
+                args.extend(ids.iter().map(|id| Cow::from(id.to_string().into_bytes())));
+                args
+            }
+            Command::XPending(key, group, range) => {
+                let mut args = vec![Cow::from(key.as_slice()), Cow::from(group.as_slice())];
+                if let Some(range) = range {
+                    args.extend(range.args());
+                }
+                args
+            }
+            Command::XClaim(key, group, consumer, min_idle, ids) => {
+                let mut args = vec![
+                    Cow::from(key.as_slice()),
+                    Cow::from(group.as_slice()),
+                    Cow::from(consumer.as_slice()),
+                    Cow::from(min_idle.to_string().into_bytes()),
+                ];
+                args.extend(ids.iter().map(|id| Cow::from(id.to_string().into_bytes())));
+                args
+            }
+            Command::HIncrBy(key, field, delta) => vec![
+                Cow::from(key.as_slice()),
+                Cow::from(field.as_slice()),
+                Cow::from(delta.to_string().into_bytes()),
+            ],
+            Command::GetSet(key, value)
+            | Command::HGet(key, value)
+            | Command::HExists(key, value)
+            | Command::SIsMember(key, value)
+            | Command::ZScore(key, value)
+            | Command::ZRank(key, value) => {
+                vec![Cow::from(key.as_slice()), Cow::from(value.as_slice())]
+            }
+            Command::Set(key, value, options) => {
+                let mut args = vec![Cow::from(key.as_slice()), Cow::from(value.as_slice())];
+                args.extend(options.args());
+                args
+            }
+            Command::Cas(key, value, version, options) => {
+                let mut args = vec![
+                    Cow::from(key.as_slice()),
+                    Cow::from(value.as_slice()),
+                    Cow::from(version.to_string().into_bytes()),
+                ];
+                args.extend(options.args());
+                args
+            }
+            Command::CasValue(key, expected, value, options) => {
+                let mut args = vec![
+                    Cow::from(key.as_slice()),
+                    Cow::from(expected.as_slice()),
+                    Cow::from(value.as_slice()),
+                ];
+                args.extend(options.args());
+                args
+            }
+            Command::IncrExisting(key, delta) | Command::IncrBy(key, delta) => vec![
+                Cow::from(key.as_slice()),
+                Cow::from(delta.to_string().into_bytes()),
+            ],
+            Command::IncrByFloat(key, delta) => vec![
+                Cow::from(key.as_slice()),
+                Cow::from(delta.to_string().into_bytes()),
+            ],
+            Command::Save | Command::BgSave | Command::Shutdown(ShutdownMode::Default) => {
+                Vec::new()
+            }
+            Command::Shutdown(ShutdownMode::Save) => vec![Cow::from(b"save".as_slice())],
+            Command::Shutdown(ShutdownMode::NoSave) => vec![Cow::from(b"nosave".as_slice())],
+            Command::Hello(version) => vec![Cow::from(version.to_string().into_bytes())],
+            Command::Keys(prefix) if prefix.is_empty() => Vec::new(),
+            Command::Keys(prefix) => vec![Cow::from(prefix.as_slice())],
+            Command::MGet(keys)
+            | Command::Exists(keys)
+            | Command::DeleteMany(keys)
+            | Command::SInter(keys)
+            | Command::SUnion(keys)
+            | Command::SDiff(keys) => keys.iter().map(|key| Cow::from(key.as_slice())).collect(),
+            Command::MSet(pairs) | Command::MSetNx(pairs) => pairs
+                .iter()
+                .flat_map(|(key, value)| [Cow::from(key.as_slice()), Cow::from(value.as_slice())])
+                .collect(),
+            Command::Expire(key, timeout)
+            | Command::PExpire(key, timeout)
+            | Command::PExpireAt(key, timeout) => vec![
+                Cow::from(key.as_slice()),
+                Cow::from(timeout.to_string().into_bytes()),
+            ],
         }
+    }
+
+    /// Parse a command out of its parts, the first part being the name of the command
+    /// (case-insensitive) and the rest being its arguments, e.g. `["set", "key", "value"]`.
+    pub fn from_parts(parts: Vec<Vec<u8>>) -> Result<Self, CommandError> {
+        let mut parts = parts.into_iter();
+        let name = match parts.next() {
+            Some(name) => name.to_ascii_lowercase(),
+            None => return Err(CommandError::Invalid("No command provided".to_string())),
+        };
+        let args: Vec<Vec<u8>> = parts.collect();
+
+        let command = match name.as_slice() {
+            b"get" => {
+                let [key] = exact_args("get", args)?;
+                Command::Get(key)
+            }
+            b"set" => {
+                if args.len() < 2 {
+                    return Err(CommandError::WrongArity(
+                        "\"set\" command needs 2 arguments".to_string(),
+                    ));
+                }
+
+                let mut args = args.into_iter();
+                let key = args.next().unwrap();
+                let value = args.next().unwrap();
+                Command::Set(key, value, SetOptions::parse("set", args)?)
+            }
+            b"cas" => {
+                if args.len() < 3 {
+                    return Err(CommandError::WrongArity(
+                        "\"cas\" command needs 3 arguments".to_string(),
+                    ));
+                }
+
+                let mut args = args.into_iter();
+                let key = args.next().unwrap();
+                let value = args.next().unwrap();
+                let version = parse_number(&args.next().unwrap())?;
+                Command::Cas(
+                    key,
+                    value,
+                    version,
+                    SetOptions::parse_unconditional("cas", args)?,
+                )
+            }
+            b"casvalue" => {
+                if args.len() < 3 {
+                    return Err(CommandError::WrongArity(
+                        "\"casvalue\" command needs 3 arguments".to_string(),
+                    ));
+                }
+
+                let mut args = args.into_iter();
+                let key = args.next().unwrap();
+                let expected = args.next().unwrap();
+                let value = args.next().unwrap();
+                let options = SetOptions::parse_unconditional("casvalue", args)?;
+                Command::CasValue(key, expected, value, options)
+            }
+            b"getset" => {
+                let [key, value] = exact_args("getset", args)?;
+                Command::GetSet(key, value)
+            }
+            b"getdel" => {
+                let [key] = exact_args("getdel", args)?;
+                Command::GetDel(key)
+            }
+            b"gets" => {
+                let [key] = exact_args("gets", args)?;
+                Command::Gets(key)
+            }
+            b"incrx" => {
+                let [key, delta] = exact_args("incrx", args)?;
+                Command::IncrExisting(key, parse_number(&delta)?)
+            }
+            b"incr" => {
+                let [key] = exact_args("incr", args)?;
+                Command::IncrBy(key, 1)
+            }
+            b"decr" => {
+                let [key] = exact_args("decr", args)?;
+                Command::IncrBy(key, -1)
+            }
+            b"incrby" => {
+                let [key, delta] = exact_args("incrby", args)?;
+                Command::IncrBy(key, parse_number(&delta)?)
+            }
+            b"decrby" => {
+                let [key, delta] = exact_args("decrby", args)?;
+                let delta = parse_number::<i64>(&delta)?.checked_neg().ok_or_else(|| {
+                    CommandError::Invalid("Decrement is out of range".to_string())
+                })?;
+                Command::IncrBy(key, delta)
+            }
+            b"incrbyfloat" => {
+                let [key, delta] = exact_args("incrbyfloat", args)?;
+                Command::IncrByFloat(key, parse_float(&delta)?)
+            }
+            b"del" if args.len() > 1 => Command::DeleteMany(args),
+            b"del" => {
+                let [key] = exact_args("del", args)?;
+                Command::Delete(key)
+            }
+            b"mdel" => Command::DeleteMany(at_least_one_arg("mdel", args)?),
+            b"mget" => Command::MGet(at_least_one_arg("mget", args)?),
+            b"exists" => Command::Exists(at_least_one_arg("exists", args)?),
+            b"mset" => Command::MSet(pairs("mset", args)?),
+            b"msetnx" => Command::MSetNx(pairs("msetnx", args)?),
+            b"lpush" => {
+                let (key, values) = key_and_values("lpush", args)?;
+                Command::LPush(key, values)
+            }
+            b"rpush" => {
+                let (key, values) = key_and_values("rpush", args)?;
+                Command::RPush(key, values)
+            }
+            b"lpop" => {
+                let (key, count) = key_and_count("lpop", args)?;
+                Command::LPop(key, count)
+            }
+            b"rpop" => {
+                let (key, count) = key_and_count("rpop", args)?;
+                Command::RPop(key, count)
+            }
+            b"lrange" => {
+                let [key, start, stop] = exact_args("lrange", args)?;
+                Command::LRange(key, parse_number(&start)?, parse_number(&stop)?)
+            }
+            b"llen" => {
+                let [key] = exact_args("llen", args)?;
+                Command::LLen(key)
+            }
+            b"lindex" => {
+                let [key, index] = exact_args("lindex", args)?;
+                Command::LIndex(key, parse_number(&index)?)
+            }
+            b"lset" => {
+                let [key, index, value] = exact_args("lset", args)?;
+                Command::LSet(key, parse_number(&index)?, value)
+            }
+            b"ltrim" => {
+                let [key, start, stop] = exact_args("ltrim", args)?;
+                Command::LTrim(key, parse_number(&start)?, parse_number(&stop)?)
+            }
+            b"lrem" => {
+                let [key, count, value] = exact_args("lrem", args)?;
+                Command::LRem(key, parse_number(&count)?, value)
+            }
+            b"hset" => {
+                let (key, args) = key_and_values("hset", args)?;
+                Command::HSet(key, pairs("hset", args)?)
+            }
+            b"hget" => {
+                let [key, field] = exact_args("hget", args)?;
+                Command::HGet(key, field)
+            }
+            b"hmget" => {
+                let (key, fields) = key_and_values("hmget", args)?;
+                Command::HMGet(key, fields)
+            }
+            b"hdel" => {
+                let (key, fields) = key_and_values("hdel", args)?;
+                Command::HDel(key, fields)
+            }
+            b"hgetall" => {
+                let [key] = exact_args("hgetall", args)?;
+                Command::HGetAll(key)
+            }
+            b"hkeys" => {
+                let [key] = exact_args("hkeys", args)?;
+                Command::HKeys(key)
+            }
+            b"hvals" => {
+                let [key] = exact_args("hvals", args)?;
+                Command::HVals(key)
+            }
+            b"hlen" => {
+                let [key] = exact_args("hlen", args)?;
+                Command::HLen(key)
+            }
+            b"hexists" => {
+                let [key, field] = exact_args("hexists", args)?;
+                Command::HExists(key, field)
+            }
+            b"hincrby" => {
+                let [key, field, delta] = exact_args("hincrby", args)?;
+                Command::HIncrBy(key, field, parse_number(&delta)?)
+            }
+            b"sadd" => {
+                let (key, members) = key_and_values("sadd", args)?;
+                Command::SAdd(key, members)
+            }
+            b"srem" => {
+                let (key, members) = key_and_values("srem", args)?;
+                Command::SRem(key, members)
+            }
+            b"sismember" => {
+                let [key, member] = exact_args("sismember", args)?;
+                Command::SIsMember(key, member)
+            }
+            b"smembers" => {
+                let [key] = exact_args("smembers", args)?;
+                Command::SMembers(key)
+            }
+            b"scard" => {
+                let [key] = exact_args("scard", args)?;
+                Command::SCard(key)
+            }
+            b"spop" => {
+                let (key, count) = key_and_count("spop", args)?;
+                Command::SPop(key, count)
+            }
+            b"srandmember" => {
+                let (key, count) = key_and_count("srandmember", args)?;
+                Command::SRandMember(key, count)
+            }
+            b"sinter" => Command::SInter(at_least_one_arg("sinter", args)?),
+            b"sunion" => Command::SUnion(at_least_one_arg("sunion", args)?),
+            b"sdiff" => Command::SDiff(at_least_one_arg("sdiff", args)?),
+            b"sinterstore" => {
+                let (destination, keys) = key_and_values("sinterstore", args)?;
+                Command::SInterStore(destination, keys)
+            }
+            b"sunionstore" => {
+                let (destination, keys) = key_and_values("sunionstore", args)?;
+                Command::SUnionStore(destination, keys)
+            }
+            b"sdiffstore" => {
+                let (destination, keys) = key_and_values("sdiffstore", args)?;
+                Command::SDiffStore(destination, keys)
+            }
+            b"zadd" => {
+                let (key, args) = key_and_values("zadd", args)?;
+                let members = pairs("zadd", args)?
+                    .into_iter()
+                    .map(|(score, member)| Ok((parse_score(&score)?, member)))
+                    .collect::<Result<_, CommandError>>()?;
+                Command::ZAdd(key, members)
+            }
+            b"zrem" => {
+                let (key, members) = key_and_values("zrem", args)?;
+                Command::ZRem(key, members)
+            }
+            b"zscore" => {
+                let [key, member] = exact_args("zscore", args)?;
+                Command::ZScore(key, member)
+            }
+            b"zincrby" => {
+                let [key, delta, member] = exact_args("zincrby", args)?;
+                Command::ZIncrBy(key, parse_score(&delta)?, member)
+            }
+            b"zrank" => {
+                let [key, member] = exact_args("zrank", args)?;
+                Command::ZRank(key, member)
+            }
+            b"zrange" => {
+                let (key, start, stop, with_scores) = rank_range("zrange", args)?;
+                Command::ZRange(key, start, stop, with_scores)
+            }
+            b"zrevrange" => {
+                let (key, start, stop, with_scores) = rank_range("zrevrange", args)?;
+                Command::ZRevRange(key, start, stop, with_scores)
+            }
+            b"zrangebyscore" => {
+                let mut args = args.into_iter();
+                let (Some(key), Some(min), Some(max)) = (args.next(), args.next(), args.next())
+                else {
+                    return Err(CommandError::WrongArity(
+                        "\"zrangebyscore\" command needs at least 3 arguments".to_string(),
+                    ));
+                };
+
+                Command::ZRangeByScore(
+                    key,
+                    ScoreBound::parse(&min)?,
+                    ScoreBound::parse(&max)?,
+                    ScoreRangeOptions::parse("zrangebyscore", args)?,
+                )
+            }
+            b"zcard" => {
+                let [key] = exact_args("zcard", args)?;
+                Command::ZCard(key)
+            }
+            b"xadd" => {
+                let (key, mut args) = key_and_values("xadd", args)?;
+                let id = match args.remove(0).as_slice() {
+                    b"*" => None,
+                    id => Some(parse_stream_id(id)?),
+                };
+                Command::XAdd(key, id, pairs("xadd", args)?)
+            }
+            b"xrange" => {
+                let mut args = args.into_iter();
+                let (Some(key), Some(start), Some(end)) = (args.next(), args.next(), args.next())
+                else {
+                    return Err(CommandError::WrongArity(
+                        "\"xrange\" command needs 3 or 5 arguments".to_string(),
+                    ));
+                };
+
+                let count = match (args.next(), args.next(), args.next()) {
+                    (None, _, _) => None,
+                    (Some(option), Some(count), None) if option.eq_ignore_ascii_case(b"count") => {
+                        Some(parse_number(&count)?)
+                    }
+                    (Some(_), Some(_), None) => {
+                        return Err(CommandError::Invalid(
+                            "Invalid option for \"xrange\" command".to_string(),
+                        ))
+                    }
+                    _ => {
+                        return Err(CommandError::WrongArity(
+                            "\"xrange\" command needs 3 or 5 arguments".to_string(),
+                        ))
+                    }
+                };
 
-        let command = splitted_string[0];
-        let command = match command {
-            "get" => {
-                let key = if splitted_string.len() < 2 {
-                    return Err("\"get\" command needs 1 argument".to_string());
-                } else {
-                    splitted_string[1]
+                Command::XRange(key, range_start(&start)?, range_end(&end)?, count)
+            }
+            b"xlen" => {
+                let [key] = exact_args("xlen", args)?;
+                Command::XLen(key)
+            }
+            b"xtrim" => {
+                let [key, strategy, max_len] = exact_args("xtrim", args)?;
+                if !strategy.eq_ignore_ascii_case(b"maxlen") {
+                    return Err(CommandError::Invalid(
+                        "Only \"maxlen\" is supported by \"xtrim\" command".to_string(),
+                    ));
+                }
+                Command::XTrim(key, parse_number(&max_len)?)
+            }
+            b"xgroup" => {
+                let mut args = args.into_iter();
+                match args.next() {
+                    Some(subcommand) if subcommand.eq_ignore_ascii_case(b"create") => {}
+                    Some(_) => {
+                        return Err(CommandError::Invalid(
+                            "Only \"create\" is supported by \"xgroup\" command".to_string(),
+                        ))
+                    }
+                    None => {
+                        return Err(CommandError::WrongArity(
+                            "\"xgroup\" command needs a subcommand".to_string(),
+                        ))
+                    }
+                }
+
+                let arity = || {
+                    CommandError::WrongArity(
+                        "\"xgroup create\" command needs 3 or 4 arguments".to_string(),
+                    )
+                };
+                let (Some(key), Some(group), Some(start)) = (args.next(), args.next(), args.next())
+                else {
+                    return Err(arity());
+                };
+
+                let mkstream = match (args.next(), args.next()) {
+                    (None, _) => false,
+                    (Some(option), None) if option.eq_ignore_ascii_case(b"mkstream") => true,
+                    (Some(_), None) => {
+                        return Err(CommandError::Invalid(
+                            "Invalid option for \"xgroup create\" command".to_string(),
+                        ))
+                    }
+                    (Some(_), Some(_)) => return Err(arity()),
+                };
+
+                let start = match start.as_slice() {
+                    b"$" => None,
+                    start => Some(parse_stream_id(start)?),
+                };
+                Command::XGroupCreate(key, group, start, mkstream)
+            }
+            b"xreadgroup" => read_group(args)?,
+            b"xack" => {
+                let mut args = args.into_iter();
+                let (Some(key), Some(group), Some(id)) = (args.next(), args.next(), args.next())
+                else {
+                    return Err(CommandError::WrongArity(
+                        "\"xack\" command needs at least 3 arguments".to_string(),
+                    ));
+                };
+
+                let ids = std::iter::once(id)
+                    .chain(args)
+                    .map(|id| parse_stream_id(&id))
+                    .collect::<Result<_, _>>()?;
+                Command::XAck(key, group, ids)
+            }
+            b"xpending" => {
+                let mut args = args.into_iter();
+                let (Some(key), Some(group)) = (args.next(), args.next()) else {
+                    return Err(CommandError::WrongArity(
+                        "\"xpending\" command needs at least 2 arguments".to_string(),
+                    ));
                 };
 
-                Command::Get(key.to_owned())
+                let range = match args.len() {
+                    0 => None,
+                    _ => Some(PendingRange::parse(args)?),
+                };
+                Command::XPending(key, group, range)
             }
-            "set" => {
-                let (key, value) = if splitted_string.len() < 3 {
-                    return Err("\"set\" command needs 2 arguments".to_string());
-                } else {
-                    (splitted_string[1], splitted_string[2])
+            b"xclaim" => {
+                let mut args = args.into_iter();
+                let (Some(key), Some(group), Some(consumer), Some(min_idle), Some(id)) = (
+                    args.next(),
+                    args.next(),
+                    args.next(),
+                    args.next(),
+                    args.next(),
+                ) else {
+                    return Err(CommandError::WrongArity(
+                        "\"xclaim\" command needs at least 5 arguments".to_string(),
+                    ));
                 };
 
-                Command::Set(key.to_owned(), value.to_owned())
-            }
-            "del" => {
-                let key = if splitted_string.len() < 2 {
-                    return Err("\"del\" command needs 1 argument".to_string());
-                } else {
-                    splitted_string[1]
-                };
+                let ids = std::iter::once(id)
+                    .chain(args)
+                    .map(|id| parse_stream_id(&id))
+                    .collect::<Result<_, _>>()?;
+                Command::XClaim(key, group, consumer, parse_number(&min_idle)?, ids)
+            }
+            b"expire" => {
+                let [key, secs] = exact_args("expire", args)?;
+                Command::Expire(key, parse_number(&secs)?)
+            }
+            b"pexpire" => {
+                let [key, millis] = exact_args("pexpire", args)?;
+                Command::PExpire(key, parse_number(&millis)?)
+            }
+            b"pexpireat" => {
+                let [key, timestamp] = exact_args("pexpireat", args)?;
+                Command::PExpireAt(key, parse_number(&timestamp)?)
+            }
+            b"ttl" => {
+                let [key] = exact_args("ttl", args)?;
+                Command::Ttl(key)
+            }
+            b"persist" => {
+                let [key] = exact_args("persist", args)?;
+                Command::Persist(key)
+            }
+            b"save" => {
+                let [] = exact_args("save", args)?;
+                Command::Save
+            }
+            b"bgsave" => {
+                let [] = exact_args("bgsave", args)?;
+                Command::BgSave
+            }
+            b"shutdown" => {
+                let mode = match args.as_slice() {
+                    [] => ShutdownMode::Default,
+                    [mode] => match mode.to_ascii_lowercase().as_slice() {
+                        b"save" => ShutdownMode::Save,
+                        b"nosave" => ShutdownMode::NoSave,
+                        _ => {
+                            return Err(CommandError::Invalid(
+                                "Invalid option for \"shutdown\" command".to_string(),
+                            ))
+                        }
+                    },
+                    _ => {
+                        return Err(CommandError::WrongArity(
+                            "\"shutdown\" command needs at most 1 argument".to_string(),
+                        ))
+                    }
+                };
+
+                Command::Shutdown(mode)
+            }
+            b"hello" => {
+                let [version] = exact_args("hello", args)?;
+                Command::Hello(parse_number(&version)?)
+            }
+            b"keys" => match <[Vec<u8>; 1]>::try_from(args) {
+                Ok([prefix]) => Command::Keys(prefix),
+                Err(args) if args.is_empty() => Command::Keys(Vec::new()),
+                Err(_) => {
+                    return Err(CommandError::WrongArity(
+                        "\"keys\" command needs at most 1 argument".to_string(),
+                    ))
+                }
+            },
+            _ => {
+                return Err(CommandError::Unknown(format!(
+                    "Unknown command \"{}\"",
+                    String::from_utf8_lossy(&name)
+                )))
+            }
+        };
+
+        Ok(command)
+    }
+}
+
+impl SetOptions {
+    /// Parse the options out of their `name value` pairs and the conditions, which go
+    /// without a value, e.g. `["ex", "10", "nx", "flags", "3"]`.
+    fn parse(command: &str, mut args: impl Iterator<Item = Vec<u8>>) -> Result<Self, CommandError> {
+        let mut options = SetOptions::default();
+        while let Some(name) = args.next() {
+            let name = name.to_ascii_lowercase();
+            let condition = match name.as_slice() {
+                b"nx" => Some(SetCondition::IfAbsent),
+                b"xx" => Some(SetCondition::IfPresent),
+                _ => None,
+            };
+            if let Some(condition) = condition {
+                if options.condition.is_some_and(|other| other != condition) {
+                    return Err(CommandError::Invalid(format!(
+                        "\"nx\" and \"xx\" can't be combined in \"{}\" command",
+                        command
+                    )));
+                }
+
+                options.condition = Some(condition);
+                continue;
+            }
+
+            let Some(amount) = args.next() else {
+                return Err(CommandError::WrongArity(format!(
+                    "Option of \"{}\" command needs a value",
+                    command
+                )));
+            };
+            if name == b"flags" {
+                options.flags = parse_number(&amount)?;
+                continue;
+            }
+
+            let amount = parse_number(&amount)?;
+            if amount == 0 {
+                return Err(CommandError::Invalid(format!(
+                    "Invalid expire time in \"{}\" command",
+                    command
+                )));
+            }
+
+            options.expiry = match name.as_slice() {
+                b"ex" => Some(Expiry::Seconds(amount)),
+                b"px" => Some(Expiry::Milliseconds(amount)),
+                b"pxat" => Some(Expiry::UnixMillis(amount)),
+                _ => {
+                    return Err(CommandError::Invalid(format!(
+                        "Invalid option for \"{}\" command",
+                        command
+                    )))
+                }
+            };
+        }
+
+        Ok(options)
+    }
+
+    /// Same as [`SetOptions::parse`], for the commands that have a condition of their own.
+    fn parse_unconditional(
+        command: &str,
+        args: impl Iterator<Item = Vec<u8>>,
+    ) -> Result<Self, CommandError> {
+        let options = Self::parse(command, args)?;
+        if options.condition.is_some() {
+            return Err(CommandError::Invalid(format!(
+                "Invalid option for \"{}\" command",
+                command
+            )));
+        }
+
+        Ok(options)
+    }
+
+    /// The options as they're sent over the wire.
+    fn args(&self) -> Vec<Cow<'_, [u8]>> {
+        let mut args = Vec::new();
+        match self.expiry {
+            Some(Expiry::Seconds(secs)) => {
+                args.push(Cow::from(b"ex".as_slice()));
+                args.push(Cow::from(secs.to_string().into_bytes()));
+            }
+            Some(Expiry::Milliseconds(millis)) => {
+                args.push(Cow::from(b"px".as_slice()));
+                args.push(Cow::from(millis.to_string().into_bytes()));
+            }
+            Some(Expiry::UnixMillis(timestamp)) => {
+                args.push(Cow::from(b"pxat".as_slice()));
+                args.push(Cow::from(timestamp.to_string().into_bytes()));
+            }
+            None => {}
+        }
+
+        if self.flags != 0 {
+            args.push(Cow::from(b"flags".as_slice()));
+            args.push(Cow::from(self.flags.to_string().into_bytes()));
+        }
+
+        match self.condition {
+            Some(SetCondition::IfAbsent) => args.push(Cow::from(b"nx".as_slice())),
+            Some(SetCondition::IfPresent) => args.push(Cow::from(b"xx".as_slice())),
+            None => {}
+        }
+
+        args
+    }
+}
+
+impl ScoreRangeOptions {
+    /// Parse the options out of `withscores` and `limit offset count`, in any order.
+    fn parse(command: &str, mut args: impl Iterator<Item = Vec<u8>>) -> Result<Self, CommandError> {
+        let mut options = ScoreRangeOptions::default();
+        while let Some(name) = args.next() {
+            match name.to_ascii_lowercase().as_slice() {
+                b"withscores" => options.with_scores = true,
+                b"limit" => {
+                    let (Some(offset), Some(count)) = (args.next(), args.next()) else {
+                        return Err(CommandError::WrongArity(format!(
+                            "\"limit\" of \"{}\" command needs an offset and a count",
+                            command
+                        )));
+                    };
+
+                    options.limit = Some((parse_number(&offset)?, parse_number(&count)?));
+                }
+                _ => {
+                    return Err(CommandError::Invalid(format!(
+                        "Invalid option for \"{}\" command",
+                        command
+                    )))
+                }
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// The options as they're sent over the wire.
+    fn args(&self) -> Vec<Cow<'_, [u8]>> {
+        let mut args = Vec::new();
+        if self.with_scores {
+            args.push(Cow::from(b"withscores".as_slice()));
+        }
+
+        if let Some((offset, count)) = self.limit {
+            args.push(Cow::from(b"limit".as_slice()));
+            args.push(Cow::from(offset.to_string().into_bytes()));
+            args.push(Cow::from(count.to_string().into_bytes()));
+        }
+
+        args
+    }
+}
+
+impl PendingRange {
+    fn parse(mut args: impl Iterator<Item = Vec<u8>>) -> Result<Self, CommandError> {
+        let arity = || {
+            CommandError::WrongArity(
+                "\"xpending\" command needs a start, an end and a count".to_string(),
+            )
+        };
+
+        let mut start = args.next().ok_or_else(arity)?;
+        let mut min_idle = None;
+        if start.eq_ignore_ascii_case(b"idle") {
+            let idle = args.next().ok_or_else(arity)?;
+            min_idle = Some(parse_number(&idle)?);
+            start = args.next().ok_or_else(arity)?;
+        }
+
+        let (Some(end), Some(count), consumer, None) =
+            (args.next(), args.next(), args.next(), args.next())
+        else {
+            return Err(arity());
+        };
+
+        Ok(PendingRange {
+            min_idle,
+            start: range_start(&start)?,
+            end: range_end(&end)?,
+            count: parse_number(&count)?,
+            consumer,
+        })
+    }
+
+    /// The range as it's sent over the wire.
+    fn args(&self) -> Vec<Cow<'_, [u8]>> {
+        let mut args = Vec::new();
+        if let Some(min_idle) = self.min_idle {
+            args.push(Cow::from(b"idle".as_slice()));
+            args.push(Cow::from(min_idle.to_string().into_bytes()));
+        }
+
+        args.push(Cow::from(self.start.to_string().into_bytes()));
+        args.push(Cow::from(self.end.to_string().into_bytes()));
+        args.push(Cow::from(self.count.to_string().into_bytes()));
+        if let Some(consumer) = &self.consumer {
+            args.push(Cow::from(consumer.as_slice()));
+        }
+
+        args
+    }
+}
+
+/// Make sure the command received exactly `N` arguments.
+fn exact_args<const N: usize>(
+    command: &str,
+    args: Vec<Vec<u8>>,
+) -> Result<[Vec<u8>; N], CommandError> {
+    args.try_into().map_err(|_| {
+        let plural = if N == 1 { "" } else { "s" };
+        CommandError::WrongArity(format!(
+            "\"{}\" command needs {} argument{}",
+            command, N, plural
+        ))
+    })
+}
+
+/// Make sure the command received at least one argument.
+fn at_least_one_arg(command: &str, args: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>, CommandError> {
+    if args.is_empty() {
+        return Err(CommandError::WrongArity(format!(
+            "\"{}\" command needs at least 1 argument",
+            command
+        )));
+    }
+
+    Ok(args)
+}
+
+/// Split the arguments into the key and the values that follow it, there has to be at
+/// least one value.
+fn key_and_values(
+    command: &str,
+    args: Vec<Vec<u8>>,
+) -> Result<(Vec<u8>, Vec<Vec<u8>>), CommandError> {
+    if args.len() < 2 {
+        return Err(CommandError::WrongArity(format!(
+            "\"{}\" command needs at least 2 arguments",
+            command
+        )));
+    }
+
+    let mut args = args.into_iter();
+    let key = args.next().unwrap();
+    Ok((key, args.collect()))
+}
+
+/// Split the arguments into the key and the optional count that follows it.
+fn key_and_count<T: FromStr>(
+    command: &str,
+    args: Vec<Vec<u8>>,
+) -> Result<(Vec<u8>, Option<T>), CommandError> {
+    let mut args = args.into_iter();
+    let (Some(key), count, None) = (args.next(), args.next(), args.next()) else {
+        return Err(CommandError::WrongArity(format!(
+            "\"{}\" command needs 1 or 2 arguments",
+            command
+        )));
+    };
+
+    let count = count.map(|count| parse_number(&count)).transpose()?;
+    Ok((key, count))
+}
+
+/// Split the arguments of a range of positions into the key, the start, the stop and
+/// whether `withscores` is given after them.
+fn rank_range(
+    command: &str,
+    args: Vec<Vec<u8>>,
+) -> Result<(Vec<u8>, i64, i64, bool), CommandError> {
+    let mut args = args.into_iter();
+    let (Some(key), Some(start), Some(stop)) = (args.next(), args.next(), args.next()) else {
+        return Err(CommandError::WrongArity(format!(
+            "\"{}\" command needs 3 or 4 arguments",
+            command
+        )));
+    };
+
+    let with_scores = match (args.next(), args.next()) {
+        (None, _) => false,
+        (Some(option), None) if option.eq_ignore_ascii_case(b"withscores") => true,
+        (Some(_), None) => {
+            return Err(CommandError::Invalid(format!(
+                "Invalid option for \"{}\" command",
+                command
+            )))
+        }
+        (Some(_), Some(_)) => {
+            return Err(CommandError::WrongArity(format!(
+                "\"{}\" command needs 3 or 4 arguments",
+                command
+            )))
+        }
+    };
+
+    Ok((
+        key,
+        parse_number(&start)?,
+        parse_number(&stop)?,
+        with_scores,
+    ))
+}
+
+/// Parse the arguments of `xreadgroup`, which are
+/// `group group consumer [count count] streams key [key ...] id [id ...]`.
+fn read_group(args: Vec<Vec<u8>>) -> Result<Command, CommandError> {
+    let arity = || {
+        CommandError::WrongArity(
+            "\"xreadgroup\" command needs a group, a consumer, and keys with their IDs".to_string(),
+        )
+    };
+    let invalid = || CommandError::Invalid("Invalid option for \"xreadgroup\" command".to_string());
+
+    let mut args = args.into_iter();
+    let (Some(option), Some(group), Some(consumer)) = (args.next(), args.next(), args.next())
+    else {
+        return Err(arity());
+    };
+    if !option.eq_ignore_ascii_case(b"group") {
+        return Err(invalid());
+    }
+
+    let mut count = None;
+    loop {
+        let option = args.next().ok_or_else(arity)?;
+        match option.to_ascii_lowercase().as_slice() {
+            b"count" => count = Some(parse_number(&args.next().ok_or_else(arity)?)?),
+            b"streams" => break,
+            _ => return Err(invalid()),
+        }
+    }
+
+    let mut keys: Vec<Vec<u8>> = args.collect();
+    if keys.is_empty() || !keys.len().is_multiple_of(2) {
+        return Err(arity());
+    }
+
+    let ids = keys.split_off(keys.len() / 2);
+    let streams = keys
+        .into_iter()
+        .zip(ids)
+        .map(|(key, id)| match id.as_slice() {
+            b">" => Ok((key, None)),
+            id => Ok((key, Some(parse_stream_id(id)?))),
+        })
+        .collect::<Result<_, CommandError>>()?;
+
+    Ok(Command::XReadGroup(group, consumer, count, streams))
+}
+
+/// Keys, or the fields of a hash, along with their values.
+type Pairs = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// Group the arguments into key and value pairs, there has to be at least one.
+fn pairs(command: &str, args: Vec<Vec<u8>>) -> Result<Pairs, CommandError> {
+    if args.is_empty() || !args.len().is_multiple_of(2) {
+        return Err(CommandError::WrongArity(format!(
+            "\"{}\" command needs pairs of keys and values",
+            command
+        )));
+    }
+
+    let mut args = args.into_iter();
+    let mut pairs = Vec::with_capacity(args.len() / 2);
+    while let (Some(key), Some(value)) = (args.next(), args.next()) {
+        pairs.push((key, value));
+    }
+
+    Ok(pairs)
+}
+
+/// Parse a number out of its decimal string representation.
+fn parse_number<T: FromStr>(arg: &[u8]) -> Result<T, CommandError> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|arg| arg.parse().ok())
+        .ok_or_else(|| {
+            CommandError::Invalid("Value is not a valid number or out of range".to_string())
+        })
+}
+
+/// Parse a finite floating point number, NaN and the infinities can't be stored.
+fn parse_float(arg: &[u8]) -> Result<f64, CommandError> {
+    let number: f64 = parse_number(arg)?;
+    if !number.is_finite() {
+        return Err(CommandError::Invalid(
+            "Value is not a valid float".to_string(),
+        ));
+    }
+
+    Ok(number)
+}
+
+/// Parse the ID of an entry of a stream, `ms-seq` or `ms` alone.
+fn parse_stream_id(arg: &[u8]) -> Result<StreamId, CommandError> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|arg| arg.parse().ok())
+        .ok_or_else(|| CommandError::Invalid("Invalid stream ID".to_string()))
+}
 
-                Command::Delete(key.to_owned())
-            }
-            _ => return Err("Invalid command".to_string()),
-        };
+/// Parse the start of a range of stream IDs, `-` being the lowest ID.
+fn range_start(arg: &[u8]) -> Result<StreamId, CommandError> {
+    match arg {
+        b"-" => Ok(StreamId::MIN),
+        arg => parse_stream_id(arg),
+    }
+}
 
-        Ok(command)
+/// Parse the end of a range of stream IDs, `+` being the highest ID. An ID without
+/// its sequence number includes all the entries of the millisecond.
+fn range_end(arg: &[u8]) -> Result<StreamId, CommandError> {
+    match arg {
+        b"+" => Ok(StreamId::MAX),
+        arg if !arg.contains(&b'-') => Ok(StreamId {
+            seq: u64::MAX,
+            ..parse_stream_id(arg)?
+        }),
+        arg => parse_stream_id(arg),
     }
 }
 
-impl TryFrom<Args> for Command {
-    type Error = String;
+/// Parse the score of a member of a sorted set, the infinities are valid scores but NaN
+/// isn't.
+fn parse_score(arg: &[u8]) -> Result<f64, CommandError> {
+    let score: f64 = parse_number(arg)?;
+    if score.is_nan() {
+        return Err(CommandError::Invalid(
+            "Score is not a valid float".to_string(),
+        ));
+    }
 
-    fn try_from(value: Args) -> Result<Self, Self::Error> {
-        let mut args = value.skip(1);
-        let command = match args.next() {
-            Some(command) => command,
-            None => return Err("No command provided".to_string()),
-        };
+    Ok(score)
+}
 
-        let command = match command.as_str() {
-            "get" => {
-                let arg = match args.next() {
-                    Some(value) => value,
-                    None => return Err("'get' command needs 1 argument".to_string()),
-                };
+impl TryFrom<String> for Command {
+    type Error = CommandError;
 
-                Command::Get(arg)
-            }
-            "set" => {
-                if args.len() < 2 {
-                    return Err("'set' command needs 2 arguments".to_string());
-                }
+    fn try_from(string_command: String) -> Result<Self, Self::Error> {
+        let string_command = string_command.to_lowercase();
+        let parts = string_command
+            .split(' ')
+            .map(|part| part.as_bytes().to_vec())
+            .collect();
 
-                Command::Set(args.next().unwrap(), args.next().unwrap())
-            }
-            "del" => {
-                let arg = match args.next() {
-                    Some(value) => value,
-                    None => return Err("'del' command needs 1 argument".to_string()),
-                };
+        Command::from_parts(parts)
+    }
+}
 
-                Command::Delete(arg)
-            }
-            _ => return Err("Invalid command".to_string()),
-        };
+impl TryFrom<Args> for Command {
+    type Error = CommandError;
 
-        Ok(command)
+    fn try_from(value: Args) -> Result<Self, Self::Error> {
+        let parts = value.skip(1).map(String::into_bytes).collect();
+        Command::from_parts(parts)
     }
 }
 
@@ -107,39 +1901,20 @@ impl Extract<Request> for Command {
     {
         let mut payload: Vec<u8> = Vec::new();
 
-        let args_len = match self {
-            Command::Get(_) | Command::Delete(_) => 1,
-            Command::Set(_, _) => 2,
-        };
-
-        let command = match self {
-            Command::Get(_) => "get",
-            Command::Set(_, _) => "set",
-            Command::Delete(_) => "del",
-        };
+        let command = self.name();
+        let args = self.args();
 
         // Header = the command + the number of arguments for the command
-        let header: u32 = 1 + args_len;
+        let header: u32 = 1 + args.len() as u32;
 
         payload.append(&mut header.to_le_bytes().into());
         payload.append(&mut (command.len() as u32).to_le_bytes().into());
         payload.append(&mut command.into());
 
-        match self {
-            Command::Get(arg) | Command::Delete(arg) => {
-                let arg_len = arg.len() as u32;
-                payload.append(&mut arg_len.to_le_bytes().into());
-                payload.append(&mut arg.clone().into_bytes());
-            }
-            Command::Set(key, value) => {
-                let key_len = key.len() as u32;
-                payload.append(&mut key_len.to_le_bytes().into());
-                payload.append(&mut key.clone().into_bytes());
-
-                let value_len = value.len() as u32;
-                payload.append(&mut value_len.to_le_bytes().into());
-                payload.append(&mut value.clone().into_bytes());
-            }
+        for arg in args {
+            let arg_len = arg.len() as u32;
+            payload.append(&mut arg_len.to_le_bytes().into());
+            payload.extend_from_slice(&arg);
         }
 
         Ok(Request::new_with_payload(payload))
@@ -148,30 +1923,607 @@ impl Extract<Request> for Command {
 
 #[cfg(test)]
 mod command_from_string {
-    use crate::Command;
+    use crate::{
+        Command, CommandError, Expiry, PendingRange, ScoreBound, ScoreRangeOptions, SetCondition,
+        SetOptions, ShutdownMode, StatusCodes, StreamId,
+    };
 
     #[test]
     pub fn valid_string_should_parses_to_command() {
         let command = Command::try_from("get key".to_string()).unwrap();
-        assert_eq!(command, Command::Get("key".to_owned()));
+        assert_eq!(command, Command::Get(b"key".to_vec()));
 
         let command = Command::try_from("set key value".to_string()).unwrap();
-        assert_eq!(command, Command::Set("key".to_owned(), "value".to_owned()));
+        assert_eq!(
+            command,
+            Command::Set(b"key".to_vec(), b"value".to_vec(), SetOptions::default())
+        );
 
         let command = Command::try_from("del key".to_string()).unwrap();
-        assert_eq!(command, Command::Delete("key".to_owned()));
+        assert_eq!(command, Command::Delete(b"key".to_vec()));
     }
 
     #[test]
     pub fn valid_string_but_in_uppercase_should_parses_to_command_in_lowercase() {
         let command = Command::try_from("GET KEY".to_string()).unwrap();
-        assert_eq!(command, Command::Get("key".to_owned()));
+        assert_eq!(command, Command::Get(b"key".to_vec()));
 
         let command = Command::try_from("SET key Value".to_string()).unwrap();
-        assert_eq!(command, Command::Set("key".to_owned(), "value".to_owned()));
+        assert_eq!(
+            command,
+            Command::Set(b"key".to_vec(), b"value".to_vec(), SetOptions::default())
+        );
 
         let command = Command::try_from("DEL key".to_string()).unwrap();
-        assert_eq!(command, Command::Delete("key".to_owned()));
+        assert_eq!(command, Command::Delete(b"key".to_vec()));
+    }
+
+    #[test]
+    pub fn expiration_commands_should_parses_to_command() {
+        let command = Command::try_from("set key value ex 10".to_string()).unwrap();
+        let options = SetOptions {
+            expiry: Some(Expiry::Seconds(10)),
+            ..Default::default()
+        };
+        assert_eq!(
+            command,
+            Command::Set(b"key".to_vec(), b"value".to_vec(), options)
+        );
+
+        let command = Command::try_from("SET key value PX 1500".to_string()).unwrap();
+        let options = SetOptions {
+            expiry: Some(Expiry::Milliseconds(1500)),
+            ..Default::default()
+        };
+        assert_eq!(
+            command,
+            Command::Set(b"key".to_vec(), b"value".to_vec(), options)
+        );
+
+        let command = Command::try_from("expire key 10".to_string()).unwrap();
+        assert_eq!(command, Command::Expire(b"key".to_vec(), 10));
+
+        let command = Command::try_from("pexpire key 10".to_string()).unwrap();
+        assert_eq!(command, Command::PExpire(b"key".to_vec(), 10));
+
+        let command = Command::try_from("set key value pxat 1700000000000".to_string()).unwrap();
+        let options = SetOptions {
+            expiry: Some(Expiry::UnixMillis(1700000000000)),
+            ..Default::default()
+        };
+        assert_eq!(
+            command,
+            Command::Set(b"key".to_vec(), b"value".to_vec(), options)
+        );
+
+        let command = Command::try_from("pexpireat key 1700000000000".to_string()).unwrap();
+        assert_eq!(command, Command::PExpireAt(b"key".to_vec(), 1700000000000));
+
+        let command = Command::try_from("ttl key".to_string()).unwrap();
+        assert_eq!(command, Command::Ttl(b"key".to_vec()));
+
+        let command = Command::try_from("persist key".to_string()).unwrap();
+        assert_eq!(command, Command::Persist(b"key".to_vec()));
+    }
+
+    #[test]
+    pub fn snapshot_commands_should_parses_to_command() {
+        let command = Command::try_from("save".to_string()).unwrap();
+        assert_eq!(command, Command::Save);
+
+        let command = Command::try_from("BGSAVE".to_string()).unwrap();
+        assert_eq!(command, Command::BgSave);
+    }
+
+    #[test]
+    pub fn shutdown_command_should_parses_to_command() {
+        let command = Command::try_from("shutdown".to_string()).unwrap();
+        assert_eq!(command, Command::Shutdown(ShutdownMode::Default));
+
+        let command = Command::try_from("shutdown save".to_string()).unwrap();
+        assert_eq!(command, Command::Shutdown(ShutdownMode::Save));
+
+        let command = Command::try_from("shutdown NOSAVE".to_string()).unwrap();
+        assert_eq!(command, Command::Shutdown(ShutdownMode::NoSave));
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn shutdown_command_with_unknown_option_should_result_in_err() {
+        Command::try_from("shutdown now".to_string()).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn save_command_with_arguments_should_result_in_err() {
+        Command::try_from("save now".to_string()).unwrap();
+    }
+
+    #[test]
+    pub fn hello_command_should_parses_to_command() {
+        let command = Command::try_from("hello 1".to_string()).unwrap();
+        assert_eq!(command, Command::Hello(1));
+    }
+
+    #[test]
+    pub fn versioned_commands_should_parses_to_command() {
+        let command = Command::try_from("set key value flags 3 ex 10".to_string()).unwrap();
+        let options = SetOptions {
+            expiry: Some(Expiry::Seconds(10)),
+            flags: 3,
+            ..Default::default()
+        };
+        assert_eq!(
+            command,
+            Command::Set(b"key".to_vec(), b"value".to_vec(), options)
+        );
+
+        let command = Command::try_from("gets key".to_string()).unwrap();
+        assert_eq!(command, Command::Gets(b"key".to_vec()));
+
+        let command = Command::try_from("cas key value 42".to_string()).unwrap();
+        assert_eq!(
+            command,
+            Command::Cas(
+                b"key".to_vec(),
+                b"value".to_vec(),
+                42,
+                SetOptions::default()
+            )
+        );
+
+        let command = Command::try_from("incrx key -5".to_string()).unwrap();
+        assert_eq!(command, Command::IncrExisting(b"key".to_vec(), -5));
+    }
+
+    #[test]
+    pub fn conditional_writes_should_parses_to_command() {
+        let command = Command::try_from("set key value nx ex 10".to_string()).unwrap();
+        let options = SetOptions {
+            expiry: Some(Expiry::Seconds(10)),
+            condition: Some(SetCondition::IfAbsent),
+            ..Default::default()
+        };
+        assert_eq!(
+            command,
+            Command::Set(b"key".to_vec(), b"value".to_vec(), options)
+        );
+
+        let command = Command::try_from("SET key value XX".to_string()).unwrap();
+        let options = SetOptions {
+            condition: Some(SetCondition::IfPresent),
+            ..Default::default()
+        };
+        assert_eq!(
+            command,
+            Command::Set(b"key".to_vec(), b"value".to_vec(), options)
+        );
+
+        let command = Command::try_from("getset key value".to_string()).unwrap();
+        assert_eq!(command, Command::GetSet(b"key".to_vec(), b"value".to_vec()));
+
+        let command = Command::try_from("getdel key".to_string()).unwrap();
+        assert_eq!(command, Command::GetDel(b"key".to_vec()));
+
+        let command = Command::try_from("casvalue key old new".to_string()).unwrap();
+        assert_eq!(
+            command,
+            Command::CasValue(
+                b"key".to_vec(),
+                b"old".to_vec(),
+                b"new".to_vec(),
+                SetOptions::default()
+            )
+        );
+
+        for invalid in [
+            "set key value nx xx",
+            "set key value ex",
+            "cas key value 1 nx",
+            "casvalue key old new xx",
+            "casvalue key old",
+        ] {
+            assert!(Command::try_from(invalid.to_string()).is_err());
+        }
+    }
+
+    #[test]
+    pub fn list_commands_should_parses_to_command() {
+        let command = Command::try_from("lpush queue a b".to_string()).unwrap();
+        assert_eq!(
+            command,
+            Command::LPush(b"queue".to_vec(), vec![b"a".to_vec(), b"b".to_vec()])
+        );
+
+        let command = Command::try_from("rpop queue".to_string()).unwrap();
+        assert_eq!(command, Command::RPop(b"queue".to_vec(), None));
+
+        let command = Command::try_from("lpop queue 3".to_string()).unwrap();
+        assert_eq!(command, Command::LPop(b"queue".to_vec(), Some(3)));
+
+        let command = Command::try_from("lrange queue 0 -1".to_string()).unwrap();
+        assert_eq!(command, Command::LRange(b"queue".to_vec(), 0, -1));
+
+        let command = Command::try_from("lrem queue -2 a".to_string()).unwrap();
+        assert_eq!(command, Command::LRem(b"queue".to_vec(), -2, b"a".to_vec()));
+
+        for (invalid, status_code) in [
+            ("lpush queue", StatusCodes::ErrWrongArity),
+            ("lpop queue 1 2", StatusCodes::ErrWrongArity),
+            ("lpop queue -1", StatusCodes::ErrInvalidRequest),
+            ("lindex queue first", StatusCodes::ErrInvalidRequest),
+            ("lset queue 0", StatusCodes::ErrWrongArity),
+        ] {
+            let err = Command::try_from(invalid.to_string()).unwrap_err();
+            assert_eq!(err.status_code(), status_code);
+        }
+    }
+
+    #[test]
+    pub fn hash_commands_should_parses_to_command() {
+        let command = Command::try_from("hset user name ada age 36".to_string()).unwrap();
+        assert_eq!(
+            command,
+            Command::HSet(
+                b"user".to_vec(),
+                vec![
+                    (b"name".to_vec(), b"ada".to_vec()),
+                    (b"age".to_vec(), b"36".to_vec())
+                ]
+            )
+        );
+
+        let command = Command::try_from("hmget user name email".to_string()).unwrap();
+        assert_eq!(
+            command,
+            Command::HMGet(b"user".to_vec(), vec![b"name".to_vec(), b"email".to_vec()])
+        );
+
+        let command = Command::try_from("hgetall user".to_string()).unwrap();
+        assert_eq!(command, Command::HGetAll(b"user".to_vec()));
+
+        let command = Command::try_from("hincrby user age -1".to_string()).unwrap();
+        assert_eq!(
+            command,
+            Command::HIncrBy(b"user".to_vec(), b"age".to_vec(), -1)
+        );
+
+        for (invalid, status_code) in [
+            ("hset user", StatusCodes::ErrWrongArity),
+            ("hset user name", StatusCodes::ErrWrongArity),
+            ("hget user", StatusCodes::ErrWrongArity),
+            ("hdel user", StatusCodes::ErrWrongArity),
+            ("hincrby user age", StatusCodes::ErrWrongArity),
+            ("hincrby user age 1.5", StatusCodes::ErrInvalidRequest),
+        ] {
+            let err = Command::try_from(invalid.to_string()).unwrap_err();
+            assert_eq!(err.status_code(), status_code);
+        }
+    }
+
+    #[test]
+    pub fn set_commands_should_parses_to_command() {
+        let command = Command::try_from("sadd flags a b".to_string()).unwrap();
+        assert_eq!(
+            command,
+            Command::SAdd(b"flags".to_vec(), vec![b"a".to_vec(), b"b".to_vec()])
+        );
+
+        let command = Command::try_from("spop flags 2".to_string()).unwrap();
+        assert_eq!(command, Command::SPop(b"flags".to_vec(), Some(2)));
+
+        let command = Command::try_from("srandmember flags -5".to_string()).unwrap();
+        assert_eq!(command, Command::SRandMember(b"flags".to_vec(), Some(-5)));
+
+        let command = Command::try_from("sinter a b c".to_string()).unwrap();
+        assert_eq!(
+            command,
+            Command::SInter(vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()])
+        );
+        assert_eq!(command.keys(), vec![b"a".as_slice(), b"b", b"c"]);
+
+        let command = Command::try_from("sdiffstore dest a b".to_string()).unwrap();
+        assert_eq!(
+            command,
+            Command::SDiffStore(b"dest".to_vec(), vec![b"a".to_vec(), b"b".to_vec()])
+        );
+        assert_eq!(command.keys(), vec![b"dest".as_slice(), b"a", b"b"]);
+
+        for (invalid, status_code) in [
+            ("sadd flags", StatusCodes::ErrWrongArity),
+            ("sismember flags", StatusCodes::ErrWrongArity),
+            ("spop flags -1", StatusCodes::ErrInvalidRequest),
+            ("sunion", StatusCodes::ErrWrongArity),
+            ("sunionstore dest", StatusCodes::ErrWrongArity),
+        ] {
+            let err = Command::try_from(invalid.to_string()).unwrap_err();
+            assert_eq!(err.status_code(), status_code);
+        }
+    }
+
+    #[test]
+    pub fn sorted_set_commands_should_parses_to_command() {
+        let command = Command::try_from("zadd board 1.5 ada -inf bob".to_string()).unwrap();
+        assert_eq!(
+            command,
+            Command::ZAdd(
+                b"board".to_vec(),
+                vec![(1.5, b"ada".to_vec()), (f64::NEG_INFINITY, b"bob".to_vec())]
+            )
+        );
+
+        let command = Command::try_from("zrevrange board 0 -1 withscores".to_string()).unwrap();
+        assert_eq!(command, Command::ZRevRange(b"board".to_vec(), 0, -1, true));
+
+        let command = Command::try_from("zrangebyscore queue -inf (100".to_string()).unwrap();
+        assert_eq!(
+            command,
+            Command::ZRangeByScore(
+                b"queue".to_vec(),
+                ScoreBound::inclusive(f64::NEG_INFINITY),
+                ScoreBound::exclusive(100.0),
+                ScoreRangeOptions::default()
+            )
+        );
+
+        let command =
+            Command::try_from("zrangebyscore queue 1 +inf limit 5 10 withscores".to_string())
+                .unwrap();
+        assert_eq!(
+            command,
+            Command::ZRangeByScore(
+                b"queue".to_vec(),
+                ScoreBound::inclusive(1.0),
+                ScoreBound::inclusive(f64::INFINITY),
+                ScoreRangeOptions {
+                    with_scores: true,
+                    limit: Some((5, 10)),
+                }
+            )
+        );
+
+        for (invalid, status_code) in [
+            ("zadd board 1 ada 2", StatusCodes::ErrWrongArity),
+            ("zadd board nan ada", StatusCodes::ErrInvalidRequest),
+            ("zincrby board ada 1", StatusCodes::ErrInvalidRequest),
+            ("zrange board 0", StatusCodes::ErrWrongArity),
+            ("zrange board 0 -1 scores", StatusCodes::ErrInvalidRequest),
+            ("zrangebyscore queue (nan 1", StatusCodes::ErrInvalidRequest),
+            (
+                "zrangebyscore queue 0 1 limit 0",
+                StatusCodes::ErrWrongArity,
+            ),
+            (
+                "zrangebyscore queue 0 1 limit 0 -1",
+                StatusCodes::ErrInvalidRequest,
+            ),
+        ] {
+            let err = Command::try_from(invalid.to_string()).unwrap_err();
+            assert_eq!(err.status_code(), status_code);
+        }
+    }
+
+    #[test]
+    pub fn stream_commands_should_parses_to_command() {
+        let command = Command::try_from("xadd events * type click".to_string()).unwrap();
+        assert_eq!(
+            command,
+            Command::XAdd(
+                b"events".to_vec(),
+                None,
+                vec![(b"type".to_vec(), b"click".to_vec())]
+            )
+        );
+
+        let command = Command::try_from("xadd events 5-1 a 1 b 2".to_string()).unwrap();
+        assert_eq!(
+            command,
+            Command::XAdd(
+                b"events".to_vec(),
+                Some(StreamId::new(5, 1)),
+                vec![
+                    (b"a".to_vec(), b"1".to_vec()),
+                    (b"b".to_vec(), b"2".to_vec())
+                ]
+            )
+        );
+
+        // The end of a range without a sequence number includes the whole millisecond.
+        let command = Command::try_from("xrange events - 5 count 10".to_string()).unwrap();
+        assert_eq!(
+            command,
+            Command::XRange(
+                b"events".to_vec(),
+                StreamId::MIN,
+                StreamId::new(5, u64::MAX),
+                Some(10)
+            )
+        );
+
+        let command = Command::try_from("xrange events 5 +".to_string()).unwrap();
+        assert_eq!(
+            command,
+            Command::XRange(b"events".to_vec(), StreamId::new(5, 0), StreamId::MAX, None)
+        );
+
+        let command =
+            Command::try_from("xgroup create events workers $ mkstream".to_string()).unwrap();
+        assert_eq!(
+            command,
+            Command::XGroupCreate(b"events".to_vec(), b"workers".to_vec(), None, true)
+        );
+
+        let command = Command::try_from(
+            "xreadgroup group workers ada count 2 streams events jobs > 0".to_string(),
+        )
+        .unwrap();
+        assert_eq!(
+            command,
+            Command::XReadGroup(
+                b"workers".to_vec(),
+                b"ada".to_vec(),
+                Some(2),
+                vec![
+                    (b"events".to_vec(), None),
+                    (b"jobs".to_vec(), Some(StreamId::MIN))
+                ]
+            )
+        );
+
+        let command = Command::try_from("xpending events workers".to_string()).unwrap();
+        assert_eq!(
+            command,
+            Command::XPending(b"events".to_vec(), b"workers".to_vec(), None)
+        );
+
+        let command =
+            Command::try_from("xpending events workers idle 500 - + 10 ada".to_string()).unwrap();
+        assert_eq!(
+            command,
+            Command::XPending(
+                b"events".to_vec(),
+                b"workers".to_vec(),
+                Some(PendingRange {
+                    min_idle: Some(500),
+                    start: StreamId::MIN,
+                    end: StreamId::MAX,
+                    count: 10,
+                    consumer: Some(b"ada".to_vec()),
+                })
+            )
+        );
+
+        let command =
+            Command::try_from("xclaim events workers bob 1000 1-0 2-3".to_string()).unwrap();
+        assert_eq!(
+            command,
+            Command::XClaim(
+                b"events".to_vec(),
+                b"workers".to_vec(),
+                b"bob".to_vec(),
+                1000,
+                vec![StreamId::new(1, 0), StreamId::new(2, 3)]
+            )
+        );
+
+        for (invalid, status_code) in [
+            ("xadd events *", StatusCodes::ErrWrongArity),
+            ("xadd events 1-x a 1", StatusCodes::ErrInvalidRequest),
+            ("xrange events - + count", StatusCodes::ErrWrongArity),
+            ("xtrim events minid 5", StatusCodes::ErrInvalidRequest),
+            (
+                "xgroup destroy events workers",
+                StatusCodes::ErrInvalidRequest,
+            ),
+            ("xgroup create events workers", StatusCodes::ErrWrongArity),
+            (
+                "xreadgroup group workers ada streams events",
+                StatusCodes::ErrWrongArity,
+            ),
+            (
+                "xreadgroup group workers ada block 0 streams events >",
+                StatusCodes::ErrInvalidRequest,
+            ),
+            ("xack events workers", StatusCodes::ErrWrongArity),
+            ("xpending events workers - +", StatusCodes::ErrWrongArity),
+            (
+                "xclaim events workers bob -1 1-0",
+                StatusCodes::ErrInvalidRequest,
+            ),
+        ] {
+            let err = Command::try_from(invalid.to_string()).unwrap_err();
+            assert_eq!(err.status_code(), status_code);
+        }
+    }
+
+    #[test]
+    pub fn counter_commands_should_parses_to_command() {
+        let command = Command::try_from("incr key".to_string()).unwrap();
+        assert_eq!(command, Command::IncrBy(b"key".to_vec(), 1));
+
+        let command = Command::try_from("decr key".to_string()).unwrap();
+        assert_eq!(command, Command::IncrBy(b"key".to_vec(), -1));
+
+        let command = Command::try_from("incrby key -7".to_string()).unwrap();
+        assert_eq!(command, Command::IncrBy(b"key".to_vec(), -7));
+
+        let command = Command::try_from("decrby key 7".to_string()).unwrap();
+        assert_eq!(command, Command::IncrBy(b"key".to_vec(), -7));
+
+        let command = Command::try_from("incrbyfloat key 2.5e2".to_string()).unwrap();
+        assert_eq!(command, Command::IncrByFloat(b"key".to_vec(), 250.0));
+
+        for invalid in [
+            "incrby key 1.5",
+            "decrby key -9223372036854775808",
+            "incrbyfloat key nan",
+            "incrbyfloat key inf",
+        ] {
+            let err = Command::try_from(invalid.to_string()).unwrap_err();
+            assert_eq!(err.status_code(), StatusCodes::ErrInvalidRequest);
+        }
+    }
+
+    #[test]
+    pub fn multi_key_commands_should_parses_to_command() {
+        let command = Command::try_from("del a b".to_string()).unwrap();
+        assert_eq!(
+            command,
+            Command::DeleteMany(vec![b"a".to_vec(), b"b".to_vec()])
+        );
+
+        let command = Command::try_from("mset a 1 b 2".to_string()).unwrap();
+        assert_eq!(
+            command,
+            Command::MSet(vec![
+                (b"a".to_vec(), b"1".to_vec()),
+                (b"b".to_vec(), b"2".to_vec())
+            ])
+        );
+
+        let err = Command::try_from("mset a 1 b".to_string()).unwrap_err();
+        assert_eq!(err.status_code(), StatusCodes::ErrWrongArity);
+
+        let err = Command::try_from("mget".to_string()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "\"mget\" command needs at least 1 argument"
+        );
+    }
+
+    #[test]
+    pub fn invalid_commands_should_result_in_err_with_matching_status_code() {
+        let err = Command::try_from("nope key".to_string()).unwrap_err();
+        assert_eq!(
+            err,
+            CommandError::Unknown("Unknown command \"nope\"".to_string())
+        );
+        assert_eq!(err.status_code(), StatusCodes::ErrUnknownCommand);
+
+        let err = Command::try_from("get".to_string()).unwrap_err();
+        assert_eq!(err.status_code(), StatusCodes::ErrWrongArity);
+        assert_eq!(err.to_string(), "\"get\" command needs 1 argument");
+
+        let err = Command::try_from("expire key soon".to_string()).unwrap_err();
+        assert_eq!(err.status_code(), StatusCodes::ErrInvalidRequest);
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn set_command_with_invalid_expire_time_should_result_in_err() {
+        Command::try_from("set key value ex ten".to_string()).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn set_command_with_unknown_option_should_result_in_err() {
+        Command::try_from("set key value nx 10".to_string()).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn expire_command_without_timeout_should_result_in_err() {
+        Command::try_from("expire key".to_string()).unwrap();
     }
 
     #[test]
@@ -213,11 +2565,14 @@ mod command_from_string {
 
 #[cfg(test)]
 mod extract_request_from_command {
-    use crate::{Command, Extract, Request};
+    use crate::{
+        Command, Expiry, Extract, PendingRange, Request, ScoreBound, ScoreRangeOptions,
+        SetCondition, SetOptions, StreamId,
+    };
 
     #[test]
     pub fn get_command_should_be_properly_converted_to_request() {
-        let mut command = Command::Get("key".to_owned());
+        let mut command = Command::Get(b"key".to_vec());
         let request = command.extract().unwrap();
 
         let mut expected_payload: Vec<u8> = Vec::new();
@@ -234,7 +2589,7 @@ mod extract_request_from_command {
 
     #[test]
     pub fn set_command_should_be_properly_converted_to_request() {
-        let mut command = Command::Set("key".to_owned(), "value".to_owned());
+        let mut command = Command::Set(b"key".to_vec(), b"value".to_vec(), SetOptions::default());
         let request = command.extract().unwrap();
 
         let mut expected_payload: Vec<u8> = Vec::new();
@@ -253,7 +2608,7 @@ mod extract_request_from_command {
 
     #[test]
     pub fn del_command_should_be_properly_converted_to_request() {
-        let mut command = Command::Delete("key".to_owned());
+        let mut command = Command::Delete(b"key".to_vec());
         let request = command.extract().unwrap();
 
         let mut expected_payload: Vec<u8> = Vec::new();
@@ -267,4 +2622,302 @@ mod extract_request_from_command {
 
         assert_eq!(request, expected_request);
     }
+
+    #[test]
+    pub fn set_command_with_expiry_should_be_properly_converted_to_request() {
+        let options = SetOptions {
+            expiry: Some(Expiry::Seconds(10)),
+            ..Default::default()
+        };
+        let mut command = Command::Set(b"key".to_vec(), b"value".to_vec(), options);
+        let request = command.extract().unwrap();
+
+        let mut expected_payload: Vec<u8> = Vec::new();
+        expected_payload.append(&mut 5_u32.to_le_bytes().into());
+        expected_payload.append(&mut 3_u32.to_le_bytes().into());
+        expected_payload.append(&mut "set".into());
+        expected_payload.append(&mut 3_u32.to_le_bytes().into());
+        expected_payload.append(&mut "key".into());
+        expected_payload.append(&mut 5_u32.to_le_bytes().into());
+        expected_payload.append(&mut "value".into());
+        expected_payload.append(&mut 2_u32.to_le_bytes().into());
+        expected_payload.append(&mut "ex".into());
+        expected_payload.append(&mut 2_u32.to_le_bytes().into());
+        expected_payload.append(&mut "10".into());
+
+        let expected_request = Request::new_with_payload(expected_payload);
+
+        assert_eq!(request, expected_request);
+    }
+
+    #[test]
+    pub fn cas_command_should_round_trip_through_request() {
+        let options = SetOptions {
+            expiry: Some(Expiry::UnixMillis(1700000000000)),
+            flags: 7,
+            ..Default::default()
+        };
+        let mut command = Command::Cas(b"key".to_vec(), b"value".to_vec(), 42, options);
+        let request = command.extract().unwrap();
+        let parsed: Command = request.try_into().unwrap();
+
+        assert_eq!(parsed, command);
+    }
+
+    #[test]
+    pub fn expire_command_should_round_trip_through_request() {
+        let mut command = Command::PExpire(b"key".to_vec(), 2500);
+        let request = command.extract().unwrap();
+        let parsed: Command = request.try_into().unwrap();
+
+        assert_eq!(parsed, command);
+    }
+
+    #[test]
+    pub fn keys_command_should_round_trip_through_request() {
+        for prefix in [b"user:".to_vec(), Vec::new()] {
+            let mut command = Command::Keys(prefix);
+            let request = command.extract().unwrap();
+            let parsed: Command = request.try_into().unwrap();
+
+            assert_eq!(parsed, command);
+        }
+    }
+
+    #[test]
+    pub fn conditional_writes_should_round_trip_through_request() {
+        let options = SetOptions {
+            expiry: Some(Expiry::Milliseconds(100)),
+            flags: 1,
+            condition: Some(SetCondition::IfPresent),
+        };
+
+        for mut command in [
+            Command::Set(b"key".to_vec(), b"value".to_vec(), options),
+            Command::GetSet(b"key".to_vec(), b"value".to_vec()),
+            Command::GetDel(b"key".to_vec()),
+            Command::CasValue(
+                b"key".to_vec(),
+                b"old".to_vec(),
+                b"new".to_vec(),
+                SetOptions::default(),
+            ),
+        ] {
+            let request = command.extract().unwrap();
+            let parsed: Command = request.try_into().unwrap();
+
+            assert_eq!(parsed, command);
+        }
+    }
+
+    #[test]
+    pub fn list_commands_should_round_trip_through_request() {
+        let key = b"queue".to_vec();
+        let values = vec![b"a".to_vec(), b"b".to_vec()];
+
+        for mut command in [
+            Command::LPush(key.clone(), values.clone()),
+            Command::RPush(key.clone(), values),
+            Command::LPop(key.clone(), None),
+            Command::RPop(key.clone(), Some(2)),
+            Command::LRange(key.clone(), -3, -1),
+            Command::LLen(key.clone()),
+            Command::LIndex(key.clone(), 4),
+            Command::LSet(key.clone(), -1, b"c".to_vec()),
+            Command::LTrim(key.clone(), 1, 10),
+            Command::LRem(key, 0, b"a".to_vec()),
+        ] {
+            let request = command.extract().unwrap();
+            let parsed: Command = request.try_into().unwrap();
+
+            assert_eq!(parsed, command);
+        }
+    }
+
+    #[test]
+    pub fn hash_commands_should_round_trip_through_request() {
+        let key = b"user".to_vec();
+        let fields = vec![b"name".to_vec(), b"age".to_vec()];
+
+        for mut command in [
+            Command::HSet(key.clone(), vec![(b"name".to_vec(), b"ada".to_vec())]),
+            Command::HGet(key.clone(), b"name".to_vec()),
+            Command::HMGet(key.clone(), fields.clone()),
+            Command::HDel(key.clone(), fields),
+            Command::HGetAll(key.clone()),
+            Command::HKeys(key.clone()),
+            Command::HVals(key.clone()),
+            Command::HLen(key.clone()),
+            Command::HExists(key.clone(), b"name".to_vec()),
+            Command::HIncrBy(key, b"age".to_vec(), 1),
+        ] {
+            let request = command.extract().unwrap();
+            let parsed: Command = request.try_into().unwrap();
+
+            assert_eq!(parsed, command);
+        }
+    }
+
+    #[test]
+    pub fn set_commands_should_round_trip_through_request() {
+        let key = b"flags".to_vec();
+        let members = vec![b"a".to_vec(), b"b".to_vec()];
+
+        for mut command in [
+            Command::SAdd(key.clone(), members.clone()),
+            Command::SRem(key.clone(), members.clone()),
+            Command::SIsMember(key.clone(), b"a".to_vec()),
+            Command::SMembers(key.clone()),
+            Command::SCard(key.clone()),
+            Command::SPop(key.clone(), None),
+            Command::SPop(key.clone(), Some(3)),
+            Command::SRandMember(key.clone(), None),
+            Command::SRandMember(key.clone(), Some(-3)),
+            Command::SInter(members.clone()),
+            Command::SUnion(members.clone()),
+            Command::SDiff(members.clone()),
+            Command::SInterStore(key.clone(), members.clone()),
+            Command::SUnionStore(key.clone(), members.clone()),
+            Command::SDiffStore(key, members),
+        ] {
+            let request = command.extract().unwrap();
+            let parsed: Command = request.try_into().unwrap();
+
+            assert_eq!(parsed, command);
+        }
+    }
+
+    #[test]
+    pub fn sorted_set_commands_should_round_trip_through_request() {
+        let key = b"board".to_vec();
+        let options = ScoreRangeOptions {
+            with_scores: true,
+            limit: Some((0, 10)),
+        };
+
+        for mut command in [
+            Command::ZAdd(
+                key.clone(),
+                vec![(0.1, b"a".to_vec()), (f64::INFINITY, b"b".to_vec())],
+            ),
+            Command::ZRem(key.clone(), vec![b"a".to_vec(), b"b".to_vec()]),
+            Command::ZScore(key.clone(), b"a".to_vec()),
+            Command::ZIncrBy(key.clone(), -2.5, b"a".to_vec()),
+            Command::ZRank(key.clone(), b"a".to_vec()),
+            Command::ZRange(key.clone(), 0, -1, false),
+            Command::ZRevRange(key.clone(), -3, 2, true),
+            Command::ZRangeByScore(
+                key.clone(),
+                ScoreBound::exclusive(f64::NEG_INFINITY),
+                ScoreBound::inclusive(1e-7),
+                ScoreRangeOptions::default(),
+            ),
+            Command::ZRangeByScore(
+                key.clone(),
+                ScoreBound::inclusive(-1.5),
+                ScoreBound::exclusive(f64::INFINITY),
+                options,
+            ),
+            Command::ZCard(key),
+        ] {
+            let request = command.extract().unwrap();
+            let parsed: Command = request.try_into().unwrap();
+
+            assert_eq!(parsed, command);
+        }
+    }
+
+    #[test]
+    pub fn stream_commands_should_round_trip_through_request() {
+        let key = b"events".to_vec();
+        let group = b"workers".to_vec();
+        let range = PendingRange {
+            min_idle: None,
+            start: StreamId::new(1, 0),
+            end: StreamId::MAX,
+            count: 5,
+            consumer: None,
+        };
+
+        for mut command in [
+            Command::XAdd(key.clone(), None, vec![(b"a".to_vec(), Vec::new())]),
+            Command::XAdd(
+                key.clone(),
+                Some(StreamId::new(7, 2)),
+                vec![(b"a".to_vec(), b"1".to_vec())],
+            ),
+            Command::XRange(key.clone(), StreamId::MIN, StreamId::MAX, None),
+            Command::XRange(
+                key.clone(),
+                StreamId::new(1, 1),
+                StreamId::new(2, 0),
+                Some(3),
+            ),
+            Command::XLen(key.clone()),
+            Command::XTrim(key.clone(), 100),
+            Command::XGroupCreate(key.clone(), group.clone(), None, false),
+            Command::XGroupCreate(key.clone(), group.clone(), Some(StreamId::MIN), true),
+            Command::XReadGroup(
+                group.clone(),
+                b"ada".to_vec(),
+                None,
+                vec![
+                    (key.clone(), None),
+                    (b"jobs".to_vec(), Some(StreamId::new(3, 0))),
+                ],
+            ),
+            Command::XAck(key.clone(), group.clone(), vec![StreamId::new(1, 0)]),
+            Command::XPending(key.clone(), group.clone(), None),
+            Command::XPending(key.clone(), group.clone(), Some(range)),
+            Command::XClaim(
+                key,
+                group,
+                b"bob".to_vec(),
+                60000,
+                vec![StreamId::new(1, 0), StreamId::new(2, 0)],
+            ),
+        ] {
+            let request = command.extract().unwrap();
+            let parsed: Command = request.try_into().unwrap();
+
+            assert_eq!(parsed, command);
+        }
+    }
+
+    #[test]
+    pub fn counter_commands_should_round_trip_through_request() {
+        for mut command in [
+            Command::IncrBy(b"key".to_vec(), i64::MIN),
+            Command::IncrByFloat(b"key".to_vec(), 0.1),
+            Command::IncrByFloat(b"key".to_vec(), -1e300),
+        ] {
+            let request = command.extract().unwrap();
+            let parsed: Command = request.try_into().unwrap();
+
+            assert_eq!(parsed, command);
+        }
+    }
+
+    #[test]
+    pub fn multi_key_commands_should_round_trip_through_request() {
+        let keys = vec![b"a".to_vec(), b"b".to_vec()];
+        let pairs = vec![
+            (b"a".to_vec(), b"1".to_vec()),
+            (b"b".to_vec(), b"2".to_vec()),
+        ];
+
+        for mut command in [
+            Command::MGet(keys.clone()),
+            Command::Exists(keys.clone()),
+            Command::DeleteMany(keys.clone()),
+            Command::DeleteMany(vec![b"a".to_vec()]),
+            Command::MSet(pairs.clone()),
+            Command::MSetNx(pairs),
+        ] {
+            let request = command.extract().unwrap();
+            let parsed: Command = request.try_into().unwrap();
+
+            assert_eq!(parsed, command);
+        }
+    }
 }
@@ -0,0 +1,750 @@
+use super::{RawResponse, Request};
+use std::{
+    fmt,
+    io::{self, ErrorKind, Read},
+    ops::Deref,
+};
+
+/// The size of the integers used in the frame for the header and the message headers.
+const LEN_SIZE: usize = 4;
+
+/// The size of the scratch buffer used when reading from a source.
+const READ_CHUNK_SIZE: usize = 4096;
+
+/// Limits on the size of the requests a [`RequestDecoder`] accepts, checked against the
+/// headers of the frame as soon as they arrive, so a hostile header can't make the decoder
+/// buffer more than the limits allow.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameLimits {
+    /// The maximum number of messages in a request, including the name of the command.
+    pub max_args: usize,
+
+    /// The maximum size in bytes of the name of the command and of the message after it,
+    /// which is the key for the commands that have one.
+    pub max_key_size: usize,
+
+    /// The maximum size in bytes of the rest of the messages.
+    pub max_value_size: usize,
+
+    /// The maximum size in bytes of a whole request, headers included.
+    pub max_frame_size: usize,
+}
+
+impl FrameLimits {
+    /// No limits at all, for sources that are trusted, e.g. the server's own files.
+    pub fn unlimited() -> Self {
+        Self {
+            max_args: usize::MAX,
+            max_key_size: usize::MAX,
+            max_value_size: usize::MAX,
+            max_frame_size: usize::MAX,
+        }
+    }
+}
+
+impl Default for FrameLimits {
+    fn default() -> Self {
+        Self {
+            max_args: 1024 * 1024,
+            max_key_size: 1024 * 1024,
+            max_value_size: 512 * 1024 * 1024,
+            max_frame_size: 512 * 1024 * 1024,
+        }
+    }
+}
+
+/// A request that exceeds the [`FrameLimits`] or is malformed. The rest of the stream
+/// can't be trusted after it, so the connection is meant to be closed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrameError {
+    TooManyArgs {
+        args: u32,
+        max: usize,
+    },
+    KeyTooLarge {
+        size: usize,
+        max: usize,
+    },
+    ValueTooLarge {
+        size: usize,
+        max: usize,
+    },
+    FrameTooLarge {
+        max: usize,
+    },
+    /// The bytes don't follow the framing of the protocol.
+    Malformed(String),
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::TooManyArgs { args, max } => {
+                write!(f, "Request has {} arguments, the maximum is {}", args, max)
+            }
+            FrameError::KeyTooLarge { size, max } => {
+                write!(f, "Key is {} bytes, the maximum is {}", size, max)
+            }
+            FrameError::ValueTooLarge { size, max } => {
+                write!(f, "Value is {} bytes, the maximum is {}", size, max)
+            }
+            FrameError::FrameTooLarge { max } => {
+                write!(f, "Request is larger than the maximum of {} bytes", max)
+            }
+            FrameError::Malformed(msg) => write!(f, "Protocol error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+/// Where the decoder is at in the current frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DecodeState {
+    /// Waiting for the 4 bytes header, which is the number of messages in the frame.
+    Header,
+    /// Waiting for the 4 bytes length of the next message.
+    /// Holds the number of messages that haven't been read yet, including the next one,
+    /// and the index of the next one.
+    MessageHeader { msgs_left: u32, index: u32 },
+    /// Waiting for the message itself.
+    Message {
+        msgs_left: u32,
+        index: u32,
+        msg_len: usize,
+    },
+}
+
+/// Incrementally decodes [`Request`]s out of a stream of bytes.
+///
+/// Bytes are fed to the decoder as they arrive (see [`RequestDecoder::feed`] and
+/// [`RequestDecoder::read_from`]), and a [`Request`] is only yielded by
+/// [`RequestDecoder::decode`] once its whole frame is buffered. Partial frames are kept
+/// in the buffer until the rest of their bytes arrive, so a request split across
+/// multiple TCP segments is never lost.
+///
+/// The frame structure is described in [`Request`].
+///
+/// Frames exceeding the [`FrameLimits`] are rejected as soon as their headers arrive, and
+/// [`RequestDecoder::read_from`] stops reading once a frame's worth of bytes is buffered,
+/// so the buffer never grows much past [`FrameLimits::max_frame_size`].
+#[derive(Debug)]
+pub struct RequestDecoder {
+    /// The bytes received so far that haven't been yielded as a [`Request`] yet.
+    buffer: ReadBuffer,
+
+    limits: FrameLimits,
+
+    /// The position in the buffer up to which the current frame has been parsed.
+    cursor: usize,
+
+    /// Which part of the current frame we're waiting for.
+    state: DecodeState,
+}
+
+impl RequestDecoder {
+    /// Create a decoder with the default [`FrameLimits`].
+    pub fn new() -> Self {
+        Self::with_limits(FrameLimits::default())
+    }
+
+    pub fn with_limits(limits: FrameLimits) -> Self {
+        Self {
+            buffer: ReadBuffer::default(),
+            limits,
+            cursor: 0,
+            state: DecodeState::Header,
+        }
+    }
+
+    /// Append the given bytes to the decoder's buffer.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.buffer.feed(bytes);
+    }
+
+    /// Read everything that is currently available from the source into the buffer.
+    ///
+    /// Meant to be used with non-blocking sources: reading stops as soon as the source
+    /// returns [`ErrorKind::WouldBlock`], and the number of bytes read is returned.
+    /// Returns an [`ErrorKind::UnexpectedEof`] error if the source is closed and nothing
+    /// was read, otherwise the bytes read before it closed are returned first.
+    ///
+    /// Reading also stops once [`FrameLimits::max_frame_size`] bytes are buffered, see
+    /// [`RequestDecoder::is_full`], the buffered frames have to be decoded before reading more.
+    pub fn read_from<R: Read>(&mut self, source: &mut R) -> Result<usize, io::Error> {
+        self.buffer.read_from(source, self.limits.max_frame_size)
+    }
+
+    /// Whether [`RequestDecoder::read_from`] stopped reading because the buffer is full,
+    /// in which case there might be more to read once the buffered frames are decoded.
+    pub fn is_full(&self) -> bool {
+        self.buffer.len() >= self.limits.max_frame_size
+    }
+
+    /// Whether there are buffered bytes that haven't been yielded as a [`Request`] yet.
+    pub fn has_pending_bytes(&self) -> bool {
+        !self.buffer.is_empty()
+    }
+
+    /// Yield the next complete [`Request`] in the buffer.
+    /// Returns None if the buffer doesn't contain a complete frame yet, and an error if the
+    /// frame exceeds the limits, after which the decoder shouldn't be used anymore.
+    pub fn decode(&mut self) -> Result<Option<Request>, FrameError> {
+        loop {
+            match self.state {
+                DecodeState::Header => {
+                    let Some(msg_count) = self.read_len() else {
+                        return Ok(None);
+                    };
+                    if msg_count == 0 {
+                        return Ok(Some(self.split_frame()));
+                    }
+
+                    if msg_count as usize > self.limits.max_args {
+                        return Err(FrameError::TooManyArgs {
+                            args: msg_count,
+                            max: self.limits.max_args,
+                        });
+                    }
+
+                    self.state = DecodeState::MessageHeader {
+                        msgs_left: msg_count,
+                        index: 0,
+                    };
+                }
+                DecodeState::MessageHeader { msgs_left, index } => {
+                    // Checked before waiting for the header, so that the frame's bytes buffered
+                    // so far never reach the limit without being rejected or completed.
+                    if self.cursor.saturating_add(LEN_SIZE) > self.limits.max_frame_size {
+                        return Err(FrameError::FrameTooLarge {
+                            max: self.limits.max_frame_size,
+                        });
+                    }
+
+                    let Some(msg_len) = self.read_len() else {
+                        return Ok(None);
+                    };
+                    let msg_len = msg_len as usize;
+                    self.check_msg_len(index, msg_len)?;
+
+                    self.state = DecodeState::Message {
+                        msgs_left,
+                        index,
+                        msg_len,
+                    };
+                }
+                DecodeState::Message {
+                    msgs_left,
+                    index,
+                    msg_len,
+                } => {
+                    if self.buffer.len() < self.cursor + msg_len {
+                        return Ok(None);
+                    }
+
+                    self.cursor += msg_len;
+                    if msgs_left == 1 {
+                        return Ok(Some(self.split_frame()));
+                    }
+
+                    self.state = DecodeState::MessageHeader {
+                        msgs_left: msgs_left - 1,
+                        index: index + 1,
+                    };
+                }
+            }
+        }
+    }
+
+    /// Make sure the message at the index, and the frame with it, are within the limits.
+    fn check_msg_len(&self, index: u32, msg_len: usize) -> Result<(), FrameError> {
+        if index < 2 && msg_len > self.limits.max_key_size {
+            return Err(FrameError::KeyTooLarge {
+                size: msg_len,
+                max: self.limits.max_key_size,
+            });
+        }
+
+        if index >= 2 && msg_len > self.limits.max_value_size {
+            return Err(FrameError::ValueTooLarge {
+                size: msg_len,
+                max: self.limits.max_value_size,
+            });
+        }
+
+        if self.cursor.saturating_add(msg_len) > self.limits.max_frame_size {
+            return Err(FrameError::FrameTooLarge {
+                max: self.limits.max_frame_size,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Read a 4 bytes length at the cursor, advancing the cursor past it.
+    /// Returns None if those bytes haven't arrived yet.
+    fn read_len(&mut self) -> Option<u32> {
+        if self.buffer.len() < self.cursor + LEN_SIZE {
+            return None;
+        }
+
+        let mut len = [0u8; LEN_SIZE];
+        len.copy_from_slice(&self.buffer[self.cursor..self.cursor + LEN_SIZE]);
+        self.cursor += LEN_SIZE;
+
+        Some(u32::from_le_bytes(len))
+    }
+
+    /// Take the fully parsed frame out of the buffer and reset the state for the next one.
+    fn split_frame(&mut self) -> Request {
+        let frame = self.buffer.take(self.cursor);
+
+        self.cursor = 0;
+        self.state = DecodeState::Header;
+
+        Request::new_with_payload(frame)
+    }
+}
+
+impl std::default::Default for RequestDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Incrementally decodes [`RawResponse`]s out of a stream of bytes.
+///
+/// The counterpart of [`RequestDecoder`] for the client side, a [`RawResponse`] is only
+/// yielded once its whole frame is buffered, which makes it possible to read the responses
+/// of multiple pipelined requests as they arrive.
+///
+/// The frame structure is described in [`RawResponse`].
+#[derive(Debug, Default)]
+pub struct ResponseDecoder {
+    /// The bytes received so far that haven't been yielded as a [`RawResponse`] yet.
+    buffer: ReadBuffer,
+}
+
+impl ResponseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append the given bytes to the decoder's buffer.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.buffer.feed(bytes);
+    }
+
+    /// Read everything that is currently available from the source into the buffer.
+    /// Behaves the same as [`RequestDecoder::read_from`].
+    pub fn read_from<R: Read>(&mut self, source: &mut R) -> Result<usize, io::Error> {
+        self.buffer.read_from(source, usize::MAX)
+    }
+
+    /// Yield the next complete [`RawResponse`] in the buffer.
+    /// Returns None if the buffer doesn't contain a complete frame yet.
+    pub fn decode(&mut self) -> Option<RawResponse> {
+        // The status code and the length of the message.
+        const HEADERS_SIZE: usize = LEN_SIZE * 2;
+        if self.buffer.len() < HEADERS_SIZE {
+            return None;
+        }
+
+        let mut msg_len = [0u8; LEN_SIZE];
+        msg_len.copy_from_slice(&self.buffer[LEN_SIZE..HEADERS_SIZE]);
+        let frame_len = HEADERS_SIZE + u32::from_le_bytes(msg_len) as usize;
+        if self.buffer.len() < frame_len {
+            return None;
+        }
+
+        Some(RawResponse(self.buffer.take(frame_len)))
+    }
+}
+
+/// The bytes read from a source that a decoder hasn't consumed yet, which it sees through
+/// [`Deref`].
+///
+/// The consumed bytes are only skipped over, and dropped all at once before the next bytes
+/// are added, so decoding a batch of pipelined frames doesn't move the rest of the batch
+/// every time one of them is taken out.
+#[derive(Debug, Default)]
+pub(crate) struct ReadBuffer {
+    bytes: Vec<u8>,
+
+    /// How many bytes at the start of `bytes` were consumed.
+    consumed: usize,
+}
+
+impl ReadBuffer {
+    pub(crate) fn feed(&mut self, bytes: &[u8]) {
+        self.compact();
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    /// See [`read_available`], the consumed bytes don't count towards `max_buffered`.
+    pub(crate) fn read_from<R: Read>(
+        &mut self,
+        source: &mut R,
+        max_buffered: usize,
+    ) -> Result<usize, io::Error> {
+        self.compact();
+        read_available(&mut self.bytes, source, max_buffered)
+    }
+
+    /// Skip over the next `len` bytes.
+    pub(crate) fn consume(&mut self, len: usize) {
+        self.consumed += len;
+        debug_assert!(self.consumed <= self.bytes.len());
+
+        if self.consumed == self.bytes.len() {
+            self.bytes.clear();
+            self.consumed = 0;
+        }
+    }
+
+    /// Take the next `len` bytes out, e.g. a whole frame.
+    pub(crate) fn take(&mut self, len: usize) -> Vec<u8> {
+        let taken = self[..len].to_vec();
+        self.consume(len);
+        taken
+    }
+
+    fn compact(&mut self) {
+        self.bytes.drain(..self.consumed);
+        self.consumed = 0;
+    }
+}
+
+impl Deref for ReadBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes[self.consumed..]
+    }
+}
+
+/// Read everything that is currently available from the source into the buffer,
+/// or until the buffer holds at least `max_buffered` bytes.
+pub(crate) fn read_available<R: Read>(
+    buffer: &mut Vec<u8>,
+    source: &mut R,
+    max_buffered: usize,
+) -> Result<usize, io::Error> {
+    let mut chunk = [0u8; READ_CHUNK_SIZE];
+    let mut bytes_read = 0;
+
+    while buffer.len() < max_buffered {
+        match source.read(&mut chunk) {
+            // The bytes read before the end have to be decoded first, the end is reported
+            // by the next call, which reads nothing.
+            Ok(0) if bytes_read > 0 => return Ok(bytes_read),
+            Ok(0) => {
+                return Err(io::Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "Connection closed by peer.",
+                ))
+            }
+            Ok(n) => {
+                buffer.extend_from_slice(&chunk[..n]);
+                bytes_read += n;
+            }
+
+            // Would block "errors" are the OS's way of saying that there's
+            // nothing left to read for now.
+            Err(ref err) if err.kind() == ErrorKind::WouldBlock => return Ok(bytes_read),
+            Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
+            // Other errors we'll consider fatal.
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(bytes_read)
+}
+
+#[cfg(test)]
+mod request_decoder {
+    use super::{FrameError, FrameLimits, RequestDecoder};
+    use crate::{Command, Extract, Request, SetOptions};
+    use std::io::Read;
+
+    fn set_request_payload() -> Vec<u8> {
+        let mut command = Command::Set(b"key".to_vec(), b"value".to_vec(), SetOptions::default());
+        let request: Request = command.extract().unwrap();
+        request.payload().to_vec()
+    }
+
+    #[test]
+    pub fn complete_frame_should_be_decoded_to_request() {
+        let payload = set_request_payload();
+        let mut decoder = RequestDecoder::new();
+        decoder.feed(&payload);
+
+        let request = decoder.decode().unwrap().unwrap();
+        assert_eq!(request, Request::new_with_payload(payload));
+        assert!(!decoder.has_pending_bytes());
+        assert!(decoder.decode().unwrap().is_none());
+    }
+
+    #[test]
+    pub fn frame_fed_byte_by_byte_should_only_be_decoded_once_complete() {
+        let payload = set_request_payload();
+        let mut decoder = RequestDecoder::new();
+
+        for byte in &payload[..payload.len() - 1] {
+            decoder.feed(&[*byte]);
+            assert!(decoder.decode().unwrap().is_none());
+        }
+
+        decoder.feed(&payload[payload.len() - 1..]);
+        let command: Command = decoder.decode().unwrap().unwrap().try_into().unwrap();
+        assert_eq!(
+            command,
+            Command::Set(b"key".to_vec(), b"value".to_vec(), SetOptions::default())
+        );
+    }
+
+    #[test]
+    pub fn bytes_of_the_next_frame_should_be_kept_in_the_buffer() {
+        let payload = set_request_payload();
+        let mut decoder = RequestDecoder::new();
+        decoder.feed(&payload);
+        decoder.feed(&payload[..5]);
+
+        assert_eq!(
+            decoder.decode().unwrap().unwrap(),
+            Request::new_with_payload(payload.clone())
+        );
+        assert!(decoder.decode().unwrap().is_none());
+        assert!(decoder.has_pending_bytes());
+
+        decoder.feed(&payload[5..]);
+        assert_eq!(
+            decoder.decode().unwrap().unwrap(),
+            Request::new_with_payload(payload)
+        );
+    }
+
+    #[test]
+    pub fn read_from_should_stop_on_would_block() {
+        let payload = set_request_payload();
+        let mut source = std::io::Cursor::new(payload.clone()).chain(WouldBlockReader);
+        let mut decoder = RequestDecoder::new();
+
+        let bytes_read = decoder.read_from(&mut source).unwrap();
+        assert_eq!(bytes_read, payload.len());
+        assert_eq!(
+            decoder.decode().unwrap().unwrap(),
+            Request::new_with_payload(payload)
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn read_from_closed_source_should_result_in_err() {
+        let mut decoder = RequestDecoder::new();
+        decoder.read_from(&mut std::io::empty()).unwrap();
+    }
+
+    #[test]
+    pub fn bytes_read_before_the_source_closes_should_be_decoded_first() {
+        let payload = set_request_payload();
+        let mut source = std::io::Cursor::new(payload.clone());
+        let mut decoder = RequestDecoder::new();
+
+        assert_eq!(decoder.read_from(&mut source).unwrap(), payload.len());
+        assert_eq!(
+            decoder.decode().unwrap().unwrap(),
+            Request::new_with_payload(payload)
+        );
+
+        let err = decoder.read_from(&mut source).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+
+    fn header(len: u32) -> [u8; 4] {
+        len.to_le_bytes()
+    }
+
+    #[test]
+    pub fn hostile_message_count_should_be_rejected_before_buffering_messages() {
+        let mut decoder = RequestDecoder::new();
+        decoder.feed(&header(u32::MAX));
+
+        assert_eq!(
+            decoder.decode(),
+            Err(FrameError::TooManyArgs {
+                args: u32::MAX,
+                max: FrameLimits::default().max_args
+            })
+        );
+    }
+
+    #[test]
+    pub fn hostile_message_length_should_be_rejected_before_the_message_arrives() {
+        let mut decoder = RequestDecoder::new();
+        decoder.feed(&header(3));
+        decoder.feed(&header(3));
+        decoder.feed(b"set");
+        decoder.feed(&header(3));
+        decoder.feed(b"key");
+        decoder.feed(&header(u32::MAX));
+
+        assert!(matches!(
+            decoder.decode(),
+            Err(FrameError::ValueTooLarge { size, .. }) if size == u32::MAX as usize
+        ));
+    }
+
+    #[test]
+    pub fn key_over_the_limit_should_be_rejected() {
+        let limits = FrameLimits {
+            max_key_size: 4,
+            ..FrameLimits::default()
+        };
+        let mut decoder = RequestDecoder::with_limits(limits);
+        decoder.feed(&header(2));
+        decoder.feed(&header(3));
+        decoder.feed(b"get");
+        decoder.feed(&header(5));
+
+        assert_eq!(
+            decoder.decode(),
+            Err(FrameError::KeyTooLarge { size: 5, max: 4 })
+        );
+    }
+
+    #[test]
+    pub fn frame_over_the_limit_should_be_rejected_even_if_every_message_is_within_limits() {
+        let limits = FrameLimits {
+            max_frame_size: 32,
+            ..FrameLimits::default()
+        };
+        let mut decoder = RequestDecoder::with_limits(limits);
+        decoder.feed(&header(100));
+        for _ in 0..100 {
+            decoder.feed(&header(4));
+            decoder.feed(b"argv");
+        }
+
+        assert_eq!(decoder.decode(), Err(FrameError::FrameTooLarge { max: 32 }));
+    }
+
+    #[test]
+    pub fn frame_within_the_limits_should_be_decoded() {
+        let payload = set_request_payload();
+        let limits = FrameLimits {
+            max_args: 3,
+            max_key_size: 3,
+            max_value_size: 5,
+            max_frame_size: payload.len(),
+        };
+        let mut decoder = RequestDecoder::with_limits(limits);
+        decoder.feed(&payload);
+
+        assert_eq!(
+            decoder.decode().unwrap().unwrap(),
+            Request::new_with_payload(payload)
+        );
+    }
+
+    #[test]
+    pub fn read_from_should_stop_once_a_frame_worth_of_bytes_is_buffered() {
+        let limits = FrameLimits {
+            max_frame_size: 16,
+            ..FrameLimits::default()
+        };
+        let mut decoder = RequestDecoder::with_limits(limits);
+        let mut source = std::io::repeat(0).take(1024 * 1024);
+
+        let bytes_read = decoder.read_from(&mut source).unwrap();
+        assert!(bytes_read < 1024 * 1024);
+        assert!(decoder.is_full());
+    }
+
+    #[test]
+    pub fn large_pipelined_batch_should_be_decoded_without_moving_the_rest_of_it() {
+        // Moving the rest of the batch for every frame takes minutes for a batch this large.
+        let payload = set_request_payload();
+        let frames = 200_000;
+        let mut decoder = RequestDecoder::new();
+        decoder.feed(&payload.repeat(frames));
+        // The last frame is still missing a byte.
+        decoder.feed(&payload[..payload.len() - 1]);
+
+        let mut decoded = 0;
+        while let Some(request) = decoder.decode().unwrap() {
+            assert_eq!(request.payload(), payload.as_slice());
+            decoded += 1;
+        }
+        assert_eq!(decoded, frames);
+
+        decoder.feed(&payload[payload.len() - 1..]);
+        assert_eq!(
+            decoder.decode().unwrap().unwrap(),
+            Request::new_with_payload(payload)
+        );
+        assert!(!decoder.has_pending_bytes());
+    }
+
+    struct WouldBlockReader;
+
+    impl std::io::Read for WouldBlockReader {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::WouldBlock.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod response_decoder {
+    use super::ResponseDecoder;
+    use crate::{RawResponse, Response, StatusCodes};
+
+    #[test]
+    pub fn multiple_responses_should_be_decoded_in_order() {
+        let mut bytes = RawResponse::new(StatusCodes::Ok, Some(b"first".to_vec())).0;
+        bytes.append(&mut RawResponse::new(StatusCodes::ErrNotFound, None).0);
+        bytes.append(&mut RawResponse::new(StatusCodes::Ok, Some(b"third".to_vec())).0);
+
+        let mut decoder = ResponseDecoder::new();
+        decoder.feed(&bytes);
+
+        let response: Response = decoder.decode().unwrap().into();
+        assert_eq!(response.message(), Some(b"first".as_slice()));
+
+        let response: Response = decoder.decode().unwrap().into();
+        assert_eq!(response.status_code(), StatusCodes::ErrNotFound);
+
+        let response: Response = decoder.decode().unwrap().into();
+        assert_eq!(response.message(), Some(b"third".as_slice()));
+
+        assert!(decoder.decode().is_none());
+    }
+
+    #[test]
+    pub fn large_pipelined_batch_should_be_decoded() {
+        let bytes = RawResponse::new(StatusCodes::Ok, Some(b"value".to_vec())).0;
+        let responses = 200_000;
+        let mut decoder = ResponseDecoder::new();
+        decoder.feed(&bytes.repeat(responses));
+
+        let decoded = std::iter::from_fn(|| decoder.decode()).count();
+        assert_eq!(decoded, responses);
+    }
+
+    #[test]
+    pub fn partial_response_should_only_be_decoded_once_complete() {
+        let bytes = RawResponse::new(StatusCodes::Ok, Some(b"value".to_vec())).0;
+        let mut decoder = ResponseDecoder::new();
+
+        decoder.feed(&bytes[..6]);
+        assert!(decoder.decode().is_none());
+
+        decoder.feed(&bytes[6..10]);
+        assert!(decoder.decode().is_none());
+
+        decoder.feed(&bytes[10..]);
+        let response: Response = decoder.decode().unwrap().into();
+        assert_eq!(response.message(), Some(b"value".as_slice()));
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
+
+/// The server's reply to [`crate::Command::Hello`], sent as the message of the response.
+///
+/// It's encoded as one `field:value` line per field, e.g.:
+///
+/// ```text
+/// version:1
+/// server:skaja 0.1.0
+/// capabilities:pipelining,expiry
+/// ```
+///
+/// Fields that aren't recognized are ignored, so that newer servers can report more.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hello {
+    /// The version of the protocol the connection speaks from now on.
+    pub version: u32,
+
+    /// The name and version of the server.
+    pub server: String,
+
+    /// What the server supports on top of the basic commands.
+    pub capabilities: Vec<String>,
+}
+
+impl Hello {
+    /// Whether the server reported the given capability.
+    pub fn has_capability(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        format!(
+            "version:{}\nserver:{}\ncapabilities:{}",
+            self.version,
+            self.server,
+            self.capabilities.join(",")
+        )
+        .into_bytes()
+    }
+
+    pub fn decode(msg: &[u8]) -> Result<Self, String> {
+        let msg = std::str::from_utf8(msg).map_err(|_| "Hello reply isn't valid UTF-8")?;
+
+        let mut version = None;
+        let mut server = String::new();
+        let mut capabilities = Vec::new();
+        for line in msg.lines() {
+            match line.split_once(':') {
+                Some(("version", value)) => version = value.parse().ok(),
+                Some(("server", value)) => server = value.to_string(),
+                Some(("capabilities", value)) => {
+                    capabilities = value
+                        .split(',')
+                        .filter(|c| !c.is_empty())
+                        .map(str::to_string)
+                        .collect();
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            version: version.ok_or("Hello reply doesn't contain a valid version")?,
+            server,
+            capabilities,
+        })
+    }
+}
+
+/// Pick the version of the protocol to speak with a peer that speaks up to `requested`,
+/// None if the peer is too old.
+pub fn negotiate_version(requested: u32) -> Option<u32> {
+    match requested {
+        requested if requested < MIN_PROTOCOL_VERSION => None,
+        requested => Some(requested.min(PROTOCOL_VERSION)),
+    }
+}
+
+#[cfg(test)]
+mod hello_reply {
+    use super::{negotiate_version, Hello};
+    use crate::PROTOCOL_VERSION;
+
+    #[test]
+    pub fn encoded_hello_should_decode_to_the_same_hello() {
+        let hello = Hello {
+            version: 1,
+            server: "skaja 0.1.0".to_string(),
+            capabilities: vec!["pipelining".to_string(), "expiry".to_string()],
+        };
+
+        assert_eq!(Hello::decode(&hello.encode()).unwrap(), hello);
+        assert!(hello.has_capability("expiry"));
+        assert!(!hello.has_capability("lists"));
+    }
+
+    #[test]
+    pub fn unknown_fields_should_be_ignored() {
+        let hello = Hello::decode(b"version:2\nmodules:none\ncapabilities:").unwrap();
+        assert_eq!(hello.version, 2);
+        assert!(hello.capabilities.is_empty());
+    }
+
+    #[test]
+    pub fn hello_without_version_should_result_in_err() {
+        assert!(Hello::decode(b"server:skaja").is_err());
+    }
+
+    #[test]
+    pub fn newer_version_should_be_negotiated_down() {
+        assert_eq!(
+            negotiate_version(PROTOCOL_VERSION + 1),
+            Some(PROTOCOL_VERSION)
+        );
+        assert_eq!(negotiate_version(PROTOCOL_VERSION), Some(PROTOCOL_VERSION));
+        assert_eq!(negotiate_version(0), None);
+    }
+}
@@ -0,0 +1,368 @@
+use super::decoder::ReadBuffer;
+use crate::{FrameError, FrameLimits};
+use std::io::{self, Read};
+
+/// How long the request line and the headers of a request can be altogether.
+const MAX_HEAD_SIZE: usize = 8 * 1024;
+
+/// A request in HTTP/1.x, with its body read according to its `Content-Length`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpRequest {
+    /// e.g. `GET`, as it was sent.
+    pub method: String,
+
+    /// The path along with the query string, still percent-encoded, e.g. `/keys?prefix=a`.
+    pub target: String,
+
+    /// The minor version of HTTP/1.x.
+    pub minor_version: u8,
+
+    /// The headers in the order they were sent, their names are lowercased.
+    pub headers: Vec<(String, Vec<u8>)>,
+
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    /// The value of the first header with the given lowercase name.
+    pub fn header(&self, name: &str) -> Option<&[u8]> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_slice())
+    }
+
+    /// Whether the connection stays open after the response, which is the default
+    /// since HTTP/1.1 unless the client says otherwise.
+    pub fn keep_alive(&self) -> bool {
+        let connection = self.header("connection").map(<[u8]>::to_ascii_lowercase);
+        match connection.as_deref() {
+            Some(b"close") => false,
+            Some(b"keep-alive") => true,
+            _ => self.minor_version >= 1,
+        }
+    }
+}
+
+/// Incrementally decodes the requests sent in HTTP/1.x.
+///
+/// Works like [`crate::RequestDecoder`], the [`FrameLimits`] are enforced as soon as the
+/// headers arrive, the body being the value and each header counting as an argument.
+/// Only bodies with a `Content-Length` are supported.
+#[derive(Debug)]
+pub struct HttpDecoder {
+    /// The bytes received so far that haven't been yielded as a request yet.
+    buffer: ReadBuffer,
+
+    limits: FrameLimits,
+
+    /// Whether the request being received asked for a `100 Continue` before sending its body,
+    /// see [`HttpDecoder::take_expect_continue`].
+    expect_continue: ExpectContinue,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ExpectContinue {
+    No,
+    Pending,
+    Answered,
+}
+
+impl HttpDecoder {
+    pub fn new() -> Self {
+        Self::with_limits(FrameLimits::default())
+    }
+
+    pub fn with_limits(limits: FrameLimits) -> Self {
+        Self {
+            buffer: ReadBuffer::default(),
+            limits,
+            expect_continue: ExpectContinue::No,
+        }
+    }
+
+    /// Append the given bytes to the decoder's buffer.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.buffer.feed(bytes);
+    }
+
+    /// Read everything that is currently available from the source into the buffer.
+    /// Behaves the same as [`crate::RequestDecoder::read_from`].
+    pub fn read_from<R: Read>(&mut self, source: &mut R) -> Result<usize, io::Error> {
+        self.buffer.read_from(source, self.limits.max_frame_size)
+    }
+
+    /// Whether [`HttpDecoder::read_from`] stopped reading because the buffer is full.
+    pub fn is_full(&self) -> bool {
+        self.buffer.len() >= self.limits.max_frame_size
+    }
+
+    /// Whether the client is waiting for a `100 Continue` before sending the body of the
+    /// request, only true once per request.
+    pub fn take_expect_continue(&mut self) -> bool {
+        if self.expect_continue != ExpectContinue::Pending {
+            return false;
+        }
+
+        self.expect_continue = ExpectContinue::Answered;
+        true
+    }
+
+    /// Yield the next complete request in the buffer.
+    /// Returns None if the buffer doesn't contain a complete request yet, and an error if
+    /// the request is malformed or exceeds the limits.
+    pub fn decode(&mut self) -> Result<Option<HttpRequest>, FrameError> {
+        let Some(head_end) = find(&self.buffer, b"\r\n\r\n") else {
+            if self.buffer.len() >= MAX_HEAD_SIZE.min(self.limits.max_frame_size) {
+                return Err(FrameError::Malformed("headers too large".to_string()));
+            }
+
+            return Ok(None);
+        };
+
+        let mut request = parse_head(&self.buffer[..head_end])?;
+        self.check_headers(&request)?;
+
+        let content_length = match request.header("content-length") {
+            Some(length) => std::str::from_utf8(length)
+                .ok()
+                .and_then(|length| length.trim().parse::<usize>().ok())
+                .ok_or_else(|| FrameError::Malformed("invalid content length".to_string()))?,
+            None => 0,
+        };
+
+        let start = head_end + 4;
+        self.check_body_len(content_length, start)?;
+
+        let end = start + content_length;
+        if self.buffer.len() < end {
+            let expects_continue = request
+                .header("expect")
+                .is_some_and(|expect| expect.eq_ignore_ascii_case(b"100-continue"));
+            if expects_continue && self.expect_continue == ExpectContinue::No {
+                self.expect_continue = ExpectContinue::Pending;
+            }
+
+            return Ok(None);
+        }
+
+        request.body = self.buffer[start..end].to_vec();
+        self.buffer.consume(end);
+        self.expect_continue = ExpectContinue::No;
+
+        Ok(Some(request))
+    }
+
+    fn check_headers(&self, request: &HttpRequest) -> Result<(), FrameError> {
+        if request.headers.len() > self.limits.max_args {
+            return Err(FrameError::TooManyArgs {
+                args: request.headers.len().try_into().unwrap_or(u32::MAX),
+                max: self.limits.max_args,
+            });
+        }
+
+        if request.header("transfer-encoding").is_some() {
+            return Err(FrameError::Malformed(
+                "only bodies with a content length are supported".to_string(),
+            ));
+        }
+
+        // Where the body ends would depend on which of the lengths is trusted.
+        let mut lengths = request
+            .headers
+            .iter()
+            .filter(|(header, _)| header == "content-length")
+            .map(|(_, length)| length.trim_ascii());
+        if let Some(first) = lengths.next() {
+            if lengths.any(|length| length != first) {
+                return Err(FrameError::Malformed(
+                    "conflicting content lengths".to_string(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Make sure the body, and the request with it, are within the limits.
+    fn check_body_len(&self, len: usize, start: usize) -> Result<(), FrameError> {
+        if len > self.limits.max_value_size {
+            return Err(FrameError::ValueTooLarge {
+                size: len,
+                max: self.limits.max_value_size,
+            });
+        }
+
+        if start.saturating_add(len) > self.limits.max_frame_size {
+            return Err(FrameError::FrameTooLarge {
+                max: self.limits.max_frame_size,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for HttpDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parse the request line and the headers, without the empty line that ends them.
+fn parse_head(head: &[u8]) -> Result<HttpRequest, FrameError> {
+    let malformed = |msg: &str| FrameError::Malformed(msg.to_string());
+
+    let mut lines = head
+        .split(|byte| *byte == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line));
+    let request_line = std::str::from_utf8(lines.next().unwrap_or_default())
+        .map_err(|_| malformed("invalid request line"))?;
+
+    let mut parts = request_line.split(' ');
+    let (Some(method), Some(target), Some(version), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(malformed("invalid request line"));
+    };
+
+    let minor_version = match version {
+        "HTTP/1.0" => 0,
+        "HTTP/1.1" => 1,
+        _ => return Err(malformed("unsupported HTTP version")),
+    };
+
+    let mut headers = Vec::new();
+    for line in lines {
+        let Some(colon) = line.iter().position(|byte| *byte == b':') else {
+            return Err(malformed("invalid header"));
+        };
+
+        let name = std::str::from_utf8(&line[..colon])
+            .map_err(|_| malformed("invalid header"))?
+            .to_ascii_lowercase();
+        headers.push((name, line[colon + 1..].trim_ascii().to_vec()));
+    }
+
+    Ok(HttpRequest {
+        method: method.to_string(),
+        target: target.to_string(),
+        minor_version,
+        headers,
+        body: Vec::new(),
+    })
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod http_decoder {
+    use super::HttpDecoder;
+    use crate::{FrameError, FrameLimits};
+
+    #[test]
+    pub fn request_should_be_decoded_with_its_body() {
+        let mut decoder = HttpDecoder::new();
+        decoder.feed(b"PUT /keys/a HTTP/1.1\r\nHost: x\r\nContent-Length: 5\r\n\r\nhelloGET /keys/a HTTP/1.0\r\n\r\n");
+
+        let request = decoder.decode().unwrap().unwrap();
+        assert_eq!(request.method, "PUT");
+        assert_eq!(request.target, "/keys/a");
+        assert_eq!(request.header("host"), Some(b"x".as_slice()));
+        assert_eq!(request.body, b"hello");
+        assert!(request.keep_alive());
+
+        let request = decoder.decode().unwrap().unwrap();
+        assert_eq!(request.method, "GET");
+        assert!(request.body.is_empty());
+        assert!(!request.keep_alive());
+
+        assert_eq!(decoder.decode().unwrap(), None);
+    }
+
+    #[test]
+    pub fn request_fed_byte_by_byte_should_only_be_decoded_once_complete() {
+        let frame = b"PUT /keys/a HTTP/1.1\r\nContent-Length: 2\r\nExpect: 100-continue\r\n\r\nhi";
+        let mut decoder = HttpDecoder::new();
+
+        let mut continues = 0;
+        for byte in &frame[..frame.len() - 1] {
+            decoder.feed(&[*byte]);
+            assert_eq!(decoder.decode().unwrap(), None);
+            continues += decoder.take_expect_continue() as usize;
+        }
+        assert_eq!(continues, 1);
+
+        decoder.feed(&frame[frame.len() - 1..]);
+        assert_eq!(decoder.decode().unwrap().unwrap().body, b"hi");
+    }
+
+    #[test]
+    pub fn repeated_content_length_should_be_accepted_when_it_agrees() {
+        let mut decoder = HttpDecoder::new();
+        decoder
+            .feed(b"PUT /keys/a HTTP/1.1\r\nContent-Length: 5\r\nContent-Length:  5\r\n\r\nhello");
+
+        assert_eq!(decoder.decode().unwrap().unwrap().body, b"hello");
+    }
+
+    #[test]
+    pub fn large_pipelined_batch_should_be_decoded() {
+        let frame = b"PUT /keys/a HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello";
+        let requests = 100_000;
+        let mut decoder = HttpDecoder::new();
+        decoder.feed(&frame.repeat(requests));
+        decoder.feed(b"GET /keys/a HTTP/1.1\r\n\r");
+
+        let mut decoded = 0;
+        while let Some(request) = decoder.decode().unwrap() {
+            assert_eq!(request.body, b"hello");
+            decoded += 1;
+        }
+        assert_eq!(decoded, requests);
+
+        decoder.feed(b"\n");
+        assert_eq!(decoder.decode().unwrap().unwrap().method, "GET");
+    }
+
+    #[test]
+    pub fn hostile_content_length_should_be_rejected_before_the_body_arrives() {
+        let mut decoder = HttpDecoder::new();
+        decoder.feed(b"PUT /keys/a HTTP/1.1\r\nContent-Length: 99999999999\r\n\r\n");
+
+        assert!(matches!(
+            decoder.decode(),
+            Err(FrameError::ValueTooLarge {
+                size: 99999999999,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    pub fn malformed_requests_should_result_in_err() {
+        for frame in [
+            b"GET /keys/a\r\n\r\n".as_slice(),
+            b"GET /keys/a HTTP/2\r\n\r\n",
+            b"GET /keys/a HTTP/1.1\r\nno colon\r\n\r\n",
+            b"PUT /keys/a HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n",
+            b"PUT /keys/a HTTP/1.1\r\nContent-Length: 1\r\nContent-Length: 5\r\n\r\nhello",
+        ] {
+            let mut decoder = HttpDecoder::new();
+            decoder.feed(frame);
+            assert!(matches!(decoder.decode(), Err(FrameError::Malformed(_))));
+        }
+
+        let limits = FrameLimits {
+            max_frame_size: 16,
+            ..FrameLimits::default()
+        };
+        let mut decoder = HttpDecoder::with_limits(limits);
+        decoder.feed(&[b'a'; 16]);
+        assert!(matches!(decoder.decode(), Err(FrameError::Malformed(_))));
+    }
+}
@@ -0,0 +1,284 @@
+use super::decoder::ReadBuffer;
+use crate::{FrameError, FrameLimits};
+use std::io::{self, Read};
+
+/// How long the command line of a request can be, memcached allows up to 2048 bytes.
+const MAX_LINE_SIZE: usize = 2048;
+
+/// The commands that are followed by a data block, the length of which is their 5th word.
+const STORAGE_COMMANDS: &[&[u8]] = &[b"set", b"add", b"replace", b"append", b"prepend", b"cas"];
+
+/// A request in memcached's text protocol.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemcachedRequest {
+    /// The words of the command line, e.g. `["set", "key", "0", "0", "5"]`.
+    pub args: Vec<Vec<u8>>,
+
+    /// The data block following the command line of the storage commands.
+    pub data: Option<Vec<u8>>,
+}
+
+/// Incrementally decodes the requests sent in memcached's text protocol.
+///
+/// Works like [`crate::RequestDecoder`], the [`FrameLimits`] are enforced as soon as the
+/// command line arrives, the second word being the key and the data block being the value.
+/// Storage commands whose command line doesn't tell the length of the data block are yielded
+/// without it, for the server to reject.
+#[derive(Debug)]
+pub struct MemcachedDecoder {
+    /// The bytes received so far that haven't been yielded as a request yet.
+    buffer: ReadBuffer,
+
+    limits: FrameLimits,
+}
+
+impl MemcachedDecoder {
+    pub fn new() -> Self {
+        Self::with_limits(FrameLimits::default())
+    }
+
+    pub fn with_limits(limits: FrameLimits) -> Self {
+        Self {
+            buffer: ReadBuffer::default(),
+            limits,
+        }
+    }
+
+    /// Append the given bytes to the decoder's buffer.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.buffer.feed(bytes);
+    }
+
+    /// Read everything that is currently available from the source into the buffer.
+    /// Behaves the same as [`crate::RequestDecoder::read_from`].
+    pub fn read_from<R: Read>(&mut self, source: &mut R) -> Result<usize, io::Error> {
+        self.buffer.read_from(source, self.limits.max_frame_size)
+    }
+
+    /// Whether [`MemcachedDecoder::read_from`] stopped reading because the buffer is full.
+    pub fn is_full(&self) -> bool {
+        self.buffer.len() >= self.limits.max_frame_size
+    }
+
+    /// Yield the next complete request in the buffer.
+    /// Returns None if the buffer doesn't contain a complete request yet, and an error if
+    /// the request is malformed or exceeds the limits.
+    pub fn decode(&mut self) -> Result<Option<MemcachedRequest>, FrameError> {
+        loop {
+            let Some(end) = self.buffer.iter().position(|byte| *byte == b'\n') else {
+                if self.buffer.len() >= MAX_LINE_SIZE.min(self.limits.max_frame_size) {
+                    return Err(FrameError::Malformed("line too long".to_string()));
+                }
+
+                return Ok(None);
+            };
+
+            let line = &self.buffer[..end];
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            let args: Vec<Vec<u8>> = line
+                .split(|byte| *byte == b' ')
+                .filter(|arg| !arg.is_empty())
+                .map(<[u8]>::to_vec)
+                .collect();
+
+            // Empty lines are skipped.
+            if args.is_empty() {
+                self.buffer.consume(end + 1);
+                continue;
+            }
+
+            self.check_args(&args)?;
+
+            let data_len = match STORAGE_COMMANDS.contains(&args[0].as_slice()) {
+                true => args.get(4).and_then(|len| parse_len(len)),
+                false => None,
+            };
+            let Some(data_len) = data_len else {
+                self.buffer.consume(end + 1);
+                return Ok(Some(MemcachedRequest { args, data: None }));
+            };
+
+            let start = end + 1;
+            self.check_data_len(data_len, start)?;
+
+            let data_end = start + data_len;
+            if self.buffer.len() < data_end + 2 {
+                return Ok(None);
+            }
+
+            if &self.buffer[data_end..data_end + 2] != b"\r\n" {
+                return Err(FrameError::Malformed("bad data chunk".to_string()));
+            }
+
+            let data = self.buffer[start..data_end].to_vec();
+            self.buffer.consume(data_end + 2);
+            return Ok(Some(MemcachedRequest {
+                args,
+                data: Some(data),
+            }));
+        }
+    }
+
+    fn check_args(&self, args: &[Vec<u8>]) -> Result<(), FrameError> {
+        if args.len() > self.limits.max_args {
+            return Err(FrameError::TooManyArgs {
+                args: args.len().try_into().unwrap_or(u32::MAX),
+                max: self.limits.max_args,
+            });
+        }
+
+        match args
+            .iter()
+            .skip(1)
+            .find(|key| key.len() > self.limits.max_key_size)
+        {
+            Some(key) => Err(FrameError::KeyTooLarge {
+                size: key.len(),
+                max: self.limits.max_key_size,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Make sure the data block, and the request with it, are within the limits.
+    fn check_data_len(&self, len: usize, start: usize) -> Result<(), FrameError> {
+        if len > self.limits.max_value_size {
+            return Err(FrameError::ValueTooLarge {
+                size: len,
+                max: self.limits.max_value_size,
+            });
+        }
+
+        if start.saturating_add(len).saturating_add(2) > self.limits.max_frame_size {
+            return Err(FrameError::FrameTooLarge {
+                max: self.limits.max_frame_size,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for MemcachedDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn parse_len(digits: &[u8]) -> Option<usize> {
+    std::str::from_utf8(digits).ok()?.parse().ok()
+}
+
+#[cfg(test)]
+mod memcached_decoder {
+    use super::{MemcachedDecoder, MemcachedRequest};
+    use crate::{FrameError, FrameLimits};
+
+    fn request(args: &[&str], data: Option<&str>) -> MemcachedRequest {
+        MemcachedRequest {
+            args: args.iter().map(|arg| arg.as_bytes().to_vec()).collect(),
+            data: data.map(|data| data.as_bytes().to_vec()),
+        }
+    }
+
+    #[test]
+    pub fn storage_command_should_be_decoded_with_its_data_block() {
+        let mut decoder = MemcachedDecoder::new();
+        decoder.feed(b"set key 3 0 7\r\nva\r\nlue\r\nget key other\r\n");
+
+        assert_eq!(
+            decoder.decode().unwrap(),
+            Some(request(&["set", "key", "3", "0", "7"], Some("va\r\nlue")))
+        );
+        assert_eq!(
+            decoder.decode().unwrap(),
+            Some(request(&["get", "key", "other"], None))
+        );
+        assert_eq!(decoder.decode().unwrap(), None);
+    }
+
+    #[test]
+    pub fn request_fed_byte_by_byte_should_only_be_decoded_once_complete() {
+        let frame = b"cas key 0 0 1 42 noreply\r\nv\r\n";
+        let mut decoder = MemcachedDecoder::new();
+
+        for byte in &frame[..frame.len() - 1] {
+            decoder.feed(&[*byte]);
+            assert_eq!(decoder.decode().unwrap(), None);
+        }
+
+        decoder.feed(&frame[frame.len() - 1..]);
+        assert_eq!(
+            decoder.decode().unwrap(),
+            Some(request(
+                &["cas", "key", "0", "0", "1", "42", "noreply"],
+                Some("v")
+            ))
+        );
+    }
+
+    #[test]
+    pub fn storage_command_without_length_should_be_decoded_without_data() {
+        let mut decoder = MemcachedDecoder::new();
+        decoder.feed(b"set key\r\n");
+
+        assert_eq!(
+            decoder.decode().unwrap(),
+            Some(request(&["set", "key"], None))
+        );
+    }
+
+    #[test]
+    pub fn large_pipelined_batch_should_be_decoded() {
+        let requests = 100_000;
+        let mut decoder = MemcachedDecoder::new();
+        decoder.feed(&b"set key 0 0 5\r\nvalue\r\nget key\r\n".repeat(requests));
+        decoder.feed(b"delete key");
+
+        let mut decoded = 0;
+        while let Some(decoded_request) = decoder.decode().unwrap() {
+            let expected = match decoded % 2 {
+                0 => request(&["set", "key", "0", "0", "5"], Some("value")),
+                _ => request(&["get", "key"], None),
+            };
+            assert_eq!(decoded_request, expected);
+            decoded += 1;
+        }
+        assert_eq!(decoded, requests * 2);
+
+        decoder.feed(b"\r\n");
+        assert_eq!(
+            decoder.decode().unwrap(),
+            Some(request(&["delete", "key"], None))
+        );
+    }
+
+    #[test]
+    pub fn hostile_data_length_should_be_rejected_before_the_data_arrives() {
+        let mut decoder = MemcachedDecoder::new();
+        decoder.feed(b"set key 0 0 4294967295\r\n");
+
+        assert!(matches!(
+            decoder.decode(),
+            Err(FrameError::ValueTooLarge {
+                size: 4294967295,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    pub fn malformed_requests_should_result_in_err() {
+        let mut decoder = MemcachedDecoder::new();
+        decoder.feed(b"set key 0 0 1\r\nvalue\r\n");
+        assert!(matches!(decoder.decode(), Err(FrameError::Malformed(_))));
+
+        let limits = FrameLimits {
+            max_frame_size: 16,
+            ..FrameLimits::default()
+        };
+        let mut decoder = MemcachedDecoder::with_limits(limits);
+        decoder.feed(&[b'a'; 16]);
+        assert!(matches!(decoder.decode(), Err(FrameError::Malformed(_))));
+    }
+}
@@ -1,7 +1,21 @@
 mod command;
+mod decoder;
+mod hello;
+mod http;
+mod memcached;
 mod request;
+mod resp;
 mod response;
+mod stream;
+mod versioned;
 
 pub use command::*;
+pub use decoder::*;
+pub use hello::*;
+pub use http::*;
+pub use memcached::*;
 pub use request::*;
+pub use resp::*;
 pub use response::*;
+pub use stream::*;
+pub use versioned::*;
@@ -1,4 +1,4 @@
-use super::command::Command;
+use super::command::{Command, CommandError};
 
 #[derive(Debug, PartialEq)]
 pub struct Request {
@@ -18,6 +18,7 @@ pub struct Request {
     ///
     /// The first chunk is called the header.
     /// The even chunks (or odd if it's 0-based) are called the message headers.
+    /// Both are 32bit unsigned integers in little-endian byte order.
     payload: Vec<u8>,
 
     /// The position of the pointer in the payload.
@@ -59,7 +60,7 @@ impl Request {
     pub fn header(&mut self) -> u32 {
         let mut header = [0u8; 4];
         header.copy_from_slice(&self.payload[0..4]);
-        u32::from_ne_bytes(header)
+        u32::from_le_bytes(header)
     }
 
     /// Get the length of the next message in the payload.
@@ -77,12 +78,12 @@ impl Request {
         msg_len.copy_from_slice(&self.payload[self.pointer_pos..self.pointer_pos + BYTES_TO_READ]);
         self.pointer_pos += BYTES_TO_READ;
 
-        Some(u32::from_ne_bytes(msg_len))
+        Some(u32::from_le_bytes(msg_len))
     }
 
     /// Get the next message in the payload.
     /// Returns None if there is none.
-    pub fn next_msg(&mut self) -> Option<String> {
+    pub fn next_msg(&mut self) -> Option<Vec<u8>> {
         let next_msg_len: usize = match self.next_msg_len() {
             Some(value) => value as usize,
             None => return None,
@@ -96,8 +97,7 @@ impl Request {
         let mut msg = vec![0u8; next_msg_len];
         msg.copy_from_slice(&self.payload[self.pointer_pos..self.pointer_pos + next_msg_len]);
         self.pointer_pos += next_msg_len;
-        let msg = String::from_utf8_lossy(&msg);
-        Some(msg.to_string())
+        Some(msg)
     }
 }
 
@@ -108,57 +108,32 @@ impl std::default::Default for Request {
 }
 
 impl TryInto<Command> for Request {
-    type Error = String;
+    type Error = CommandError;
 
     /// Implements [`TryFrom`] trait instead of [`From`] because the payload might be invalid.
     /// Even though it's unlikely that the client binary will send invalid payload
     /// given it already has enough validations to ensure the payload is valid.
     /// You can't be too safe by adding server-side payload validation.
     fn try_into(mut self) -> Result<Command, Self::Error> {
-        let next_msg = match self.next_msg() {
-            Some(next_msg) => next_msg,
-            None => return Err("Payload doesn't contain any command.".to_string()),
-        };
+        let mut parts = Vec::new();
+        while let Some(msg) = self.next_msg() {
+            parts.push(msg);
+        }
 
-        match next_msg.as_str() {
-            "get" => {
-                let arg = match self.next_msg() {
-                    Some(arg) => arg,
-                    None => return Err("Missing argument for command \"get\".".to_string()),
-                };
-
-                Ok(Command::Get(arg))
-            }
-            "set" => {
-                let key = match self.next_msg() {
-                    Some(key) => key,
-                    None => return Err("Missing key argument for command \"set\".".to_string()),
-                };
-
-                let value = match self.next_msg() {
-                    Some(val) => val,
-                    None => return Err("Missing value argument for command \"set\".".to_string()),
-                };
-
-                Ok(Command::Set(key, value))
-            }
-            "del" => {
-                let arg = match self.next_msg() {
-                    Some(arg) => arg,
-                    None => return Err("Missing argument for command \"del\".".to_string()),
-                };
-
-                Ok(Command::Delete(arg))
-            }
-            _ => Err("Invalid command.".to_string()),
+        if parts.is_empty() {
+            return Err(CommandError::Invalid(
+                "Payload doesn't contain any command.".to_string(),
+            ));
         }
+
+        Command::from_parts(parts)
     }
 }
 
 #[cfg(test)]
 mod request_to_command {
     use super::Request;
-    use crate::domains::command::Command;
+    use crate::domains::command::{Command, SetOptions};
 
     #[test]
     pub fn valid_get_payload_should_deserialized_correctly() {
@@ -168,7 +143,7 @@ mod request_to_command {
         ];
         let request = Request::new_with_payload(payload);
         let command: Command = request.try_into().unwrap();
-        assert_eq!(command, Command::Get("testing".to_string()));
+        assert_eq!(command, Command::Get(b"testing".to_vec()));
     }
 
     #[test]
@@ -182,7 +157,7 @@ mod request_to_command {
         let command: Command = request.try_into().unwrap();
         assert_eq!(
             command,
-            Command::Set("key".to_string(), "value".to_string())
+            Command::Set(b"key".to_vec(), b"value".to_vec(), SetOptions::default())
         );
     }
 
@@ -194,7 +169,26 @@ mod request_to_command {
         ];
         let request = Request::new_with_payload(payload);
         let command: Command = request.try_into().unwrap();
-        assert_eq!(command, Command::Delete("testing".to_string()));
+        assert_eq!(command, Command::Delete(b"testing".to_vec()));
+    }
+
+    #[test]
+    pub fn non_utf8_set_payload_should_deserialized_as_is() {
+        // Payload for "set key <non-UTF-8 bytes>" command
+        let payload = vec![
+            3, 0, 0, 0, 3, 0, 0, 0, 115, 101, 116, 3, 0, 0, 0, 107, 101, 121, 4, 0, 0, 0, 0, 159,
+            146, 255,
+        ];
+        let request = Request::new_with_payload(payload);
+        let command: Command = request.try_into().unwrap();
+        assert_eq!(
+            command,
+            Command::Set(
+                b"key".to_vec(),
+                vec![0, 159, 146, 255],
+                SetOptions::default()
+            )
+        );
     }
 
     #[test]
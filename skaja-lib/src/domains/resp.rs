@@ -0,0 +1,502 @@
+use super::decoder::{FrameError, FrameLimits, ReadBuffer};
+use std::io::{self, Read};
+
+/// The longest inline command, and the longest line of a multibulk header, that is
+/// buffered while waiting for its line ending.
+const MAX_INLINE_SIZE: usize = 64 * 1024;
+
+/// The version of RESP spoken on a connection, RESP2 unless the client switched to
+/// RESP3 with `HELLO 3`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum RespVersion {
+    #[default]
+    Resp2,
+    Resp3,
+}
+
+/// A reply in the Redis serialization protocol.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RespValue {
+    Simple(String),
+    Error(String),
+    Integer(i64),
+    Bulk(Vec<u8>),
+    /// The null bulk string in RESP2, and the null type in RESP3.
+    Null,
+    Array(Vec<RespValue>),
+    /// Sent as an array of the keys and values interleaved in RESP2.
+    Map(Vec<(RespValue, RespValue)>),
+}
+
+impl RespValue {
+    /// Append the encoded value to `out`, in the given version of the protocol.
+    pub fn encode(&self, version: RespVersion, out: &mut Vec<u8>) {
+        match self {
+            RespValue::Simple(msg) => encode_line(out, b'+', msg.as_bytes()),
+            RespValue::Error(msg) => encode_line(out, b'-', msg.as_bytes()),
+            RespValue::Integer(int) => encode_line(out, b':', int.to_string().as_bytes()),
+            RespValue::Bulk(bytes) => {
+                encode_line(out, b'$', bytes.len().to_string().as_bytes());
+                out.extend_from_slice(bytes);
+                out.extend_from_slice(b"\r\n");
+            }
+            RespValue::Null => match version {
+                RespVersion::Resp2 => out.extend_from_slice(b"$-1\r\n"),
+                RespVersion::Resp3 => out.extend_from_slice(b"_\r\n"),
+            },
+            RespValue::Array(values) => {
+                encode_line(out, b'*', values.len().to_string().as_bytes());
+                for value in values {
+                    value.encode(version, out);
+                }
+            }
+            RespValue::Map(entries) => {
+                match version {
+                    RespVersion::Resp2 => {
+                        let len = entries.len() * 2;
+                        encode_line(out, b'*', len.to_string().as_bytes());
+                    }
+                    RespVersion::Resp3 => {
+                        encode_line(out, b'%', entries.len().to_string().as_bytes())
+                    }
+                }
+
+                for (key, value) in entries {
+                    key.encode(version, out);
+                    value.encode(version, out);
+                }
+            }
+        }
+    }
+}
+
+fn encode_line(out: &mut Vec<u8>, prefix: u8, line: &[u8]) {
+    out.push(prefix);
+    out.extend_from_slice(line);
+    out.extend_from_slice(b"\r\n");
+}
+
+/// The parts of a command and how many bytes of the buffer it took.
+type Frame = (Vec<Vec<u8>>, usize);
+
+/// Where the decoder is at in the current multibulk.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DecodeState {
+    /// Waiting for the next command, either a multibulk or an inline one.
+    Header,
+    /// Waiting for the length line of the next bulk string.
+    /// Holds the number of bulk strings that haven't been read yet, including the next one.
+    BulkHeader { bulks_left: usize },
+    /// Waiting for the bulk string itself and its line ending.
+    Bulk { bulks_left: usize, len: usize },
+}
+
+/// Incrementally decodes the commands sent in the Redis serialization protocol, either as
+/// arrays of bulk strings or as inline commands, into their parts, e.g. `["SET", "k", "v"]`.
+///
+/// Works like [`crate::RequestDecoder`], the [`FrameLimits`] are enforced as soon as the
+/// headers arrive, the second part being the key.
+#[derive(Debug)]
+pub struct RespDecoder {
+    /// The bytes received so far that haven't been yielded as a command yet.
+    buffer: ReadBuffer,
+
+    limits: FrameLimits,
+
+    /// The position in the buffer up to which the current multibulk has been parsed.
+    cursor: usize,
+
+    /// The bulk strings of the current multibulk parsed so far.
+    parts: Vec<Vec<u8>>,
+
+    /// Which part of the current multibulk we're waiting for.
+    state: DecodeState,
+}
+
+impl RespDecoder {
+    pub fn new() -> Self {
+        Self::with_limits(FrameLimits::default())
+    }
+
+    pub fn with_limits(limits: FrameLimits) -> Self {
+        Self {
+            buffer: ReadBuffer::default(),
+            limits,
+            cursor: 0,
+            parts: Vec::new(),
+            state: DecodeState::Header,
+        }
+    }
+
+    /// Append the given bytes to the decoder's buffer.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.buffer.feed(bytes);
+    }
+
+    /// Read everything that is currently available from the source into the buffer.
+    /// Behaves the same as [`crate::RequestDecoder::read_from`].
+    pub fn read_from<R: Read>(&mut self, source: &mut R) -> Result<usize, io::Error> {
+        self.buffer.read_from(source, self.limits.max_frame_size)
+    }
+
+    /// Whether [`RespDecoder::read_from`] stopped reading because the buffer is full.
+    pub fn is_full(&self) -> bool {
+        self.buffer.len() >= self.limits.max_frame_size
+    }
+
+    /// Yield the parts of the next complete command in the buffer.
+    /// Returns None if the buffer doesn't contain a complete command yet, and an error if
+    /// the command is malformed or exceeds the limits.
+    pub fn decode(&mut self) -> Result<Option<Vec<Vec<u8>>>, FrameError> {
+        loop {
+            let decoded = match (self.state, self.buffer.first()) {
+                (DecodeState::Header, None) => return Ok(None),
+                (DecodeState::Header, Some(b'*')) => self.decode_multibulk()?,
+                (DecodeState::Header, Some(_)) => self.decode_inline()?,
+                // A multibulk that is partly parsed carries on where it stopped.
+                _ => self.decode_multibulk()?,
+            };
+
+            let Some((parts, frame_len)) = decoded else {
+                return Ok(None);
+            };
+            self.buffer.consume(frame_len);
+            self.cursor = 0;
+            self.state = DecodeState::Header;
+
+            // Empty commands are skipped, the same as Redis does.
+            if !parts.is_empty() {
+                return Ok(Some(parts));
+            }
+        }
+    }
+
+    /// The longest line that is buffered while waiting for its line ending.
+    fn max_line_size(&self) -> usize {
+        MAX_INLINE_SIZE.min(self.limits.max_frame_size)
+    }
+
+    /// Find the line starting at `start`, returns it without the line ending
+    /// and the position right after the line ending.
+    fn read_line(&self, start: usize) -> Result<Option<(&[u8], usize)>, FrameError> {
+        let rest = &self.buffer[start..];
+        match rest.windows(2).position(|window| window == b"\r\n") {
+            Some(end) => Ok(Some((&rest[..end], start + end + 2))),
+            None if rest.len() >= self.max_line_size() => {
+                Err(FrameError::Malformed("too big header line".to_string()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn decode_inline(&self) -> Result<Option<Frame>, FrameError> {
+        let Some(end) = self.buffer.iter().position(|byte| *byte == b'\n') else {
+            if self.buffer.len() >= self.max_line_size() {
+                return Err(FrameError::Malformed("too big inline request".to_string()));
+            }
+
+            return Ok(None);
+        };
+
+        let line = &self.buffer[..end];
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let parts: Vec<Vec<u8>> = line
+            .split(|byte| byte.is_ascii_whitespace())
+            .filter(|part| !part.is_empty())
+            .map(<[u8]>::to_vec)
+            .collect();
+
+        if parts.len() > self.limits.max_args {
+            return Err(FrameError::TooManyArgs {
+                args: parts.len().try_into().unwrap_or(u32::MAX),
+                max: self.limits.max_args,
+            });
+        }
+
+        Ok(Some((parts, end + 1)))
+    }
+
+    fn decode_multibulk(&mut self) -> Result<Option<Frame>, FrameError> {
+        loop {
+            match self.state {
+                DecodeState::Header => {
+                    let Some((line, next)) = self.read_line(0)? else {
+                        return Ok(None);
+                    };
+
+                    let count: i64 = parse_len(&line[1..], "invalid multibulk length")?;
+                    if count <= 0 {
+                        return Ok(Some((Vec::new(), next)));
+                    }
+
+                    if count as u64 > self.limits.max_args as u64 {
+                        return Err(FrameError::TooManyArgs {
+                            args: count.try_into().unwrap_or(u32::MAX),
+                            max: self.limits.max_args,
+                        });
+                    }
+
+                    self.cursor = next;
+                    self.state = DecodeState::BulkHeader {
+                        bulks_left: count as usize,
+                    };
+                }
+                DecodeState::BulkHeader { bulks_left } => {
+                    let Some((line, next)) = self.read_line(self.cursor)? else {
+                        return Ok(None);
+                    };
+
+                    if line.first() != Some(&b'$') {
+                        let found = line.first().map(|byte| *byte as char).unwrap_or(' ');
+                        return Err(FrameError::Malformed(format!(
+                            "expected '$', got '{}'",
+                            found
+                        )));
+                    }
+
+                    let len: usize = parse_len(&line[1..], "invalid bulk length")?;
+                    self.check_len(self.parts.len(), len, next)?;
+
+                    self.cursor = next;
+                    self.state = DecodeState::Bulk { bulks_left, len };
+                }
+                DecodeState::Bulk { bulks_left, len } => {
+                    let end = self.cursor + len;
+                    if self.buffer.len() < end + 2 {
+                        return Ok(None);
+                    }
+
+                    if &self.buffer[end..end + 2] != b"\r\n" {
+                        return Err(FrameError::Malformed(
+                            "bulk string isn't terminated by CRLF".to_string(),
+                        ));
+                    }
+
+                    self.parts.push(self.buffer[self.cursor..end].to_vec());
+                    self.cursor = end + 2;
+                    if bulks_left == 1 {
+                        return Ok(Some((std::mem::take(&mut self.parts), self.cursor)));
+                    }
+
+                    self.state = DecodeState::BulkHeader {
+                        bulks_left: bulks_left - 1,
+                    };
+                }
+            }
+        }
+    }
+
+    /// Make sure the bulk string at the index, and the frame with it, are within the limits.
+    fn check_len(&self, index: usize, len: usize, start: usize) -> Result<(), FrameError> {
+        if index < 2 && len > self.limits.max_key_size {
+            return Err(FrameError::KeyTooLarge {
+                size: len,
+                max: self.limits.max_key_size,
+            });
+        }
+
+        if index >= 2 && len > self.limits.max_value_size {
+            return Err(FrameError::ValueTooLarge {
+                size: len,
+                max: self.limits.max_value_size,
+            });
+        }
+
+        if start.saturating_add(len).saturating_add(2) > self.limits.max_frame_size {
+            return Err(FrameError::FrameTooLarge {
+                max: self.limits.max_frame_size,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for RespDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn parse_len<T: std::str::FromStr>(digits: &[u8], err: &str) -> Result<T, FrameError> {
+    std::str::from_utf8(digits)
+        .ok()
+        .and_then(|digits| digits.parse().ok())
+        .ok_or_else(|| FrameError::Malformed(err.to_string()))
+}
+
+#[cfg(test)]
+mod resp_decoder {
+    use super::RespDecoder;
+    use crate::{FrameError, FrameLimits};
+
+    fn parts(parts: &[&str]) -> Vec<Vec<u8>> {
+        parts.iter().map(|part| part.as_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    pub fn multibulk_command_should_be_decoded_to_its_parts() {
+        let mut decoder = RespDecoder::new();
+        decoder.feed(b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$5\r\nva\r\nl\r\n");
+
+        assert_eq!(
+            decoder.decode().unwrap(),
+            Some(parts(&["SET", "key", "va\r\nl"]))
+        );
+        assert_eq!(decoder.decode().unwrap(), None);
+    }
+
+    #[test]
+    pub fn command_fed_byte_by_byte_should_only_be_decoded_once_complete() {
+        let frame = b"*2\r\n$3\r\nGET\r\n$3\r\nkey\r\n";
+        let mut decoder = RespDecoder::new();
+
+        for byte in &frame[..frame.len() - 1] {
+            decoder.feed(&[*byte]);
+            assert_eq!(decoder.decode().unwrap(), None);
+        }
+
+        decoder.feed(&frame[frame.len() - 1..]);
+        assert_eq!(decoder.decode().unwrap(), Some(parts(&["GET", "key"])));
+    }
+
+    #[test]
+    pub fn multibulk_fed_a_part_at_a_time_should_be_decoded_once_complete() {
+        let count = 100_000;
+        let mut decoder = RespDecoder::new();
+        decoder.feed(format!("*{}\r\n", count).as_bytes());
+
+        for _ in 0..count - 1 {
+            decoder.feed(b"$5\r\nvalue\r\n");
+            assert_eq!(decoder.decode().unwrap(), None);
+        }
+
+        decoder.feed(b"$5\r\nvalue\r\n*1\r\n$4\r\nPING\r\n");
+        assert_eq!(
+            decoder.decode().unwrap(),
+            Some(vec![b"value".to_vec(); count])
+        );
+        assert_eq!(decoder.decode().unwrap(), Some(parts(&["PING"])));
+    }
+
+    #[test]
+    pub fn inline_commands_should_be_decoded_to_their_parts() {
+        let mut decoder = RespDecoder::new();
+        decoder.feed(b"PING\r\n\r\nset  key value\n");
+
+        assert_eq!(decoder.decode().unwrap(), Some(parts(&["PING"])));
+        assert_eq!(
+            decoder.decode().unwrap(),
+            Some(parts(&["set", "key", "value"]))
+        );
+        assert_eq!(decoder.decode().unwrap(), None);
+    }
+
+    #[test]
+    pub fn large_pipelined_batch_should_be_decoded() {
+        let frame = b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$5\r\nvalue\r\n";
+        let commands = 200_000;
+        let mut decoder = RespDecoder::new();
+        decoder.feed(&frame.repeat(commands));
+        decoder.feed(b"PING");
+
+        let mut decoded = 0;
+        while let Some(command) = decoder.decode().unwrap() {
+            assert_eq!(command, parts(&["SET", "key", "value"]));
+            decoded += 1;
+        }
+        assert_eq!(decoded, commands);
+
+        decoder.feed(b"\r\n");
+        assert_eq!(decoder.decode().unwrap(), Some(parts(&["PING"])));
+    }
+
+    #[test]
+    pub fn hostile_bulk_length_should_be_rejected_before_the_bulk_arrives() {
+        let mut decoder = RespDecoder::new();
+        decoder.feed(b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$4294967295\r\n");
+
+        assert!(matches!(
+            decoder.decode(),
+            Err(FrameError::ValueTooLarge {
+                size: 4294967295,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    pub fn hostile_multibulk_length_should_be_rejected() {
+        let mut decoder = RespDecoder::new();
+        decoder.feed(b"*99999999999\r\n");
+
+        assert!(matches!(
+            decoder.decode(),
+            Err(FrameError::TooManyArgs { .. })
+        ));
+    }
+
+    #[test]
+    pub fn malformed_frames_should_result_in_err() {
+        let mut decoder = RespDecoder::new();
+        decoder.feed(b"*1\r\n+GET\r\n");
+        assert!(matches!(decoder.decode(), Err(FrameError::Malformed(_))));
+
+        let mut decoder = RespDecoder::new();
+        decoder.feed(b"*1\r\n$3\r\nGETXX");
+        assert!(matches!(decoder.decode(), Err(FrameError::Malformed(_))));
+
+        let limits = FrameLimits {
+            max_frame_size: 16,
+            ..FrameLimits::default()
+        };
+        let mut decoder = RespDecoder::with_limits(limits);
+        decoder.feed(&[b'a'; 16]);
+        assert!(matches!(decoder.decode(), Err(FrameError::Malformed(_))));
+    }
+}
+
+#[cfg(test)]
+mod resp_value {
+    use super::{RespValue, RespVersion};
+
+    fn encode(value: RespValue, version: RespVersion) -> Vec<u8> {
+        let mut out = Vec::new();
+        value.encode(version, &mut out);
+        out
+    }
+
+    #[test]
+    pub fn values_should_be_encoded_in_resp2() {
+        let value = RespValue::Array(vec![
+            RespValue::Simple("OK".to_string()),
+            RespValue::Error("ERR nope".to_string()),
+            RespValue::Integer(-2),
+            RespValue::Bulk(b"hi".to_vec()),
+            RespValue::Null,
+        ]);
+
+        assert_eq!(
+            encode(value, RespVersion::Resp2),
+            b"*5\r\n+OK\r\n-ERR nope\r\n:-2\r\n$2\r\nhi\r\n$-1\r\n"
+        );
+    }
+
+    #[test]
+    pub fn null_and_maps_should_be_encoded_with_their_own_types_in_resp3() {
+        let map = RespValue::Map(vec![(
+            RespValue::Bulk(b"proto".to_vec()),
+            RespValue::Integer(3),
+        )]);
+
+        assert_eq!(
+            encode(map.clone(), RespVersion::Resp3),
+            b"%1\r\n$5\r\nproto\r\n:3\r\n"
+        );
+        assert_eq!(
+            encode(map, RespVersion::Resp2),
+            b"*2\r\n$5\r\nproto\r\n:3\r\n"
+        );
+        assert_eq!(encode(RespValue::Null, RespVersion::Resp3), b"_\r\n");
+    }
+}
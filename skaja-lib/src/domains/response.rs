@@ -1,14 +1,52 @@
+/// The status of a response, sent as a number in the header of the response.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum StatusCodes {
     Ok,
+    /// The request couldn't be parsed into a command, e.g. an argument is malformed.
+    ErrInvalidRequest,
+    /// The name of the command isn't recognized.
+    ErrUnknownCommand,
     ErrNotFound,
+    /// The command got the wrong number of arguments.
+    ErrWrongArity,
+    /// The command doesn't support the type of the value stored at the key.
+    ErrWrongType,
+    /// The request is bigger than what the server accepts.
+    ErrPayloadTooLarge,
+    /// The client has to authenticate before sending commands.
+    ErrAuthRequired,
+    /// The server doesn't accept writes.
+    ErrReadOnly,
+    /// The server failed running the command, e.g. it couldn't write to the disk.
+    ErrInternal,
+    /// The key was written since its version was read, see [`crate::Command::Cas`].
+    ErrConflict,
+    /// The value stored at the key isn't a number, or the result doesn't fit in one,
+    /// see [`crate::Command::IncrBy`].
+    ErrNotNumeric,
+    /// The consumer group doesn't exist on the stream, see [`crate::Command::XGroupCreate`].
+    ErrNoGroup,
+    /// A status code this version doesn't know about, e.g. sent by a newer server.
+    Unknown(u32),
 }
 
 impl std::fmt::Display for StatusCodes {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let msg = match self {
             StatusCodes::Ok => "OK",
+            StatusCodes::ErrInvalidRequest => "Invalid request",
+            StatusCodes::ErrUnknownCommand => "Unknown command",
             StatusCodes::ErrNotFound => "Key not found",
+            StatusCodes::ErrWrongArity => "Wrong number of arguments",
+            StatusCodes::ErrWrongType => "Wrong type of value",
+            StatusCodes::ErrPayloadTooLarge => "Payload too large",
+            StatusCodes::ErrAuthRequired => "Authentication required",
+            StatusCodes::ErrReadOnly => "Server is read-only",
+            StatusCodes::ErrInternal => "Internal server error",
+            StatusCodes::ErrConflict => "Key was modified since it was read",
+            StatusCodes::ErrNotNumeric => "Value is not a number or out of range",
+            StatusCodes::ErrNoGroup => "No such consumer group",
+            StatusCodes::Unknown(code) => return write!(f, "Unknown status code {}", code),
         };
 
         write!(f, "{}", msg)
@@ -19,21 +57,54 @@ impl From<StatusCodes> for u32 {
     fn from(value: StatusCodes) -> Self {
         match value {
             StatusCodes::Ok => 0,
+            StatusCodes::ErrInvalidRequest => 1,
+            StatusCodes::ErrUnknownCommand => 2,
             StatusCodes::ErrNotFound => 3,
+            StatusCodes::ErrWrongArity => 4,
+            StatusCodes::ErrWrongType => 5,
+            StatusCodes::ErrPayloadTooLarge => 6,
+            StatusCodes::ErrAuthRequired => 7,
+            StatusCodes::ErrReadOnly => 8,
+            StatusCodes::ErrInternal => 9,
+            StatusCodes::ErrConflict => 10,
+            StatusCodes::ErrNotNumeric => 11,
+            StatusCodes::ErrNoGroup => 12,
+            StatusCodes::Unknown(code) => code,
         }
     }
 }
 
-impl From<u32> for StatusCodes {
-    fn from(value: u32) -> Self {
-        match value {
+impl TryFrom<u32> for StatusCodes {
+    type Error = String;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        let status_code = match value {
             0 => StatusCodes::Ok,
+            1 => StatusCodes::ErrInvalidRequest,
+            2 => StatusCodes::ErrUnknownCommand,
             3 => StatusCodes::ErrNotFound,
-            _ => panic!("Invalid status code."),
-        }
+            4 => StatusCodes::ErrWrongArity,
+            5 => StatusCodes::ErrWrongType,
+            6 => StatusCodes::ErrPayloadTooLarge,
+            7 => StatusCodes::ErrAuthRequired,
+            8 => StatusCodes::ErrReadOnly,
+            9 => StatusCodes::ErrInternal,
+            10 => StatusCodes::ErrConflict,
+            11 => StatusCodes::ErrNotNumeric,
+            12 => StatusCodes::ErrNoGroup,
+            _ => return Err(format!("Unknown status code: {}", value)),
+        };
+
+        Ok(status_code)
     }
 }
 
+/// Decode the status code of a response, falling back to [`StatusCodes::Unknown`]
+/// so that a client doesn't choke on codes it doesn't know about.
+fn decode_status_code(value: u32) -> StatusCodes {
+    StatusCodes::try_from(value).unwrap_or(StatusCodes::Unknown(value))
+}
+
 /// A response is a payload that is sent from the server to the client.
 /// The following is the structure of the payload:
 ///
@@ -47,37 +118,45 @@ impl From<u32> for StatusCodes {
 ///
 /// The first chunk is called the header.
 /// The second chunk is called the message header.
+/// Both are 32bit unsigned integers in little-endian byte order.
 #[derive(Debug)]
 pub struct RawResponse(pub Vec<u8>);
 
 impl RawResponse {
-    pub fn new(status_code: StatusCodes, msg: Option<String>) -> Self {
+    pub fn new(status_code: StatusCodes, msg: Option<Vec<u8>>) -> Self {
         let mut payload: Vec<u8> = Vec::new();
 
         // The length of the status code string, truncated to 32bit.
         let status_code_int: u32 = status_code.into();
         payload.append(&mut status_code_int.to_le_bytes().to_vec());
 
-        if let Some(msg) = msg {
+        if let Some(mut msg) = msg {
             // Status code + the number of arguments for the command
             let msg_len = msg.len() as u32;
 
             payload.append(&mut msg_len.to_le_bytes().to_vec());
-            payload.append(&mut msg.into_bytes());
+            payload.append(&mut msg);
 
             return RawResponse(payload);
         }
 
         let msg_len = 0_u32;
-        let msg = "".to_string();
         payload.append(&mut msg_len.to_le_bytes().to_vec());
-        payload.append(&mut msg.into_bytes());
         RawResponse(payload)
     }
 
     pub fn payload(&self) -> &[u8] {
         &self.0
     }
+
+    pub fn status_code(&self) -> StatusCodes {
+        decode_status_code(u32::from_le_bytes(self.0[0..4].try_into().unwrap()))
+    }
+
+    /// The raw bytes of the response message, if any.
+    pub fn message(&self) -> Option<&[u8]> {
+        self.0.get(8..).filter(|msg| !msg.is_empty())
+    }
 }
 
 impl From<RawResponse> for Vec<u8> {
@@ -89,11 +168,11 @@ impl From<RawResponse> for Vec<u8> {
 #[derive(Debug)]
 pub struct Response {
     status_code: StatusCodes,
-    message: Option<String>,
+    message: Option<Vec<u8>>,
 }
 
 impl Response {
-    pub fn new(status_code: StatusCodes, msg: Option<String>) -> Self {
+    pub fn new(status_code: StatusCodes, msg: Option<Vec<u8>>) -> Self {
         Response {
             status_code,
             message: msg,
@@ -104,66 +183,164 @@ impl Response {
         self.status_code
     }
 
-    pub fn message(&self) -> Option<&str> {
+    /// The raw bytes of the response message, if any.
+    pub fn message(&self) -> Option<&[u8]> {
         if let Some(ref msg) = self.message {
-            return Some(msg.as_str());
+            return Some(msg.as_slice());
         }
 
         None
     }
+
+    /// The items of a message encoded with [`encode_array`], e.g. the reply to
+    /// [`crate::Command::Keys`].
+    pub fn array(&self) -> Result<Vec<Vec<u8>>, String> {
+        decode_array(self.message().unwrap_or_default())
+    }
+
+    /// The items of a message encoded with [`encode_nullable_array`], e.g. the reply to
+    /// [`crate::Command::MGet`].
+    pub fn nullable_array(&self) -> Result<Vec<Option<Vec<u8>>>, String> {
+        decode_nullable_array(self.message().unwrap_or_default())
+    }
+}
+
+/// The length that stands for a nil item in an array.
+const NIL_LEN: u32 = u32::MAX;
+
+/// Encode the items as the message of a response, framed the same way as the messages of
+/// a [`crate::Request`]: the number of items, and then each item preceded by its length.
+/// The integers are 32bit unsigned in little-endian byte order.
+pub fn encode_array(items: &[Vec<u8>]) -> Vec<u8> {
+    let len = items.iter().map(|item| 4 + item.len()).sum::<usize>();
+    let mut msg = Vec::with_capacity(4 + len);
+    msg.extend_from_slice(&(items.len() as u32).to_le_bytes());
+    for item in items {
+        msg.extend_from_slice(&(item.len() as u32).to_le_bytes());
+        msg.extend_from_slice(item);
+    }
+    msg
+}
+
+/// Same as [`encode_array`], but the items can be nil, their length being `u32::MAX`.
+pub fn encode_nullable_array(items: &[Option<Vec<u8>>]) -> Vec<u8> {
+    let len = items.iter().flatten().map(Vec::len).sum::<usize>();
+    let mut msg = Vec::with_capacity(4 + 4 * items.len() + len);
+    msg.extend_from_slice(&(items.len() as u32).to_le_bytes());
+    for item in items {
+        match item {
+            Some(item) => {
+                msg.extend_from_slice(&(item.len() as u32).to_le_bytes());
+                msg.extend_from_slice(item);
+            }
+            None => msg.extend_from_slice(&NIL_LEN.to_le_bytes()),
+        }
+    }
+    msg
+}
+
+/// Decode a message encoded with [`encode_array`], an empty message being an empty array.
+pub fn decode_array(msg: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+    decode_nullable_array(msg)?
+        .into_iter()
+        .map(|item| item.ok_or_else(|| "Array has a nil item".to_string()))
+        .collect()
+}
+
+/// Decode a message encoded with [`encode_nullable_array`], an empty message being an
+/// empty array.
+pub fn decode_nullable_array(msg: &[u8]) -> Result<Vec<Option<Vec<u8>>>, String> {
+    if msg.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let truncated = || "Array is truncated".to_string();
+    let mut rest = msg;
+    let count = take_len(&mut rest).ok_or_else(truncated)?;
+    // Every item takes at least 4 bytes, so a hostile count can't allocate more than that.
+    let mut items = Vec::with_capacity(count.min(rest.len() / 4));
+    for _ in 0..count {
+        let len = take_len(&mut rest).ok_or_else(truncated)?;
+        if len == NIL_LEN as usize {
+            items.push(None);
+            continue;
+        }
+
+        if rest.len() < len {
+            return Err(truncated());
+        }
+
+        let (item, tail) = rest.split_at(len);
+        items.push(Some(item.to_vec()));
+        rest = tail;
+    }
+
+    Ok(items)
+}
+
+/// Take the length at the start of the bytes, moving past it.
+fn take_len(bytes: &mut &[u8]) -> Option<usize> {
+    let (len, rest) = bytes.split_first_chunk::<4>()?;
+    *bytes = rest;
+    Some(u32::from_le_bytes(*len) as usize)
 }
 
 impl From<RawResponse> for Response {
     fn from(value: RawResponse) -> Self {
         let payload = value.payload();
 
-        let status_code = u32::from_ne_bytes(payload[0..4].try_into().unwrap());
+        let status_code = u32::from_le_bytes(payload[0..4].try_into().unwrap());
         let mut msg_len = 0;
         if payload.len() > 8 {
-            msg_len = u32::from_ne_bytes(payload[4..8].try_into().unwrap());
+            msg_len = u32::from_le_bytes(payload[4..8].try_into().unwrap());
         }
 
         if msg_len == 0 {
             return Response {
-                status_code: StatusCodes::from(status_code),
+                status_code: decode_status_code(status_code),
                 message: None,
             };
         }
 
-        let msg = String::from_utf8_lossy(&payload[8..(8 + msg_len as usize)]);
+        let msg = payload[8..(8 + msg_len as usize)].to_vec();
         Response {
-            status_code: StatusCodes::from(status_code),
-            message: Some(msg.into()),
+            status_code: decode_status_code(status_code),
+            message: Some(msg),
         }
     }
 }
 
+/// Meant for displaying the response to humans, the message is converted to UTF-8
+/// lossily, so binary messages might not be displayed as is.
 impl std::fmt::Display for Response {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let msg = match self.status_code {
             StatusCodes::Ok => {
                 if let Some(ref msg) = self.message {
-                    msg
+                    String::from_utf8_lossy(msg)
                 } else {
-                    "<Ok>"
+                    "<Ok>".into()
                 }
             }
-            StatusCodes::ErrNotFound => "<nil>",
+            StatusCodes::ErrNotFound => "<nil>".into(),
+            status_code => match self.message {
+                Some(ref msg) => format!("(error) {}", String::from_utf8_lossy(msg)).into(),
+                None => format!("(error) {}", status_code).into(),
+            },
         };
 
-        let msg = msg.to_string();
         write!(f, "{}", msg)
     }
 }
 
 #[cfg(test)]
 mod raw_response {
-    use super::{RawResponse, StatusCodes};
+    use super::{decode_array, encode_array, encode_nullable_array, RawResponse, StatusCodes};
     use crate::Response;
 
     #[test]
     pub fn new_ok_should_result_in_correct_payload() {
-        let raw_response = RawResponse::new(StatusCodes::Ok, Some("OK".to_string()));
+        let raw_response = RawResponse::new(StatusCodes::Ok, Some(b"OK".to_vec()));
         let payload = raw_response.payload();
 
         let header = &payload[0..4];
@@ -176,10 +353,19 @@ mod raw_response {
         assert_eq!(msg, [79, 75]);
     }
 
+    #[test]
+    pub fn message_should_be_read_without_decoding_the_response() {
+        let raw_response = RawResponse::new(StatusCodes::Ok, Some(b"OK".to_vec()));
+        assert_eq!(raw_response.message(), Some(b"OK".as_slice()));
+
+        let raw_response = RawResponse::new(StatusCodes::ErrNotFound, None);
+        assert_eq!(raw_response.message(), None);
+    }
+
     #[test]
     pub fn new_not_found_err_should_result_in_correct_payload() {
         let raw_response =
-            RawResponse::new(StatusCodes::ErrNotFound, Some(r#"Server error"#.into()));
+            RawResponse::new(StatusCodes::ErrNotFound, Some(b"Server error".to_vec()));
         let payload = raw_response.payload();
 
         let header = &payload[0..4];
@@ -197,20 +383,89 @@ mod raw_response {
 
     #[test]
     pub fn ok_should_be_parsed_correctly_to_response() {
-        let raw_response = RawResponse::new(StatusCodes::Ok, Some("OK".into()));
+        let raw_response = RawResponse::new(StatusCodes::Ok, Some(b"OK".to_vec()));
         let response: Response = raw_response.into();
 
         assert_eq!(response.status_code(), StatusCodes::Ok);
-        assert_eq!(response.message(), Some("OK"));
+        assert_eq!(response.message(), Some(b"OK".as_slice()));
     }
 
     #[test]
     pub fn client_err_should_be_parsed_correctly_to_response() {
         let raw_response =
-            RawResponse::new(StatusCodes::ErrNotFound, Some("There's an error".into()));
+            RawResponse::new(StatusCodes::ErrNotFound, Some(b"There's an error".to_vec()));
         let response: Response = raw_response.into();
 
         assert_eq!(response.status_code(), StatusCodes::ErrNotFound);
-        assert_eq!(response.message(), Some("There's an error"));
+        assert_eq!(response.message(), Some(b"There's an error".as_slice()));
+    }
+
+    #[test]
+    pub fn error_with_msg_should_be_parsed_correctly_to_response() {
+        let raw_response = RawResponse::new(
+            StatusCodes::ErrWrongArity,
+            Some(b"\"get\" command needs 1 argument".to_vec()),
+        );
+        assert_eq!(&raw_response.payload()[0..4], [4, 0, 0, 0]);
+
+        let response: Response = raw_response.into();
+        assert_eq!(response.status_code(), StatusCodes::ErrWrongArity);
+        assert_eq!(
+            response.to_string(),
+            "(error) \"get\" command needs 1 argument"
+        );
+    }
+
+    #[test]
+    pub fn unknown_status_code_should_be_parsed_without_panicking() {
+        let mut payload = 42_u32.to_le_bytes().to_vec();
+        payload.extend_from_slice(&[0, 0, 0, 0]);
+        let response: Response = RawResponse(payload).into();
+
+        assert_eq!(response.status_code(), StatusCodes::Unknown(42));
+        assert!(StatusCodes::try_from(42).is_err());
+        assert_eq!(u32::from(StatusCodes::Unknown(42)), 42);
+    }
+
+    #[test]
+    pub fn status_codes_should_round_trip_through_u32() {
+        for code in 0..=12 {
+            let status_code = StatusCodes::try_from(code).unwrap();
+            assert_eq!(u32::from(status_code), code);
+        }
+    }
+
+    #[test]
+    pub fn array_msg_should_be_parsed_to_its_items() {
+        let items = vec![b"a".to_vec(), Vec::new(), vec![0, 255]];
+        let raw_response = RawResponse::new(StatusCodes::Ok, Some(encode_array(&items)));
+        let response: Response = raw_response.into();
+
+        assert_eq!(response.array().unwrap(), items);
+        assert_eq!(
+            decode_array(&encode_array(&[])).unwrap(),
+            Vec::<Vec<u8>>::new()
+        );
+        assert!(decode_array(&[1, 0, 0, 0, 5, 0, 0, 0, b'a']).is_err());
+    }
+
+    #[test]
+    pub fn nullable_array_msg_should_be_parsed_with_its_nil_items() {
+        let items = vec![Some(b"a".to_vec()), None, Some(Vec::new())];
+        let raw_response = RawResponse::new(StatusCodes::Ok, Some(encode_nullable_array(&items)));
+        let response: Response = raw_response.into();
+
+        assert_eq!(response.nullable_array().unwrap(), items);
+        assert!(response.array().is_err());
+    }
+
+    #[test]
+    pub fn binary_msg_should_be_parsed_as_is_to_response() {
+        let msg = vec![0, 159, 146, 150, 255];
+        let raw_response = RawResponse::new(StatusCodes::Ok, Some(msg.clone()));
+        let response: Response = raw_response.into();
+
+        assert_eq!(response.status_code(), StatusCodes::Ok);
+        assert_eq!(response.message(), Some(msg.as_slice()));
     }
 }
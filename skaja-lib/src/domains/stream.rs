@@ -0,0 +1,302 @@
+use crate::{decode_array, decode_nullable_array, encode_array, encode_nullable_array};
+use std::{fmt, str::FromStr};
+
+/// The ID of an entry of a stream, `ms-seq` on the wire: the unix timestamp in milliseconds
+/// the entry was added at, and a sequence number telling apart the entries added in the
+/// same millisecond. The IDs of the entries of a stream only ever grow.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+impl StreamId {
+    /// The lowest ID, `-` at the start of a range.
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+
+    /// The highest ID, `+` at the end of a range.
+    pub const MAX: StreamId = StreamId {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+
+    pub fn new(ms: u64, seq: u64) -> Self {
+        StreamId { ms, seq }
+    }
+
+    /// The ID that comes right after this one, None if it's the highest.
+    pub fn next(&self) -> Option<StreamId> {
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(StreamId::new(self.ms, seq)),
+            None => Some(StreamId::new(self.ms.checked_add(1)?, 0)),
+        }
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+impl FromStr for StreamId {
+    type Err = String;
+
+    /// Parse `ms-seq`, or `ms` alone with a sequence number of 0.
+    fn from_str(id: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid stream ID \"{}\"", id);
+        let (ms, seq) = id.split_once('-').unwrap_or((id, "0"));
+        Ok(StreamId {
+            ms: ms.parse().map_err(|_| invalid())?,
+            seq: seq.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// An entry of a stream, with its fields and their values in the order they were given.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamEntry {
+    pub id: StreamId,
+    pub fields: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl StreamEntry {
+    /// Encode the entry with [`encode_array`], as its ID followed by the fields and their
+    /// values.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut items = vec![self.id.to_string().into_bytes()];
+        for (field, value) in &self.fields {
+            items.push(field.clone());
+            items.push(value.clone());
+        }
+        encode_array(&items)
+    }
+
+    pub fn decode(msg: &[u8]) -> Result<Self, String> {
+        let mut items = decode_array(msg)?.into_iter();
+        let id = items.next().ok_or("Stream entry has no ID")?;
+        let id = String::from_utf8_lossy(&id).parse()?;
+        if items.len() % 2 != 0 {
+            return Err("Stream entry is not made of fields and values".to_string());
+        }
+
+        let mut fields = Vec::with_capacity(items.len() / 2);
+        while let (Some(field), Some(value)) = (items.next(), items.next()) {
+            fields.push((field, value));
+        }
+
+        Ok(StreamEntry { id, fields })
+    }
+
+    /// Encode the entries with [`encode_array`], each of them encoded on its own.
+    pub fn encode_many(entries: &[StreamEntry]) -> Vec<u8> {
+        let items: Vec<Vec<u8>> = entries.iter().map(StreamEntry::encode).collect();
+        encode_array(&items)
+    }
+
+    pub fn decode_many(msg: &[u8]) -> Result<Vec<Self>, String> {
+        decode_array(msg)?
+            .iter()
+            .map(|entry| StreamEntry::decode(entry))
+            .collect()
+    }
+}
+
+/// The entries read from each of the streams, along with the key of the stream.
+pub type ReadStreams = Vec<(Vec<u8>, Vec<StreamEntry>)>;
+
+/// Encode the entries read from each of the streams with [`encode_array`], as the key of
+/// each stream followed by its entries encoded with [`StreamEntry::encode_many`].
+pub fn encode_streams(streams: &[(Vec<u8>, Vec<StreamEntry>)]) -> Vec<u8> {
+    let items: Vec<Vec<u8>> = streams
+        .iter()
+        .flat_map(|(key, entries)| [key.clone(), StreamEntry::encode_many(entries)])
+        .collect();
+    encode_array(&items)
+}
+
+/// Decode a message encoded with [`encode_streams`].
+pub fn decode_streams(msg: &[u8]) -> Result<ReadStreams, String> {
+    let mut items = decode_array(msg)?.into_iter();
+    let mut streams = Vec::with_capacity(items.len() / 2);
+    while let Some(key) = items.next() {
+        let entries = items.next().ok_or("Stream has no entries")?;
+        streams.push((key, StreamEntry::decode_many(&entries)?));
+    }
+
+    Ok(streams)
+}
+
+/// An entry delivered to a consumer of a group that it hasn't acknowledged yet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingEntry {
+    pub id: StreamId,
+    pub consumer: Vec<u8>,
+
+    /// The milliseconds since the entry was last delivered.
+    pub idle: u64,
+
+    /// The number of times the entry was delivered.
+    pub deliveries: u64,
+}
+
+impl PendingEntry {
+    /// Encode the entries with [`encode_array`], each of them encoded on its own as its ID,
+    /// its consumer, its idle time and its number of deliveries.
+    pub fn encode_many(entries: &[PendingEntry]) -> Vec<u8> {
+        let items: Vec<Vec<u8>> = entries
+            .iter()
+            .map(|entry| {
+                encode_array(&[
+                    entry.id.to_string().into_bytes(),
+                    entry.consumer.clone(),
+                    entry.idle.to_string().into_bytes(),
+                    entry.deliveries.to_string().into_bytes(),
+                ])
+            })
+            .collect();
+        encode_array(&items)
+    }
+
+    pub fn decode_many(msg: &[u8]) -> Result<Vec<Self>, String> {
+        decode_array(msg)?
+            .iter()
+            .map(|entry| {
+                let [id, consumer, idle, deliveries] =
+                    <[Vec<u8>; 4]>::try_from(decode_array(entry)?)
+                        .map_err(|_| "Pending entry is not made of 4 items".to_string())?;
+                Ok(PendingEntry {
+                    id: parse(&id)?,
+                    consumer,
+                    idle: parse(&idle)?,
+                    deliveries: parse(&deliveries)?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// The entries of a consumer group that haven't been acknowledged yet, as a whole.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PendingSummary {
+    pub count: u64,
+
+    /// The lowest and the highest of the IDs, None if there are no pending entries.
+    pub range: Option<(StreamId, StreamId)>,
+
+    /// The consumers with pending entries, along with how many each of them has.
+    pub consumers: Vec<(Vec<u8>, u64)>,
+}
+
+impl PendingSummary {
+    /// Encode the summary with [`encode_nullable_array`], as the count, the lowest and
+    /// the highest IDs, nil if there are none, followed by each consumer and its count.
+    pub fn encode(&self) -> Vec<u8> {
+        let (min, max) = match self.range {
+            Some((min, max)) => (
+                Some(min.to_string().into_bytes()),
+                Some(max.to_string().into_bytes()),
+            ),
+            None => (None, None),
+        };
+
+        let mut items = vec![Some(self.count.to_string().into_bytes()), min, max];
+        for (consumer, count) in &self.consumers {
+            items.push(Some(consumer.clone()));
+            items.push(Some(count.to_string().into_bytes()));
+        }
+        encode_nullable_array(&items)
+    }
+
+    pub fn decode(msg: &[u8]) -> Result<Self, String> {
+        let mut items = decode_nullable_array(msg)?.into_iter();
+        let (Some(Some(count)), Some(min), Some(max)) = (items.next(), items.next(), items.next())
+        else {
+            return Err("Pending summary is truncated".to_string());
+        };
+
+        let range = match (min, max) {
+            (Some(min), Some(max)) => Some((parse(&min)?, parse(&max)?)),
+            _ => None,
+        };
+
+        let mut consumers = Vec::with_capacity(items.len() / 2);
+        while let (Some(Some(consumer)), Some(Some(count))) = (items.next(), items.next()) {
+            consumers.push((consumer, parse(&count)?));
+        }
+
+        Ok(PendingSummary {
+            count: parse(&count)?,
+            range,
+            consumers,
+        })
+    }
+}
+
+fn parse<T: FromStr>(item: &[u8]) -> Result<T, String> {
+    std::str::from_utf8(item)
+        .ok()
+        .and_then(|item| item.parse().ok())
+        .ok_or_else(|| format!("\"{}\" is malformed", String::from_utf8_lossy(item)))
+}
+
+#[cfg(test)]
+mod stream_replies {
+    use crate::{
+        decode_streams, encode_streams, PendingEntry, PendingSummary, StreamEntry, StreamId,
+    };
+
+    fn entry(ms: u64, fields: &[(&str, &str)]) -> StreamEntry {
+        StreamEntry {
+            id: StreamId::new(ms, 1),
+            fields: fields
+                .iter()
+                .map(|(field, value)| (field.as_bytes().to_vec(), value.as_bytes().to_vec()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    pub fn stream_ids_should_be_parsed_and_ordered() {
+        assert_eq!("5-3".parse(), Ok(StreamId::new(5, 3)));
+        assert_eq!("5".parse(), Ok(StreamId::new(5, 0)));
+        assert!("5-".parse::<StreamId>().is_err());
+        assert!("-3".parse::<StreamId>().is_err());
+        assert!(StreamId::new(5, 3) < StreamId::new(6, 0));
+        assert_eq!(StreamId::new(5, 3).to_string(), "5-3");
+        assert_eq!(StreamId::new(5, u64::MAX).next(), Some(StreamId::new(6, 0)));
+        assert_eq!(StreamId::MAX.next(), None);
+    }
+
+    #[test]
+    pub fn encoded_entries_should_decode_to_the_same_entries() {
+        let entries = vec![entry(1, &[("a", "1"), ("b", "")]), entry(2, &[])];
+        let msg = StreamEntry::encode_many(&entries);
+        assert_eq!(StreamEntry::decode_many(&msg).unwrap(), entries);
+
+        let streams = vec![(b"s1".to_vec(), entries), (b"s2".to_vec(), Vec::new())];
+        assert_eq!(decode_streams(&encode_streams(&streams)).unwrap(), streams);
+    }
+
+    #[test]
+    pub fn encoded_pending_entries_should_decode_to_the_same_entries() {
+        let summary = PendingSummary {
+            count: 3,
+            range: Some((StreamId::new(1, 0), StreamId::new(4, 2))),
+            consumers: vec![(b"a".to_vec(), 2), (b"b".to_vec(), 1)],
+        };
+        assert_eq!(PendingSummary::decode(&summary.encode()).unwrap(), summary);
+
+        let empty = PendingSummary::default();
+        assert_eq!(PendingSummary::decode(&empty.encode()).unwrap(), empty);
+
+        let entries = vec![PendingEntry {
+            id: StreamId::new(1, 0),
+            consumer: b"a".to_vec(),
+            idle: 1500,
+            deliveries: 2,
+        }];
+        let msg = PendingEntry::encode_many(&entries);
+        assert_eq!(PendingEntry::decode_many(&msg).unwrap(), entries);
+    }
+}
@@ -0,0 +1,61 @@
+/// The server's reply to [`crate::Command::Gets`], sent as the message of the response.
+///
+/// The following is the structure of the message, the integers are little-endian:
+///
+/// | flags | version | value |
+/// |-------|---------|-------|
+/// | u32   | u64     |       |
+#[derive(Debug, Clone, PartialEq)]
+pub struct Versioned {
+    pub value: Vec<u8>,
+
+    /// Opaque to the server, set along with the value, see [`crate::SetOptions::flags`].
+    pub flags: u32,
+
+    /// Changes every time the value is written, pass it to [`crate::Command::Cas`] to write
+    /// the key only if nobody else has written it in the meantime.
+    pub version: u64,
+}
+
+impl Versioned {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut msg = Vec::with_capacity(4 + 8 + self.value.len());
+        msg.extend_from_slice(&self.flags.to_le_bytes());
+        msg.extend_from_slice(&self.version.to_le_bytes());
+        msg.extend_from_slice(&self.value);
+        msg
+    }
+
+    pub fn decode(msg: &[u8]) -> Result<Self, String> {
+        if msg.len() < 12 {
+            return Err("Versioned value is truncated".to_string());
+        }
+
+        Ok(Self {
+            flags: u32::from_le_bytes(msg[0..4].try_into().unwrap()),
+            version: u64::from_le_bytes(msg[4..12].try_into().unwrap()),
+            value: msg[12..].to_vec(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod versioned_value {
+    use crate::Versioned;
+
+    #[test]
+    pub fn encoded_value_should_decode_to_the_same_value() {
+        let versioned = Versioned {
+            value: b"value".to_vec(),
+            flags: 42,
+            version: 7,
+        };
+
+        assert_eq!(Versioned::decode(&versioned.encode()).unwrap(), versioned);
+    }
+
+    #[test]
+    pub fn truncated_message_should_fail_to_decode() {
+        assert!(Versioned::decode(&[0, 0, 0, 0, 1]).is_err());
+    }
+}
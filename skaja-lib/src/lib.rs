@@ -43,64 +43,6 @@ where
     }
 }
 
-impl Extract<Request> for TcpStream {
-    type Error = io::Error;
-
-    fn extract(&mut self) -> Result<Request, Self::Error> {
-        // The container for the data we'll receive.
-        let mut received_data = Vec::new();
-
-        // The current chunk we're reading.
-        let mut current_chunk = 0;
-
-        // How many chunks we expect to receive.
-        let mut chunks_len = 0;
-
-        // In bytes.
-        let mut next_chunk_size = 4;
-
-        loop {
-            if current_chunk > chunks_len {
-                break;
-            }
-
-            let chunk_is_header = current_chunk == 0;
-            let chunk_is_msg_header = current_chunk % 2 != 0;
-            if !chunk_is_header && chunk_is_msg_header {
-                // If msg_count is odd, we read 4 bytes which is the length of the next message.
-                next_chunk_size = 4;
-            }
-
-            let mut buf = vec![0; next_chunk_size];
-            match self.read_exact(&mut buf) {
-                Ok(_) => {
-                    if chunk_is_header {
-                        // The first chunk is the header, which is the number of chunks in the payload.
-                        // We set it to its value * 2 because each message in the payload has 2 chunks:
-                        // 1. The length of the message.
-                        // 2. The message itself.
-                        chunks_len = u32::from_ne_bytes(buf.clone().try_into().unwrap()) * 2;
-                    } else if chunk_is_msg_header {
-                        next_chunk_size =
-                            u32::from_ne_bytes(buf.clone().try_into().unwrap()) as usize;
-                    }
-
-                    received_data.append(&mut buf);
-                    current_chunk += 1;
-                }
-
-                // Would block "errors" are the OS's way of saying that the
-                // connection is not actually ready to perform this I/O operation.
-                Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
-                // Other errors we'll consider fatal.
-                Err(err) => return Err(err),
-            }
-        }
-
-        Ok(Request::new_with_payload(received_data))
-    }
-}
-
 impl Extract<RawResponse> for TcpStream {
     type Error = io::Error;
 
@@ -130,12 +72,23 @@ impl Extract<RawResponse> for TcpStream {
                 Ok(_) => {
                     if chunk_is_msg_header {
                         next_chunk_len =
-                            u32::from_ne_bytes(buf.clone().try_into().unwrap()) as usize;
+                            u32::from_le_bytes(buf.clone().try_into().unwrap()) as usize;
 
                         if next_chunk_len == 0 {
                             done_reading = true;
                             break;
                         }
+
+                        // Don't let the length in the header decide how much is allocated.
+                        let max_frame_size = FrameLimits::default().max_frame_size;
+                        if next_chunk_len > max_frame_size {
+                            return Err(io::Error::new(
+                                ErrorKind::InvalidData,
+                                FrameError::FrameTooLarge {
+                                    max: max_frame_size,
+                                },
+                            ));
+                        }
                     }
 
                     received_data.append(&mut buf);
@@ -0,0 +1,144 @@
+use crate::{
+    http::{HttpCodec, HttpReply},
+    memcached::{MemcachedCodec, MemcachedReply},
+    resp::{ReplyKind, RespCodec},
+};
+use mio::net::TcpStream;
+use skaja_lib::{
+    Command, CommandError, FrameError, FrameLimits, RawResponse, RequestDecoder, StatusCodes,
+};
+use std::io;
+
+/// The protocols the server speaks, each one on a listener of its own.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Protocol {
+    /// The binary framing of [`skaja_lib::Request`] and [`skaja_lib::RawResponse`].
+    Native,
+    /// The Redis serialization protocol, so that Redis clients and tools can be used.
+    Resp,
+    /// Memcached's text protocol, so that memcached clients can be used.
+    Memcached,
+    /// HTTP/1.x with JSON bodies, so that the keyspace can be used with e.g. curl.
+    Http,
+}
+
+impl std::fmt::Display for Protocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Protocol::Native => write!(f, "skaja"),
+            Protocol::Resp => write!(f, "RESP"),
+            Protocol::Memcached => write!(f, "memcached"),
+            Protocol::Http => write!(f, "HTTP"),
+        }
+    }
+}
+
+/// What a client asked for.
+pub(crate) enum Incoming {
+    /// A command to run, its response is encoded with [`Codec::encode`].
+    Command(Command, Reply),
+    /// A request the codec answered by itself, e.g. an invalid one.
+    Reply(Vec<u8>),
+    /// Same as [`Incoming::Reply`], but the connection is closed once the reply is written.
+    Close(Vec<u8>),
+}
+
+/// How the response to a command is encoded, decided when the command is decoded since
+/// the response alone doesn't say, e.g. whether its message is a number or a value.
+pub(crate) enum Reply {
+    Native,
+    Resp(ReplyKind),
+    Memcached(MemcachedReply),
+    Http(HttpReply),
+}
+
+/// Decodes the requests of a connection and encodes the responses, in its protocol.
+pub(crate) enum Codec {
+    Native(RequestDecoder),
+    Resp(RespCodec),
+    Memcached(MemcachedCodec),
+    Http(HttpCodec),
+}
+
+impl Codec {
+    pub(crate) fn new(protocol: Protocol, limits: FrameLimits) -> Self {
+        match protocol {
+            Protocol::Native => Codec::Native(RequestDecoder::with_limits(limits)),
+            Protocol::Resp => Codec::Resp(RespCodec::new(limits)),
+            Protocol::Memcached => Codec::Memcached(MemcachedCodec::new(limits)),
+            Protocol::Http => Codec::Http(HttpCodec::new(limits)),
+        }
+    }
+
+    /// Read everything that is currently available from the connection.
+    /// See [`RequestDecoder::read_from`].
+    pub(crate) fn read_from(&mut self, connection: &mut TcpStream) -> Result<usize, io::Error> {
+        match self {
+            Codec::Native(decoder) => decoder.read_from(connection),
+            Codec::Resp(codec) => codec.read_from(connection),
+            Codec::Memcached(codec) => codec.read_from(connection),
+            Codec::Http(codec) => codec.read_from(connection),
+        }
+    }
+
+    /// Whether reading stopped because the buffer is full, see [`RequestDecoder::is_full`].
+    pub(crate) fn is_full(&self) -> bool {
+        match self {
+            Codec::Native(decoder) => decoder.is_full(),
+            Codec::Resp(codec) => codec.is_full(),
+            Codec::Memcached(codec) => codec.is_full(),
+            Codec::Http(codec) => codec.is_full(),
+        }
+    }
+
+    /// Yield the next complete request that has been read.
+    pub(crate) fn decode(&mut self) -> Result<Option<Incoming>, FrameError> {
+        match self {
+            Codec::Native(decoder) => {
+                let Some(request) = decoder.decode()? else {
+                    return Ok(None);
+                };
+
+                let incoming = match request.try_into() {
+                    Ok(command) => Incoming::Command(command, Reply::Native),
+                    Err(err) => Incoming::Reply(invalid_command(err).into()),
+                };
+                Ok(Some(incoming))
+            }
+            Codec::Resp(codec) => codec.decode(),
+            Codec::Memcached(codec) => codec.decode(),
+            Codec::Http(codec) => codec.decode(),
+        }
+    }
+
+    /// Encode the response to a command the way it was decided when it was decoded.
+    pub(crate) fn encode(&self, reply: Reply, response: RawResponse) -> Vec<u8> {
+        match (self, reply) {
+            (Codec::Resp(codec), Reply::Resp(kind)) => codec.encode(kind, response),
+            (Codec::Memcached(_), Reply::Memcached(reply)) => reply.encode(response),
+            (Codec::Http(_), Reply::Http(reply)) => reply.encode(response),
+            _ => response.into(),
+        }
+    }
+
+    /// Encode the reply to a request that broke the framing or exceeded the limits.
+    pub(crate) fn encode_frame_error(&self, err: &FrameError) -> Vec<u8> {
+        match self {
+            Codec::Native(_) => {
+                let status_code = match err {
+                    FrameError::Malformed(_) => StatusCodes::ErrInvalidRequest,
+                    _ => StatusCodes::ErrPayloadTooLarge,
+                };
+                RawResponse::new(status_code, Some(err.to_string().into_bytes())).into()
+            }
+            Codec::Resp(codec) => codec.encode_frame_error(err),
+            Codec::Memcached(_) => MemcachedReply::encode_frame_error(err),
+            Codec::Http(_) => HttpReply::encode_frame_error(err),
+        }
+    }
+}
+
+/// The response to a request that couldn't be parsed into a command, telling the client why.
+fn invalid_command(err: CommandError) -> RawResponse {
+    RawResponse::new(err.status_code(), Some(err.to_string().into_bytes()))
+}
@@ -0,0 +1,132 @@
+use super::config::FsyncPolicy;
+use skaja_lib::{Command, FrameLimits, Request, RequestDecoder};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+use tracing::{debug, warn};
+
+/// How often the file is fsynced with the [`FsyncPolicy::Everysec`] policy.
+const FSYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+/// An append-only log of every command that modified the keyspace.
+///
+/// Commands are written using the same framing as [`Request`]s, so the log can be replayed
+/// by decoding it the same way the server decodes requests coming from the clients.
+pub struct AppendOnlyFile {
+    file: File,
+    fsync: FsyncPolicy,
+
+    /// Whether there are writes that haven't been fsynced yet.
+    dirty: bool,
+    last_fsync: Instant,
+}
+
+impl AppendOnlyFile {
+    /// Open the file at the given path for appending, creating it if it doesn't exist.
+    pub fn open(path: &Path, fsync: FsyncPolicy) -> Result<Self, io::Error> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(Self {
+            file,
+            fsync,
+            dirty: false,
+            last_fsync: Instant::now(),
+        })
+    }
+
+    /// Read the commands logged in the file at the given path, in the order they were written.
+    ///
+    /// A frame that was only partially written, e.g. because the server crashed in the
+    /// middle of writing it, is discarded and truncated from the file so that new commands
+    /// can be appended after the last complete one.
+    ///
+    /// Anything but a regular file, e.g. a device the log is sent to, has nothing to replay.
+    pub fn load(path: &Path) -> Result<Vec<Command>, io::Error> {
+        match fs::metadata(path) {
+            Ok(metadata) if !metadata.is_file() => return Ok(Vec::new()),
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        }
+
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        // The file was written by the server itself, the limits were enforced when the
+        // commands were received, which might've been with different limits than now.
+        let mut decoder = RequestDecoder::with_limits(FrameLimits::unlimited());
+        decoder.feed(&bytes);
+
+        let mut commands = Vec::new();
+        let mut valid_len = 0;
+        while let Some(request) = decoder
+            .decode()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?
+        {
+            valid_len += request.payload().len();
+            let command: Command = request.try_into().map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid command in append-only file: {}", e),
+                )
+            })?;
+
+            commands.push(command);
+        }
+
+        if decoder.has_pending_bytes() {
+            warn!(
+                "Append-only file ends with an incomplete command, truncating {} bytes.",
+                bytes.len() - valid_len
+            );
+
+            OpenOptions::new()
+                .write(true)
+                .open(path)?
+                .set_len(valid_len as u64)?;
+        }
+
+        Ok(commands)
+    }
+
+    /// Append the command to the file, fsyncing right away with the [`FsyncPolicy::Always`] policy.
+    pub fn append(&mut self, request: &Request) -> Result<(), io::Error> {
+        self.file.write_all(request.payload())?;
+        self.dirty = true;
+
+        if self.fsync == FsyncPolicy::Always {
+            self.sync()?;
+        }
+
+        Ok(())
+    }
+
+    /// Meant to be called periodically, fsyncs the file with the [`FsyncPolicy::Everysec`]
+    /// policy if there are pending writes and a second has passed since the last fsync.
+    pub fn tick(&mut self) -> Result<(), io::Error> {
+        if self.fsync == FsyncPolicy::Everysec
+            && self.dirty
+            && self.last_fsync.elapsed() >= FSYNC_INTERVAL
+        {
+            self.sync()?;
+        }
+
+        Ok(())
+    }
+
+    /// Fsync the pending writes regardless of the policy.
+    pub fn sync(&mut self) -> Result<(), io::Error> {
+        self.file.sync_data()?;
+        self.dirty = false;
+        self.last_fsync = Instant::now();
+        debug!("Append-only file fsynced.");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,37 @@
+use skaja_lib::Expiry;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// The current unix timestamp in milliseconds.
+pub fn unix_millis_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// The unix timestamp in milliseconds of the given point in time.
+pub fn unix_millis_at(instant: Instant) -> u64 {
+    let now = Instant::now();
+    if instant >= now {
+        let until = instant.duration_since(now).as_millis() as u64;
+        unix_millis_now().saturating_add(until)
+    } else {
+        let since = now.duration_since(instant).as_millis() as u64;
+        unix_millis_now().saturating_sub(since)
+    }
+}
+
+/// The point in time at which something with the given expiry expires.
+/// Returns None if it's too far in the future to be represented.
+pub fn deadline(expiry: Expiry) -> Option<Instant> {
+    let timeout = match expiry {
+        Expiry::Seconds(secs) => Duration::from_secs(secs),
+        Expiry::Milliseconds(millis) => Duration::from_millis(millis),
+        // Timestamps in the past expire right away.
+        Expiry::UnixMillis(timestamp) => {
+            Duration::from_millis(timestamp.saturating_sub(unix_millis_now()))
+        }
+    };
+
+    Instant::now().checked_add(timeout)
+}
@@ -1,6 +1,193 @@
 use serde::Deserialize;
+use skaja_lib::FrameLimits;
+use std::{
+    fs, io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+use tracing::error;
 
 #[derive(Deserialize)]
 pub struct Config {
     pub address: Option<String>,
+
+    /// The address of the listener speaking RESP, for Redis clients. Disabled if not set.
+    pub resp_address: Option<String>,
+
+    /// The address of the listener speaking memcached's text protocol. Disabled if not set.
+    pub memcached_address: Option<String>,
+
+    /// The address of the listener serving the keyspace over HTTP. Disabled if not set.
+    pub http_address: Option<String>,
+
+    /// The number of threads serving the connections, defaults to 1.
+    pub threads: Option<usize>,
+
+    /// Logs every command that modifies the keyspace to a file so that the data
+    /// survives restarts. Disabled if not set.
+    pub append_only: Option<AppendOnlyConfig>,
+
+    /// Where the snapshots of the keyspace are saved and how often.
+    pub snapshot: Option<SnapshotConfig>,
+
+    /// How many bytes of responses can pile up for a client that doesn't read them.
+    pub output_buffer: Option<OutputBufferLimits>,
+
+    /// How large the requests of the clients can be.
+    pub request_limits: Option<RequestLimits>,
+}
+
+impl Config {
+    /// Read and parse the TOML config file at the path.
+    pub fn load(path: &Path) -> Result<Self, io::Error> {
+        let config_file = fs::read_to_string(path).inspect_err(|_| {
+            error!(
+                "Failed reading config file at {}, make sure the file exists.",
+                path.display()
+            )
+        })?;
+
+        toml::from_str(&config_file).map_err(|e| {
+            error!("Failed parsing config file, check if it's valid.");
+            io::Error::new(io::ErrorKind::InvalidData, e)
+        })
+    }
+}
+
+/// Parse the address the server binds to, e.g. "127.0.0.1:8080".
+pub fn parse_address(address: &str) -> Result<SocketAddr, io::Error> {
+    address.parse().map_err(|e| {
+        let mut message = "Failed parsing target address".to_string();
+        if address.contains("localhost") {
+            message = format!(r#"{}, use "127.0.0.1" instead of "localhost"."#, message);
+        } else {
+            message = format!(r#"{}, {}."#, message, e);
+        }
+
+        error!(message);
+        io::Error::new(io::ErrorKind::InvalidInput, message)
+    })
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AppendOnlyConfig {
+    /// The path of the append-only file.
+    pub path: PathBuf,
+
+    #[serde(default)]
+    pub fsync: FsyncPolicy,
+}
+
+/// When the append-only file is fsynced, trading durability for throughput.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FsyncPolicy {
+    /// After every write, nothing is lost on a crash but it's the slowest.
+    Always,
+    /// Once every second, at most a second worth of writes is lost on a crash.
+    #[default]
+    Everysec,
+    /// Never, it's up to the OS to flush the writes to the disk.
+    No,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SnapshotConfig {
+    /// The path of the snapshot file, it's loaded when the server starts.
+    #[serde(default = "default_snapshot_path")]
+    pub path: PathBuf,
+
+    /// Save the snapshot in the background when any of the rules is satisfied.
+    #[serde(default)]
+    pub save: Vec<SaveRule>,
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        Self {
+            path: default_snapshot_path(),
+            save: Vec::new(),
+        }
+    }
+}
+
+fn default_snapshot_path() -> PathBuf {
+    PathBuf::from("dump.skaja")
+}
+
+/// Satisfied when there have been at least `writes` writes and
+/// at least `seconds` seconds have passed since the last save.
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct SaveRule {
+    pub seconds: u64,
+    pub writes: u64,
+}
+
+/// Limits on the responses buffered for a client that doesn't read them fast enough,
+/// the client is disconnected once any of them is exceeded. A limit of 0 disables it.
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct OutputBufferLimits {
+    /// In bytes, exceeding it disconnects the client right away.
+    pub hard_limit: usize,
+
+    /// In bytes, exceeding it for `soft_seconds` seconds disconnects the client.
+    pub soft_limit: usize,
+    pub soft_seconds: u64,
+}
+
+impl Default for OutputBufferLimits {
+    fn default() -> Self {
+        Self {
+            hard_limit: 256 * 1024 * 1024,
+            soft_limit: 64 * 1024 * 1024,
+            soft_seconds: 60,
+        }
+    }
+}
+
+/// Limits on the size of the requests, a client sending a request over any of them is
+/// answered with an error and disconnected. Defaults to [`FrameLimits::default`].
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct RequestLimits {
+    /// The maximum number of arguments, including the name of the command.
+    pub max_args: usize,
+
+    /// In bytes, the maximum size of the key.
+    pub max_key_size: usize,
+
+    /// In bytes, the maximum size of each of the other arguments.
+    pub max_value_size: usize,
+
+    /// In bytes, the maximum size of a whole request.
+    pub max_request_size: usize,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        FrameLimits::default().into()
+    }
+}
+
+impl From<FrameLimits> for RequestLimits {
+    fn from(limits: FrameLimits) -> Self {
+        Self {
+            max_args: limits.max_args,
+            max_key_size: limits.max_key_size,
+            max_value_size: limits.max_value_size,
+            max_request_size: limits.max_frame_size,
+        }
+    }
+}
+
+impl From<RequestLimits> for FrameLimits {
+    fn from(limits: RequestLimits) -> Self {
+        Self {
+            max_args: limits.max_args,
+            max_key_size: limits.max_key_size,
+            max_value_size: limits.max_value_size,
+            max_frame_size: limits.max_request_size,
+        }
+    }
 }
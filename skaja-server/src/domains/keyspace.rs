@@ -0,0 +1,128 @@
+use super::store::Store;
+use std::{
+    collections::hash_map::RandomState,
+    hash::BuildHasher,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+/// The keyspace split into shards by the hash of the keys, each one behind its own lock,
+/// so that commands on keys that live in different shards can run in parallel.
+#[derive(Debug)]
+pub struct Keyspace {
+    shards: Vec<Mutex<Store>>,
+    hasher: RandomState,
+}
+
+impl Keyspace {
+    /// Create an empty keyspace with the given number of shards, at least one.
+    pub fn new(shards: usize) -> Self {
+        Self {
+            shards: (0..shards.max(1))
+                .map(|_| Mutex::new(Store::new()))
+                .collect(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    /// Lock the shard the key belongs to.
+    pub fn shard(&self, key: &[u8]) -> MutexGuard<'_, Store> {
+        lock(&self.shards[self.index(key)])
+    }
+
+    /// Lock the shards the keys belong to, so that a command on several keys sees and
+    /// changes all of them at once. The shards are locked in the same order as
+    /// [`Keyspace::lock_all`] does, so that none of these can deadlock.
+    pub fn lock_keys<'k>(&self, keys: impl IntoIterator<Item = &'k [u8]>) -> Shards<'_> {
+        let mut indexes: Vec<usize> = keys.into_iter().map(|key| self.index(key)).collect();
+        indexes.sort_unstable();
+        indexes.dedup();
+
+        Shards {
+            keyspace: self,
+            guards: indexes
+                .into_iter()
+                .map(|index| (index, lock(&self.shards[index])))
+                .collect(),
+        }
+    }
+
+    /// Lock all of the shards, for the operations that need a consistent view of the whole
+    /// keyspace. They're always locked in the same order so that two of these can't deadlock.
+    pub fn lock_all(&self) -> Vec<MutexGuard<'_, Store>> {
+        self.shards.iter().map(lock).collect()
+    }
+
+    /// Lock the shards one at a time, handing each of them to the function, for the
+    /// operations that would stall every other one if they locked the whole keyspace.
+    pub fn for_each_shard(&self, mut f: impl FnMut(&Store)) {
+        for shard in &self.shards {
+            f(&lock(shard));
+        }
+    }
+
+    /// The keys that start with the prefix and haven't expired, sorted. The shards are
+    /// locked one at a time, so the keys written in the meantime might be missing.
+    pub fn keys(&self, prefix: &[u8]) -> Vec<Vec<u8>> {
+        let mut keys: Vec<Vec<u8>> = self
+            .shards
+            .iter()
+            .flat_map(|shard| {
+                lock(shard)
+                    .entries()
+                    .filter(|(key, _, _)| key.starts_with(prefix))
+                    .map(|(key, _, _)| key.clone())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        keys.sort_unstable();
+        keys
+    }
+
+    fn index(&self, key: &[u8]) -> usize {
+        (self.hasher.hash_one(key) % self.shards.len() as u64) as usize
+    }
+
+    /// Run [`Store::expire_cycle`] on the shards one by one, removing at most `max_keys`
+    /// keys in total. Returns the number of removed keys.
+    pub fn expire_cycle(&self, max_keys: usize) -> usize {
+        let mut removed = 0;
+        for shard in &self.shards {
+            if removed >= max_keys {
+                break;
+            }
+
+            removed += lock(shard).expire_cycle(max_keys - removed);
+        }
+
+        removed
+    }
+}
+
+/// The shards holding a set of keys, locked by [`Keyspace::lock_keys`].
+pub struct Shards<'a> {
+    keyspace: &'a Keyspace,
+
+    /// Sorted by the index of the shard.
+    guards: Vec<(usize, MutexGuard<'a, Store>)>,
+}
+
+impl Shards<'_> {
+    /// The store of the shard the key belongs to.
+    ///
+    /// Panics if the key wasn't given to [`Keyspace::lock_keys`].
+    pub fn store(&mut self, key: &[u8]) -> &mut Store {
+        let index = self.keyspace.index(key);
+        let position = self
+            .guards
+            .binary_search_by_key(&index, |(index, _)| *index)
+            .expect("The shard of the key isn't locked.");
+        &mut self.guards[position].1
+    }
+}
+
+/// Lock the mutex even if a thread panicked while holding it, so that a panicking command
+/// doesn't take the whole server down with it.
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
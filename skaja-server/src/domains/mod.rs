@@ -1 +1,10 @@
+pub mod aof;
+pub mod clock;
 pub mod config;
+pub mod keyspace;
+pub mod output_buffer;
+pub mod shutdown;
+pub mod snapshot;
+pub mod sorted_set;
+pub mod store;
+pub mod stream;
@@ -0,0 +1,105 @@
+use super::config::OutputBufferLimits;
+use std::{
+    io::{self, ErrorKind, Write},
+    time::{Duration, Instant},
+};
+
+/// The responses of a connection that haven't been written to it yet.
+///
+/// The connections are non-blocking, so a response is only partially written when the client
+/// doesn't read fast enough. The rest stays in the buffer until the connection is writable again.
+#[derive(Debug, Default)]
+pub struct OutputBuffer {
+    buffer: Vec<u8>,
+
+    /// How many bytes at the start of the buffer were already written. They're only dropped
+    /// once they're at least half of it, so a slow client doesn't have the rest of the buffer
+    /// moved on every partial write.
+    head: usize,
+
+    /// When the buffer grew past the soft limit, None if it's below it.
+    over_soft_limit_since: Option<Instant>,
+}
+
+impl OutputBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffer.len() - self.head
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Append the bytes to the end of the buffer.
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Write as much of the buffer as the sink accepts without blocking.
+    /// Returns the number of bytes written.
+    pub fn write_to<W: Write>(&mut self, sink: &mut W) -> Result<usize, io::Error> {
+        let mut written = 0;
+
+        while !self.is_empty() {
+            match sink.write(&self.buffer[self.head..]) {
+                Ok(0) => {
+                    self.compact();
+                    return Err(io::Error::new(
+                        ErrorKind::WriteZero,
+                        "Failed writing the whole output buffer.",
+                    ));
+                }
+                Ok(n) => {
+                    self.head += n;
+                    written += n;
+                }
+                Err(ref err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    self.compact();
+                    return Err(err);
+                }
+            }
+        }
+
+        self.compact();
+        if written > 0 {
+            sink.flush()?;
+        }
+
+        Ok(written)
+    }
+
+    /// Whether the buffer is past the hard limit, or has been past the soft limit for longer
+    /// than allowed. A limit of 0 disables it.
+    pub fn exceeds(&mut self, limits: &OutputBufferLimits) -> bool {
+        let len = self.len();
+        if limits.hard_limit > 0 && len > limits.hard_limit {
+            return true;
+        }
+
+        if limits.soft_limit == 0 || len <= limits.soft_limit {
+            self.over_soft_limit_since = None;
+            return false;
+        }
+
+        let since = *self.over_soft_limit_since.get_or_insert_with(Instant::now);
+        since.elapsed() >= Duration::from_secs(limits.soft_seconds)
+    }
+
+    /// Drop the bytes already written once there's nothing left or they're at least half
+    /// of the buffer.
+    fn compact(&mut self) {
+        if self.head == self.buffer.len() {
+            self.buffer.clear();
+            self.head = 0;
+        } else if self.head >= self.buffer.len() / 2 {
+            self.buffer.drain(..self.head);
+            self.head = 0;
+        }
+    }
+}
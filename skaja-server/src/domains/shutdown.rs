@@ -0,0 +1,34 @@
+use super::keyspace::lock;
+use mio::Waker;
+use skaja_lib::ShutdownMode;
+use std::{io, sync::Mutex};
+
+/// Tells the server to stop, it's requested by the termination signals, the
+/// [`skaja_lib::Command::Shutdown`] command, or the embedder through a [`crate::ServerHandle`].
+#[derive(Debug)]
+pub struct Shutdown {
+    requested: Mutex<Option<ShutdownMode>>,
+
+    /// Wakes the worker accepting the connections up, which then tells the others.
+    waker: Waker,
+}
+
+impl Shutdown {
+    pub fn new(waker: Waker) -> Self {
+        Self {
+            requested: Mutex::new(None),
+            waker,
+        }
+    }
+
+    /// Ask the server to shut down, the mode of the first request is the one that's used.
+    pub fn request(&self, mode: ShutdownMode) -> Result<(), io::Error> {
+        lock(&self.requested).get_or_insert(mode);
+        self.waker.wake()
+    }
+
+    /// The mode the shutdown was requested with, None if it hasn't been requested.
+    pub fn requested(&self) -> Option<ShutdownMode> {
+        *lock(&self.requested)
+    }
+}
@@ -0,0 +1,681 @@
+use super::{
+    clock,
+    config::{SaveRule, SnapshotConfig},
+    keyspace::Keyspace,
+    sorted_set::SortedSet,
+    store::Value,
+    stream::{ConsumerGroup, Delivery, Stream},
+};
+use skaja_lib::{Expiry, StreamId};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+use tracing::{error, info};
+
+/// Identifies a skaja snapshot file.
+const MAGIC: &[u8] = b"SKAJA";
+/// The version of the format the snapshots are written with. The files written with an
+/// earlier version are read the same way, they just don't have the records added since.
+const VERSION: u32 = 2;
+
+/// Marks a record holding a string value.
+const RECORD_STRING: u8 = 0;
+/// Marks a record holding a string value with flags, see [`super::store::Entry::flags`].
+const RECORD_FLAGGED_STRING: u8 = 1;
+/// Marks a record holding a list.
+const RECORD_LIST: u8 = 2;
+/// Marks a record holding a hash.
+const RECORD_HASH: u8 = 3;
+/// Marks a record holding a set.
+const RECORD_SET: u8 = 4;
+/// Marks a record holding a sorted set.
+const RECORD_SORTED_SET: u8 = 5;
+/// Marks a record holding a stream.
+const RECORD_STREAM: u8 = 6;
+/// Marks the end of the records.
+const RECORD_EOF: u8 = 0xFF;
+
+/// A key in the snapshot, `expires_at` is a unix timestamp in milliseconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotEntry {
+    pub key: Vec<u8>,
+    pub value: Value,
+    pub flags: u32,
+    pub expires_at: Option<u64>,
+}
+
+/// Writes and loads point-in-time snapshots of the whole keyspace.
+///
+/// The following is the structure of the snapshot file, all integers are little-endian:
+///
+/// | magic   | version | record 1 | ... | record N | EOF marker | checksum |
+/// |---------|---------|----------|-----|----------|------------|----------|
+/// | "SKAJA" | u32     |          | ... |          | 0xFF       | u32      |
+///
+/// Each record starts with its type, the unix timestamp in milliseconds the key expires at,
+/// or 0 if it never does, and the key as a chunk. A chunk is a u32 length followed by the
+/// bytes, a count is a u32 and a stream ID is its two parts as u64s. The rest depends on
+/// the type:
+///
+/// | type              | rest of the record                                          |
+/// |-------------------|-------------------------------------------------------------|
+/// | 0 string          | value chunk                                                 |
+/// | 1 flagged string  | flags u32, value chunk                                      |
+/// | 2 list            | count, item chunks                                          |
+/// | 3 hash            | count, (field chunk, value chunk) pairs                     |
+/// | 4 set             | count, member chunks                                        |
+/// | 5 sorted set      | count, (member chunk, score as the u64 bits of the f64)     |
+/// | 6 stream          | last ID, count, entries, count, groups                      |
+/// | stream entry      | ID, count, (field chunk, value chunk) pairs                 |
+/// | consumer group    | name chunk, last delivered ID, count, pending entries       |
+/// | pending entry     | ID, consumer chunk, delivery time u64, deliveries u64       |
+///
+/// The checksum is the CRC32 of everything that comes before it.
+pub struct Snapshotter {
+    config: SnapshotConfig,
+
+    /// The number of writes since the last successful save.
+    dirty: u64,
+    last_save: Instant,
+    background_save: Option<BackgroundSave>,
+}
+
+struct BackgroundSave {
+    handle: JoinHandle<Result<(), io::Error>>,
+    /// The number of writes the save covers.
+    dirty: u64,
+}
+
+impl Snapshotter {
+    pub fn new(config: SnapshotConfig) -> Self {
+        Self {
+            config,
+            dirty: 0,
+            last_save: Instant::now(),
+            background_save: None,
+        }
+    }
+
+    /// Keep track of a write to the keyspace, for the periodic save rules.
+    pub fn record_write(&mut self) {
+        self.dirty += 1;
+    }
+
+    pub fn is_saving(&self) -> bool {
+        self.background_save.is_some()
+    }
+
+    /// Whether the snapshot is saved periodically, rather than only on demand.
+    pub fn has_save_rules(&self) -> bool {
+        !self.config.save.is_empty()
+    }
+
+    /// Block until the background save in progress, if any, is done.
+    pub fn wait_for_background_save(&mut self) {
+        if let Some(background_save) = self.background_save.take() {
+            info!("Waiting for the background save to finish.");
+            self.finish_background_save(background_save);
+        }
+    }
+
+    /// Load the snapshot file into the keyspace, skipping the keys that have expired.
+    /// Returns the number of loaded keys, which is 0 if the file doesn't exist.
+    pub fn load_into(&self, keyspace: &Keyspace) -> Result<usize, io::Error> {
+        let entries = match read_snapshot(&self.config.path) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+
+        let now = clock::unix_millis_now();
+        let mut loaded = 0;
+        for entry in entries {
+            let expires_at = match entry.expires_at {
+                Some(timestamp) if timestamp <= now => continue,
+                Some(timestamp) => clock::deadline(Expiry::UnixMillis(timestamp)),
+                None => None,
+            };
+
+            keyspace
+                .shard(&entry.key)
+                .set(entry.key, entry.value, entry.flags, expires_at);
+            loaded += 1;
+        }
+
+        Ok(loaded)
+    }
+
+    /// Write the snapshot of the keyspace, blocking until it's done.
+    /// The whole keyspace is locked in the meantime.
+    pub fn save(&mut self, keyspace: &Keyspace) -> Result<(), io::Error> {
+        let shards = keyspace.lock_all();
+        let entries =
+            shards
+                .iter()
+                .flat_map(|store| store.entries())
+                .map(|(key, entry, expires_at)| {
+                    (
+                        key.as_slice(),
+                        &entry.value,
+                        entry.flags,
+                        expires_at.map(clock::unix_millis_at),
+                    )
+                });
+
+        write_snapshot(&self.config.path, entries)?;
+        self.dirty = 0;
+        self.last_save = Instant::now();
+        info!("Snapshot saved to: {}", self.config.path.display());
+
+        Ok(())
+    }
+
+    /// Write the snapshot of the keyspace on another thread, working on a copy of the keyspace
+    /// so that the server can keep serving requests in the meantime.
+    /// Returns false if there's already a save in progress.
+    ///
+    /// The shards are copied one at a time, so a write spanning several shards that comes in
+    /// while copying might only be partly in the snapshot.
+    pub fn background_save(&mut self, keyspace: &Keyspace) -> bool {
+        if self.is_saving() {
+            return false;
+        }
+
+        let mut entries: Vec<SnapshotEntry> = Vec::new();
+        keyspace.for_each_shard(|store| {
+            entries.extend(
+                store
+                    .entries()
+                    .map(|(key, entry, expires_at)| SnapshotEntry {
+                        key: key.clone(),
+                        value: entry.value.clone(),
+                        flags: entry.flags,
+                        expires_at: expires_at.map(clock::unix_millis_at),
+                    }),
+            )
+        });
+
+        let path = self.config.path.clone();
+        let handle = thread::spawn(move || {
+            let entries = entries.iter().map(|entry| {
+                (
+                    entry.key.as_slice(),
+                    &entry.value,
+                    entry.flags,
+                    entry.expires_at,
+                )
+            });
+            write_snapshot(&path, entries)
+        });
+
+        info!("Background saving started.");
+        self.background_save = Some(BackgroundSave {
+            handle,
+            dirty: self.dirty,
+        });
+
+        true
+    }
+
+    /// Meant to be called periodically, wraps up the finished background save and
+    /// starts a new one if any of the save rules is satisfied.
+    pub fn tick(&mut self, keyspace: &Keyspace) {
+        if let Some(background_save) = self.background_save.take() {
+            if !background_save.handle.is_finished() {
+                self.background_save = Some(background_save);
+                return;
+            }
+
+            self.finish_background_save(background_save);
+        }
+
+        let elapsed = self.last_save.elapsed();
+        let rule_satisfied = self.config.save.iter().any(|SaveRule { seconds, writes }| {
+            self.dirty >= *writes && elapsed >= Duration::from_secs(*seconds)
+        });
+
+        if self.dirty > 0 && rule_satisfied {
+            info!("{} writes since the last save, saving.", self.dirty);
+            self.background_save(keyspace);
+        }
+    }
+
+    fn finish_background_save(&mut self, background_save: BackgroundSave) {
+        match background_save.handle.join() {
+            Ok(Ok(_)) => {
+                // Writes that came in while saving aren't covered by the snapshot.
+                self.dirty = self.dirty.saturating_sub(background_save.dirty);
+                self.last_save = Instant::now();
+                info!("Background save finished: {}", self.config.path.display());
+            }
+            Ok(Err(e)) => error!("Background save failed: {}", e),
+            Err(_) => error!("Background save thread panicked."),
+        }
+    }
+}
+
+/// Write the entries to a temporary file first and then move it to the given path,
+/// so the previous snapshot is left intact if something goes wrong along the way.
+fn write_snapshot<'a>(
+    path: &Path,
+    entries: impl Iterator<Item = (&'a [u8], &'a Value, u32, Option<u64>)>,
+) -> Result<(), io::Error> {
+    let mut temp_path = PathBuf::from(path);
+    temp_path.set_extension("tmp");
+
+    let file = File::create(&temp_path)?;
+    let mut writer = ChecksumWriter::new(BufWriter::new(file));
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+
+    for (key, value, flags, expires_at) in entries {
+        let expires_at = expires_at.unwrap_or(0).to_le_bytes();
+        match value {
+            Value::String(value) => {
+                if flags == 0 {
+                    writer.write_all(&[RECORD_STRING])?;
+                    writer.write_all(&expires_at)?;
+                } else {
+                    writer.write_all(&[RECORD_FLAGGED_STRING])?;
+                    writer.write_all(&expires_at)?;
+                    writer.write_all(&flags.to_le_bytes())?;
+                }
+                write_chunk(&mut writer, key)?;
+                write_chunk(&mut writer, value)?;
+            }
+            Value::List(items) => {
+                writer.write_all(&[RECORD_LIST])?;
+                writer.write_all(&expires_at)?;
+                write_chunk(&mut writer, key)?;
+                write_chunks(&mut writer, items.iter())?;
+            }
+            Value::Hash(fields) => {
+                writer.write_all(&[RECORD_HASH])?;
+                writer.write_all(&expires_at)?;
+                write_chunk(&mut writer, key)?;
+                writer.write_all(&(fields.len() as u32).to_le_bytes())?;
+                for (field, value) in fields {
+                    write_chunk(&mut writer, field)?;
+                    write_chunk(&mut writer, value)?;
+                }
+            }
+            Value::Set(members) => {
+                writer.write_all(&[RECORD_SET])?;
+                writer.write_all(&expires_at)?;
+                write_chunk(&mut writer, key)?;
+                write_chunks(&mut writer, members.iter())?;
+            }
+            Value::SortedSet(members) => {
+                writer.write_all(&[RECORD_SORTED_SET])?;
+                writer.write_all(&expires_at)?;
+                write_chunk(&mut writer, key)?;
+                writer.write_all(&(members.len() as u32).to_le_bytes())?;
+                for (member, score) in members.iter() {
+                    write_chunk(&mut writer, member)?;
+                    writer.write_all(&score.to_bits().to_le_bytes())?;
+                }
+            }
+            Value::Stream(stream) => {
+                writer.write_all(&[RECORD_STREAM])?;
+                writer.write_all(&expires_at)?;
+                write_chunk(&mut writer, key)?;
+                write_stream_id(&mut writer, stream.last_id())?;
+                writer.write_all(&(stream.len() as u32).to_le_bytes())?;
+                for (id, fields) in stream.entries() {
+                    write_stream_id(&mut writer, *id)?;
+                    writer.write_all(&(fields.len() as u32).to_le_bytes())?;
+                    for (field, value) in fields {
+                        write_chunk(&mut writer, field)?;
+                        write_chunk(&mut writer, value)?;
+                    }
+                }
+
+                writer.write_all(&(stream.groups().len() as u32).to_le_bytes())?;
+                for (name, group) in stream.groups() {
+                    write_chunk(&mut writer, name)?;
+                    write_stream_id(&mut writer, group.last_delivered)?;
+                    writer.write_all(&(group.pending.len() as u32).to_le_bytes())?;
+                    for (id, delivery) in &group.pending {
+                        write_stream_id(&mut writer, *id)?;
+                        write_chunk(&mut writer, &delivery.consumer)?;
+                        writer.write_all(&delivery.delivered_at.to_le_bytes())?;
+                        writer.write_all(&delivery.deliveries.to_le_bytes())?;
+                    }
+                }
+            }
+        }
+    }
+
+    writer.write_all(&[RECORD_EOF])?;
+
+    let checksum = writer.checksum();
+    let mut writer = writer.into_inner();
+    writer.write_all(&checksum.to_le_bytes())?;
+
+    let file = writer.into_inner().map_err(|e| e.into_error())?;
+    file.sync_all()?;
+    fs::rename(&temp_path, path)
+}
+
+fn write_stream_id(writer: &mut impl Write, id: StreamId) -> Result<(), io::Error> {
+    writer.write_all(&id.ms.to_le_bytes())?;
+    writer.write_all(&id.seq.to_le_bytes())
+}
+
+/// Write the bytes prefixed with their length.
+fn write_chunk(writer: &mut impl Write, bytes: &[u8]) -> Result<(), io::Error> {
+    writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    writer.write_all(bytes)
+}
+
+/// Write the number of items followed by each of them as a chunk.
+fn write_chunks<'a>(
+    writer: &mut impl Write,
+    items: impl ExactSizeIterator<Item = &'a Vec<u8>>,
+) -> Result<(), io::Error> {
+    writer.write_all(&(items.len() as u32).to_le_bytes())?;
+    for item in items {
+        write_chunk(writer, item)?;
+    }
+
+    Ok(())
+}
+
+/// Read the entries of the snapshot file, making sure it's not corrupted.
+fn read_snapshot(path: &Path) -> Result<Vec<SnapshotEntry>, io::Error> {
+    let bytes = fs::read(path)?;
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+    if bytes.len() < MAGIC.len() + 4 + 1 + 4 || !bytes.starts_with(MAGIC) {
+        return Err(invalid("Not a snapshot file."));
+    }
+
+    // Checked before the checksum, which a later version might compute differently.
+    let version = &bytes[MAGIC.len()..MAGIC.len() + 4];
+    let version = u32::from_le_bytes(version.try_into().unwrap());
+    if !(1..=VERSION).contains(&version) {
+        return Err(invalid(&format!(
+            "Unsupported snapshot version {}, the versions up to {} are supported.",
+            version, VERSION
+        )));
+    }
+
+    let (content, checksum) = bytes.split_at(bytes.len() - 4);
+    if crc32fast::hash(content) != u32::from_le_bytes(checksum.try_into().unwrap()) {
+        return Err(invalid(
+            "Snapshot checksum mismatch, the file is corrupted.",
+        ));
+    }
+
+    let mut reader = SliceReader::new(&content[MAGIC.len() + 4..]);
+    let mut entries = Vec::new();
+    loop {
+        match reader.u8().ok_or_else(|| invalid("Truncated snapshot."))? {
+            RECORD_EOF => break,
+            record @ (RECORD_STRING | RECORD_FLAGGED_STRING) => {
+                let entry = reader
+                    .string_record(record == RECORD_FLAGGED_STRING)
+                    .ok_or_else(|| invalid("Truncated snapshot."))?;
+                entries.push(entry);
+            }
+            RECORD_LIST => {
+                let entry = reader
+                    .list_record()
+                    .ok_or_else(|| invalid("Truncated snapshot."))?;
+                entries.push(entry);
+            }
+            RECORD_HASH => {
+                let entry = reader
+                    .hash_record()
+                    .ok_or_else(|| invalid("Truncated snapshot."))?;
+                entries.push(entry);
+            }
+            RECORD_SET => {
+                let entry = reader
+                    .set_record()
+                    .ok_or_else(|| invalid("Truncated snapshot."))?;
+                entries.push(entry);
+            }
+            RECORD_SORTED_SET => {
+                let entry = reader
+                    .sorted_set_record()
+                    .ok_or_else(|| invalid("Truncated snapshot."))?;
+                entries.push(entry);
+            }
+            RECORD_STREAM => {
+                let entry = reader
+                    .stream_record()
+                    .ok_or_else(|| invalid("Truncated snapshot."))?;
+                entries.push(entry);
+            }
+            _ => return Err(invalid("Unknown record type in snapshot.")),
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Computes the CRC32 of everything written through it.
+struct ChecksumWriter<W: Write> {
+    inner: W,
+    hasher: crc32fast::Hasher,
+}
+
+impl<W: Write> ChecksumWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: crc32fast::Hasher::new(),
+        }
+    }
+
+    fn checksum(&self) -> u32 {
+        self.hasher.clone().finalize()
+    }
+
+    fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reads little-endian integers and length-prefixed chunks out of a slice.
+struct SliceReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> SliceReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.bytes.len() < len {
+            return None;
+        }
+
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Some(taken)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|bytes| bytes[0])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take(4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        self.take(8)
+            .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn chunk(&mut self) -> Option<Vec<u8>> {
+        let len = self.u32()? as usize;
+        self.take(len).map(<[u8]>::to_vec)
+    }
+
+    fn stream_id(&mut self) -> Option<StreamId> {
+        Some(StreamId::new(self.u64()?, self.u64()?))
+    }
+
+    fn string_record(&mut self, flagged: bool) -> Option<SnapshotEntry> {
+        let expires_at = self.u64()?;
+        let flags = match flagged {
+            true => self.u32()?,
+            false => 0,
+        };
+        let key = self.chunk()?;
+        let value = self.chunk()?;
+
+        Some(SnapshotEntry {
+            key,
+            value: Value::String(value),
+            flags,
+            expires_at: (expires_at != 0).then_some(expires_at),
+        })
+    }
+
+    /// The number of items followed by each of them as a chunk.
+    fn chunks(&mut self) -> Option<Vec<Vec<u8>>> {
+        let len = self.u32()? as usize;
+        // The length isn't trusted to preallocate, the file might be corrupted.
+        let mut items = Vec::new();
+        for _ in 0..len {
+            items.push(self.chunk()?);
+        }
+
+        Some(items)
+    }
+
+    fn list_record(&mut self) -> Option<SnapshotEntry> {
+        let expires_at = self.u64()?;
+        let key = self.chunk()?;
+        let items = self.chunks()?;
+
+        Some(SnapshotEntry {
+            key,
+            value: Value::List(items.into()),
+            flags: 0,
+            expires_at: (expires_at != 0).then_some(expires_at),
+        })
+    }
+
+    fn hash_record(&mut self) -> Option<SnapshotEntry> {
+        let expires_at = self.u64()?;
+        let key = self.chunk()?;
+        let len = self.u32()?;
+        let mut fields = HashMap::new();
+        for _ in 0..len {
+            fields.insert(self.chunk()?, self.chunk()?);
+        }
+
+        Some(SnapshotEntry {
+            key,
+            value: Value::Hash(fields),
+            flags: 0,
+            expires_at: (expires_at != 0).then_some(expires_at),
+        })
+    }
+
+    fn set_record(&mut self) -> Option<SnapshotEntry> {
+        let expires_at = self.u64()?;
+        let key = self.chunk()?;
+        let members = self.chunks()?;
+
+        Some(SnapshotEntry {
+            key,
+            value: Value::Set(members.into_iter().collect()),
+            flags: 0,
+            expires_at: (expires_at != 0).then_some(expires_at),
+        })
+    }
+
+    fn sorted_set_record(&mut self) -> Option<SnapshotEntry> {
+        let expires_at = self.u64()?;
+        let key = self.chunk()?;
+        let len = self.u32()?;
+        let mut members = SortedSet::default();
+        for _ in 0..len {
+            let member = self.chunk()?;
+            let score = f64::from_bits(self.u64()?);
+            members.insert(member, score);
+        }
+
+        Some(SnapshotEntry {
+            key,
+            value: Value::SortedSet(members),
+            flags: 0,
+            expires_at: (expires_at != 0).then_some(expires_at),
+        })
+    }
+
+    fn stream_record(&mut self) -> Option<SnapshotEntry> {
+        let expires_at = self.u64()?;
+        let key = self.chunk()?;
+        let last_id = self.stream_id()?;
+
+        let len = self.u32()?;
+        let mut entries = BTreeMap::new();
+        for _ in 0..len {
+            let id = self.stream_id()?;
+            let fields_len = self.u32()?;
+            let mut fields = Vec::new();
+            for _ in 0..fields_len {
+                fields.push((self.chunk()?, self.chunk()?));
+            }
+            entries.insert(id, fields);
+        }
+
+        let groups_len = self.u32()?;
+        let mut groups = HashMap::new();
+        for _ in 0..groups_len {
+            let name = self.chunk()?;
+            let last_delivered = self.stream_id()?;
+            let pending_len = self.u32()?;
+            let mut pending = BTreeMap::new();
+            for _ in 0..pending_len {
+                let id = self.stream_id()?;
+                let delivery = Delivery {
+                    consumer: self.chunk()?,
+                    delivered_at: self.u64()?,
+                    deliveries: self.u64()?,
+                };
+                pending.insert(id, delivery);
+            }
+
+            let group = ConsumerGroup {
+                last_delivered,
+                pending,
+            };
+            groups.insert(name, group);
+        }
+
+        Some(SnapshotEntry {
+            key,
+            value: Value::Stream(Stream::restore(entries, last_id, groups)),
+            flags: 0,
+            expires_at: (expires_at != 0).then_some(expires_at),
+        })
+    }
+}
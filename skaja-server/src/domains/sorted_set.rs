@@ -0,0 +1,100 @@
+use skaja_lib::ScoreBound;
+use std::{
+    cmp::Ordering,
+    collections::{BTreeSet, HashMap},
+    ops::Bound,
+};
+
+/// Members along with a score, kept ordered by the score and by the members themselves
+/// when the scores are the same.
+///
+/// The scores are looked up through a map, the ordering is kept in a B-tree so that the
+/// ranges by score start from where the lowest bound is without going through the lower
+/// members.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SortedSet {
+    scores: HashMap<Vec<u8>, f64>,
+    ordered: BTreeSet<(Score, Vec<u8>)>,
+}
+
+/// A score that can be ordered, there are no NaN scores.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Score(f64);
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl SortedSet {
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    pub fn score(&self, member: &[u8]) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Add the member with the score or update its score, true if it's a new member.
+    /// The score must not be NaN.
+    pub fn insert(&mut self, member: Vec<u8>, score: f64) -> bool {
+        // -0.0 and 0.0 are the same score, but `total_cmp` orders them apart.
+        let score = score + 0.0;
+        let old = self.scores.insert(member.clone(), score);
+        if let Some(old) = old {
+            self.ordered.remove(&(Score(old), member.clone()));
+        }
+        self.ordered.insert((Score(score), member));
+
+        old.is_none()
+    }
+
+    /// Remove the member, true if it was there.
+    pub fn remove(&mut self, member: &[u8]) -> bool {
+        match self.scores.remove_entry(member) {
+            Some((member, score)) => self.ordered.remove(&(Score(score), member)),
+            None => false,
+        }
+    }
+
+    /// The position of the member from the lowest score. Counts the members before it, so
+    /// it takes longer the higher the member is.
+    pub fn rank(&self, member: &[u8]) -> Option<usize> {
+        let score = self.score(member)?;
+        let before = self.ordered.range(..(Score(score), member.to_vec()));
+        Some(before.count())
+    }
+
+    /// The members with their score, from the lowest score.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&Vec<u8>, f64)> {
+        self.ordered.iter().map(|(score, member)| (member, score.0))
+    }
+
+    /// The members with a score between the bounds, from the lowest score.
+    pub fn range_by_score(
+        &self,
+        min: ScoreBound,
+        max: ScoreBound,
+    ) -> impl Iterator<Item = (&Vec<u8>, f64)> {
+        // The empty member comes before all the others with the same score.
+        let start = Bound::Included((Score(min.score + 0.0), Vec::new()));
+        self.ordered
+            .range((start, Bound::Unbounded))
+            .map(|(score, member)| (member, score.0))
+            .skip_while(move |(_, score)| !min.is_below(*score))
+            .take_while(move |(_, score)| max.is_above(*score))
+    }
+}
@@ -0,0 +1,436 @@
+use super::{sorted_set::SortedSet, stream::Stream};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    time::{Duration, Instant},
+};
+
+/// The in-memory keyspace, keeps track of the keys' values and when they expire.
+///
+/// Expired keys are removed lazily, when they're accessed, and actively through
+/// [`Store::expire_cycle`] which is meant to be called periodically so that keys that
+/// are never accessed again don't linger around forever.
+#[derive(Debug, Default)]
+pub struct Store {
+    entries: HashMap<Vec<u8>, Entry>,
+
+    /// When each of the keys with a timeout expires.
+    expires: HashMap<Vec<u8>, Instant>,
+
+    /// Same as `expires` but ordered by the deadline, so the expire cycle can find
+    /// the expired keys without going through all of them.
+    deadlines: BTreeSet<(Instant, Vec<u8>)>,
+
+    /// The version given to the last value written, see [`Entry::version`].
+    last_version: u64,
+}
+
+/// A value along with what's kept alongside it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub value: Value,
+
+    /// Opaque to the server, stored for the clients, e.g. memcached's client flags.
+    pub flags: u32,
+
+    /// Changes every time the value is written, so that a client can tell whether the value
+    /// it read is still the current one. Only meaningful while the server is running.
+    pub version: u64,
+}
+
+/// The value of a key, of one of the types the commands work on.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(Vec<u8>),
+    List(VecDeque<Vec<u8>>),
+    Hash(HashMap<Vec<u8>, Vec<u8>>),
+    Set(HashSet<Vec<u8>>),
+    SortedSet(SortedSet),
+    Stream(Stream),
+}
+
+impl Value {
+    /// Whether the value is a collection without any items left, such keys are removed
+    /// rather than kept around empty. Streams are kept, along with their groups.
+    fn is_empty_collection(&self) -> bool {
+        match self {
+            Value::String(_) => false,
+            Value::List(items) => items.is_empty(),
+            Value::Hash(fields) => fields.is_empty(),
+            Value::Set(members) => members.is_empty(),
+            Value::SortedSet(members) => members.is_empty(),
+            Value::Stream(_) => false,
+        }
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(value: Vec<u8>) -> Self {
+        Value::String(value)
+    }
+}
+
+/// The key holds a value of another type than the one the command works on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WrongType;
+
+/// A type a [`Value`] can be of, so that the commands can get at the value as the type
+/// they work on, see [`Store::get_as`].
+pub trait ValueType: Default + Into<Value> {
+    fn of(value: &Value) -> Option<&Self>;
+    fn of_mut(value: &mut Value) -> Option<&mut Self>;
+}
+
+impl ValueType for Vec<u8> {
+    fn of(value: &Value) -> Option<&Self> {
+        match value {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn of_mut(value: &mut Value) -> Option<&mut Self> {
+        match value {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl From<VecDeque<Vec<u8>>> for Value {
+    fn from(items: VecDeque<Vec<u8>>) -> Self {
+        Value::List(items)
+    }
+}
+
+impl ValueType for VecDeque<Vec<u8>> {
+    fn of(value: &Value) -> Option<&Self> {
+        match value {
+            Value::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    fn of_mut(value: &mut Value) -> Option<&mut Self> {
+        match value {
+            Value::List(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+impl From<HashMap<Vec<u8>, Vec<u8>>> for Value {
+    fn from(fields: HashMap<Vec<u8>, Vec<u8>>) -> Self {
+        Value::Hash(fields)
+    }
+}
+
+impl ValueType for HashMap<Vec<u8>, Vec<u8>> {
+    fn of(value: &Value) -> Option<&Self> {
+        match value {
+            Value::Hash(fields) => Some(fields),
+            _ => None,
+        }
+    }
+
+    fn of_mut(value: &mut Value) -> Option<&mut Self> {
+        match value {
+            Value::Hash(fields) => Some(fields),
+            _ => None,
+        }
+    }
+}
+
+impl From<HashSet<Vec<u8>>> for Value {
+    fn from(members: HashSet<Vec<u8>>) -> Self {
+        Value::Set(members)
+    }
+}
+
+impl ValueType for HashSet<Vec<u8>> {
+    fn of(value: &Value) -> Option<&Self> {
+        match value {
+            Value::Set(members) => Some(members),
+            _ => None,
+        }
+    }
+
+    fn of_mut(value: &mut Value) -> Option<&mut Self> {
+        match value {
+            Value::Set(members) => Some(members),
+            _ => None,
+        }
+    }
+}
+
+impl From<SortedSet> for Value {
+    fn from(members: SortedSet) -> Self {
+        Value::SortedSet(members)
+    }
+}
+
+impl ValueType for SortedSet {
+    fn of(value: &Value) -> Option<&Self> {
+        match value {
+            Value::SortedSet(members) => Some(members),
+            _ => None,
+        }
+    }
+
+    fn of_mut(value: &mut Value) -> Option<&mut Self> {
+        match value {
+            Value::SortedSet(members) => Some(members),
+            _ => None,
+        }
+    }
+}
+
+impl From<Stream> for Value {
+    fn from(stream: Stream) -> Self {
+        Value::Stream(stream)
+    }
+}
+
+impl ValueType for Stream {
+    fn of(value: &Value) -> Option<&Self> {
+        match value {
+            Value::Stream(stream) => Some(stream),
+            _ => None,
+        }
+    }
+
+    fn of_mut(value: &mut Value) -> Option<&mut Self> {
+        match value {
+            Value::Stream(stream) => Some(stream),
+            _ => None,
+        }
+    }
+}
+
+impl Store {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of keys in the store, including the ones that are expired but
+    /// haven't been removed yet.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterate over the keys that haven't expired, along with their entries and deadlines.
+    pub fn entries(&self) -> impl Iterator<Item = (&Vec<u8>, &Entry, Option<Instant>)> {
+        let now = Instant::now();
+        self.entries.iter().filter_map(move |(key, entry)| {
+            let expires_at = self.expires.get(key).copied();
+            match expires_at {
+                Some(deadline) if deadline <= now => None,
+                _ => Some((key, entry, expires_at)),
+            }
+        })
+    }
+
+    /// Get the string value of the key, returns None if it doesn't exist or has expired.
+    pub fn get(&mut self, key: &[u8]) -> Result<Option<&Vec<u8>>, WrongType> {
+        self.get_as(key)
+    }
+
+    /// Whether the key exists and hasn't expired, whatever the type of its value.
+    pub fn contains_key(&mut self, key: &[u8]) -> bool {
+        self.get_entry(key).is_some()
+    }
+
+    /// Get the value of the key as the given type, returns None if it doesn't exist or
+    /// has expired.
+    pub fn get_as<T: ValueType>(&mut self, key: &[u8]) -> Result<Option<&T>, WrongType> {
+        match self.get_entry(key) {
+            Some(entry) => T::of(&entry.value).map(Some).ok_or(WrongType),
+            None => Ok(None),
+        }
+    }
+
+    /// Same as [`Store::get_as`], but to change the value in place. A value that was
+    /// actually changed this way has to be handed to [`Store::changed`].
+    pub fn get_as_mut<T: ValueType>(&mut self, key: &[u8]) -> Result<Option<&mut T>, WrongType> {
+        self.remove_if_expired(key, Instant::now());
+        match self.entries.get_mut(key) {
+            Some(entry) => T::of_mut(&mut entry.value).map(Some).ok_or(WrongType),
+            None => Ok(None),
+        }
+    }
+
+    /// Same as [`Store::get_as_mut`], but the key is created with an empty value if it
+    /// doesn't exist.
+    pub fn get_or_insert_as<T: ValueType>(&mut self, key: &[u8]) -> Result<&mut T, WrongType> {
+        // Checked once, otherwise the key could expire between checking and getting it.
+        self.remove_if_expired(key, Instant::now());
+        let version = self.next_version();
+        let entry = self.entries.entry(key.to_vec()).or_insert_with(|| Entry {
+            value: T::default().into(),
+            flags: 0,
+            version,
+        });
+
+        T::of_mut(&mut entry.value).ok_or(WrongType)
+    }
+
+    /// Give the key a new version after its value was changed in place, removing it if
+    /// that left a collection without any items.
+    pub fn changed(&mut self, key: &[u8]) {
+        let version = self.next_version();
+        let Some(entry) = self.entries.get_mut(key) else {
+            return;
+        };
+
+        match entry.value.is_empty_collection() {
+            true => {
+                self.remove(key);
+            }
+            false => entry.version = version,
+        }
+    }
+
+    /// Same as [`Store::get`], but along with the flags and the version of the value.
+    pub fn get_entry(&mut self, key: &[u8]) -> Option<&Entry> {
+        self.remove_if_expired(key, Instant::now());
+        self.entries.get(key)
+    }
+
+    /// Set the value and the flags of the key, discarding any timeout it previously had
+    /// and whatever the type of the previous value was.
+    /// The key expires at the given deadline if any.
+    pub fn set(
+        &mut self,
+        key: Vec<u8>,
+        value: impl Into<Value>,
+        flags: u32,
+        expires_at: Option<Instant>,
+    ) {
+        self.clear_expiry(&key);
+        if let Some(deadline) = expires_at {
+            self.expires.insert(key.clone(), deadline);
+            self.deadlines.insert((deadline, key.clone()));
+        }
+
+        let version = self.next_version();
+        self.entries.insert(
+            key,
+            Entry {
+                value: value.into(),
+                flags,
+                version,
+            },
+        );
+    }
+
+    /// Replace the value of the key with a string, keeping its flags and timeout.
+    /// Returns false if the key doesn't exist.
+    pub fn update(&mut self, key: &[u8], value: Vec<u8>) -> bool {
+        self.remove_if_expired(key, Instant::now());
+        let version = self.next_version();
+        match self.entries.get_mut(key) {
+            Some(entry) => {
+                entry.value = Value::String(value);
+                entry.version = version;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove the key, returns its value if it existed and hasn't expired.
+    pub fn remove(&mut self, key: &[u8]) -> Option<Value> {
+        self.remove_if_expired(key, Instant::now());
+        self.clear_expiry(key);
+        self.entries.remove(key).map(|entry| entry.value)
+    }
+
+    /// Make the key expire at the given deadline, a deadline in the past removes the key
+    /// right away. Returns false if the key doesn't exist.
+    pub fn set_expiry(&mut self, key: &[u8], deadline: Instant) -> bool {
+        let now = Instant::now();
+        self.remove_if_expired(key, now);
+        if !self.entries.contains_key(key) {
+            return false;
+        }
+
+        if deadline <= now {
+            self.remove(key);
+            return true;
+        }
+
+        self.clear_expiry(key);
+        self.expires.insert(key.to_vec(), deadline);
+        self.deadlines.insert((deadline, key.to_vec()));
+        true
+    }
+
+    /// Get the remaining time to live of the key.
+    /// Returns None if the key doesn't exist, and Some(None) if it doesn't have a timeout.
+    pub fn ttl(&mut self, key: &[u8]) -> Option<Option<Duration>> {
+        let now = Instant::now();
+        self.remove_if_expired(key, now);
+        if !self.entries.contains_key(key) {
+            return None;
+        }
+
+        Some(
+            self.expires
+                .get(key)
+                .map(|deadline| deadline.saturating_duration_since(now)),
+        )
+    }
+
+    /// Remove the timeout of the key. Returns false if the key doesn't exist.
+    pub fn persist(&mut self, key: &[u8]) -> bool {
+        self.remove_if_expired(key, Instant::now());
+        if !self.entries.contains_key(key) {
+            return false;
+        }
+
+        self.clear_expiry(key);
+        true
+    }
+
+    /// Remove at most `max_keys` keys that have expired, starting from the ones that
+    /// expired the earliest. Returns the number of removed keys.
+    pub fn expire_cycle(&mut self, max_keys: usize) -> usize {
+        let now = Instant::now();
+        let mut removed = 0;
+
+        while removed < max_keys {
+            let key = match self.deadlines.first() {
+                Some((deadline, key)) if *deadline <= now => key.clone(),
+                _ => break,
+            };
+
+            self.clear_expiry(&key);
+            self.entries.remove(&key);
+            removed += 1;
+        }
+
+        removed
+    }
+
+    fn next_version(&mut self) -> u64 {
+        self.last_version += 1;
+        self.last_version
+    }
+
+    fn remove_if_expired(&mut self, key: &[u8], now: Instant) {
+        let expired = matches!(self.expires.get(key), Some(deadline) if *deadline <= now);
+        if expired {
+            self.clear_expiry(key);
+            self.entries.remove(key);
+        }
+    }
+
+    fn clear_expiry(&mut self, key: &[u8]) {
+        if let Some(deadline) = self.expires.remove(key) {
+            self.deadlines.remove(&(deadline, key.to_vec()));
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use skaja_lib::{PendingEntry, PendingRange, PendingSummary, StreamEntry, StreamId};
+use std::collections::{BTreeMap, HashMap};
+
+type Fields = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// Entries appended with growing IDs, along with the consumer groups reading them.
+///
+/// Unlike the other collections, a stream is kept once it has no entries left, so that
+/// its groups and the ID of its last entry aren't lost.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stream {
+    entries: BTreeMap<StreamId, Fields>,
+
+    /// The ID of the last entry added, the IDs keep growing from it even once the entry
+    /// is trimmed.
+    last_id: StreamId,
+
+    groups: HashMap<Vec<u8>, ConsumerGroup>,
+}
+
+/// Consumers sharing the entries of a stream, each entry being delivered to one of them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConsumerGroup {
+    /// The ID of the last entry delivered to one of the consumers.
+    pub last_delivered: StreamId,
+
+    /// The entries delivered to the consumers that they haven't acknowledged yet.
+    pub pending: BTreeMap<StreamId, Delivery>,
+}
+
+/// Who an entry was last delivered to, and when.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Delivery {
+    pub consumer: Vec<u8>,
+
+    /// The unix timestamp in milliseconds of the last delivery.
+    pub delivered_at: u64,
+
+    /// The number of times the entry was delivered.
+    pub deliveries: u64,
+}
+
+/// The consumer group doesn't exist on the stream.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoGroup;
+
+impl Stream {
+    /// Rebuild a stream out of what it's made of, e.g. when it's loaded from a snapshot.
+    pub fn restore(
+        entries: BTreeMap<StreamId, Fields>,
+        last_id: StreamId,
+        groups: HashMap<Vec<u8>, ConsumerGroup>,
+    ) -> Self {
+        Stream {
+            entries,
+            last_id,
+            groups,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn last_id(&self) -> StreamId {
+        self.last_id
+    }
+
+    pub fn entries(&self) -> &BTreeMap<StreamId, Fields> {
+        &self.entries
+    }
+
+    pub fn groups(&self) -> &HashMap<Vec<u8>, ConsumerGroup> {
+        &self.groups
+    }
+
+    /// Append the entry, its ID must be higher than the one of the last entry.
+    pub fn add(&mut self, id: StreamId, fields: Fields) {
+        debug_assert!(id > self.last_id, "Stream IDs only ever grow.");
+        self.entries.insert(id, fields);
+        self.last_id = id;
+    }
+
+    /// The entries between the IDs, both included, at most `count` of them.
+    pub fn range(&self, start: StreamId, end: StreamId, count: usize) -> Vec<StreamEntry> {
+        if start > end {
+            return Vec::new();
+        }
+
+        self.entries
+            .range(start..=end)
+            .take(count)
+            .map(|(id, fields)| entry(*id, fields))
+            .collect()
+    }
+
+    /// Remove the oldest entries so that at most `max_len` are left, returns how many were
+    /// removed. They aren't pending in any group anymore either.
+    pub fn trim(&mut self, max_len: usize) -> usize {
+        let removed = self.entries.len().saturating_sub(max_len);
+        for _ in 0..removed {
+            let Some((id, _)) = self.entries.pop_first() else {
+                break;
+            };
+            for group in self.groups.values_mut() {
+                group.pending.remove(&id);
+            }
+        }
+        removed
+    }
+
+    /// Create the group, starting after the given ID, false if it already exists.
+    pub fn create_group(&mut self, name: Vec<u8>, start: StreamId) -> bool {
+        if self.groups.contains_key(&name) {
+            return false;
+        }
+
+        let group = ConsumerGroup {
+            last_delivered: start,
+            pending: BTreeMap::new(),
+        };
+        self.groups.insert(name, group);
+        true
+    }
+
+    pub fn has_group(&self, name: &[u8]) -> bool {
+        self.groups.contains_key(name)
+    }
+
+    /// Read at most `count` entries for the consumer of the group. Without an ID, the
+    /// entries never delivered to the group are delivered to the consumer, otherwise the
+    /// ones pending for the consumer after the ID are read again.
+    pub fn read_group(
+        &mut self,
+        group: &[u8],
+        consumer: &[u8],
+        after: Option<StreamId>,
+        count: usize,
+        now: u64,
+    ) -> Result<Vec<StreamEntry>, NoGroup> {
+        let group = self.groups.get_mut(group).ok_or(NoGroup)?;
+        let Some(after) = after else {
+            let Some(start) = group.last_delivered.next() else {
+                return Ok(Vec::new());
+            };
+
+            let entries: Vec<StreamEntry> = self
+                .entries
+                .range(start..)
+                .take(count)
+                .map(|(id, fields)| entry(*id, fields))
+                .collect();
+            for entry in &entries {
+                let delivery = Delivery {
+                    consumer: consumer.to_vec(),
+                    delivered_at: now,
+                    deliveries: 1,
+                };
+                group.pending.insert(entry.id, delivery);
+                group.last_delivered = entry.id;
+            }
+            return Ok(entries);
+        };
+
+        let Some(start) = after.next() else {
+            return Ok(Vec::new());
+        };
+        // The entries trimmed since they were delivered can't be read anymore.
+        let entries = group
+            .pending
+            .range(start..)
+            .filter(|(_, delivery)| delivery.consumer == consumer)
+            .filter_map(|(id, _)| Some(entry(*id, self.entries.get(id)?)))
+            .take(count)
+            .collect();
+        Ok(entries)
+    }
+
+    /// Acknowledge the entries pending in the group, returns how many of them were.
+    pub fn ack(&mut self, group: &[u8], ids: &[StreamId]) -> Result<usize, NoGroup> {
+        let group = self.groups.get_mut(group).ok_or(NoGroup)?;
+        let acknowledged = ids
+            .iter()
+            .filter(|id| group.pending.remove(id).is_some())
+            .count();
+        Ok(acknowledged)
+    }
+
+    pub fn pending_summary(&self, group: &[u8]) -> Result<PendingSummary, NoGroup> {
+        let group = self.groups.get(group).ok_or(NoGroup)?;
+        let first = group.pending.keys().next();
+        let last = group.pending.keys().next_back();
+
+        let mut consumers: BTreeMap<&[u8], u64> = BTreeMap::new();
+        for delivery in group.pending.values() {
+            *consumers.entry(&delivery.consumer).or_default() += 1;
+        }
+
+        Ok(PendingSummary {
+            count: group.pending.len() as u64,
+            range: first.zip(last).map(|(first, last)| (*first, *last)),
+            consumers: consumers
+                .into_iter()
+                .map(|(consumer, count)| (consumer.to_vec(), count))
+                .collect(),
+        })
+    }
+
+    pub fn pending(
+        &self,
+        group: &[u8],
+        range: &PendingRange,
+        now: u64,
+    ) -> Result<Vec<PendingEntry>, NoGroup> {
+        let group = self.groups.get(group).ok_or(NoGroup)?;
+        if range.start > range.end {
+            return Ok(Vec::new());
+        }
+
+        let entries = group
+            .pending
+            .range(range.start..=range.end)
+            .map(|(id, delivery)| PendingEntry {
+                id: *id,
+                consumer: delivery.consumer.clone(),
+                idle: now.saturating_sub(delivery.delivered_at),
+                deliveries: delivery.deliveries,
+            })
+            .filter(|entry| range.min_idle.is_none_or(|min_idle| entry.idle >= min_idle))
+            .filter(|entry| {
+                range
+                    .consumer
+                    .as_ref()
+                    .is_none_or(|consumer| entry.consumer == *consumer)
+            })
+            .take(range.count)
+            .collect();
+        Ok(entries)
+    }
+
+    /// Hand the entries pending in the group that have been idle for at least `min_idle`
+    /// milliseconds over to the consumer, returns the ones it got.
+    pub fn claim(
+        &mut self,
+        group: &[u8],
+        consumer: &[u8],
+        min_idle: u64,
+        ids: &[StreamId],
+        now: u64,
+    ) -> Result<Vec<StreamEntry>, NoGroup> {
+        let group = self.groups.get_mut(group).ok_or(NoGroup)?;
+        let mut claimed = Vec::new();
+        for id in ids {
+            let Some(delivery) = group.pending.get_mut(id) else {
+                continue;
+            };
+            if now.saturating_sub(delivery.delivered_at) < min_idle {
+                continue;
+            }
+
+            let Some(fields) = self.entries.get(id) else {
+                continue;
+            };
+
+            delivery.consumer = consumer.to_vec();
+            delivery.delivered_at = now;
+            delivery.deliveries += 1;
+            claimed.push(entry(*id, fields));
+        }
+
+        Ok(claimed)
+    }
+}
+
+fn entry(id: StreamId, fields: &Fields) -> StreamEntry {
+    StreamEntry {
+        id,
+        fields: fields.clone(),
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{
+    integer, misrouted,
+    store::{Store, WrongType},
+    wrong_type,
+};
+use skaja_lib::{encode_array, encode_nullable_array, Command, RawResponse, StatusCodes};
+use std::collections::HashMap;
+
+type Hash = HashMap<Vec<u8>, Vec<u8>>;
+
+/// Execute a hash command against the store holding its key.
+pub(crate) fn execute(data_store: &mut Store, command: Command) -> RawResponse {
+    run(data_store, command).unwrap_or_else(|WrongType| wrong_type())
+}
+
+fn run(data_store: &mut Store, command: Command) -> Result<RawResponse, WrongType> {
+    let response = match command {
+        Command::HSet(key, pairs) => {
+            let hash = data_store.get_or_insert_as::<Hash>(&key)?;
+            let mut added = 0;
+            for (field, value) in pairs {
+                if hash.insert(field, value).is_none() {
+                    added += 1;
+                }
+            }
+            data_store.changed(&key);
+            integer(added)
+        }
+        Command::HGet(key, field) => match get_field(data_store, &key, &field)? {
+            Some(value) => RawResponse::new(StatusCodes::Ok, Some(value.clone())),
+            None => RawResponse::new(StatusCodes::ErrNotFound, None),
+        },
+        Command::HMGet(key, fields) => {
+            let hash = data_store.get_as::<Hash>(&key)?;
+            let values: Vec<Option<Vec<u8>>> = fields
+                .iter()
+                .map(|field| hash.and_then(|hash| hash.get(field)).cloned())
+                .collect();
+            RawResponse::new(StatusCodes::Ok, Some(encode_nullable_array(&values)))
+        }
+        Command::HDel(key, fields) => {
+            let Some(hash) = data_store.get_as_mut::<Hash>(&key)? else {
+                return Ok(integer(0));
+            };
+
+            let removed = fields
+                .iter()
+                .filter(|field| hash.remove(*field).is_some())
+                .count();
+            if removed > 0 {
+                data_store.changed(&key);
+            }
+            integer(removed as i64)
+        }
+        Command::HGetAll(key) => {
+            let items: Vec<Vec<u8>> = data_store
+                .get_as::<Hash>(&key)?
+                .into_iter()
+                .flatten()
+                .flat_map(|(field, value)| [field.clone(), value.clone()])
+                .collect();
+            RawResponse::new(StatusCodes::Ok, Some(encode_array(&items)))
+        }
+        Command::HKeys(key) => {
+            let fields: Vec<Vec<u8>> = data_store
+                .get_as::<Hash>(&key)?
+                .into_iter()
+                .flat_map(Hash::keys)
+                .cloned()
+                .collect();
+            RawResponse::new(StatusCodes::Ok, Some(encode_array(&fields)))
+        }
+        Command::HVals(key) => {
+            let values: Vec<Vec<u8>> = data_store
+                .get_as::<Hash>(&key)?
+                .into_iter()
+                .flat_map(Hash::values)
+                .cloned()
+                .collect();
+            RawResponse::new(StatusCodes::Ok, Some(encode_array(&values)))
+        }
+        Command::HLen(key) => {
+            let len = data_store.get_as::<Hash>(&key)?.map_or(0, Hash::len);
+            integer(len as i64)
+        }
+        Command::HExists(key, field) => match get_field(data_store, &key, &field)? {
+            Some(_) => RawResponse::new(StatusCodes::Ok, None),
+            None => RawResponse::new(StatusCodes::ErrNotFound, None),
+        },
+        Command::HIncrBy(key, field, delta) => {
+            let current = match get_field(data_store, &key, &field)? {
+                Some(value) => std::str::from_utf8(value)
+                    .ok()
+                    .and_then(|value| value.parse::<i64>().ok()),
+                None => Some(0),
+            };
+
+            let Some(number) = current.and_then(|current| current.checked_add(delta)) else {
+                let msg = b"Hash value is not an integer or out of range".to_vec();
+                return Ok(RawResponse::new(StatusCodes::ErrNotNumeric, Some(msg)));
+            };
+
+            let hash = data_store.get_or_insert_as::<Hash>(&key)?;
+            hash.insert(field, number.to_string().into_bytes());
+            data_store.changed(&key);
+            integer(number)
+        }
+        // Only hash commands are handled here.
+        command => misrouted(&command),
+    };
+
+    Ok(response)
+}
+
+/// Get the value of the field, None if either the key or the field doesn't exist.
+fn get_field<'a>(
+    data_store: &'a mut Store,
+    key: &[u8],
+    field: &[u8],
+) -> Result<Option<&'a Vec<u8>>, WrongType> {
+    let hash = data_store.get_as::<Hash>(key)?;
+    Ok(hash.and_then(|hash| hash.get(field)))
+}
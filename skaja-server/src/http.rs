@@ -0,0 +1,362 @@
+use crate::codec::{Incoming, Reply};
+use mio::net::TcpStream;
+use skaja_lib::{
+    Command, Expiry, FrameError, FrameLimits, HttpDecoder, HttpRequest, RawResponse, Response,
+    SetOptions, StatusCodes,
+};
+use std::{collections::VecDeque, io};
+
+/// How the response to a command is answered in HTTP.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct HttpReply {
+    body: Body,
+    keep_alive: bool,
+}
+
+/// The JSON body of a successful response, the failed ones are answered with an error.
+#[derive(Debug, Clone, PartialEq)]
+enum Body {
+    /// `{"key": ..., "value": ...}`
+    Value(Vec<u8>),
+    /// `{"key": ..., "stored": true}`
+    Stored(Vec<u8>),
+    /// `{"key": ..., "deleted": true}`
+    Deleted(Vec<u8>),
+    /// `{"keys": [...]}`
+    Keys,
+}
+
+impl HttpReply {
+    pub(crate) fn encode(self, response: RawResponse) -> Vec<u8> {
+        let response = Response::from(response);
+        if response.status_code() != StatusCodes::Ok {
+            return error_response(&response, self.keep_alive);
+        }
+
+        let mut body = String::from("{");
+        match self.body {
+            Body::Value(key) => {
+                bytes_field(&mut body, "key", &key);
+                body.push(',');
+                bytes_field(&mut body, "value", response.message().unwrap_or_default());
+            }
+            Body::Stored(key) => {
+                bytes_field(&mut body, "key", &key);
+                body.push_str(",\"stored\":true");
+            }
+            Body::Deleted(key) => {
+                bytes_field(&mut body, "key", &key);
+                body.push_str(",\"deleted\":true");
+            }
+            Body::Keys => {
+                let keys = match response.array() {
+                    Ok(keys) => keys,
+                    Err(e) => {
+                        let response = Response::new(StatusCodes::ErrInternal, Some(e.into()));
+                        return error_response(&response, self.keep_alive);
+                    }
+                };
+
+                body.push_str("\"keys\":[");
+                for (i, key) in keys.iter().enumerate() {
+                    if i > 0 {
+                        body.push(',');
+                    }
+                    string(&mut body, &String::from_utf8_lossy(key));
+                }
+                body.push(']');
+            }
+        }
+        body.push('}');
+
+        http_response(200, &body, self.keep_alive, &[])
+    }
+
+    pub(crate) fn encode_frame_error(err: &FrameError) -> Vec<u8> {
+        let status = match err {
+            FrameError::Malformed(_) => 400,
+            FrameError::KeyTooLarge { .. } => 414,
+            FrameError::TooManyArgs { .. } => 431,
+            FrameError::ValueTooLarge { .. } | FrameError::FrameTooLarge { .. } => 413,
+        };
+
+        http_response(status, &error_body(&err.to_string()), false, &[])
+    }
+}
+
+/// Serves the keyspace over HTTP/1.x with JSON bodies, translating the requests into
+/// [`Command`]s:
+///
+/// - `GET /keys/{key}` gets the value of the key.
+/// - `PUT /keys/{key}[?ttl=seconds]` sets the value of the key to the body of the request.
+/// - `DELETE /keys/{key}` deletes the key.
+/// - `GET /keys[?prefix=...]` lists the keys, starting with the prefix if there's one.
+///
+/// Keys are percent-decoded from the path. Values that aren't valid UTF-8 are sent in
+/// base64 in a `value_base64` field instead, and so are such keys in a `key_base64` one.
+pub(crate) struct HttpCodec {
+    decoder: HttpDecoder,
+    limits: FrameLimits,
+
+    /// What's left to yield of the request that's being answered, e.g. closing the
+    /// connection after the response.
+    pending: VecDeque<Incoming>,
+}
+
+impl HttpCodec {
+    pub(crate) fn new(limits: FrameLimits) -> Self {
+        Self {
+            decoder: HttpDecoder::with_limits(limits),
+            limits,
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn read_from(&mut self, connection: &mut TcpStream) -> Result<usize, io::Error> {
+        self.decoder.read_from(connection)
+    }
+
+    pub(crate) fn is_full(&self) -> bool {
+        self.decoder.is_full()
+    }
+
+    pub(crate) fn decode(&mut self) -> Result<Option<Incoming>, FrameError> {
+        if let Some(incoming) = self.pending.pop_front() {
+            return Ok(Some(incoming));
+        }
+
+        let Some(request) = self.decoder.decode()? else {
+            if self.decoder.take_expect_continue() {
+                return Ok(Some(Incoming::Reply(
+                    b"HTTP/1.1 100 Continue\r\n\r\n".to_vec(),
+                )));
+            }
+
+            return Ok(None);
+        };
+
+        let keep_alive = request.keep_alive();
+        let incoming = match self.route(request, keep_alive)? {
+            Incoming::Reply(reply) if !keep_alive => Incoming::Close(reply),
+            Incoming::Command(command, reply) if !keep_alive => {
+                self.pending.push_back(Incoming::Close(Vec::new()));
+                Incoming::Command(command, reply)
+            }
+            incoming => incoming,
+        };
+
+        Ok(Some(incoming))
+    }
+
+    // Maps the request onto the command it stands for, or answers it right away.
+    fn route(&self, request: HttpRequest, keep_alive: bool) -> Result<Incoming, FrameError> {
+        let (path, query) = match request.target.split_once('?') {
+            Some((path, query)) => (path, query),
+            None => (request.target.as_str(), ""),
+        };
+        let reply = |body: Body| Reply::Http(HttpReply { body, keep_alive });
+        let client_error = |status: u16, msg: &str, headers: &[(&str, &str)]| {
+            Incoming::Reply(http_response(status, &error_body(msg), keep_alive, headers))
+        };
+
+        if path == "/keys" {
+            if request.method != "GET" {
+                return Ok(client_error(405, "Method not allowed", &[("Allow", "GET")]));
+            }
+
+            let prefix = match query_param(query, "prefix") {
+                Some(Some(prefix)) => prefix,
+                Some(None) => return Ok(client_error(400, "Invalid prefix", &[])),
+                None => Vec::new(),
+            };
+            return Ok(Incoming::Command(Command::Keys(prefix), reply(Body::Keys)));
+        }
+
+        let Some(key) = path.strip_prefix("/keys/").filter(|key| !key.is_empty()) else {
+            return Ok(client_error(404, "Not found", &[]));
+        };
+        let Some(key) = percent_decode(key, false) else {
+            return Ok(client_error(400, "Invalid key", &[]));
+        };
+        if key.len() > self.limits.max_key_size {
+            return Err(FrameError::KeyTooLarge {
+                size: key.len(),
+                max: self.limits.max_key_size,
+            });
+        }
+
+        let incoming = match request.method.as_str() {
+            "GET" => Incoming::Command(Command::Get(key.clone()), reply(Body::Value(key))),
+            "PUT" => {
+                let expiry = match query_param(query, "ttl") {
+                    Some(ttl) => match ttl.as_deref().and_then(parse::<u64>) {
+                        Some(secs) if secs > 0 => Some(Expiry::Seconds(secs)),
+                        _ => return Ok(client_error(400, "Invalid ttl", &[])),
+                    },
+                    None => None,
+                };
+                let options = SetOptions {
+                    expiry,
+                    ..Default::default()
+                };
+
+                let command = Command::Set(key.clone(), request.body, options);
+                Incoming::Command(command, reply(Body::Stored(key)))
+            }
+            "DELETE" => Incoming::Command(Command::Delete(key.clone()), reply(Body::Deleted(key))),
+            _ => client_error(405, "Method not allowed", &[("Allow", "GET, PUT, DELETE")]),
+        };
+
+        Ok(incoming)
+    }
+}
+
+/// The HTTP status of a failed command, along with a JSON body saying why.
+fn error_response(response: &Response, keep_alive: bool) -> Vec<u8> {
+    let status_code = response.status_code();
+    let status = match status_code {
+        StatusCodes::ErrInvalidRequest
+        | StatusCodes::ErrUnknownCommand
+        | StatusCodes::ErrWrongArity => 400,
+        StatusCodes::ErrAuthRequired => 401,
+        StatusCodes::ErrReadOnly => 403,
+        StatusCodes::ErrNotFound | StatusCodes::ErrNoGroup => 404,
+        StatusCodes::ErrWrongType | StatusCodes::ErrConflict | StatusCodes::ErrNotNumeric => 409,
+        StatusCodes::ErrPayloadTooLarge => 413,
+        StatusCodes::Ok | StatusCodes::ErrInternal | StatusCodes::Unknown(_) => 500,
+    };
+
+    let msg = match response.message() {
+        Some(msg) => String::from_utf8_lossy(msg).into_owned(),
+        None => status_code.to_string(),
+    };
+    http_response(status, &error_body(&msg), keep_alive, &[])
+}
+
+fn http_response(status: u16, body: &str, keep_alive: bool, headers: &[(&str, &str)]) -> Vec<u8> {
+    let mut out = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n",
+        status,
+        reason(status),
+        body.len()
+    );
+    if !keep_alive {
+        out.push_str("Connection: close\r\n");
+    }
+    for (name, value) in headers {
+        out.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    out.push_str("\r\n");
+    out.push_str(body);
+    out.into_bytes()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        414 => "URI Too Long",
+        431 => "Request Header Fields Too Large",
+        _ => "Internal Server Error",
+    }
+}
+
+fn error_body(msg: &str) -> String {
+    let mut body = String::from("{\"error\":");
+    string(&mut body, msg);
+    body.push('}');
+    body
+}
+
+/// A `"name": "..."` field, or `"name_base64": "..."` if the bytes aren't valid UTF-8.
+fn bytes_field(out: &mut String, name: &str, bytes: &[u8]) {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => {
+            string(out, name);
+            out.push(':');
+            string(out, text);
+        }
+        Err(_) => {
+            string(out, &format!("{}_base64", name));
+            out.push(':');
+            string(out, &base64(bytes));
+        }
+    }
+}
+
+/// Append the text as a JSON string.
+fn string(out: &mut String, text: &str) {
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Standard base64 with padding.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, byte)| n | (*byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char),
+                false => out.push('='),
+            }
+        }
+    }
+    out
+}
+
+/// The value of the parameter in the query string: None if it isn't there, and Some(None)
+/// if it can't be decoded.
+fn query_param(query: &str, name: &str) -> Option<Option<Vec<u8>>> {
+    query
+        .split('&')
+        .map(|param| param.split_once('=').unwrap_or((param, "")))
+        .find(|(param, _)| *param == name)
+        .map(|(_, value)| percent_decode(value, true))
+}
+
+/// Decode the `%XX` escapes, and the `+`s into spaces in query strings.
+fn percent_decode(text: &str, query: bool) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len());
+    let mut bytes = text.bytes();
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'%' => {
+                let hex = [bytes.next()?, bytes.next()?];
+                if !hex.iter().all(u8::is_ascii_hexdigit) {
+                    return None;
+                }
+                out.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+            }
+            b'+' if query => out.push(b' '),
+            byte => out.push(byte),
+        }
+    }
+    Some(out)
+}
+
+fn parse<T: std::str::FromStr>(arg: &[u8]) -> Option<T> {
+    std::str::from_utf8(arg).ok()?.parse().ok()
+}
@@ -1,32 +1,156 @@
-use mio::{
-    event::Event,
-    net::{TcpListener, TcpStream},
-    Events, Interest, Poll, Token,
+use aof::AppendOnlyFile;
+use codec::Protocol;
+use config::{AppendOnlyConfig, Config, OutputBufferLimits, SnapshotConfig};
+use keyspace::{lock, Keyspace, Shards};
+use mio::{net::TcpListener, Interest, Poll, Waker};
+use shutdown::Shutdown;
+use skaja_lib::{
+    encode_array, encode_nullable_array, negotiate_version, Command, Expiry, FrameLimits, Hello,
+    OutOf, RawResponse, Request, SetCondition, SetOptions, ShutdownMode, StatusCodes, Versioned,
+    MIN_PROTOCOL_VERSION, PROTOCOL_VERSION,
 };
-use skaja_lib::{Command, OutOf, RawResponse, Request, StatusCodes, SERVER_TOKEN};
+use snapshot::Snapshotter;
 use std::{
-    collections::HashMap,
     default::Default,
-    io::{self, Write},
+    io,
     net::SocketAddr,
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
+use store::{Entry, Store, Value, WrongType};
 use tracing::{debug, error, info};
+use worker::{listener_token, Worker, WAKER_TOKEN};
 
+mod codec;
 mod domains;
+mod hashes;
+mod http;
+mod lists;
+mod memcached;
+mod resp;
+mod sets;
+mod sorted_sets;
+mod streams;
+mod worker;
 pub use domains::*;
 
-pub struct Connection {
-    connection: TcpStream,
-    ip: SocketAddr,
-    payload: Option<Command>,
-}
+/// How long the poller waits for events before giving the server a chance to do
+/// its periodic work, e.g. removing expired keys.
+const POLL_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// The maximum number of expired keys removed in one expire cycle, so that the
+/// cycle doesn't stall the event loop when lots of keys expire at once.
+const EXPIRE_CYCLE_MAX_KEYS: usize = 1000;
+
+/// The number of shards the keyspace is split into, a lot more than the number of threads
+/// so that two of them rarely need the same shard at the same time.
+const KEYSPACE_SHARDS: usize = 256;
+
+/// What the server supports on top of the basic commands, reported by [`Command::Hello`].
+const CAPABILITIES: &[&str] = &[
+    "pipelining",
+    "expiry",
+    "persistence",
+    "shutdown",
+    "multi-key",
+    "counters",
+    "conditional-writes",
+    "lists",
+    "hashes",
+    "sets",
+    "sorted-sets",
+    "streams",
+];
 
 pub struct Server {
     address: Option<SocketAddr>,
     listener: Option<TcpListener>,
+    /// The addresses of the listeners speaking the other protocols, e.g. RESP.
+    protocol_addresses: Vec<(Protocol, SocketAddr)>,
+    protocol_listeners: Vec<(Protocol, TcpListener)>,
     poller: Option<Poll>,
-    data_store: HashMap<String, String>,
-    connections_store: HashMap<Token, Connection>,
+    threads: usize,
+    keyspace: Keyspace,
+    append_only: Option<AppendOnlyConfig>,
+    aof: Option<AppendOnlyFile>,
+    snapshotter: Snapshotter,
+    output_buffer_limits: OutputBufferLimits,
+    request_limits: FrameLimits,
+    shutdown: Option<Arc<Shutdown>>,
+    handle_signals: bool,
+}
+
+/// A server running on a background thread, see [`Server::spawn`].
+///
+/// The server is shut down when the handle is dropped.
+pub struct ServerHandle {
+    local_addr: SocketAddr,
+    protocol_addrs: Vec<(Protocol, SocketAddr)>,
+    shutdown: Arc<Shutdown>,
+    thread: Option<JoinHandle<Result<(), io::Error>>>,
+}
+
+impl ServerHandle {
+    /// The address the server is listening on, with the actual port if it was bound to port 0.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// The address the RESP listener is listening on, if it's enabled.
+    pub fn resp_addr(&self) -> Option<SocketAddr> {
+        protocol_address(&self.protocol_addrs, Protocol::Resp)
+    }
+
+    /// The address the memcached listener is listening on, if it's enabled.
+    pub fn memcached_addr(&self) -> Option<SocketAddr> {
+        protocol_address(&self.protocol_addrs, Protocol::Memcached)
+    }
+
+    /// The address the HTTP listener is listening on, if it's enabled.
+    pub fn http_addr(&self) -> Option<SocketAddr> {
+        protocol_address(&self.protocol_addrs, Protocol::Http)
+    }
+
+    /// Ask the server to shut down, it stops once the responses that are already on their
+    /// way have been written and the keyspace has been persisted.
+    pub fn shutdown(&self) -> Result<(), io::Error> {
+        self.shutdown.request(ShutdownMode::Default)
+    }
+
+    /// Wait for the server to stop, returns the error that made it stop if any.
+    pub fn join(mut self) -> Result<(), io::Error> {
+        match self.thread.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => Err(io::Error::other("Server thread panicked.")),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for ServerHandle {
+    fn drop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            if let Err(e) = self.shutdown() {
+                error!("Failed requesting shutdown: {}", e);
+            }
+
+            let _ = thread.join();
+        }
+    }
+}
+
+/// The state shared by all of the workers.
+///
+/// The locks are always taken in the following order so that the workers can't deadlock:
+/// the snapshotter, the keyspace's shards, and then the append-only file.
+pub(crate) struct Shared {
+    keyspace: Keyspace,
+    aof: Option<Mutex<AppendOnlyFile>>,
+    snapshotter: Mutex<Snapshotter>,
+    output_buffer_limits: OutputBufferLimits,
+    request_limits: FrameLimits,
+    shutdown: Arc<Shutdown>,
 }
 
 impl Default for Server {
@@ -41,60 +165,62 @@ impl Server {
         Self {
             address: None,
             listener: None,
+            protocol_addresses: Vec::new(),
+            protocol_listeners: Vec::new(),
             poller: None,
-            data_store: HashMap::new(),
-            connections_store: HashMap::new(),
+            threads: 1,
+            keyspace: Keyspace::new(KEYSPACE_SHARDS),
+            append_only: None,
+            aof: None,
+            snapshotter: Snapshotter::new(SnapshotConfig::default()),
+            output_buffer_limits: OutputBufferLimits::default(),
+            request_limits: FrameLimits::default(),
+            shutdown: None,
+            handle_signals: false,
         }
     }
 
     // Creates a new instance of the server and binds it to the address.
-    pub fn bootstrap(address: SocketAddr) -> Self {
+    pub fn bootstrap(address: SocketAddr) -> Result<Self, io::Error> {
         let mut server = Self::new();
         server.set_address(address);
         server.bind()
     }
 
-    // Binds the server to the address already tied to the instance.
-    pub fn bind(self) -> Self {
-        if let Some(address) = self.address {
-            let mut listener_binding = match TcpListener::bind(address) {
-                Ok(listener) => listener,
-                Err(err) if err.kind() == io::ErrorKind::AddrInUse => {
-                    error!("Address already in use: {}", address);
-                    std::process::exit(-1);
-                }
-                Err(err) => {
-                    error!("Failed starting server: {}", err);
-                    std::process::exit(-1);
-                }
-            };
-
-            debug!("Server bound to: {}", address);
-
-            let poller = Poll::new().unwrap_or_else(|_| {
-                error!("Failed creating poller.");
-                std::process::exit(-1);
-            });
-
-            poller
-                .registry()
-                .register(&mut listener_binding, SERVER_TOKEN, Interest::READABLE)
-                .unwrap_or_else(|_| {
-                    error!("Failed registering server to poller.");
-                    std::process::exit(-1);
-                });
-
-            Self {
-                address: Some(address),
-                listener: Some(listener_binding),
-                poller: Some(poller),
-                data_store: self.data_store,
-                connections_store: self.connections_store,
-            }
-        } else {
+    // Binds the server to the address already tied to the instance. Binding to port 0 picks
+    // an available port, which is then reported by `address`.
+    pub fn bind(self) -> Result<Self, io::Error> {
+        let address = self.address.ok_or_else(|| {
             error!("Server address is not set.");
-            std::process::exit(-1);
+            io::Error::new(io::ErrorKind::InvalidInput, "Server address is not set.")
+        })?;
+
+        let poller = Poll::new().inspect_err(|_| error!("Failed creating poller."))?;
+
+        let listener_binding = bind_listener(&poller, address, Protocol::Native)?;
+        let address = listener_binding.local_addr()?;
+        debug!("Server bound to: {}", address);
+
+        let mut protocol_addresses = Vec::with_capacity(self.protocol_addresses.len());
+        let mut protocol_listeners = Vec::with_capacity(self.protocol_addresses.len());
+        for &(protocol, address) in &self.protocol_addresses {
+            let listener = bind_listener(&poller, address, protocol)?;
+            protocol_addresses.push((protocol, listener.local_addr()?));
+            protocol_listeners.push((protocol, listener));
         }
+
+        let waker = Waker::new(poller.registry(), WAKER_TOKEN)
+            .inspect_err(|_| error!("Failed creating waker."))?;
+
+        Ok(Self {
+            address: Some(address),
+            listener: Some(listener_binding),
+            protocol_addresses,
+            protocol_listeners,
+            poller: Some(poller),
+            shutdown: Some(Arc::new(Shutdown::new(waker))),
+            ..self
+        })
     }
 
     pub fn address(&self) -> Option<SocketAddr> {
@@ -105,206 +231,923 @@ impl Server {
         self.address = Some(address);
     }
 
-    // Listen for incoming connections.
-    pub fn listen(mut self) -> Result<(), io::Error> {
-        if self.address.is_none() {
-            error!("Server hasn't been initialized.");
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "Server address is not set.",
-            ));
+    // The address the RESP listener is listening on, if it's enabled.
+    pub fn resp_address(&self) -> Option<SocketAddr> {
+        protocol_address(&self.protocol_addresses, Protocol::Resp)
+    }
+
+    // Enables a second listener speaking RESP2 and RESP3, so that Redis clients and tools
+    // can be used with the server. The commands are executed just like the native ones.
+    pub fn set_resp_address(&mut self, address: SocketAddr) {
+        self.set_protocol_address(Protocol::Resp, address);
+    }
+
+    // The address the memcached listener is listening on, if it's enabled.
+    pub fn memcached_address(&self) -> Option<SocketAddr> {
+        protocol_address(&self.protocol_addresses, Protocol::Memcached)
+    }
+
+    // Enables a listener speaking memcached's text protocol, so that memcached clients can
+    // be used with the server. The values are shared with the other protocols.
+    pub fn set_memcached_address(&mut self, address: SocketAddr) {
+        self.set_protocol_address(Protocol::Memcached, address);
+    }
+
+    // The address the HTTP listener is listening on, if it's enabled.
+    pub fn http_address(&self) -> Option<SocketAddr> {
+        protocol_address(&self.protocol_addresses, Protocol::Http)
+    }
+
+    // Enables a listener serving the keyspace over HTTP with JSON bodies, under `/keys`,
+    // so that it can be used with curl and from browsers without a client library.
+    pub fn set_http_address(&mut self, address: SocketAddr) {
+        self.set_protocol_address(Protocol::Http, address);
+    }
+
+    fn set_protocol_address(&mut self, protocol: Protocol, address: SocketAddr) {
+        self.protocol_addresses
+            .retain(|(other, _)| *other != protocol);
+        self.protocol_addresses.push((protocol, address));
+    }
+
+    // Enables logging the writes to an append-only file, which is replayed when the server starts listening.
+    pub fn set_append_only(&mut self, config: AppendOnlyConfig) {
+        self.append_only = Some(config);
+    }
+
+    // Sets where the snapshots are saved and how often, by default they're only saved on demand.
+    pub fn set_snapshot(&mut self, config: SnapshotConfig) {
+        self.snapshotter = Snapshotter::new(config);
+    }
+
+    // Sets the number of threads serving the connections, each with its own event loop.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    // Sets how many bytes of responses can pile up for a client before it's disconnected.
+    pub fn set_output_buffer_limits(&mut self, limits: OutputBufferLimits) {
+        self.output_buffer_limits = limits;
+    }
+
+    // Sets how large the requests can be, the clients exceeding the limits are disconnected.
+    pub fn set_request_limits(&mut self, limits: FrameLimits) {
+        self.request_limits = limits;
+    }
+
+    // Makes the server shut down gracefully on SIGTERM and SIGINT, only supported on unix.
+    pub fn set_handle_signals(&mut self, handle_signals: bool) {
+        self.handle_signals = handle_signals;
+    }
+
+    // Applies everything that's set in the config, leaving the rest as is.
+    pub fn apply_config(&mut self, config: Config) -> Result<(), io::Error> {
+        if let Some(address) = config.address {
+            self.set_address(config::parse_address(&address)?);
         }
 
-        info!("Server listening on: {}", self.address().unwrap());
-        let mut events_store = Events::with_capacity(1024);
-        // Unique token for each connection
-        let unique_token = Token(SERVER_TOKEN.0 + 1);
+        if let Some(address) = config.resp_address {
+            self.set_resp_address(config::parse_address(&address)?);
+        }
 
-        loop {
-            debug!("Polling for events...");
-            if let Err(e) = self.poller.as_mut().unwrap().poll(&mut events_store, None) {
-                if e.kind() == io::ErrorKind::Interrupted {
-                    debug!("Polling interrupted.");
-                    continue;
-                }
+        if let Some(address) = config.memcached_address {
+            self.set_memcached_address(config::parse_address(&address)?);
+        }
+
+        if let Some(address) = config.http_address {
+            self.set_http_address(config::parse_address(&address)?);
+        }
+
+        if let Some(threads) = config.threads {
+            self.set_threads(threads);
+        }
+
+        if let Some(append_only) = config.append_only {
+            self.set_append_only(append_only);
+        }
+
+        if let Some(snapshot) = config.snapshot {
+            self.set_snapshot(snapshot);
+        }
 
-                error!("An error occurred when polling events: {}", e);
-                return Err(e);
+        if let Some(limits) = config.output_buffer {
+            self.set_output_buffer_limits(limits);
+        }
+
+        if let Some(limits) = config.request_limits {
+            self.set_request_limits(limits.into());
+        }
+
+        Ok(())
+    }
+
+    // Restores the keyspace from the append-only file if it's enabled, since it's more
+    // up to date than the snapshot. Otherwise restores it from the snapshot file.
+    fn restore(&mut self) -> Result<(), io::Error> {
+        let config = match self.append_only.as_ref() {
+            Some(config) => config,
+            None => {
+                let loaded = self.snapshotter.load_into(&self.keyspace).map_err(|e| {
+                    error!("Failed loading snapshot: {}", e);
+                    e
+                })?;
+
+                info!("Loaded {} keys from the snapshot.", loaded);
+                return Ok(());
             }
+        };
 
-            for event in events_store.iter() {
-                match event.token() {
-                    SERVER_TOKEN => {
-                        debug!("Handling server event: {:?}", event);
-                        let (mut connection, address) =
-                            match self.listener.as_ref().unwrap().accept() {
-                                Ok((connection, addr)) => (connection, addr),
-                                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
-                                    // If we get a `WouldBlock` error we know our
-                                    // listener has no more incoming connections queued,
-                                    // so we can return to polling and wait for some
-                                    // more.
-                                    debug!("No more incoming connections, returning to polling.");
-                                    break;
-                                }
-                                Err(e) => {
-                                    error!("Failed accepting connection: {}", e);
-                                    return Err(e);
-                                }
-                            };
-
-                        info!("Accepted connection from: {}", address);
-
-                        let connection_token = Token(unique_token.0 + self.connections_store.len());
-                        self.poller.as_ref().unwrap().registry().register(
-                            &mut connection,
-                            connection_token,
-                            Interest::READABLE,
-                        )?;
-
-                        // For some reason we need to reregister the listener
-                        // because if not, when there are multiple clients connected
-                        // there will be cases where one of the clients just randomly
-                        // hangs and doesn't receive any response from the server.
-                        // I don't know why this happens, but reregistering the
-                        // listener fixes it.
-                        self.poller.as_ref().unwrap().registry().reregister(
-                            self.listener.as_mut().unwrap(),
-                            SERVER_TOKEN,
-                            Interest::READABLE,
-                        )?;
-
-                        self.connections_store.insert(
-                            connection_token,
-                            Connection {
-                                connection,
-                                ip: address,
-                                payload: None,
-                            },
-                        );
-                    }
-                    token => {
-                        debug!("Handling connection event: {:?}", token);
-                        let done = match self.handle_connection_event(event) {
-                            Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
-
-                            Ok(_) => false,
-                            Err(e) if e.kind() == io::ErrorKind::Interrupted => false,
-
-                            // Connection is done only if we get the following errors.
-                            // I don't know if this is the best way to handle this. But it works.
-                            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => true,
-                            Err(e) if e.kind() == io::ErrorKind::ConnectionReset => true,
-                            Err(e) if e.kind() == io::ErrorKind::ConnectionAborted => true,
-                            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => true,
-
-                            Err(e) => return Err(e),
-                        };
-
-                        if done {
-                            if let Some(mut conn) = self.connections_store.remove(&token) {
-                                info!("Connection closed: {}", conn.ip);
-                                self.poller
-                                    .as_ref()
-                                    .unwrap()
-                                    .registry()
-                                    .deregister(&mut conn.connection)?;
-                                continue;
-                            }
-
-                            debug!("Unable to remove connection from store, returning to polling.");
-                        }
+        let commands = AppendOnlyFile::load(&config.path).map_err(|e| {
+            error!("Failed loading append-only file: {}", e);
+            e
+        })?;
+
+        info!(
+            "Replaying {} commands from the append-only file: {}",
+            commands.len(),
+            config.path.display()
+        );
+        for command in commands {
+            execute(&mut lock_shards(&self.keyspace, &command), command);
+        }
+
+        self.aof = Some(AppendOnlyFile::open(&config.path, config.fsync)?);
+        Ok(())
+    }
+
+    // Listen for incoming connections, blocking until the server is shut down.
+    // The server is bound first if it isn't already.
+    pub fn listen(self) -> Result<(), io::Error> {
+        self.start()?.run()
+    }
+
+    // Starts the server on a background thread, the server is bound first if it isn't already.
+    // Errors that happen before the server starts listening, e.g. failing to bind or to
+    // restore the keyspace, are returned right away.
+    pub fn spawn(self) -> Result<ServerHandle, io::Error> {
+        let running = self.start()?;
+        let local_addr = running.address;
+        let protocol_addrs = running.protocol_addresses.clone();
+        let shutdown = Arc::clone(&running.shared.shutdown);
+
+        let thread = thread::Builder::new()
+            .name("skaja-server".to_string())
+            .spawn(move || running.run())?;
+
+        Ok(ServerHandle {
+            local_addr,
+            protocol_addrs,
+            shutdown,
+            thread: Some(thread),
+        })
+    }
+
+    // Restores the keyspace and starts the workers other than the accepting one.
+    fn start(self) -> Result<Running, io::Error> {
+        let mut server = match self.listener {
+            Some(_) => self,
+            None => self.bind()?,
+        };
+
+        server.restore()?;
+
+        let shutdown = server.shutdown.unwrap();
+        let shared = Arc::new(Shared {
+            keyspace: server.keyspace,
+            aof: server.aof.map(Mutex::new),
+            snapshotter: Mutex::new(server.snapshotter),
+            output_buffer_limits: server.output_buffer_limits,
+            request_limits: server.request_limits,
+            shutdown,
+        });
+
+        let mut peers = Vec::with_capacity(server.threads - 1);
+        let mut peer_threads = Vec::with_capacity(server.threads - 1);
+        for id in 1..server.threads {
+            let (worker, peer) = Worker::peer(Arc::clone(&shared))?;
+            let peer_thread = thread::Builder::new()
+                .name(format!("skaja-worker-{}", id))
+                .spawn(move || {
+                    if let Err(e) = worker.run() {
+                        error!("Worker {} stopped: {}", id, e);
                     }
-                }
+                })?;
+
+            peers.push(peer);
+            peer_threads.push(peer_thread);
+        }
+
+        let mut listeners = vec![(Protocol::Native, server.listener.unwrap())];
+        listeners.extend(server.protocol_listeners);
+
+        let mut acceptor = Worker::acceptor(
+            server.poller.unwrap(),
+            listeners,
+            peers,
+            Arc::clone(&shared),
+        );
+        if server.handle_signals {
+            acceptor.handle_signals()?;
+        }
+
+        Ok(Running {
+            address: server.address.unwrap(),
+            protocol_addresses: server.protocol_addresses,
+            threads: server.threads,
+            acceptor,
+            peer_threads,
+            shared,
+        })
+    }
+}
+
+/// A server whose workers, except the accepting one, have been started.
+struct Running {
+    address: SocketAddr,
+    protocol_addresses: Vec<(Protocol, SocketAddr)>,
+    threads: usize,
+    acceptor: Worker,
+    peer_threads: Vec<JoinHandle<()>>,
+    shared: Arc<Shared>,
+}
+
+impl Running {
+    // Runs the accepting worker until the server is shut down.
+    fn run(self) -> Result<(), io::Error> {
+        info!(
+            "Server listening on: {} with {} threads",
+            self.address, self.threads
+        );
+        for (protocol, address) in &self.protocol_addresses {
+            info!("{} listening on: {}", protocol, address);
+        }
+        let result = self.acceptor.run();
+
+        // Make sure the other workers stop too if the acceptor stopped because of an error.
+        if result.is_err() {
+            self.shared.shutdown.request(ShutdownMode::NoSave)?;
+        }
+
+        for peer_thread in self.peer_threads {
+            if peer_thread.join().is_err() {
+                error!("A worker thread panicked.");
             }
         }
+
+        result?;
+        self.shared.persist_on_shutdown();
+        info!("Server shut down.");
+
+        Ok(())
     }
+}
+
+// The address of the listener speaking the protocol, if it's enabled.
+fn protocol_address(
+    addresses: &[(Protocol, SocketAddr)],
+    protocol: Protocol,
+) -> Option<SocketAddr> {
+    addresses
+        .iter()
+        .find(|(other, _)| *other == protocol)
+        .map(|(_, address)| *address)
+}
+
+// Binds a listener for the protocol and registers it to the poller.
+fn bind_listener(
+    poller: &Poll,
+    address: SocketAddr,
+    protocol: Protocol,
+) -> Result<TcpListener, io::Error> {
+    let mut listener = TcpListener::bind(address).map_err(|err| {
+        match err.kind() {
+            io::ErrorKind::AddrInUse => error!("Address already in use: {}", address),
+            _ => error!("Failed starting server: {}", err),
+        }
+        err
+    })?;
+
+    poller
+        .registry()
+        .register(&mut listener, listener_token(protocol), Interest::READABLE)
+        .inspect_err(|_| error!("Failed registering {} listener to poller.", protocol))?;
+
+    Ok(listener)
+}
 
-    fn handle_connection_event(&mut self, event: &Event) -> Result<(), io::Error> {
-        let Connection {
-            connection,
-            payload,
-            ip: _,
-        } = self
-            .connections_store
-            .get_mut(&event.token())
-            .ok_or_else(|| {
-                error!("Failed getting connection from store.");
-                io::Error::new(io::ErrorKind::Other, "Failed to get connection.")
-            })?;
-
-        if event.is_writable() {
-            debug!("Handling writable event.");
-            let response: RawResponse;
-            match payload.as_ref().unwrap() {
-                Command::Get(key) => match self.data_store.get(key) {
-                    Some(value) => {
-                        response = RawResponse::new(StatusCodes::Ok, Some(value.to_string()))
+impl Shared {
+    // Runs the command and takes care of persisting it if it modifies the keyspace.
+    fn process_command(&self, command: Command) -> Result<RawResponse, io::Error> {
+        if !command.is_write() {
+            let response = match command {
+                Command::Save => {
+                    let mut snapshotter = lock(&self.snapshotter);
+                    if snapshotter.is_saving() {
+                        return Ok(background_save_in_progress());
                     }
-                    None => response = RawResponse::new(StatusCodes::ErrNotFound, None),
+
+                    match snapshotter.save(&self.keyspace) {
+                        Ok(_) => RawResponse::new(StatusCodes::Ok, None),
+                        Err(e) => {
+                            error!("Failed saving snapshot: {}", e);
+                            let msg = format!("Failed saving snapshot: {}", e);
+                            RawResponse::new(StatusCodes::ErrInternal, Some(msg.into_bytes()))
+                        }
+                    }
+                }
+                Command::BgSave => match lock(&self.snapshotter).background_save(&self.keyspace) {
+                    true => RawResponse::new(
+                        StatusCodes::Ok,
+                        Some(b"Background saving started".to_vec()),
+                    ),
+                    false => background_save_in_progress(),
                 },
-                Command::Set(key, value) => {
-                    self.data_store.insert(key.to_string(), value.to_string());
-                    response = RawResponse::new(StatusCodes::Ok, None);
+                Command::Shutdown(mode) => {
+                    self.shutdown.request(mode)?;
+                    RawResponse::new(StatusCodes::Ok, None)
                 }
-                Command::Delete(key) => {
-                    if self.data_store.remove(key).is_none() {
-                        response = RawResponse::new(StatusCodes::ErrNotFound, None);
-                    } else {
-                        response = RawResponse::new(StatusCodes::Ok, None);
-                    }
+                Command::Hello(version) => hello(version),
+                Command::Keys(prefix) => {
+                    let keys = self.keyspace.keys(&prefix);
+                    RawResponse::new(StatusCodes::Ok, Some(encode_array(&keys)))
                 }
+                command => execute(&mut lock_shards(&self.keyspace, &command), command),
+            };
+
+            return Ok(response);
+        }
+
+        // A `getset` of a missing key still sets it, it only tells there was no old value.
+        let always_writes = matches!(command, Command::GetSet(_, _));
+        let counts_changes = counts_changes(&command);
+        let written = |response: &RawResponse| match response.status_code() {
+            _ if always_writes => true,
+            StatusCodes::Ok => !counts_changes || response.message() != Some(b"0".as_slice()),
+            _ => false,
+        };
+
+        // The shards stay locked until the command is logged, so the commands on the
+        // same key are logged in the same order as they're executed.
+        let mut shards = lock_shards(&self.keyspace, &command);
+        let (response, logged) = match self.aof.as_ref() {
+            Some(aof) => {
+                // Relative expiries are logged as absolute ones, otherwise the keys
+                // would live longer than they should when the log is replayed.
+                let mut command = with_absolute_expiry(command);
+                let request = match &command {
+                    // The versions don't survive restarts, so a compare-and-swap that went
+                    // through is logged as the set it amounted to, the same goes for the
+                    // one on the value to keep the log small.
+                    Command::Cas(key, value, _, options)
+                    | Command::CasValue(key, _, value, options) => {
+                        Request::outof(&mut Command::Set(key.clone(), value.clone(), *options))?
+                    }
+                    _ => Request::outof(&mut command)?,
+                };
+
+                // Some commands would do something else when the log is replayed, so
+                // they're logged as what they did, which is only known once executed.
+                let outcome = Outcome::of(&command);
+                let response = execute(&mut shards, command);
+                let request = match outcome {
+                    Some(outcome) => match outcome.command(&response) {
+                        Some(mut command) => Some(Request::outof(&mut command)?),
+                        None => None,
+                    },
+                    None => Some(request),
+                };
+                let logged = match request.filter(|_| written(&response)) {
+                    Some(request) => lock(aof).append(&request),
+                    None => Ok(()),
+                };
+
+                (response, logged)
+            }
+            None => (execute(&mut shards, command), Ok(())),
+        };
+        drop(shards);
+
+        if written(&response) {
+            lock(&self.snapshotter).record_write();
+        }
+
+        // The keyspace was still modified, but the client has to know the write might not
+        // survive a restart.
+        match logged {
+            Ok(_) => Ok(response),
+            Err(e) => {
+                error!("Failed writing to append-only file: {}", e);
+                let msg = format!("Failed writing to append-only file: {}", e);
+                Ok(RawResponse::new(
+                    StatusCodes::ErrInternal,
+                    Some(msg.into_bytes()),
+                ))
+            }
+        }
+    }
+
+    // Does the server-wide periodic work, meant to be called by a single worker.
+    fn tick(&self) {
+        let expired_keys = self.keyspace.expire_cycle(EXPIRE_CYCLE_MAX_KEYS);
+        if expired_keys > 0 {
+            debug!("Removed {} expired keys.", expired_keys);
+        }
+
+        if let Some(Err(e)) = self.aof.as_ref().map(|aof| lock(aof).tick()) {
+            error!("Failed fsyncing append-only file: {}", e);
+        }
+
+        lock(&self.snapshotter).tick(&self.keyspace);
+    }
+
+    // Flushes the append-only file and saves the snapshot according to the shutdown mode,
+    // meant to be called once all of the workers have stopped.
+    fn persist_on_shutdown(&self) {
+        if let Some(Err(e)) = self.aof.as_ref().map(|aof| lock(aof).sync()) {
+            error!("Failed fsyncing append-only file: {}", e);
+        }
+
+        let mut snapshotter = lock(&self.snapshotter);
+        snapshotter.wait_for_background_save();
+
+        let save = match self.shutdown.requested() {
+            Some(ShutdownMode::Save) => true,
+            Some(ShutdownMode::NoSave) => false,
+            Some(ShutdownMode::Default) | None => snapshotter.has_save_rules(),
+        };
+
+        if save {
+            info!("Saving the final snapshot.");
+            if let Err(e) = snapshotter.save(&self.keyspace) {
+                error!("Failed saving snapshot: {}", e);
             }
+        }
+    }
+}
 
-            let payload: Vec<u8> = response.into();
-            connection.write_all(&payload).map_err(|e| {
-                error!("Failed writing response: {}", e);
-                e
-            })?;
-            connection.flush()?;
+/// Whether the command replies with the number of things it changed, in which case it
+/// didn't write anything if it's 0.
+fn counts_changes(command: &Command) -> bool {
+    matches!(
+        command,
+        Command::MSetNx(_)
+            | Command::DeleteMany(_)
+            | Command::LRem(_, _, _)
+            | Command::HDel(_, _)
+            | Command::SAdd(_, _)
+            | Command::SRem(_, _)
+            | Command::ZRem(_, _)
+            | Command::XTrim(_, _)
+            | Command::XAck(_, _, _)
+    )
+}
+
+/// Lock the shards holding the keys of the command.
+fn lock_shards<'a>(keyspace: &'a Keyspace, command: &Command) -> Shards<'a> {
+    keyspace.lock_keys(command.keys())
+}
 
-            self.poller.as_ref().unwrap().registry().reregister(
-                connection,
-                event.token(),
-                Interest::READABLE,
-            )?;
+/// Execute the command against the locked shards and build the response for it.
+fn execute(shards: &mut Shards, command: Command) -> RawResponse {
+    match command {
+        Command::MGet(keys) => {
+            let values: Vec<Option<Vec<u8>>> = keys
+                .iter()
+                .map(|key| shards.store(key).get(key).ok().flatten().cloned())
+                .collect();
+            RawResponse::new(StatusCodes::Ok, Some(encode_nullable_array(&values)))
+        }
+        Command::MSet(pairs) => {
+            for (key, value) in pairs {
+                shards.store(&key).set(key, value, 0, None);
+            }
+            RawResponse::new(StatusCodes::Ok, None)
+        }
+        Command::MSetNx(pairs) => {
+            if pairs
+                .iter()
+                .any(|(key, _)| shards.store(key).contains_key(key))
+            {
+                return integer(0);
+            }
 
-            return Ok(());
+            for (key, value) in pairs {
+                shards.store(&key).set(key, value, 0, None);
+            }
+            integer(1)
+        }
+        Command::Exists(keys) => {
+            let existing = keys
+                .iter()
+                .filter(|key| shards.store(key).contains_key(key))
+                .count();
+            integer(existing as i64)
+        }
+        Command::DeleteMany(keys) => {
+            let deleted = keys
+                .iter()
+                .filter(|key| shards.store(key).remove(key).is_some())
+                .count();
+            integer(deleted as i64)
+        }
+        command @ (Command::SInter(_)
+        | Command::SUnion(_)
+        | Command::SDiff(_)
+        | Command::SInterStore(_, _)
+        | Command::SUnionStore(_, _)
+        | Command::SDiffStore(_, _)) => sets::execute_many(shards, command),
+        command @ Command::XReadGroup(_, _, _, _) => streams::execute_many(shards, command),
+        command => {
+            let store = shards.store(command.key().unwrap_or_default());
+            execute_in_store(store, command)
         }
+    }
+}
 
-        if event.is_readable() {
-            debug!("Handling readable event.");
+/// A successful response with the number as its message.
+fn integer(number: i64) -> RawResponse {
+    RawResponse::new(StatusCodes::Ok, Some(number.to_string().into_bytes()))
+}
 
-            let request = Request::outof(connection).map_err(|e| {
-                error!("Failed parsing payload to Request: {:?}", e);
-                e
-            })?;
+/// The response to a command that reached code that isn't meant to execute it, which is a
+/// bug of the server rather than something the client did wrong.
+fn misrouted(command: &Command) -> RawResponse {
+    error!("Command routed to the wrong handler: {}", command.name());
+    let msg = format!("Command can't be executed here: {}", command.name());
+    RawResponse::new(StatusCodes::ErrInternal, Some(msg.into_bytes()))
+}
 
-            let command: Command = match request.try_into() {
-                Ok(command) => command,
-                Err(err_msg) => {
-                    error!(
-                        "Payload is invalid, failed parsing Request to Command: {}",
-                        err_msg
-                    );
+/// The response to a command that doesn't work on the type of the value at the key.
+fn wrong_type() -> RawResponse {
+    let msg = b"Operation against a key holding the wrong kind of value".to_vec();
+    RawResponse::new(StatusCodes::ErrWrongType, Some(msg))
+}
 
-                    return Ok(());
+/// Execute a command on a single key against the store holding it.
+fn execute_in_store(data_store: &mut Store, command: Command) -> RawResponse {
+    match command {
+        Command::Get(key) => match data_store.get(&key) {
+            Ok(Some(value)) => RawResponse::new(StatusCodes::Ok, Some(value.clone())),
+            Ok(None) => RawResponse::new(StatusCodes::ErrNotFound, None),
+            Err(WrongType) => wrong_type(),
+        },
+        Command::Set(key, value, options) => {
+            let exists = data_store.contains_key(&key);
+            match options.condition {
+                Some(SetCondition::IfAbsent) if exists => {
+                    return RawResponse::new(StatusCodes::ErrConflict, None)
+                }
+                Some(SetCondition::IfPresent) if !exists => {
+                    return RawResponse::new(StatusCodes::ErrNotFound, None)
                 }
+                _ => {}
+            }
+
+            let expires_at = options.expiry.and_then(clock::deadline);
+            data_store.set(key, value, options.flags, expires_at);
+            RawResponse::new(StatusCodes::Ok, None)
+        }
+        Command::GetSet(key, value) => {
+            let Ok(previous) = data_store.get(&key).map(|value| value.cloned()) else {
+                return wrong_type();
             };
+            data_store.set(key, value, 0, None);
+            match previous {
+                Some(previous) => RawResponse::new(StatusCodes::Ok, Some(previous)),
+                None => RawResponse::new(StatusCodes::ErrNotFound, None),
+            }
+        }
+        Command::GetDel(key) => match data_store.get_as_mut::<Vec<u8>>(&key) {
+            Ok(Some(value)) => {
+                let value = std::mem::take(value);
+                data_store.remove(&key);
+                RawResponse::new(StatusCodes::Ok, Some(value))
+            }
+            Ok(None) => RawResponse::new(StatusCodes::ErrNotFound, None),
+            Err(WrongType) => wrong_type(),
+        },
+        Command::Gets(key) => match data_store.get_entry(&key) {
+            Some(Entry {
+                value: Value::String(value),
+                flags,
+                version,
+            }) => {
+                let versioned = Versioned {
+                    value: value.clone(),
+                    flags: *flags,
+                    version: *version,
+                };
+                RawResponse::new(StatusCodes::Ok, Some(versioned.encode()))
+            }
+            Some(_) => wrong_type(),
+            None => RawResponse::new(StatusCodes::ErrNotFound, None),
+        },
+        Command::Cas(key, value, version, options) => {
+            if data_store.get(&key).is_err() {
+                return wrong_type();
+            }
+
+            match data_store.get_entry(&key).map(|entry| entry.version) {
+                Some(current) if current == version => {
+                    let expires_at = options.expiry.and_then(clock::deadline);
+                    data_store.set(key, value, options.flags, expires_at);
+                    RawResponse::new(StatusCodes::Ok, None)
+                }
+                Some(_) => RawResponse::new(StatusCodes::ErrConflict, None),
+                None => RawResponse::new(StatusCodes::ErrNotFound, None),
+            }
+        }
+        Command::CasValue(key, expected, value, options) => match data_store.get(&key) {
+            Ok(Some(current)) if *current == expected => {
+                let expires_at = options.expiry.and_then(clock::deadline);
+                data_store.set(key, value, options.flags, expires_at);
+                RawResponse::new(StatusCodes::Ok, None)
+            }
+            Ok(Some(_)) => RawResponse::new(StatusCodes::ErrConflict, None),
+            Ok(None) => RawResponse::new(StatusCodes::ErrNotFound, None),
+            Err(WrongType) => wrong_type(),
+        },
+        Command::IncrExisting(key, delta) => incr_existing(data_store, &key, delta),
+        Command::IncrBy(key, delta) => incr_by(data_store, key, delta),
+        Command::IncrByFloat(key, delta) => incr_by_float(data_store, key, delta),
+        Command::Delete(key) => match data_store.remove(&key) {
+            Some(_) => RawResponse::new(StatusCodes::Ok, None),
+            None => RawResponse::new(StatusCodes::ErrNotFound, None),
+        },
+        Command::Expire(key, secs) => {
+            expire(data_store, &key, clock::deadline(Expiry::Seconds(secs)))
+        }
+        Command::PExpire(key, millis) => expire(
+            data_store,
+            &key,
+            clock::deadline(Expiry::Milliseconds(millis)),
+        ),
+        Command::PExpireAt(key, timestamp) => expire(
+            data_store,
+            &key,
+            clock::deadline(Expiry::UnixMillis(timestamp)),
+        ),
+        Command::Ttl(key) => match data_store.ttl(&key) {
+            // Rounded to the nearest second.
+            Some(Some(ttl)) => {
+                let secs = (ttl.as_millis() + 500) / 1000;
+                RawResponse::new(StatusCodes::Ok, Some(secs.to_string().into_bytes()))
+            }
+            Some(None) => RawResponse::new(StatusCodes::Ok, Some(b"-1".to_vec())),
+            None => RawResponse::new(StatusCodes::ErrNotFound, None),
+        },
+        Command::Persist(key) => match data_store.persist(&key) {
+            true => RawResponse::new(StatusCodes::Ok, None),
+            false => RawResponse::new(StatusCodes::ErrNotFound, None),
+        },
+        // Server commands are handled by the server.
+        command @ (Command::Save
+        | Command::BgSave
+        | Command::Shutdown(_)
+        | Command::Hello(_)
+        | Command::Keys(_)) => misrouted(&command),
+        command @ (Command::LPush(_, _)
+        | Command::RPush(_, _)
+        | Command::LPop(_, _)
+        | Command::RPop(_, _)
+        | Command::LRange(_, _, _)
+        | Command::LLen(_)
+        | Command::LIndex(_, _)
+        | Command::LSet(_, _, _)
+        | Command::LTrim(_, _, _)
+        | Command::LRem(_, _, _)) => lists::execute(data_store, command),
+        command @ (Command::HSet(_, _)
+        | Command::HGet(_, _)
+        | Command::HMGet(_, _)
+        | Command::HDel(_, _)
+        | Command::HGetAll(_)
+        | Command::HKeys(_)
+        | Command::HVals(_)
+        | Command::HLen(_)
+        | Command::HExists(_, _)
+        | Command::HIncrBy(_, _, _)) => hashes::execute(data_store, command),
+        command @ (Command::SAdd(_, _)
+        | Command::SRem(_, _)
+        | Command::SIsMember(_, _)
+        | Command::SMembers(_)
+        | Command::SCard(_)
+        | Command::SPop(_, _)
+        | Command::SRandMember(_, _)) => sets::execute(data_store, command),
+        command @ (Command::ZAdd(_, _)
+        | Command::ZRem(_, _)
+        | Command::ZScore(_, _)
+        | Command::ZIncrBy(_, _, _)
+        | Command::ZRank(_, _)
+        | Command::ZRange(_, _, _, _)
+        | Command::ZRevRange(_, _, _, _)
+        | Command::ZRangeByScore(_, _, _, _)
+        | Command::ZCard(_)) => sorted_sets::execute(data_store, command),
+        command @ (Command::XAdd(_, _, _)
+        | Command::XRange(_, _, _, _)
+        | Command::XLen(_)
+        | Command::XTrim(_, _)
+        | Command::XGroupCreate(_, _, _, _)
+        | Command::XAck(_, _, _)
+        | Command::XPending(_, _, _)
+        | Command::XClaim(_, _, _, _, _)) => streams::execute(data_store, command),
+        // Commands on several keys are handled by `execute`.
+        command @ (Command::MGet(_)
+        | Command::MSet(_)
+        | Command::MSetNx(_)
+        | Command::Exists(_)
+        | Command::DeleteMany(_)
+        | Command::SInter(_)
+        | Command::SUnion(_)
+        | Command::SDiff(_)
+        | Command::SInterStore(_, _)
+        | Command::SUnionStore(_, _)
+        | Command::SDiffStore(_, _)
+        | Command::XReadGroup(_, _, _, _)) => misrouted(&command),
+    }
+}
+
+/// Reply to the handshake with the negotiated version and what the server supports.
+fn hello(version: u32) -> RawResponse {
+    let Some(version) = negotiate_version(version) else {
+        let msg = format!(
+            "Unsupported protocol version {}, the server speaks {} to {}",
+            version, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION
+        );
+        return RawResponse::new(StatusCodes::ErrInvalidRequest, Some(msg.into_bytes()));
+    };
+
+    let hello = Hello {
+        version,
+        server: format!("skaja {}", env!("CARGO_PKG_VERSION")),
+        capabilities: CAPABILITIES.iter().map(|c| c.to_string()).collect(),
+    };
+    RawResponse::new(StatusCodes::Ok, Some(hello.encode()))
+}
+
+fn background_save_in_progress() -> RawResponse {
+    RawResponse::new(
+        StatusCodes::ErrConflict,
+        Some(b"Background saving already in progress".to_vec()),
+    )
+}
+
+fn incr_existing(data_store: &mut Store, key: &[u8], delta: i64) -> RawResponse {
+    let value = match data_store.get(key) {
+        Ok(Some(value)) => value,
+        Ok(None) => return RawResponse::new(StatusCodes::ErrNotFound, None),
+        Err(WrongType) => return wrong_type(),
+    };
+
+    let number = std::str::from_utf8(value)
+        .ok()
+        .and_then(|value| value.parse::<u64>().ok());
+    let Some(number) = number else {
+        let msg = b"Value is not an unsigned integer".to_vec();
+        return RawResponse::new(StatusCodes::ErrNotNumeric, Some(msg));
+    };
+
+    let number = match delta.is_negative() {
+        true => number.saturating_sub(delta.unsigned_abs()),
+        false => number.wrapping_add(delta as u64),
+    };
+    let number = number.to_string().into_bytes();
+    data_store.update(key, number.clone());
+    RawResponse::new(StatusCodes::Ok, Some(number))
+}
+
+fn incr_by(data_store: &mut Store, key: Vec<u8>, delta: i64) -> RawResponse {
+    let current = match data_store.get(&key) {
+        Ok(Some(value)) => std::str::from_utf8(value)
+            .ok()
+            .and_then(|value| value.parse::<i64>().ok()),
+        Ok(None) => Some(0),
+        Err(WrongType) => return wrong_type(),
+    };
+
+    let Some(number) = current.and_then(|current| current.checked_add(delta)) else {
+        let msg = b"Value is not an integer or out of range".to_vec();
+        return RawResponse::new(StatusCodes::ErrNotNumeric, Some(msg));
+    };
+
+    store_number(data_store, key, number.to_string().into_bytes())
+}
+
+fn incr_by_float(data_store: &mut Store, key: Vec<u8>, delta: f64) -> RawResponse {
+    let current = match data_store.get(&key) {
+        Ok(Some(value)) => std::str::from_utf8(value)
+            .ok()
+            .and_then(|value| value.parse::<f64>().ok())
+            .filter(|value| value.is_finite()),
+        Ok(None) => Some(0.0),
+        Err(WrongType) => return wrong_type(),
+    };
 
-            self.poller.as_mut().unwrap().registry().reregister(
-                connection,
-                event.token(),
-                Interest::WRITABLE,
-            )?;
+    let number = current
+        .map(|current| current + delta)
+        .filter(|number| number.is_finite());
+    let Some(number) = number else {
+        let msg = b"Value is not a valid float or out of range".to_vec();
+        return RawResponse::new(StatusCodes::ErrNotNumeric, Some(msg));
+    };
 
-            self.connections_store
-                .entry(event.token())
-                .and_modify(|val| {
-                    val.payload = Some(command);
-                });
+    store_number(data_store, key, number.to_string().into_bytes())
+}
+
+/// Store the result of an increment, keeping the expiry and flags of an existing key.
+fn store_number(data_store: &mut Store, key: Vec<u8>, number: Vec<u8>) -> RawResponse {
+    if !data_store.update(&key, number.clone()) {
+        data_store.set(key, number.clone(), 0, None);
+    }
+
+    RawResponse::new(StatusCodes::Ok, Some(number))
+}
+
+fn expire(data_store: &mut Store, key: &[u8], deadline: Option<Instant>) -> RawResponse {
+    let updated = match deadline {
+        Some(deadline) => data_store.set_expiry(key, deadline),
+        // The deadline is too far in the future to be represented,
+        // which practically means the key never expires.
+        None => data_store.persist(key),
+    };
+
+    match updated {
+        true => RawResponse::new(StatusCodes::Ok, None),
+        false => RawResponse::new(StatusCodes::ErrNotFound, None),
+    }
+}
+
+/// What a command did that it wouldn't do again when the log is replayed, which is logged
+/// in place of the command.
+enum Outcome {
+    /// The members popped at random from the set, logged as their removal.
+    Pop(Vec<u8>, Option<usize>),
+
+    /// The entry added with an ID made from the current time, logged with its ID.
+    Add(Vec<u8>, Vec<(Vec<u8>, Vec<u8>)>),
+
+    /// The entries claimed because they were idle long enough, logged as a claim of
+    /// those entries whatever their idle time.
+    Claim(Vec<u8>, Vec<u8>, Vec<u8>),
+}
+
+impl Outcome {
+    fn of(command: &Command) -> Option<Outcome> {
+        match command {
+            Command::SPop(key, count) => Some(Outcome::Pop(key.clone(), *count)),
+            Command::XAdd(key, None, fields) => Some(Outcome::Add(key.clone(), fields.clone())),
+            Command::XClaim(key, group, consumer, _, _) => {
+                Some(Outcome::Claim(key.clone(), group.clone(), consumer.clone()))
+            }
+            _ => None,
         }
+    }
 
-        Ok(())
+    /// The command to log given the response, None if the command did nothing.
+    fn command(self, response: &RawResponse) -> Option<Command> {
+        match self {
+            Outcome::Pop(key, count) => match sets::popped(response, count) {
+                members if members.is_empty() => None,
+                members => Some(Command::SRem(key, members)),
+            },
+            Outcome::Add(key, fields) => {
+                let id = streams::added(response)?;
+                Some(Command::XAdd(key, Some(id), fields))
+            }
+            Outcome::Claim(key, group, consumer) => match streams::claimed(response) {
+                ids if ids.is_empty() => None,
+                ids => Some(Command::XClaim(key, group, consumer, 0, ids)),
+            },
+        }
+    }
+}
+
+/// Rewrite the relative expiry of the command, if any, into an absolute one.
+fn with_absolute_expiry(command: Command) -> Command {
+    let now = clock::unix_millis_now();
+    match command {
+        Command::Set(key, value, options) => {
+            Command::Set(key, value, with_absolute_deadline(options, now))
+        }
+        Command::Cas(key, value, version, options) => {
+            Command::Cas(key, value, version, with_absolute_deadline(options, now))
+        }
+        Command::CasValue(key, expected, value, options) => {
+            Command::CasValue(key, expected, value, with_absolute_deadline(options, now))
+        }
+        Command::Expire(key, secs) => {
+            Command::PExpireAt(key, now.saturating_add(secs.saturating_mul(1000)))
+        }
+        Command::PExpire(key, millis) => Command::PExpireAt(key, now.saturating_add(millis)),
+        command => command,
     }
 }
+
+fn with_absolute_deadline(mut options: SetOptions, now: u64) -> SetOptions {
+    options.expiry = options.expiry.map(|expiry| match expiry {
+        Expiry::Seconds(secs) => Expiry::UnixMillis(now.saturating_add(secs.saturating_mul(1000))),
+        Expiry::Milliseconds(millis) => Expiry::UnixMillis(now.saturating_add(millis)),
+        Expiry::UnixMillis(timestamp) => Expiry::UnixMillis(timestamp),
+    });
+
+    options
+}
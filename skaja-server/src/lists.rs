@@ -0,0 +1,195 @@
+use crate::{
+    integer, misrouted,
+    store::{Store, WrongType},
+    wrong_type,
+};
+use skaja_lib::{encode_array, Command, RawResponse, StatusCodes};
+use std::{collections::VecDeque, ops::Range};
+
+type List = VecDeque<Vec<u8>>;
+
+/// Execute a list command against the store holding its key.
+pub(crate) fn execute(data_store: &mut Store, command: Command) -> RawResponse {
+    run(data_store, command).unwrap_or_else(|WrongType| wrong_type())
+}
+
+fn run(data_store: &mut Store, command: Command) -> Result<RawResponse, WrongType> {
+    let response = match command {
+        Command::LPush(key, values) => {
+            let list = data_store.get_or_insert_as::<List>(&key)?;
+            for value in values {
+                list.push_front(value);
+            }
+            let len = list.len();
+            data_store.changed(&key);
+            integer(len as i64)
+        }
+        Command::RPush(key, values) => {
+            let list = data_store.get_or_insert_as::<List>(&key)?;
+            list.extend(values);
+            let len = list.len();
+            data_store.changed(&key);
+            integer(len as i64)
+        }
+        Command::LPop(key, count) => pop(data_store, &key, count, List::pop_front)?,
+        Command::RPop(key, count) => pop(data_store, &key, count, List::pop_back)?,
+        Command::LRange(key, start, stop) => {
+            let items: Vec<Vec<u8>> = match data_store.get_as::<List>(&key)? {
+                Some(list) => match range(list.len(), start, stop) {
+                    Some(range) => list.range(range).cloned().collect(),
+                    None => Vec::new(),
+                },
+                None => Vec::new(),
+            };
+            RawResponse::new(StatusCodes::Ok, Some(encode_array(&items)))
+        }
+        Command::LLen(key) => {
+            let len = data_store.get_as::<List>(&key)?.map_or(0, List::len);
+            integer(len as i64)
+        }
+        Command::LIndex(key, index) => {
+            let item = data_store
+                .get_as::<List>(&key)?
+                .and_then(|list| position(list.len(), index).map(|index| list[index].clone()));
+            match item {
+                Some(item) => RawResponse::new(StatusCodes::Ok, Some(item)),
+                None => RawResponse::new(StatusCodes::ErrNotFound, None),
+            }
+        }
+        Command::LSet(key, index, value) => {
+            let Some(list) = data_store.get_as_mut::<List>(&key)? else {
+                return Ok(RawResponse::new(StatusCodes::ErrNotFound, None));
+            };
+
+            match position(list.len(), index) {
+                Some(index) => {
+                    list[index] = value;
+                    data_store.changed(&key);
+                    RawResponse::new(StatusCodes::Ok, None)
+                }
+                None => RawResponse::new(
+                    StatusCodes::ErrInvalidRequest,
+                    Some(b"Index out of range".to_vec()),
+                ),
+            }
+        }
+        Command::LTrim(key, start, stop) => {
+            if let Some(list) = data_store.get_as_mut::<List>(&key)? {
+                let len = list.len();
+                match range(len, start, stop) {
+                    Some(range) => {
+                        list.truncate(range.end);
+                        list.drain(..range.start);
+                    }
+                    None => list.clear(),
+                }
+                // Keeping the whole list changes nothing.
+                if list.len() != len {
+                    data_store.changed(&key);
+                }
+            }
+            RawResponse::new(StatusCodes::Ok, None)
+        }
+        Command::LRem(key, count, value) => {
+            let Some(list) = data_store.get_as_mut::<List>(&key)? else {
+                return Ok(integer(0));
+            };
+
+            let limit = match count {
+                0 => usize::MAX,
+                count => usize::try_from(count.unsigned_abs()).unwrap_or(usize::MAX),
+            };
+            let removed = match count.is_negative() {
+                true => remove_from_tail(list, &value, limit),
+                false => remove_from_head(list, &value, limit),
+            };
+            if removed > 0 {
+                data_store.changed(&key);
+            }
+            integer(removed as i64)
+        }
+        // Only list commands are handled here.
+        command => misrouted(&command),
+    };
+
+    Ok(response)
+}
+
+/// Pop one item, or at most `count` items if it's given, with the given function.
+fn pop(
+    data_store: &mut Store,
+    key: &[u8],
+    count: Option<usize>,
+    pop: fn(&mut List) -> Option<Vec<u8>>,
+) -> Result<RawResponse, WrongType> {
+    let Some(list) = data_store.get_as_mut::<List>(key)? else {
+        return Ok(RawResponse::new(StatusCodes::ErrNotFound, None));
+    };
+
+    let len = list.len();
+    let response = match count {
+        None => RawResponse::new(StatusCodes::Ok, pop(list)),
+        Some(count) => {
+            let items: Vec<Vec<u8>> = (0..count).map_while(|_| pop(list)).collect();
+            RawResponse::new(StatusCodes::Ok, Some(encode_array(&items)))
+        }
+    };
+    if list.len() != len {
+        data_store.changed(key);
+    }
+
+    Ok(response)
+}
+
+fn remove_from_head(list: &mut List, value: &[u8], limit: usize) -> usize {
+    let mut removed = 0;
+    list.retain(|item| {
+        let remove = removed < limit && item == value;
+        removed += remove as usize;
+        !remove
+    });
+    removed
+}
+
+fn remove_from_tail(list: &mut List, value: &[u8], limit: usize) -> usize {
+    let mut removed = 0;
+    let mut index = list.len();
+    while index > 0 && removed < limit {
+        index -= 1;
+        if list[index] == value {
+            list.remove(index);
+            removed += 1;
+        }
+    }
+    removed
+}
+
+/// The position of the index in a list of the given length, a negative index counting
+/// from the tail. None if it's out of range.
+fn position(len: usize, index: i64) -> Option<usize> {
+    let index = match index.is_negative() {
+        true => (len as i64).checked_add(index)?,
+        false => index,
+    };
+    usize::try_from(index).ok().filter(|index| *index < len)
+}
+
+/// The positions between the indexes in a list of the given length, both included and
+/// clamped to the list, negative indexes counting from the tail. None if there are none.
+pub(crate) fn range(len: usize, start: i64, stop: i64) -> Option<Range<usize>> {
+    let len = len as i64;
+    let start = match start.is_negative() {
+        true => (len + start).max(0),
+        false => start,
+    };
+    let stop = match stop.is_negative() {
+        true => len + stop,
+        false => stop.min(len - 1),
+    };
+
+    if start > stop || start >= len {
+        return None;
+    }
+
+    Some(start as usize..stop as usize + 1)
+}
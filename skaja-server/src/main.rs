@@ -1,8 +1,8 @@
 use clap::Parser;
-use skaja_server::config::Config;
+use skaja_server::config::{self, Config};
 pub use skaja_server::Server;
-use std::{fs, io, net::SocketAddr, path::PathBuf};
-use tracing::{error, Level};
+use std::{io, path::PathBuf};
+use tracing::Level;
 use tracing_subscriber::FmtSubscriber;
 
 #[derive(clap::Parser)]
@@ -11,6 +11,22 @@ pub struct Args {
     #[arg(short, long)]
     address: Option<String>,
 
+    // The address to accept RESP connections on, e.g. from redis-cli. Disabled by default.
+    #[arg(long, value_name = "ADDRESS")]
+    resp_address: Option<String>,
+
+    // The address to accept memcached text protocol connections on. Disabled by default.
+    #[arg(long, value_name = "ADDRESS")]
+    memcached_address: Option<String>,
+
+    // The address to serve the keyspace over HTTP on, e.g. for curl. Disabled by default.
+    #[arg(long, value_name = "ADDRESS")]
+    http_address: Option<String>,
+
+    // The number of threads serving the connections, defaults to 1.
+    #[arg(short, long)]
+    threads: Option<usize>,
+
     // The absolute path to the config file. Only supports TOML.
     #[arg(long, value_name = "PATH")]
     config: Option<PathBuf>,
@@ -27,45 +43,34 @@ fn main() -> Result<(), io::Error> {
     let args = Args::parse();
 
     let mut server = Server::default();
-    let mut address = "127.0.0.1:8080".to_owned();
+    server.set_address(config::parse_address("127.0.0.1:8080")?);
 
     if let Some(config_path) = args.config {
-        let config_file = fs::read_to_string(config_path).unwrap_or_else(|_| {
-            error!(
-                "Failed reading config file at {}, make sure the file exists.",
-                address
-            );
-            std::process::exit(-1);
-        });
-
-        let config: Config = toml::from_str(&config_file).unwrap_or_else(|_| {
-            error!("Failed parsing config file, check if it's valid.");
-            std::process::exit(-1);
-        });
-
-        if let Some(addr) = config.address {
-            address = addr;
-        }
+        server.apply_config(Config::load(&config_path)?)?;
     }
 
-    if let Some(addr) = args.address {
-        address = addr;
+    if let Some(address) = args.address {
+        server.set_address(config::parse_address(&address)?);
     }
 
-    let address: SocketAddr = address.parse().unwrap_or_else(|e| {
-        let mut message = "Failed parsing target address".to_string();
-        if address.contains("localhost") {
-            message = format!(r#"{}, use "127.0.0.1" instead of "localhost"."#, message);
-        } else {
-            message = format!(r#"{}, {}."#, message, e);
-        }
+    if let Some(address) = args.resp_address {
+        server.set_resp_address(config::parse_address(&address)?);
+    }
 
-        error!(message);
-        std::process::exit(-1);
-    });
+    if let Some(address) = args.memcached_address {
+        server.set_memcached_address(config::parse_address(&address)?);
+    }
+
+    if let Some(address) = args.http_address {
+        server.set_http_address(config::parse_address(&address)?);
+    }
+
+    if let Some(threads) = args.threads {
+        server.set_threads(threads);
+    }
 
-    server.set_address(address);
-    server.bind().listen().expect("Server shuts down.");
+    server.set_handle_signals(true);
+    server.bind()?.listen()?;
 
     Ok(())
 }
@@ -0,0 +1,251 @@
+use crate::codec::{Incoming, Reply};
+use mio::net::TcpStream;
+use skaja_lib::{
+    Command, Expiry, FrameError, FrameLimits, MemcachedDecoder, MemcachedRequest, RawResponse,
+    Response, SetOptions, StatusCodes, Versioned,
+};
+use std::{collections::VecDeque, io};
+
+/// Expiration times up to 30 days are relative, longer ones are unix timestamps.
+const MAX_RELATIVE_EXPTIME: i64 = 60 * 60 * 24 * 30;
+
+const BAD_FORMAT: &[u8] = b"CLIENT_ERROR bad command line format\r\n";
+
+/// How the response to a command is answered in memcached's text protocol.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum MemcachedReply {
+    /// `VALUE <key> <flags> <bytes> [<cas unique>]` followed by the data block,
+    /// nothing if the key doesn't exist.
+    Value { key: Vec<u8>, with_cas: bool },
+    /// `STORED`, `EXISTS` if the cas unique didn't match, or `NOT_FOUND`.
+    Stored,
+    /// `DELETED` or `NOT_FOUND`.
+    Deleted,
+    /// `TOUCHED` or `NOT_FOUND`.
+    Touched,
+    /// The new value of the counter or `NOT_FOUND`.
+    Number,
+    /// Nothing, the client asked for `noreply`.
+    Silent,
+}
+
+impl MemcachedReply {
+    pub(crate) fn encode(self, response: RawResponse) -> Vec<u8> {
+        let response = Response::from(response);
+        let status_code = response.status_code();
+        let msg = response.message().unwrap_or_default();
+
+        match (self, status_code) {
+            (MemcachedReply::Silent, _) => Vec::new(),
+            (MemcachedReply::Value { key, with_cas }, StatusCodes::Ok) => {
+                let Ok(versioned) = Versioned::decode(msg) else {
+                    return b"SERVER_ERROR malformed value\r\n".to_vec();
+                };
+
+                let mut out = b"VALUE ".to_vec();
+                out.extend_from_slice(&key);
+                out.extend_from_slice(
+                    format!(" {} {}", versioned.flags, versioned.value.len()).as_bytes(),
+                );
+                if with_cas {
+                    out.extend_from_slice(format!(" {}", versioned.version).as_bytes());
+                }
+                out.extend_from_slice(b"\r\n");
+                out.extend_from_slice(&versioned.value);
+                out.extend_from_slice(b"\r\n");
+                out
+            }
+            // The keys holding something else than a string, e.g. a list, are misses too.
+            (
+                MemcachedReply::Value { .. },
+                StatusCodes::ErrNotFound | StatusCodes::ErrWrongType,
+            ) => Vec::new(),
+            (MemcachedReply::Stored, StatusCodes::Ok) => b"STORED\r\n".to_vec(),
+            (MemcachedReply::Stored, StatusCodes::ErrConflict) => b"EXISTS\r\n".to_vec(),
+            (MemcachedReply::Deleted, StatusCodes::Ok) => b"DELETED\r\n".to_vec(),
+            (MemcachedReply::Touched, StatusCodes::Ok) => b"TOUCHED\r\n".to_vec(),
+            (MemcachedReply::Number, StatusCodes::Ok) => [msg, b"\r\n"].concat(),
+            (MemcachedReply::Number, StatusCodes::ErrNotNumeric) => {
+                b"CLIENT_ERROR cannot increment or decrement non-numeric value\r\n".to_vec()
+            }
+            (_, StatusCodes::ErrNotFound) => b"NOT_FOUND\r\n".to_vec(),
+            (_, status_code) => {
+                let msg = match msg.is_empty() {
+                    true => status_code.to_string(),
+                    false => String::from_utf8_lossy(msg).into_owned(),
+                };
+                format!("SERVER_ERROR {}\r\n", msg).into_bytes()
+            }
+        }
+    }
+
+    pub(crate) fn encode_frame_error(err: &FrameError) -> Vec<u8> {
+        format!("CLIENT_ERROR {}\r\n", err).into_bytes()
+    }
+}
+
+/// Speaks memcached's text protocol, translating its commands into [`Command`]s.
+///
+/// A `get` with multiple keys is split into a command per key, followed by the `END`
+/// that closes the reply.
+pub(crate) struct MemcachedCodec {
+    decoder: MemcachedDecoder,
+
+    /// The rest of the split up request that's being yielded.
+    pending: VecDeque<Incoming>,
+}
+
+impl MemcachedCodec {
+    pub(crate) fn new(limits: FrameLimits) -> Self {
+        Self {
+            decoder: MemcachedDecoder::with_limits(limits),
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn read_from(&mut self, connection: &mut TcpStream) -> Result<usize, io::Error> {
+        self.decoder.read_from(connection)
+    }
+
+    pub(crate) fn is_full(&self) -> bool {
+        self.decoder.is_full()
+    }
+
+    pub(crate) fn decode(&mut self) -> Result<Option<Incoming>, FrameError> {
+        if let Some(incoming) = self.pending.pop_front() {
+            return Ok(Some(incoming));
+        }
+
+        let Some(request) = self.decoder.decode()? else {
+            return Ok(None);
+        };
+
+        Ok(Some(self.dispatch(request)))
+    }
+
+    fn dispatch(&mut self, request: MemcachedRequest) -> Incoming {
+        let MemcachedRequest { mut args, data } = request;
+        let name = args.remove(0);
+        let noreply = args.last().is_some_and(|arg| arg == b"noreply");
+        if noreply {
+            args.pop();
+        }
+
+        let reply = |reply: MemcachedReply| match noreply {
+            true => Reply::Memcached(MemcachedReply::Silent),
+            false => Reply::Memcached(reply),
+        };
+
+        // Errors are sent even when asked for no reply, the same as memcached does.
+        match name.as_slice() {
+            b"get" | b"gets" => self.get(args, name == b"gets"),
+            b"set" | b"cas" => match storage_command(args, data, name == b"cas") {
+                Some(command) => Incoming::Command(command, reply(MemcachedReply::Stored)),
+                None => Incoming::Reply(BAD_FORMAT.to_vec()),
+            },
+            b"delete" => match <[Vec<u8>; 1]>::try_from(args) {
+                Ok([key]) => {
+                    Incoming::Command(Command::Delete(key), reply(MemcachedReply::Deleted))
+                }
+                Err(_) => Incoming::Reply(BAD_FORMAT.to_vec()),
+            },
+            b"incr" | b"decr" => match counter_command(args, name == b"decr") {
+                Ok(command) => Incoming::Command(command, reply(MemcachedReply::Number)),
+                Err(err) => Incoming::Reply(err),
+            },
+            b"touch" => match touch_command(args) {
+                Some(command) => Incoming::Command(command, reply(MemcachedReply::Touched)),
+                None => Incoming::Reply(BAD_FORMAT.to_vec()),
+            },
+            b"version" => Incoming::Reply(
+                format!("VERSION skaja {}\r\n", env!("CARGO_PKG_VERSION")).into_bytes(),
+            ),
+            b"quit" => Incoming::Close(Vec::new()),
+            _ => Incoming::Reply(b"ERROR\r\n".to_vec()),
+        }
+    }
+
+    // Splits the retrieval into a command per key, the `END` is sent after the last one.
+    fn get(&mut self, keys: Vec<Vec<u8>>, with_cas: bool) -> Incoming {
+        if keys.is_empty() {
+            return Incoming::Reply(b"ERROR\r\n".to_vec());
+        }
+
+        for key in keys {
+            let reply = Reply::Memcached(MemcachedReply::Value {
+                key: key.clone(),
+                with_cas,
+            });
+            self.pending
+                .push_back(Incoming::Command(Command::Gets(key), reply));
+        }
+        self.pending.push_back(Incoming::Reply(b"END\r\n".to_vec()));
+
+        self.pending.pop_front().unwrap()
+    }
+}
+
+// `set <key> <flags> <exptime> <bytes>` and `cas <key> <flags> <exptime> <bytes> <cas unique>`.
+fn storage_command(args: Vec<Vec<u8>>, data: Option<Vec<u8>>, cas: bool) -> Option<Command> {
+    let expected = if cas { 5 } else { 4 };
+    if args.len() != expected {
+        return None;
+    }
+
+    let mut args = args.into_iter();
+    let key = args.next()?;
+    let options = SetOptions {
+        flags: parse(&args.next()?)?,
+        expiry: expiry(parse(&args.next()?)?),
+        ..Default::default()
+    };
+    // The length of the data block, which the decoder already took care of.
+    args.next()?;
+    let value = data?;
+
+    match cas {
+        true => Some(Command::Cas(key, value, parse(&args.next()?)?, options)),
+        false => Some(Command::Set(key, value, options)),
+    }
+}
+
+// `incr <key> <value>` and `decr <key> <value>`.
+fn counter_command(args: Vec<Vec<u8>>, decr: bool) -> Result<Command, Vec<u8>> {
+    let [key, delta] = <[Vec<u8>; 2]>::try_from(args).map_err(|_| BAD_FORMAT.to_vec())?;
+    let delta: i64 =
+        parse(&delta).ok_or_else(|| b"CLIENT_ERROR invalid numeric delta argument\r\n".to_vec())?;
+    if delta < 0 {
+        return Err(b"CLIENT_ERROR invalid numeric delta argument\r\n".to_vec());
+    }
+
+    let delta = if decr { -delta } else { delta };
+    Ok(Command::IncrExisting(key, delta))
+}
+
+// `touch <key> <exptime>`.
+fn touch_command(args: Vec<Vec<u8>>) -> Option<Command> {
+    let [key, exptime] = <[Vec<u8>; 2]>::try_from(args).ok()?;
+    let command = match expiry(parse(&exptime)?) {
+        None => Command::Persist(key),
+        Some(Expiry::Seconds(secs)) => Command::Expire(key, secs),
+        Some(Expiry::Milliseconds(millis)) => Command::PExpire(key, millis),
+        Some(Expiry::UnixMillis(timestamp)) => Command::PExpireAt(key, timestamp),
+    };
+
+    Some(command)
+}
+
+/// The expiry of memcached's expiration time: 0 never expires, up to 30 days it's relative
+/// in seconds, beyond that it's a unix timestamp in seconds, and negative ones expire right away.
+fn expiry(exptime: i64) -> Option<Expiry> {
+    match exptime {
+        0 => None,
+        exptime if exptime < 0 => Some(Expiry::UnixMillis(1)),
+        exptime if exptime <= MAX_RELATIVE_EXPTIME => Some(Expiry::Seconds(exptime as u64)),
+        exptime => Some(Expiry::UnixMillis((exptime as u64).saturating_mul(1000))),
+    }
+}
+
+fn parse<T: std::str::FromStr>(arg: &[u8]) -> Option<T> {
+    std::str::from_utf8(arg).ok()?.parse().ok()
+}
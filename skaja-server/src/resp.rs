@@ -0,0 +1,418 @@
+use crate::codec::{Incoming, Reply};
+use mio::net::TcpStream;
+use skaja_lib::{
+    decode_streams, Command, CommandError, FrameError, FrameLimits, PendingEntry, PendingSummary,
+    RawResponse, RespDecoder, RespValue, RespVersion, Response, StatusCodes, StreamEntry, StreamId,
+    Versioned,
+};
+use std::io;
+
+/// How the response to a command is shaped in RESP, the same as the Redis command would
+/// reply, since the native responses don't say whether their message is a number or a value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ReplyKind {
+    /// The value, or null if the key doesn't exist.
+    Bulk,
+    /// A simple `OK`.
+    Status,
+    /// A simple `OK`, or null if the condition of the write wasn't met.
+    Conditional,
+    /// 1 if the command did something, 0 if the key doesn't exist.
+    Flag,
+    /// The time to live as a number, -2 if the key doesn't exist.
+    Ttl,
+    /// The message of the response as a simple string.
+    Text,
+    /// The message of the response as a number.
+    Integer,
+    /// A [`skaja_lib::Versioned`] as an array of the value, the flags and the version.
+    Versioned,
+    /// The items of the message as an array, the nil ones as null, see
+    /// [`skaja_lib::encode_nullable_array`].
+    Array,
+    /// Stream entries as an array of their ID and the array of their fields and values,
+    /// see [`StreamEntry::encode_many`].
+    Entries,
+    /// The entries read from streams as an array of each key and its entries, see
+    /// [`skaja_lib::encode_streams`].
+    Streams,
+    /// A [`PendingSummary`] as an array of the count, the lowest and the highest IDs and
+    /// the consumers with their counts, the IDs and the consumers null if there are none.
+    PendingSummary,
+    /// [`PendingEntry`]s as an array of their ID, consumer, idle time and deliveries.
+    Pending,
+}
+
+impl ReplyKind {
+    pub(crate) fn of(command: &Command) -> Self {
+        match command {
+            Command::Get(_)
+            | Command::GetSet(_, _)
+            | Command::GetDel(_)
+            | Command::Hello(_)
+            | Command::IncrByFloat(_, _)
+            | Command::LPop(_, None)
+            | Command::RPop(_, None)
+            | Command::LIndex(_, _)
+            | Command::HGet(_, _)
+            | Command::SPop(_, None)
+            | Command::SRandMember(_, None)
+            | Command::ZScore(_, _)
+            | Command::ZIncrBy(_, _, _)
+            | Command::XAdd(_, _, _) => ReplyKind::Bulk,
+            Command::Set(_, _, _) | Command::CasValue(_, _, _, _) => ReplyKind::Conditional,
+            Command::Cas(_, _, _, _)
+            | Command::MSet(_)
+            | Command::LSet(_, _, _)
+            | Command::LTrim(_, _, _)
+            | Command::XGroupCreate(_, _, _, _)
+            | Command::Save
+            | Command::Shutdown(_) => ReplyKind::Status,
+            Command::Delete(_)
+            | Command::Expire(_, _)
+            | Command::PExpire(_, _)
+            | Command::PExpireAt(_, _)
+            | Command::Persist(_)
+            | Command::HExists(_, _)
+            | Command::SIsMember(_, _) => ReplyKind::Flag,
+            Command::Ttl(_) => ReplyKind::Ttl,
+            Command::BgSave => ReplyKind::Text,
+            Command::IncrExisting(_, _)
+            | Command::IncrBy(_, _)
+            | Command::MSetNx(_)
+            | Command::Exists(_)
+            | Command::DeleteMany(_)
+            | Command::LPush(_, _)
+            | Command::RPush(_, _)
+            | Command::LLen(_)
+            | Command::LRem(_, _, _)
+            | Command::HSet(_, _)
+            | Command::HDel(_, _)
+            | Command::HLen(_)
+            | Command::HIncrBy(_, _, _)
+            | Command::SAdd(_, _)
+            | Command::SRem(_, _)
+            | Command::SCard(_)
+            | Command::SInterStore(_, _)
+            | Command::SUnionStore(_, _)
+            | Command::SDiffStore(_, _)
+            | Command::ZAdd(_, _)
+            | Command::ZRem(_, _)
+            | Command::ZRank(_, _)
+            | Command::ZCard(_)
+            | Command::XLen(_)
+            | Command::XTrim(_, _)
+            | Command::XAck(_, _, _) => ReplyKind::Integer,
+            Command::Gets(_) => ReplyKind::Versioned,
+            Command::Keys(_)
+            | Command::MGet(_)
+            | Command::LPop(_, Some(_))
+            | Command::RPop(_, Some(_))
+            | Command::LRange(_, _, _)
+            | Command::HMGet(_, _)
+            | Command::HGetAll(_)
+            | Command::HKeys(_)
+            | Command::HVals(_)
+            | Command::SMembers(_)
+            | Command::SPop(_, Some(_))
+            | Command::SRandMember(_, Some(_))
+            | Command::SInter(_)
+            | Command::SUnion(_)
+            | Command::SDiff(_)
+            | Command::ZRange(_, _, _, _)
+            | Command::ZRevRange(_, _, _, _)
+            | Command::ZRangeByScore(_, _, _, _) => ReplyKind::Array,
+            Command::XRange(_, _, _, _) | Command::XClaim(_, _, _, _, _) => ReplyKind::Entries,
+            Command::XReadGroup(_, _, _, _) => ReplyKind::Streams,
+            Command::XPending(_, _, None) => ReplyKind::PendingSummary,
+            Command::XPending(_, _, Some(_)) => ReplyKind::Pending,
+        }
+    }
+}
+
+/// Speaks RESP2, or RESP3 once the client switches to it with `HELLO 3`.
+///
+/// The commands that only make sense in RESP, e.g. `PING` and `HELLO`, are answered by the
+/// codec itself, the rest are parsed with [`Command::from_parts`] like the native requests.
+pub(crate) struct RespCodec {
+    decoder: RespDecoder,
+    version: RespVersion,
+}
+
+impl RespCodec {
+    pub(crate) fn new(limits: FrameLimits) -> Self {
+        Self {
+            decoder: RespDecoder::with_limits(limits),
+            version: RespVersion::Resp2,
+        }
+    }
+
+    pub(crate) fn read_from(&mut self, connection: &mut TcpStream) -> Result<usize, io::Error> {
+        self.decoder.read_from(connection)
+    }
+
+    pub(crate) fn is_full(&self) -> bool {
+        self.decoder.is_full()
+    }
+
+    pub(crate) fn decode(&mut self) -> Result<Option<Incoming>, FrameError> {
+        let Some(parts) = self.decoder.decode()? else {
+            return Ok(None);
+        };
+
+        Ok(Some(self.dispatch(parts)))
+    }
+
+    pub(crate) fn encode(&self, kind: ReplyKind, response: RawResponse) -> Vec<u8> {
+        let response = Response::from(response);
+        let value = match (response.status_code(), kind) {
+            (StatusCodes::Ok, ReplyKind::Bulk) => {
+                RespValue::Bulk(response.message().unwrap_or_default().to_vec())
+            }
+            (StatusCodes::Ok, ReplyKind::Status | ReplyKind::Conditional) => {
+                RespValue::Simple("OK".to_string())
+            }
+            (StatusCodes::Ok, ReplyKind::Flag) => RespValue::Integer(1),
+            (StatusCodes::Ok, ReplyKind::Ttl) => {
+                let ttl = String::from_utf8_lossy(response.message().unwrap_or_default());
+                RespValue::Integer(ttl.parse().unwrap_or(-1))
+            }
+            (StatusCodes::Ok, ReplyKind::Text) => RespValue::Simple(
+                String::from_utf8_lossy(response.message().unwrap_or_default()).into_owned(),
+            ),
+            (StatusCodes::Ok, ReplyKind::Integer) => {
+                let msg = response.message().unwrap_or_default();
+                let number = std::str::from_utf8(msg).ok().and_then(|n| n.parse().ok());
+                match number {
+                    Some(number) => RespValue::Integer(number),
+                    // Out of the range of RESP's integers.
+                    None => RespValue::Bulk(msg.to_vec()),
+                }
+            }
+            (StatusCodes::Ok, ReplyKind::Versioned) => {
+                match Versioned::decode(response.message().unwrap_or_default()) {
+                    Ok(versioned) => RespValue::Array(vec![
+                        RespValue::Bulk(versioned.value),
+                        RespValue::Integer(versioned.flags.into()),
+                        RespValue::Bulk(versioned.version.to_string().into_bytes()),
+                    ]),
+                    Err(e) => error(StatusCodes::ErrInternal, Some(e.as_bytes())),
+                }
+            }
+            (StatusCodes::Ok, ReplyKind::Array) => match response.nullable_array() {
+                Ok(items) => RespValue::Array(
+                    items
+                        .into_iter()
+                        .map(|item| item.map_or(RespValue::Null, RespValue::Bulk))
+                        .collect(),
+                ),
+                Err(e) => error(StatusCodes::ErrInternal, Some(e.as_bytes())),
+            },
+            (StatusCodes::Ok, ReplyKind::Entries) => {
+                match StreamEntry::decode_many(response.message().unwrap_or_default()) {
+                    Ok(entries) => entries_value(entries),
+                    Err(e) => error(StatusCodes::ErrInternal, Some(e.as_bytes())),
+                }
+            }
+            (StatusCodes::Ok, ReplyKind::Streams) => {
+                match decode_streams(response.message().unwrap_or_default()) {
+                    Ok(streams) => RespValue::Array(
+                        streams
+                            .into_iter()
+                            .map(|(key, entries)| {
+                                RespValue::Array(vec![RespValue::Bulk(key), entries_value(entries)])
+                            })
+                            .collect(),
+                    ),
+                    Err(e) => error(StatusCodes::ErrInternal, Some(e.as_bytes())),
+                }
+            }
+            (StatusCodes::Ok, ReplyKind::PendingSummary) => {
+                match PendingSummary::decode(response.message().unwrap_or_default()) {
+                    Ok(summary) => pending_summary_value(summary),
+                    Err(e) => error(StatusCodes::ErrInternal, Some(e.as_bytes())),
+                }
+            }
+            (StatusCodes::Ok, ReplyKind::Pending) => {
+                match PendingEntry::decode_many(response.message().unwrap_or_default()) {
+                    Ok(entries) => RespValue::Array(
+                        entries
+                            .into_iter()
+                            .map(|entry| {
+                                RespValue::Array(vec![
+                                    RespValue::Bulk(entry.id.to_string().into_bytes()),
+                                    RespValue::Bulk(entry.consumer),
+                                    RespValue::Integer(entry.idle as i64),
+                                    RespValue::Integer(entry.deliveries as i64),
+                                ])
+                            })
+                            .collect(),
+                    ),
+                    Err(e) => error(StatusCodes::ErrInternal, Some(e.as_bytes())),
+                }
+            }
+            (StatusCodes::ErrNotFound, ReplyKind::Flag) => RespValue::Integer(0),
+            (StatusCodes::ErrNotFound, ReplyKind::Ttl) => RespValue::Integer(-2),
+            (StatusCodes::ErrNotFound, _) => RespValue::Null,
+            (StatusCodes::ErrConflict, ReplyKind::Conditional) => RespValue::Null,
+            (status_code, _) => error(status_code, response.message()),
+        };
+
+        self.reply(value)
+    }
+
+    pub(crate) fn encode_frame_error(&self, err: &FrameError) -> Vec<u8> {
+        self.reply(RespValue::Error(format!("ERR {}", err)))
+    }
+
+    fn reply(&self, value: RespValue) -> Vec<u8> {
+        let mut out = Vec::new();
+        value.encode(self.version, &mut out);
+        out
+    }
+
+    // Answers the commands that only exist in RESP, and parses the rest into a `Command`.
+    fn dispatch(&mut self, parts: Vec<Vec<u8>>) -> Incoming {
+        let name = parts[0].to_ascii_lowercase();
+        let value = match (name.as_slice(), parts.len()) {
+            (b"ping", 1) => RespValue::Simple("PONG".to_string()),
+            (b"ping", 2) | (b"echo", 2) => RespValue::Bulk(parts[1].clone()),
+            (b"hello", _) => self.hello(&parts[1..]),
+            (b"quit", _) => return Incoming::Close(self.reply(RespValue::Simple("OK".into()))),
+            (b"select", 2) if parts[1] == b"0" => RespValue::Simple("OK".to_string()),
+            (b"select", 2) => RespValue::Error("ERR DB index is out of range".to_string()),
+            // Clients ask for the commands' docs and set their name when they connect,
+            // neither of which matters to skaja.
+            (b"command", _) => RespValue::Array(Vec::new()),
+            (b"client", _) => RespValue::Simple("OK".to_string()),
+            (b"keys", 2) => match keys_prefix(&parts[1]) {
+                Some(prefix) => {
+                    let command = Command::Keys(prefix.to_vec());
+                    return Incoming::Command(command, Reply::Resp(ReplyKind::Array));
+                }
+                None => RespValue::Error("ERR only prefix patterns are supported".to_string()),
+            },
+            (b"ping" | b"echo" | b"select", _) => {
+                let err = CommandError::WrongArity(format!(
+                    "\"{}\" command got the wrong number of arguments",
+                    String::from_utf8_lossy(&name)
+                ));
+                command_error(&err)
+            }
+            _ => match Command::from_parts(parts) {
+                Ok(command) => {
+                    let kind = ReplyKind::of(&command);
+                    return Incoming::Command(command, Reply::Resp(kind));
+                }
+                Err(err) => command_error(&err),
+            },
+        };
+
+        Incoming::Reply(self.reply(value))
+    }
+
+    // Switches the protocol version if asked to, and replies with what the server is.
+    fn hello(&mut self, args: &[Vec<u8>]) -> RespValue {
+        if let Some(version) = args.first() {
+            self.version = match version.as_slice() {
+                b"2" => RespVersion::Resp2,
+                b"3" => RespVersion::Resp3,
+                _ => return RespValue::Error("NOPROTO unsupported protocol version".into()),
+            };
+        }
+
+        let proto = match self.version {
+            RespVersion::Resp2 => 2,
+            RespVersion::Resp3 => 3,
+        };
+        let field = |name: &str| RespValue::Bulk(name.as_bytes().to_vec());
+        RespValue::Map(vec![
+            (field("server"), field("skaja")),
+            (field("version"), field(env!("CARGO_PKG_VERSION"))),
+            (field("proto"), RespValue::Integer(proto)),
+            (field("mode"), field("standalone")),
+            (field("role"), field("master")),
+            (field("modules"), RespValue::Array(Vec::new())),
+        ])
+    }
+}
+
+/// The prefix of a `KEYS` pattern, if it only matches by prefix, e.g. `*` or `user:*`.
+fn keys_prefix(pattern: &[u8]) -> Option<&[u8]> {
+    let prefix = pattern.strip_suffix(b"*")?;
+    let wildcard = prefix
+        .iter()
+        .any(|byte| matches!(byte, b'*' | b'?' | b'[' | b'\\'));
+    (!wildcard).then_some(prefix)
+}
+
+fn command_error(err: &CommandError) -> RespValue {
+    error(err.status_code(), Some(err.to_string().as_bytes()))
+}
+
+/// Stream entries as Redis replies with them, see [`ReplyKind::Entries`].
+fn entries_value(entries: Vec<StreamEntry>) -> RespValue {
+    RespValue::Array(
+        entries
+            .into_iter()
+            .map(|entry| {
+                let fields = entry
+                    .fields
+                    .into_iter()
+                    .flat_map(|(field, value)| [RespValue::Bulk(field), RespValue::Bulk(value)])
+                    .collect();
+                RespValue::Array(vec![
+                    RespValue::Bulk(entry.id.to_string().into_bytes()),
+                    RespValue::Array(fields),
+                ])
+            })
+            .collect(),
+    )
+}
+
+fn pending_summary_value(summary: PendingSummary) -> RespValue {
+    let id = |id: StreamId| RespValue::Bulk(id.to_string().into_bytes());
+    let (min, max) = match summary.range {
+        Some((min, max)) => (id(min), id(max)),
+        None => (RespValue::Null, RespValue::Null),
+    };
+    let consumers = match summary.consumers.is_empty() {
+        true => RespValue::Null,
+        false => RespValue::Array(
+            summary
+                .consumers
+                .into_iter()
+                .map(|(consumer, count)| {
+                    RespValue::Array(vec![
+                        RespValue::Bulk(consumer),
+                        RespValue::Bulk(count.to_string().into_bytes()),
+                    ])
+                })
+                .collect(),
+        ),
+    };
+
+    RespValue::Array(vec![
+        RespValue::Integer(summary.count as i64),
+        min,
+        max,
+        consumers,
+    ])
+}
+
+/// An error reply, prefixed with the error code Redis clients expect.
+fn error(status_code: StatusCodes, msg: Option<&[u8]>) -> RespValue {
+    let prefix = match status_code {
+        StatusCodes::ErrWrongType => "WRONGTYPE",
+        StatusCodes::ErrReadOnly => "READONLY",
+        StatusCodes::ErrAuthRequired => "NOAUTH",
+        StatusCodes::ErrNoGroup => "NOGROUP",
+        _ => "ERR",
+    };
+
+    let msg = match msg {
+        Some(msg) => String::from_utf8_lossy(msg).into_owned(),
+        None => status_code.to_string(),
+    };
+    RespValue::Error(format!("{} {}", prefix, msg))
+}
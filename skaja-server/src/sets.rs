@@ -0,0 +1,252 @@
+use crate::{
+    integer,
+    keyspace::Shards,
+    misrouted,
+    store::{Store, WrongType},
+    wrong_type,
+};
+use skaja_lib::{decode_array, encode_array, Command, RawResponse, StatusCodes};
+use std::{
+    collections::{hash_map::RandomState, HashSet},
+    hash::{BuildHasher, Hasher},
+};
+
+type Set = HashSet<Vec<u8>>;
+
+/// The most members a negative `srandmember` count can pick, as it's the only count that
+/// isn't bounded by the size of the set.
+const MAX_REPEATED_MEMBERS: i64 = 1024 * 1024;
+
+/// Execute a set command on a single key against the store holding it.
+pub(crate) fn execute(data_store: &mut Store, command: Command) -> RawResponse {
+    run(data_store, command).unwrap_or_else(|WrongType| wrong_type())
+}
+
+/// Execute a command combining several sets against the locked shards holding them.
+pub(crate) fn execute_many(shards: &mut Shards, command: Command) -> RawResponse {
+    run_many(shards, command).unwrap_or_else(|WrongType| wrong_type())
+}
+
+/// The members a successful [`Command::SPop`] took, given its response.
+pub(crate) fn popped(response: &RawResponse, count: Option<usize>) -> Vec<Vec<u8>> {
+    if response.status_code() != StatusCodes::Ok {
+        return Vec::new();
+    }
+
+    let msg = response.message().unwrap_or_default();
+    match count {
+        None => vec![msg.to_vec()],
+        Some(_) => decode_array(msg).unwrap_or_default(),
+    }
+}
+
+fn run(data_store: &mut Store, command: Command) -> Result<RawResponse, WrongType> {
+    let response = match command {
+        Command::SAdd(key, members) => {
+            let set = data_store.get_or_insert_as::<Set>(&key)?;
+            let mut added = 0;
+            for member in members {
+                if set.insert(member) {
+                    added += 1;
+                }
+            }
+            if added > 0 {
+                data_store.changed(&key);
+            }
+            integer(added)
+        }
+        Command::SRem(key, members) => {
+            let Some(set) = data_store.get_as_mut::<Set>(&key)? else {
+                return Ok(integer(0));
+            };
+
+            let removed = members.iter().filter(|member| set.remove(*member)).count();
+            if removed > 0 {
+                data_store.changed(&key);
+            }
+            integer(removed as i64)
+        }
+        Command::SIsMember(key, member) => {
+            match data_store
+                .get_as::<Set>(&key)?
+                .is_some_and(|set| set.contains(&member))
+            {
+                true => RawResponse::new(StatusCodes::Ok, None),
+                false => RawResponse::new(StatusCodes::ErrNotFound, None),
+            }
+        }
+        Command::SMembers(key) => {
+            let members: Vec<Vec<u8>> = data_store
+                .get_as::<Set>(&key)?
+                .into_iter()
+                .flatten()
+                .cloned()
+                .collect();
+            RawResponse::new(StatusCodes::Ok, Some(encode_array(&members)))
+        }
+        Command::SCard(key) => {
+            let len = data_store.get_as::<Set>(&key)?.map_or(0, Set::len);
+            integer(len as i64)
+        }
+        Command::SPop(key, count) => {
+            let Some(set) = data_store.get_as_mut::<Set>(&key)? else {
+                return Ok(match count {
+                    None => RawResponse::new(StatusCodes::ErrNotFound, None),
+                    Some(_) => RawResponse::new(StatusCodes::Ok, Some(encode_array(&[]))),
+                });
+            };
+
+            let members = sample(set, count.unwrap_or(1));
+            for member in &members {
+                set.remove(member);
+            }
+            if !members.is_empty() {
+                data_store.changed(&key);
+            }
+
+            match count {
+                None => RawResponse::new(StatusCodes::Ok, members.into_iter().next()),
+                Some(_) => RawResponse::new(StatusCodes::Ok, Some(encode_array(&members))),
+            }
+        }
+        Command::SRandMember(key, count) => {
+            let Some(set) = data_store.get_as::<Set>(&key)? else {
+                return Ok(match count {
+                    None => RawResponse::new(StatusCodes::ErrNotFound, None),
+                    Some(_) => RawResponse::new(StatusCodes::Ok, Some(encode_array(&[]))),
+                });
+            };
+
+            match count {
+                None => RawResponse::new(StatusCodes::Ok, sample(set, 1).into_iter().next()),
+                Some(count) if count < -MAX_REPEATED_MEMBERS => RawResponse::new(
+                    StatusCodes::ErrInvalidRequest,
+                    Some(b"Count is out of range".to_vec()),
+                ),
+                Some(count) => {
+                    let members = match count.is_negative() {
+                        true => sample_with_repeats(set, count.unsigned_abs()),
+                        false => sample(set, usize::try_from(count).unwrap_or(usize::MAX)),
+                    };
+                    RawResponse::new(StatusCodes::Ok, Some(encode_array(&members)))
+                }
+            }
+        }
+        // Only set commands on a single key are handled here.
+        command => misrouted(&command),
+    };
+
+    Ok(response)
+}
+
+fn run_many(shards: &mut Shards, command: Command) -> Result<RawResponse, WrongType> {
+    let response = match command {
+        Command::SInter(keys) => members_reply(inter(shards, &keys)?),
+        Command::SUnion(keys) => members_reply(union(shards, &keys)?),
+        Command::SDiff(keys) => members_reply(diff(shards, &keys)?),
+        Command::SInterStore(destination, keys) => {
+            let result = inter(shards, &keys)?;
+            store(shards, destination, result)
+        }
+        Command::SUnionStore(destination, keys) => {
+            let result = union(shards, &keys)?;
+            store(shards, destination, result)
+        }
+        Command::SDiffStore(destination, keys) => {
+            let result = diff(shards, &keys)?;
+            store(shards, destination, result)
+        }
+        // Only set commands on several keys are handled here.
+        command => misrouted(&command),
+    };
+
+    Ok(response)
+}
+
+/// The members of the sets at the keys, an empty set for the keys that don't exist.
+fn sets(shards: &mut Shards, keys: &[Vec<u8>]) -> Result<Vec<Set>, WrongType> {
+    keys.iter()
+        .map(|key| {
+            let set = shards.store(key).get_as::<Set>(key)?;
+            Ok(set.cloned().unwrap_or_default())
+        })
+        .collect()
+}
+
+fn inter(shards: &mut Shards, keys: &[Vec<u8>]) -> Result<Set, WrongType> {
+    let mut sets = sets(shards, keys)?;
+    // Going through the smallest set keeps the number of lookups down.
+    sets.sort_by_key(Set::len);
+    let mut sets = sets.into_iter();
+    let smallest = sets.next().unwrap_or_default();
+    let others: Vec<Set> = sets.collect();
+
+    Ok(smallest
+        .into_iter()
+        .filter(|member| others.iter().all(|set| set.contains(member)))
+        .collect())
+}
+
+fn union(shards: &mut Shards, keys: &[Vec<u8>]) -> Result<Set, WrongType> {
+    Ok(sets(shards, keys)?.into_iter().flatten().collect())
+}
+
+fn diff(shards: &mut Shards, keys: &[Vec<u8>]) -> Result<Set, WrongType> {
+    let mut sets = sets(shards, keys)?.into_iter();
+    let first = sets.next().unwrap_or_default();
+    let others: Vec<Set> = sets.collect();
+
+    Ok(first
+        .into_iter()
+        .filter(|member| others.iter().all(|set| !set.contains(member)))
+        .collect())
+}
+
+fn members_reply(members: Set) -> RawResponse {
+    let members: Vec<Vec<u8>> = members.into_iter().collect();
+    RawResponse::new(StatusCodes::Ok, Some(encode_array(&members)))
+}
+
+/// Store the set at the destination whatever was there before, an empty set removes it.
+fn store(shards: &mut Shards, destination: Vec<u8>, members: Set) -> RawResponse {
+    let len = members.len();
+    let data_store = shards.store(&destination);
+    match members.is_empty() {
+        true => {
+            data_store.remove(&destination);
+        }
+        false => data_store.set(destination, members, 0, None),
+    }
+
+    integer(len as i64)
+}
+
+/// Pick `count` distinct members at random, all of them if there aren't that many.
+fn sample(set: &Set, count: usize) -> Vec<Vec<u8>> {
+    let mut members: Vec<&Vec<u8>> = set.iter().collect();
+    let count = count.min(members.len());
+    // The first `count` steps of a Fisher-Yates shuffle.
+    for i in 0..count {
+        let j = i + random_below(members.len() - i);
+        members.swap(i, j);
+    }
+
+    members.into_iter().take(count).cloned().collect()
+}
+
+/// Pick `count` members at random, the same member possibly more than once.
+fn sample_with_repeats(set: &Set, count: u64) -> Vec<Vec<u8>> {
+    let members: Vec<&Vec<u8>> = set.iter().collect();
+    (0..count)
+        .map(|_| members[random_below(members.len())].clone())
+        .collect()
+}
+
+/// A random number below the bound, which must not be 0. Good enough to pick members,
+/// not for anything that has to be unpredictable.
+fn random_below(bound: usize) -> usize {
+    // Each `RandomState` is seeded differently, so hashing nothing still gives a new
+    // number every time.
+    let random = RandomState::new().build_hasher().finish();
+    (random % bound as u64) as usize
+}
@@ -0,0 +1,142 @@
+use crate::{
+    integer, lists, misrouted,
+    sorted_set::SortedSet,
+    store::{Store, WrongType},
+    wrong_type,
+};
+use skaja_lib::{encode_array, Command, RawResponse, StatusCodes};
+
+/// Execute a sorted set command against the store holding its key.
+pub(crate) fn execute(data_store: &mut Store, command: Command) -> RawResponse {
+    run(data_store, command).unwrap_or_else(|WrongType| wrong_type())
+}
+
+fn run(data_store: &mut Store, command: Command) -> Result<RawResponse, WrongType> {
+    let response = match command {
+        Command::ZAdd(key, members) => {
+            let set = data_store.get_or_insert_as::<SortedSet>(&key)?;
+            let mut added = 0;
+            for (score, member) in members {
+                if set.insert(member, score) {
+                    added += 1;
+                }
+            }
+            data_store.changed(&key);
+            integer(added)
+        }
+        Command::ZRem(key, members) => {
+            let Some(set) = data_store.get_as_mut::<SortedSet>(&key)? else {
+                return Ok(integer(0));
+            };
+
+            let removed = members.iter().filter(|member| set.remove(member)).count();
+            if removed > 0 {
+                data_store.changed(&key);
+            }
+            integer(removed as i64)
+        }
+        Command::ZScore(key, member) => {
+            match data_store
+                .get_as::<SortedSet>(&key)?
+                .and_then(|set| set.score(&member))
+            {
+                Some(score) => score_reply(score),
+                None => RawResponse::new(StatusCodes::ErrNotFound, None),
+            }
+        }
+        Command::ZIncrBy(key, delta, member) => {
+            let current = data_store
+                .get_as::<SortedSet>(&key)?
+                .and_then(|set| set.score(&member))
+                .unwrap_or(0.0);
+
+            // Adding the infinities of opposite signs gives NaN, which can't be ordered.
+            let score = current + delta;
+            if score.is_nan() {
+                let msg = b"Resulting score is not a number".to_vec();
+                return Ok(RawResponse::new(StatusCodes::ErrNotNumeric, Some(msg)));
+            }
+
+            let set = data_store.get_or_insert_as::<SortedSet>(&key)?;
+            set.insert(member, score);
+            data_store.changed(&key);
+            score_reply(score)
+        }
+        Command::ZRank(key, member) => {
+            match data_store
+                .get_as::<SortedSet>(&key)?
+                .and_then(|set| set.rank(&member))
+            {
+                Some(rank) => integer(rank as i64),
+                None => RawResponse::new(StatusCodes::ErrNotFound, None),
+            }
+        }
+        Command::ZRange(key, start, stop, with_scores) => {
+            let set = data_store.get_as::<SortedSet>(&key)?;
+            let members = set.and_then(|set| {
+                let range = lists::range(set.len(), start, stop)?;
+                Some(
+                    set.iter()
+                        .skip(range.start)
+                        .take(range.len())
+                        .collect::<Vec<_>>(),
+                )
+            });
+            members_reply(members.unwrap_or_default(), with_scores)
+        }
+        Command::ZRevRange(key, start, stop, with_scores) => {
+            let set = data_store.get_as::<SortedSet>(&key)?;
+            let members = set.and_then(|set| {
+                let range = lists::range(set.len(), start, stop)?;
+                Some(
+                    set.iter()
+                        .rev()
+                        .skip(range.start)
+                        .take(range.len())
+                        .collect::<Vec<_>>(),
+                )
+            });
+            members_reply(members.unwrap_or_default(), with_scores)
+        }
+        Command::ZRangeByScore(key, min, max, options) => {
+            let set = data_store.get_as::<SortedSet>(&key)?;
+            let (offset, count) = options.limit.unwrap_or((0, usize::MAX));
+            let members = set
+                .into_iter()
+                .flat_map(|set| set.range_by_score(min, max))
+                .skip(offset)
+                .take(count)
+                .collect();
+            members_reply(members, options.with_scores)
+        }
+        Command::ZCard(key) => {
+            let len = data_store
+                .get_as::<SortedSet>(&key)?
+                .map_or(0, SortedSet::len);
+            integer(len as i64)
+        }
+        // Only sorted set commands are handled here.
+        command => misrouted(&command),
+    };
+
+    Ok(response)
+}
+
+fn score_reply(score: f64) -> RawResponse {
+    RawResponse::new(StatusCodes::Ok, Some(score.to_string().into_bytes()))
+}
+
+/// Reply with the members, each followed by its score if asked for.
+fn members_reply(members: Vec<(&Vec<u8>, f64)>, with_scores: bool) -> RawResponse {
+    let items: Vec<Vec<u8>> = match with_scores {
+        true => members
+            .into_iter()
+            .flat_map(|(member, score)| [member.clone(), score.to_string().into_bytes()])
+            .collect(),
+        false => members
+            .into_iter()
+            .map(|(member, _)| member.clone())
+            .collect(),
+    };
+    RawResponse::new(StatusCodes::Ok, Some(encode_array(&items)))
+}
@@ -0,0 +1,224 @@
+use crate::{
+    clock, integer,
+    keyspace::Shards,
+    misrouted,
+    store::{Store, WrongType},
+    stream::{NoGroup, Stream},
+    wrong_type,
+};
+use skaja_lib::{
+    encode_streams, Command, PendingEntry, RawResponse, StatusCodes, StreamEntry, StreamId,
+};
+
+/// Execute a stream command on a single key against the store holding it.
+pub(crate) fn execute(data_store: &mut Store, command: Command) -> RawResponse {
+    run(data_store, command).unwrap_or_else(|WrongType| wrong_type())
+}
+
+/// Execute a read of several streams against the locked shards holding them.
+pub(crate) fn execute_many(shards: &mut Shards, command: Command) -> RawResponse {
+    run_many(shards, command).unwrap_or_else(|WrongType| wrong_type())
+}
+
+/// The ID of the entry a successful [`Command::XAdd`] added, given its response.
+pub(crate) fn added(response: &RawResponse) -> Option<StreamId> {
+    if response.status_code() != StatusCodes::Ok {
+        return None;
+    }
+
+    let msg = response.message().unwrap_or_default();
+    String::from_utf8_lossy(msg).parse().ok()
+}
+
+/// The IDs of the entries a successful [`Command::XClaim`] claimed, given its response.
+pub(crate) fn claimed(response: &RawResponse) -> Vec<StreamId> {
+    if response.status_code() != StatusCodes::Ok {
+        return Vec::new();
+    }
+
+    let msg = response.message().unwrap_or_default();
+    StreamEntry::decode_many(msg)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|entry| entry.id)
+        .collect()
+}
+
+fn run(data_store: &mut Store, command: Command) -> Result<RawResponse, WrongType> {
+    let now = clock::unix_millis_now();
+    let response = match command {
+        Command::XAdd(key, id, fields) => {
+            let last_id = data_store
+                .get_as::<Stream>(&key)?
+                .map_or(StreamId::MIN, Stream::last_id);
+            let Some(id) = next_id(last_id, id, now) else {
+                let msg = b"The ID must be higher than the one of the last entry".to_vec();
+                return Ok(RawResponse::new(StatusCodes::ErrInvalidRequest, Some(msg)));
+            };
+
+            data_store.get_or_insert_as::<Stream>(&key)?.add(id, fields);
+            data_store.changed(&key);
+            RawResponse::new(StatusCodes::Ok, Some(id.to_string().into_bytes()))
+        }
+        Command::XRange(key, start, end, count) => {
+            let entries = data_store
+                .get_as::<Stream>(&key)?
+                .map(|stream| stream.range(start, end, count.unwrap_or(usize::MAX)))
+                .unwrap_or_default();
+            entries_reply(&entries)
+        }
+        Command::XLen(key) => {
+            let len = data_store.get_as::<Stream>(&key)?.map_or(0, Stream::len);
+            integer(len as i64)
+        }
+        Command::XTrim(key, max_len) => {
+            let Some(stream) = data_store.get_as_mut::<Stream>(&key)? else {
+                return Ok(integer(0));
+            };
+            let removed = stream.trim(max_len);
+            if removed > 0 {
+                data_store.changed(&key);
+            }
+            integer(removed as i64)
+        }
+        Command::XGroupCreate(key, group, start, make_stream) => {
+            let stream = match make_stream {
+                true => data_store.get_or_insert_as::<Stream>(&key)?,
+                false => match data_store.get_as_mut::<Stream>(&key)? {
+                    Some(stream) => stream,
+                    None => {
+                        let msg = b"The stream doesn't exist, create it with mkstream".to_vec();
+                        return Ok(RawResponse::new(StatusCodes::ErrInvalidRequest, Some(msg)));
+                    }
+                },
+            };
+
+            let start = start.unwrap_or(stream.last_id());
+            match stream.create_group(group, start) {
+                true => {
+                    data_store.changed(&key);
+                    RawResponse::new(StatusCodes::Ok, None)
+                }
+                false => {
+                    let msg = b"Consumer group name already exists".to_vec();
+                    RawResponse::new(StatusCodes::ErrConflict, Some(msg))
+                }
+            }
+        }
+        Command::XAck(key, group, ids) => {
+            let Some(stream) = data_store.get_as_mut::<Stream>(&key)? else {
+                return Ok(no_group());
+            };
+            match stream.ack(&group, &ids) {
+                Ok(acknowledged) => {
+                    if acknowledged > 0 {
+                        data_store.changed(&key);
+                    }
+                    integer(acknowledged as i64)
+                }
+                Err(NoGroup) => no_group(),
+            }
+        }
+        Command::XPending(key, group, range) => {
+            let Some(stream) = data_store.get_as::<Stream>(&key)? else {
+                return Ok(no_group());
+            };
+            let msg = match range {
+                None => stream
+                    .pending_summary(&group)
+                    .map(|summary| summary.encode()),
+                Some(range) => stream
+                    .pending(&group, &range, now)
+                    .map(|entries| PendingEntry::encode_many(&entries)),
+            };
+            match msg {
+                Ok(msg) => RawResponse::new(StatusCodes::Ok, Some(msg)),
+                Err(NoGroup) => no_group(),
+            }
+        }
+        Command::XClaim(key, group, consumer, min_idle, ids) => {
+            let Some(stream) = data_store.get_as_mut::<Stream>(&key)? else {
+                return Ok(no_group());
+            };
+            match stream.claim(&group, &consumer, min_idle, &ids, now) {
+                Ok(claimed) => {
+                    if !claimed.is_empty() {
+                        data_store.changed(&key);
+                    }
+                    entries_reply(&claimed)
+                }
+                Err(NoGroup) => no_group(),
+            }
+        }
+        // Only stream commands on a single key are handled here.
+        command => misrouted(&command),
+    };
+
+    Ok(response)
+}
+
+fn run_many(shards: &mut Shards, command: Command) -> Result<RawResponse, WrongType> {
+    let (group, consumer, count, streams) = match command {
+        Command::XReadGroup(group, consumer, count, streams) => (group, consumer, count, streams),
+        // Only stream commands on several keys are handled here.
+        command => return Ok(misrouted(&command)),
+    };
+
+    // All the groups are checked first so that nothing is delivered from the first streams
+    // when the read fails on a later one.
+    for (key, _) in &streams {
+        let stream = shards.store(key).get_as::<Stream>(key)?;
+        if !stream.is_some_and(|stream| stream.has_group(&group)) {
+            return Ok(no_group());
+        }
+    }
+
+    let now = clock::unix_millis_now();
+    let mut read = Vec::new();
+    for (key, after) in streams {
+        // A stream whose timeout ran out since it was checked is read as if it expired
+        // right after this command, nothing is delivered from it.
+        let Some(stream) = shards.store(&key).get_as_mut::<Stream>(&key)? else {
+            continue;
+        };
+        let Ok(entries) =
+            stream.read_group(&group, &consumer, after, count.unwrap_or(usize::MAX), now)
+        else {
+            continue;
+        };
+        if !entries.is_empty() {
+            shards.store(&key).changed(&key);
+            read.push((key, entries));
+        }
+    }
+
+    let response = match read.is_empty() {
+        true => RawResponse::new(StatusCodes::ErrNotFound, None),
+        false => RawResponse::new(StatusCodes::Ok, Some(encode_streams(&read))),
+    };
+    Ok(response)
+}
+
+/// The ID of the entry to add after the last one, made from the current time if none is
+/// given. None if the ID isn't higher than the last one, or there's no ID left after it.
+fn next_id(last_id: StreamId, id: Option<StreamId>, now: u64) -> Option<StreamId> {
+    let id = match id {
+        Some(id) => id,
+        None if now > last_id.ms => StreamId::new(now, 0),
+        // The clock went back or the last ID was given, the IDs still have to grow.
+        None => last_id.next()?,
+    };
+
+    (id > last_id).then_some(id)
+}
+
+fn entries_reply(entries: &[StreamEntry]) -> RawResponse {
+    RawResponse::new(StatusCodes::Ok, Some(StreamEntry::encode_many(entries)))
+}
+
+/// The response to a command on a consumer group that doesn't exist, or whose stream
+/// doesn't.
+fn no_group() -> RawResponse {
+    let msg = b"No such key or consumer group".to_vec();
+    RawResponse::new(StatusCodes::ErrNoGroup, Some(msg))
+}
@@ -0,0 +1,598 @@
+use crate::{
+    codec::{Codec, Incoming, Protocol},
+    output_buffer::OutputBuffer,
+    Shared, POLL_TIMEOUT,
+};
+use mio::{
+    event::Event,
+    net::{TcpListener, TcpStream},
+    Events, Interest, Poll, Token, Waker,
+};
+use skaja_lib::{RawResponse, StatusCodes, SERVER_TOKEN};
+use slab::Slab;
+use std::{
+    io,
+    net::SocketAddr,
+    sync::{
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tracing::{debug, error, info, warn};
+
+/// Wakes a worker up when the acceptor hands it a new connection.
+pub(crate) const WAKER_TOKEN: Token = Token(SERVER_TOKEN.0 + 1);
+
+/// Notifies the accepting worker of the termination signals.
+#[cfg(unix)]
+const SIGNAL_TOKEN: Token = Token(WAKER_TOKEN.0 + 1);
+
+/// The listener of the connections speaking RESP, if enabled.
+const RESP_LISTENER_TOKEN: Token = Token(WAKER_TOKEN.0 + 2);
+
+/// The listener of the connections speaking memcached's text protocol, if enabled.
+const MEMCACHED_LISTENER_TOKEN: Token = Token(RESP_LISTENER_TOKEN.0 + 1);
+
+/// The listener of the connections speaking HTTP, if enabled.
+const HTTP_LISTENER_TOKEN: Token = Token(MEMCACHED_LISTENER_TOKEN.0 + 1);
+
+/// The connections' tokens are their keys in the connections store plus this offset,
+/// so that none of them collides with the tokens above.
+const CONNECTION_TOKEN_OFFSET: usize = HTTP_LISTENER_TOKEN.0 + 1;
+
+/// How long the workers keep writing the pending responses when shutting down,
+/// before giving up on the clients that don't read them.
+const SHUTDOWN_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct Connection {
+    connection: TcpStream,
+    ip: SocketAddr,
+    // Buffers the bytes received from the connection until they form a complete request,
+    // and encodes the responses in the protocol the client speaks.
+    codec: Codec,
+    // The responses that haven't been written to the connection yet, in the same order
+    // as the requests. Clients may send multiple requests without waiting for the responses.
+    output: OutputBuffer,
+    // Set when the client sends a request over the limits, the rest of its bytes can't be
+    // trusted so nothing is read anymore and it's closed once the responses are written.
+    // Also set when the client asks to be disconnected, e.g. with QUIT.
+    closing: bool,
+}
+
+/// An event loop serving its own set of connections with its own poller.
+///
+/// The first worker also accepts the connections and hands them out to the others, including
+/// itself, in a round-robin fashion. It's also the one doing the server-wide periodic work,
+/// e.g. removing expired keys and saving snapshots.
+pub(crate) struct Worker {
+    poller: Poll,
+    shared: Arc<Shared>,
+    connections_store: Slab<Connection>,
+    acceptor: Option<Acceptor>,
+    /// The connections handed to this worker by the acceptor.
+    incoming: Option<Receiver<(TcpStream, SocketAddr, Protocol)>>,
+    /// When the worker stops waiting for the pending responses to be written, set once
+    /// the shutdown has been requested.
+    draining: Option<Instant>,
+    #[cfg(unix)]
+    signals: Option<signal_hook_mio::v0_8::Signals>,
+}
+
+struct Acceptor {
+    /// One listener for each protocol the server speaks.
+    listeners: Vec<(Protocol, TcpListener)>,
+    peers: Vec<Peer>,
+    /// Who gets the next connection, 0 is the accepting worker itself and the rest are
+    /// the indexes of the peers shifted by one.
+    next: usize,
+}
+
+/// The handle the acceptor uses to hand connections to another worker.
+pub(crate) struct Peer {
+    sender: Sender<(TcpStream, SocketAddr, Protocol)>,
+    waker: Waker,
+}
+
+impl Worker {
+    /// The worker that accepts the connections from the listeners, which have to be
+    /// registered to the poller with the token of their protocol, see [`listener_token`].
+    pub(crate) fn acceptor(
+        poller: Poll,
+        listeners: Vec<(Protocol, TcpListener)>,
+        peers: Vec<Peer>,
+        shared: Arc<Shared>,
+    ) -> Self {
+        Self {
+            poller,
+            shared,
+            connections_store: Slab::new(),
+            acceptor: Some(Acceptor {
+                listeners,
+                peers,
+                next: 0,
+            }),
+            incoming: None,
+            draining: None,
+            #[cfg(unix)]
+            signals: None,
+        }
+    }
+
+    /// A worker that only serves the connections handed to it through the returned [`Peer`].
+    pub(crate) fn peer(shared: Arc<Shared>) -> Result<(Self, Peer), io::Error> {
+        let poller = Poll::new()?;
+        let waker = Waker::new(poller.registry(), WAKER_TOKEN)?;
+        let (sender, receiver) = mpsc::channel();
+
+        let worker = Self {
+            poller,
+            shared,
+            connections_store: Slab::new(),
+            acceptor: None,
+            incoming: Some(receiver),
+            draining: None,
+            #[cfg(unix)]
+            signals: None,
+        };
+
+        Ok((worker, Peer { sender, waker }))
+    }
+
+    /// Request the shutdown on SIGTERM and SIGINT, meant for the accepting worker.
+    #[cfg(unix)]
+    pub(crate) fn handle_signals(&mut self) -> Result<(), io::Error> {
+        use signal_hook::consts::{SIGINT, SIGTERM};
+
+        let mut signals = signal_hook_mio::v0_8::Signals::new([SIGTERM, SIGINT])?;
+        self.poller
+            .registry()
+            .register(&mut signals, SIGNAL_TOKEN, Interest::READABLE)?;
+        self.signals = Some(signals);
+
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn handle_signals(&mut self) -> Result<(), io::Error> {
+        warn!("Handling signals is only supported on unix.");
+        Ok(())
+    }
+
+    pub(crate) fn run(mut self) -> Result<(), io::Error> {
+        let mut events_store = Events::with_capacity(1024);
+
+        loop {
+            if let Err(e) = self.poller.poll(&mut events_store, Some(POLL_TIMEOUT)) {
+                if e.kind() == io::ErrorKind::Interrupted {
+                    debug!("Polling interrupted.");
+                    continue;
+                }
+
+                error!("An error occurred when polling events: {}", e);
+                return Err(e);
+            }
+
+            if self.acceptor.is_some() {
+                self.shared.tick();
+            }
+
+            if self.draining.is_none() && self.shared.shutdown.requested().is_some() {
+                self.start_draining()?;
+            }
+
+            self.enforce_output_buffer_limits()?;
+
+            for event in events_store.iter() {
+                match event.token() {
+                    token @ (SERVER_TOKEN
+                    | RESP_LISTENER_TOKEN
+                    | MEMCACHED_LISTENER_TOKEN
+                    | HTTP_LISTENER_TOKEN) => {
+                        debug!("Handling server event: {:?}", event);
+                        self.accept_connections(token)?;
+                    }
+                    WAKER_TOKEN => {
+                        debug!("Handling waker event.");
+                        self.register_incoming_connections()?;
+                    }
+                    #[cfg(unix)]
+                    SIGNAL_TOKEN => self.handle_signal_event()?,
+                    token => {
+                        debug!("Handling connection event: {:?}", token);
+
+                        // The connection might've been closed after the events were polled,
+                        // e.g. for exceeding the output buffer limits.
+                        let key = connection_key(token);
+                        if !key.is_some_and(|key| self.connections_store.contains(key)) {
+                            debug!("Ignoring event of a closed connection: {:?}", token);
+                            continue;
+                        }
+
+                        let result = self.handle_connection_event(event);
+                        let done = match result {
+                            // The connection is closed once its responses are written.
+                            Ok(_) if self.draining.is_some() || self.is_closing(token) => {
+                                !self.has_pending_output(token)
+                            }
+                            Ok(_) => false,
+                            Err(e) if e.kind() == io::ErrorKind::Interrupted => false,
+
+                            // Connection is done only if we get the following errors.
+                            // I don't know if this is the best way to handle this. But it works.
+                            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => true,
+                            Err(e) if e.kind() == io::ErrorKind::ConnectionReset => true,
+                            Err(e) if e.kind() == io::ErrorKind::ConnectionAborted => true,
+                            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => true,
+                            Err(e) if e.kind() == io::ErrorKind::WriteZero => true,
+
+                            Err(e) => return Err(e),
+                        };
+
+                        if done {
+                            self.close_connection(token)?;
+                        }
+                    }
+                }
+            }
+
+            if let Some(deadline) = self.draining {
+                if self.connections_store.is_empty() {
+                    return Ok(());
+                }
+
+                if Instant::now() >= deadline {
+                    warn!(
+                        "Giving up on writing the responses of {} connections.",
+                        self.connections_store.len()
+                    );
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    // Stops accepting connections and reading requests. The requests that have already been
+    // received are still answered, and the connections are closed once they're written.
+    fn start_draining(&mut self) -> Result<(), io::Error> {
+        info!(
+            "Shutting down, draining {} connections.",
+            self.connections_store.len()
+        );
+        self.draining = Some(Instant::now() + SHUTDOWN_DRAIN_TIMEOUT);
+
+        if let Some(mut acceptor) = self.acceptor.take() {
+            for (_, listener) in &mut acceptor.listeners {
+                self.poller.registry().deregister(listener)?;
+            }
+            for peer in &acceptor.peers {
+                peer.waker.wake()?;
+            }
+        }
+
+        let tokens: Vec<Token> = self
+            .connections_store
+            .iter()
+            .map(|(key, _)| Token(key + CONNECTION_TOKEN_OFFSET))
+            .collect();
+
+        for token in tokens {
+            let done = match self.serve_connection(token, true) {
+                Ok(_) => !self.has_pending_output(token),
+                Err(e) => {
+                    debug!("Failed draining connection: {}", e);
+                    true
+                }
+            };
+
+            if done {
+                self.close_connection(token)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn has_pending_output(&self, token: Token) -> bool {
+        connection_key(token)
+            .and_then(|key| self.connections_store.get(key))
+            .is_some_and(|conn| !conn.output.is_empty())
+    }
+
+    fn is_closing(&self, token: Token) -> bool {
+        connection_key(token)
+            .and_then(|key| self.connections_store.get(key))
+            .is_some_and(|conn| conn.closing)
+    }
+
+    #[cfg(unix)]
+    fn handle_signal_event(&mut self) -> Result<(), io::Error> {
+        let Some(signals) = self.signals.as_mut() else {
+            return Ok(());
+        };
+
+        for signal in signals.pending() {
+            info!("Received signal {}, shutting down.", signal);
+            self.shared
+                .shutdown
+                .request(skaja_lib::ShutdownMode::Default)?;
+        }
+
+        Ok(())
+    }
+
+    // Accepts the connections queued on the listener and hands them out to the workers.
+    fn accept_connections(&mut self, token: Token) -> Result<(), io::Error> {
+        loop {
+            let Some(acceptor) = self.acceptor.as_mut() else {
+                return Ok(());
+            };
+            let Some((protocol, listener)) = acceptor
+                .listeners
+                .iter()
+                .find(|(protocol, _)| listener_token(*protocol) == token)
+            else {
+                return Ok(());
+            };
+            let protocol = *protocol;
+
+            let (connection, address) = match listener.accept() {
+                Ok((connection, addr)) => (connection, addr),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    // If we get a `WouldBlock` error we know our
+                    // listener has no more incoming connections queued,
+                    // so we can return to polling and wait for some
+                    // more.
+                    debug!("No more incoming connections, returning to polling.");
+                    return Ok(());
+                }
+                Err(e) => {
+                    error!("Failed accepting connection: {}", e);
+                    return Err(e);
+                }
+            };
+
+            info!("Accepted {} connection from: {}", protocol, address);
+
+            let next = acceptor.next;
+            acceptor.next = (next + 1) % (acceptor.peers.len() + 1);
+            if next == 0 {
+                self.register_connection(connection, address, protocol)?;
+                continue;
+            }
+
+            let peer = &acceptor.peers[next - 1];
+            if peer.sender.send((connection, address, protocol)).is_err() {
+                error!("Worker {} is gone, dropping connection: {}", next, address);
+                continue;
+            }
+            peer.waker.wake()?;
+        }
+    }
+
+    // Registers the connections the acceptor handed to this worker.
+    fn register_incoming_connections(&mut self) -> Result<(), io::Error> {
+        loop {
+            let incoming = match self.incoming.as_ref().map(Receiver::try_recv) {
+                Some(Ok(incoming)) => incoming,
+                Some(Err(TryRecvError::Empty)) | None => return Ok(()),
+                // The acceptor stops before the others when shutting down.
+                Some(Err(TryRecvError::Disconnected)) if self.draining.is_some() => return Ok(()),
+                Some(Err(TryRecvError::Disconnected)) => {
+                    return Err(io::Error::other("The acceptor is gone."))
+                }
+            };
+
+            let (connection, address, protocol) = incoming;
+            if self.draining.is_some() {
+                info!("Shutting down, dropping connection: {}", address);
+                continue;
+            }
+
+            self.register_connection(connection, address, protocol)?;
+        }
+    }
+
+    fn register_connection(
+        &mut self,
+        mut connection: TcpStream,
+        address: SocketAddr,
+        protocol: Protocol,
+    ) -> Result<(), io::Error> {
+        // The store hands out the key of a vacant slot, which is either
+        // a brand new one or one freed by a closed connection. So the
+        // token is never shared with another live connection.
+        let entry = self.connections_store.vacant_entry();
+        let connection_token = Token(entry.key() + CONNECTION_TOKEN_OFFSET);
+        self.poller
+            .registry()
+            .register(&mut connection, connection_token, Interest::READABLE)?;
+
+        entry.insert(Connection {
+            connection,
+            ip: address,
+            codec: Codec::new(protocol, self.shared.request_limits),
+            output: OutputBuffer::new(),
+            closing: false,
+        });
+
+        Ok(())
+    }
+
+    fn close_connection(&mut self, token: Token) -> Result<(), io::Error> {
+        let removed = connection_key(token).and_then(|key| self.connections_store.try_remove(key));
+        match removed {
+            Some(mut conn) => {
+                info!("Connection closed: {}", conn.ip);
+                self.poller.registry().deregister(&mut conn.connection)
+            }
+            None => {
+                debug!("Unable to remove connection from store, returning to polling.");
+                Ok(())
+            }
+        }
+    }
+
+    // Disconnects the clients that have been over the soft limit of the output buffer for too long.
+    // They're checked periodically since a client that doesn't read doesn't trigger any events.
+    fn enforce_output_buffer_limits(&mut self) -> Result<(), io::Error> {
+        let limits = self.shared.output_buffer_limits;
+        let exceeded: Vec<usize> = self
+            .connections_store
+            .iter_mut()
+            .filter_map(|(key, conn)| conn.output.exceeds(&limits).then_some(key))
+            .collect();
+
+        for key in exceeded {
+            let token = Token(key + CONNECTION_TOKEN_OFFSET);
+            warn!(
+                "Output buffer limit exceeded, disconnecting: {}",
+                self.connections_store[key].ip
+            );
+            self.close_connection(token)?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_connection_event(&mut self, event: &Event) -> Result<(), io::Error> {
+        // No new requests are read when shutting down.
+        let read = event.is_readable() && self.draining.is_none();
+        self.serve_connection(event.token(), read)
+    }
+
+    // Reads the requests buffered in the connection and answers them. Returns whether the
+    // decoder's buffer was full, in which case there might be more to read.
+    fn read_requests(&mut self, token: Token) -> Result<bool, io::Error> {
+        let Connection {
+            connection, codec, ..
+        } = get_connection(&mut self.connections_store, token)?;
+
+        let bytes_read = codec.read_from(connection).map_err(|e| {
+            debug!("Failed reading from connection: {:?}", e);
+            e
+        })?;
+
+        // There might be multiple requests in the buffer, and the last one might be
+        // split across multiple TCP segments, in which case we wait for the next
+        // readable event to get the rest of it.
+        let mut requests = Vec::new();
+        let violation = loop {
+            match codec.decode() {
+                Ok(Some(request)) => requests.push(request),
+                Ok(None) => break None,
+                Err(e) => break Some(e),
+            }
+        };
+        let full = codec.is_full();
+
+        for request in requests {
+            let response = match request {
+                Incoming::Command(command, reply) => {
+                    // A command failing on the server's side, e.g. when it can't be persisted,
+                    // only fails for the client that sent it.
+                    let response = self.shared.process_command(command).unwrap_or_else(|e| {
+                        error!("Failed processing command: {}", e);
+                        let msg = format!("Failed processing command: {}", e);
+                        RawResponse::new(StatusCodes::ErrInternal, Some(msg.into_bytes()))
+                    });
+                    get_connection(&mut self.connections_store, token)?
+                        .codec
+                        .encode(reply, response)
+                }
+                Incoming::Reply(reply) => reply,
+                // Whatever the client sent after asking to be disconnected is ignored.
+                Incoming::Close(reply) => {
+                    get_connection(&mut self.connections_store, token)?.closing = true;
+                    self.push_response(token, &reply)?;
+                    return Ok(false);
+                }
+            };
+            self.push_response(token, &response)?;
+        }
+
+        if let Some(e) = violation {
+            let conn = get_connection(&mut self.connections_store, token)?;
+            warn!("Request over the limits, disconnecting: {}: {}", conn.ip, e);
+            conn.closing = true;
+
+            let response = conn.codec.encode_frame_error(&e);
+            self.push_response(token, &response)?;
+            return Ok(false);
+        }
+
+        Ok(full && bytes_read > 0)
+    }
+
+    // Queues the response to be written, disconnecting the client if it's over the limits.
+    fn push_response(&mut self, token: Token, response: &[u8]) -> Result<(), io::Error> {
+        let Connection { ip, output, .. } = get_connection(&mut self.connections_store, token)?;
+        output.push(response);
+
+        if output.exceeds(&self.shared.output_buffer_limits) {
+            warn!("Output buffer limit exceeded, disconnecting: {}", ip);
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionAborted,
+                "Output buffer limit exceeded.",
+            ));
+        }
+
+        Ok(())
+    }
+
+    // Reads and answers the requests of the connection if `read` is true,
+    // and writes as much of the pending responses as possible.
+    fn serve_connection(&mut self, token: Token, read: bool) -> Result<(), io::Error> {
+        if read && !self.is_closing(token) {
+            debug!("Handling readable event.");
+            // The decoder stops reading once it buffered a request's worth of bytes,
+            // so it's read again after the buffered requests are answered.
+            while self.read_requests(token)? {}
+        }
+
+        debug!("Writing pending responses.");
+        let Connection {
+            connection, output, ..
+        } = get_connection(&mut self.connections_store, token)?;
+        output.write_to(connection).map_err(|e| {
+            error!("Failed writing response: {}", e);
+            e
+        })?;
+
+        // Requests aren't read while there are responses the client hasn't taken yet, so a
+        // client that doesn't read can't make the server buffer its responses indefinitely.
+        let interest = match output.is_empty() {
+            true => Interest::READABLE,
+            false => Interest::WRITABLE,
+        };
+        self.poller
+            .registry()
+            .reregister(connection, token, interest)
+    }
+}
+
+/// The token the listener of the protocol is registered to the poller with.
+pub(crate) fn listener_token(protocol: Protocol) -> Token {
+    match protocol {
+        Protocol::Native => SERVER_TOKEN,
+        Protocol::Resp => RESP_LISTENER_TOKEN,
+        Protocol::Memcached => MEMCACHED_LISTENER_TOKEN,
+        Protocol::Http => HTTP_LISTENER_TOKEN,
+    }
+}
+
+/// The key of the connection in the connections store.
+fn connection_key(token: Token) -> Option<usize> {
+    token.0.checked_sub(CONNECTION_TOKEN_OFFSET)
+}
+
+fn get_connection(
+    connections_store: &mut Slab<Connection>,
+    token: Token,
+) -> Result<&mut Connection, io::Error> {
+    let connection = connection_key(token).and_then(|key| connections_store.get_mut(key));
+    connection.ok_or_else(|| {
+        error!("Failed getting connection from store.");
+        io::Error::other("Failed to get connection.")
+    })
+}